@@ -0,0 +1,18 @@
+// Run the visualizer in a desktop window:
+//
+//     cargo run --example native --features native -- path/to/track.wav
+
+fn main() {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: native <file.wav>");
+        std::process::exit(2);
+    };
+
+    let result = std::fs::read(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))
+        .and_then(|data| viber::native::run(&data));
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,1075 @@
+//! Audio analysis pipeline: decoding, framing, FFT and frequency bar mapping.
+//!
+//! Nothing in here touches wasm-bindgen or the DOM, so the same DSP runs in the browser and on
+//! native targets (desktop builds, tests, benchmarks).
+
+use std::borrow::Cow;
+use std::io::Cursor;
+use std::ops::Range;
+use std::sync::OnceLock;
+use phastft::planner::Direction;
+
+/// Samples per analysis frame (and FFT size).
+pub const FRAME_SIZE: usize = 1024;
+/// Number of FFT bins up to the Nyquist frequency.
+pub const NYQUIST_BIN: usize = FRAME_SIZE / 2;
+/// Analysis frames per second of audio the framing aims for.
+pub const TARGET_FPS: f64 = 120.0;
+/// Number of min/max columns in the precomputed waveform.
+pub const WAVEFORM_COLUMNS: usize = 1024;
+/// Lowest sample rate accepted, the telephone rate. Far lower rates leave fewer samples per
+/// second than `TARGET_FPS` frames, and nothing audible to analyze anyway.
+pub const MIN_SAMPLE_RATE: u32 = 8000;
+
+// Full audible range used by the perceptual bar distributions
+pub const DEFAULT_MIN_FREQ: f32 = 20.0;    // 20 Hz
+pub const DEFAULT_MAX_FREQ: f32 = 20000.0; // 20 kHz
+
+/// Bar magnitude of a full-scale sine: FFT magnitudes are relative to this for dBFS. A Hann
+/// window halves the peak of a sine's bin, which is FRAME_SIZE / 2 unwindowed.
+pub const FULL_SCALE_MAGNITUDE: f32 = FRAME_SIZE as f32 / 4.0;
+
+/// Magnitude of a level in dB relative to a full-scale sine.
+pub fn db_to_magnitude(db: f32) -> f32 {
+    FULL_SCALE_MAGNITUDE * 10f32.powf(db / 20.0)
+}
+
+/// Decoded mono audio and the properties of the source file.
+#[derive(Clone, Debug)]
+pub struct DecodedAudio {
+    pub samples: Vec<i16>,
+    /// Right channel of a stereo file (`samples` holds the left)
+    pub right: Option<Vec<i16>>,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+}
+
+impl DecodedAudio {
+    /// Audio decoded elsewhere as one buffer of -1.0..1.0 samples per channel, converted to
+    /// 16-bit like a WAV file; like `decode_wav`, only the left channel of anything but stereo
+    /// is kept. Reported as 32 bits per sample, the precision it arrived at.
+    pub fn from_float_channels(mut channels: Vec<Vec<f32>>, sample_rate: u32) -> Self {
+        let channel_count = channels.len() as u16;
+        let to_i16 = |channel: Vec<f32>| -> Vec<i16> {
+            channel.into_iter().map(|sample| (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16).collect()
+        };
+        let right = (channels.len() == 2).then(|| to_i16(channels.pop().unwrap_or_default()));
+        let samples = to_i16(channels.into_iter().next().unwrap_or_default());
+        DecodedAudio {
+            samples,
+            right,
+            sample_rate,
+            channels: channel_count,
+            bits_per_sample: 32,
+        }
+    }
+
+    /// Interleaved -1.0..1.0 samples with `channels` channels, as `from_float_channels`; a
+    /// trailing partial frame is dropped.
+    pub fn from_interleaved(samples: &[f32], sample_rate: u32, channels: u32) -> Result<Self, String> {
+        if channels == 0 || sample_rate < MIN_SAMPLE_RATE {
+            return Err(format!("Invalid PCM format: {} channels at {} Hz (at least {} Hz is needed)", channels, sample_rate, MIN_SAMPLE_RATE));
+        }
+        let channels = channels as usize;
+        let frames = samples.len() / channels;
+        let split = (0..channels)
+            .map(|channel| samples.iter().skip(channel).step_by(channels).take(frames).copied().collect())
+            .collect();
+        Ok(Self::from_float_channels(split, sample_rate))
+    }
+}
+
+/// Decode a WAV file and downmix it to mono (left channel only).
+pub fn decode_wav(file_data: &[u8]) -> Result<DecodedAudio, String> {
+    let reader = hound::WavReader::new(Cursor::new(file_data)).map_err(|e| {
+        log!("Error reading WAV file: {:?}", e);
+        format!("Failed to read WAV file: {:?}", e)
+    })?;
+
+    let spec = reader.spec();
+    if spec.sample_rate < MIN_SAMPLE_RATE {
+        return Err(format!("Unsupported sample rate {} Hz, at least {} Hz is needed", spec.sample_rate, MIN_SAMPLE_RATE));
+    }
+    log!("WAV file info:");
+    log!("  Channels: {}", spec.channels);
+    log!("  Sample rate: {} Hz", spec.sample_rate);
+    log!("  Bits per sample: {}", spec.bits_per_sample);
+    log!("  Sample format: {:?}", spec.sample_format);
+    log!("  Duration: {:.2} seconds", reader.duration() as f64 / spec.sample_rate as f64);
+
+    // Read all samples
+    let sample_vec = reader.into_samples().collect::<Result<Vec<i16>, _>>().map_err(|e| {
+        log!("Error reading samples: {:?}", e);
+        format!("Failed to read samples: {:?}", e)
+    })?;
+    log!("Total samples: {}", sample_vec.len());
+
+    // Convert to mono if stereo (take left channel only), keeping the right channel aside
+    let (samples, right) = if spec.channels == 2 {
+        let left = sample_vec.iter().step_by(2).cloned().collect::<Vec<i16>>();
+        let right = sample_vec.iter().skip(1).step_by(2).cloned().collect::<Vec<i16>>();
+        (left, Some(right))
+    } else {
+        (sample_vec, None)
+    };
+    log!("Mono samples: {}", samples.len());
+
+    Ok(DecodedAudio {
+        samples,
+        right,
+        sample_rate: spec.sample_rate,
+        channels: spec.channels,
+        bits_per_sample: spec.bits_per_sample,
+    })
+}
+
+/// Cutoff of the DC blocker. Low enough to leave the lowest bar alone, high enough to settle
+/// within a few frames.
+pub const DC_BLOCK_CUTOFF_HZ: f32 = 5.0;
+
+/// Filters run over the samples before they are framed and windowed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Preprocessing {
+    /// Remove DC offset with a one-pole high pass at `DC_BLOCK_CUTOFF_HZ`; without it an offset
+    /// smears energy into the lowest bins of every frame
+    pub dc_block: bool,
+    /// First-order pre-emphasis coefficient (0.0 = off, 0.97 is typical), tilting the spectrum
+    /// towards the highs by about 6 dB per octave
+    pub pre_emphasis: f32,
+}
+
+impl Preprocessing {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..1.0).contains(&self.pre_emphasis) {
+            return Err(format!("Pre-emphasis coefficient must be in [0, 1), got {}", self.pre_emphasis));
+        }
+        Ok(())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.dc_block || self.pre_emphasis > 0.0
+    }
+
+    /// The filtered samples, or `samples` itself when both filters are off. Pre-emphasis output is
+    /// scaled by 1 / (1 + coefficient) so a full-scale signal can't clip.
+    pub fn apply<'a>(&self, samples: &'a [i16], sample_rate: u32) -> Cow<'a, [i16]> {
+        if !self.is_enabled() || samples.is_empty() {
+            return Cow::Borrowed(samples);
+        }
+
+        let mut signal: Vec<f32> = samples.iter().map(|&sample| sample as f32).collect();
+        if self.dc_block {
+            // y[n] = x[n] - x[n-1] + r * y[n-1], starting from the first sample so a constant
+            // offset is removed from the start instead of decaying in
+            let r = (-2.0 * std::f32::consts::PI * DC_BLOCK_CUTOFF_HZ / sample_rate.max(1) as f32).exp();
+            let (mut previous_input, mut previous_output) = (signal[0], 0.0);
+            for value in signal.iter_mut() {
+                let output = *value - previous_input + r * previous_output;
+                previous_input = *value;
+                previous_output = output;
+                *value = output;
+            }
+        }
+        if self.pre_emphasis > 0.0 {
+            // y[n] = (x[n] - a * x[n-1]) / (1 + a)
+            let scale = 1.0 / (1.0 + self.pre_emphasis);
+            let mut previous = signal[0];
+            for value in signal.iter_mut() {
+                let input = *value;
+                *value = (input - self.pre_emphasis * previous) * scale;
+                previous = input;
+            }
+        }
+
+        Cow::Owned(signal.into_iter().map(|value| value.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16).collect())
+    }
+}
+
+/// Peak level below which `audible_range` treats audio as silence, in dB relative to full scale.
+pub const DEFAULT_SILENCE_THRESHOLD_DB: f32 = -60.0;
+
+/// The part of `samples` between the first and last sample louder than `threshold_db` (dBFS),
+/// widened by a frame on either side so the first and last sounds get whole frames. Empty if
+/// everything is below the threshold.
+pub fn audible_range(samples: &[i16], threshold_db: f32) -> Range<usize> {
+    let threshold = (i16::MAX as f32 * 10f32.powf(threshold_db / 20.0)) as i32;
+    let loud = |sample: &i16| (*sample as i32).abs() > threshold;
+    match (samples.iter().position(loud), samples.iter().rposition(loud)) {
+        (Some(first), Some(last)) => first.saturating_sub(FRAME_SIZE)..(last + 1 + FRAME_SIZE).min(samples.len()),
+        _ => 0..0,
+    }
+}
+
+/// Highest frame overlap accepted, in percent: a hop of 1/16 of a frame.
+pub const MAX_OVERLAP_PERCENT: f32 = 93.75;
+
+/// Hop size in samples for frames overlapping by `overlap_percent` (0 to `MAX_OVERLAP_PERCENT`).
+pub fn overlap_hop_size(overlap_percent: f32) -> usize {
+    ((FRAME_SIZE as f32 * (1.0 - overlap_percent / 100.0)).round() as usize).clamp(1, FRAME_SIZE)
+}
+
+/// Number of frames `frame_audio` splits `sample_count` samples into, and their hop size.
+pub fn frame_layout(sample_count: usize, sample_rate: u32, hop_size: Option<usize>) -> (usize, usize) {
+    // Calculate hop size for 120fps synchronization
+    let duration_seconds = sample_count as f64 / sample_rate.max(1) as f64;
+    let target_frames = (duration_seconds * TARGET_FPS) as usize;
+    // At least 1, as rates below `TARGET_FPS` would give more frames than samples
+    let hop_size = hop_size.unwrap_or_else(|| sample_count.checked_div(target_frames).unwrap_or(FRAME_SIZE)).max(1);
+
+    // Calculate number of frames with calculated hop size
+    let frame_count = if sample_count >= FRAME_SIZE {
+        (sample_count - FRAME_SIZE) / hop_size + 1
+    } else {
+        0
+    };
+    (frame_count, hop_size)
+}
+
+/// Split samples into overlapping Hann-windowed frames `hop_size` samples apart, or at roughly
+/// `TARGET_FPS` frames per second if it's `None`. Returns the frames and the hop size in samples.
+pub fn frame_audio(samples: &[i16], sample_rate: u32, hop_size: Option<usize>) -> (Vec<Vec<f32>>, usize) {
+    let (frame_count, hop_size) = frame_layout(samples.len(), sample_rate, hop_size);
+    log!("Audio duration: {:.2} seconds", samples.len() as f64 / sample_rate.max(1) as f64);
+    log!("Calculated hop size: {} samples", hop_size);
+    log!("Processing {} frames (hop size: {})", frame_count, hop_size);
+
+    // Generate Hann window
+    let hann_window = hann_window(FRAME_SIZE);
+    let mut frames = Vec::with_capacity(frame_count);
+
+    // Process each frame with calculated hop size
+    for frame_idx in 0..frame_count {
+        let start_idx = frame_idx * hop_size;
+        let end_idx = start_idx + FRAME_SIZE;
+
+        if end_idx <= samples.len() {
+            let frame = &samples[start_idx..end_idx];
+            frames.push(apply_hann_window(frame, &hann_window));
+
+            // Log first frame details for debugging
+            if frame_idx == 0 {
+                log!("First frame raw samples (first 10): {:?}", &frame[..10]);
+                log!("First frame windowed samples (first 10): {:?}", &frames[0][..10]);
+            }
+        }
+    }
+
+    log!("Stored {} windowed frames for 120fps visualization", frames.len());
+    (frames, hop_size)
+}
+
+// Split `length` samples into `columns` nearly equal, non-empty ranges (fewer if there are fewer
+// samples than columns)
+fn waveform_columns(length: usize, columns: usize) -> impl Iterator<Item = Range<usize>> {
+    let columns = columns.min(length);
+    let samples_per_column = length as f64 / columns.max(1) as f64;
+    (0..columns).map(move |column| {
+        let start = (column as f64 * samples_per_column) as usize;
+        let end = (((column + 1) as f64 * samples_per_column) as usize).clamp(start + 1, length);
+        start..end
+    })
+}
+
+/// Min/max columns of the whole signal, interleaved as (min, max) pairs normalized to -1.0..1.0.
+pub fn compute_waveform(samples: &[i16], columns: usize) -> Vec<f32> {
+    let mut waveform = Vec::new();
+    for range in waveform_columns(samples.len(), columns) {
+        let (min, max) = samples[range].iter().fold((i16::MAX, i16::MIN), |(lo, hi), &s| (lo.min(s), hi.max(s)));
+        waveform.push(min as f32 / i16::MAX as f32);
+        waveform.push(max as f32 / i16::MAX as f32);
+    }
+
+    log!("Computed {} waveform columns", waveform.len() / 2);
+    waveform
+}
+
+/// RMS level of each column of the whole signal, 0.0-1.0 relative to full scale.
+pub fn compute_waveform_rms(samples: &[i16], columns: usize) -> Vec<f32> {
+    waveform_columns(samples.len(), columns)
+        .map(|range| {
+            let count = range.len();
+            let sum_of_squares: f64 = samples[range].iter().map(|&s| (s as f64 / i16::MAX as f64).powi(2)).sum();
+            ((sum_of_squares / count as f64).sqrt() as f32).min(1.0)
+        })
+        .collect()
+}
+
+/// Largest zero-padding factor accepted: frames in an FFT 8 times their length.
+pub const MAX_ZERO_PADDING: usize = 8;
+
+/// FFT magnitude spectrum of one windowed frame, zero-padded to `fft_size` points.
+pub fn fft_magnitudes(frame: &[f32], fft_size: usize) -> Vec<f32> {
+    // Prepare data for FFT (real and imaginary parts), padded with zeros
+    let mut real_data = frame.to_vec();
+    real_data.resize(fft_size.max(frame.len()), 0.0);
+    let mut imag_data: Vec<f32> = vec![0.0; real_data.len()];
+
+    // Perform FFT
+    phastft::fft_32(&mut real_data, &mut imag_data, Direction::Forward);
+
+    // Calculate magnitudes (sqrt(real^2 + imag^2))
+    real_data.iter()
+        .zip(imag_data.iter())
+        .map(|(r, i)| (r * r + i * i).sqrt())
+        .collect()
+}
+
+/// Spectrum of frame `frame_index` of `samples` alone, the same as `frame_audio` followed by
+/// `compute_fft` gives for it; zeros if the frame runs past the end.
+pub fn frame_spectrum(samples: &[i16], frame_index: usize, hop_size: usize, fft_size: usize) -> Vec<f32> {
+    let start = frame_index * hop_size;
+    match samples.get(start..start + FRAME_SIZE) {
+        Some(frame) => fft_magnitudes(&apply_hann_window(frame, frame_window()), fft_size),
+        None => vec![0.0; fft_size],
+    }
+}
+
+/// Spectrum of one `FRAME_SIZE` frame of -1.0..1.0 samples, windowed as `frame_spectrum` does.
+pub fn float_frame_spectrum(frame: &[f32], fft_size: usize) -> Vec<f32> {
+    let windowed: Vec<f32> = frame.iter().zip(frame_window()).map(|(&sample, &window)| sample * window).collect();
+    fft_magnitudes(&windowed, fft_size)
+}
+
+// The Hann window for `FRAME_SIZE` frames, computed once
+fn frame_window() -> &'static [f32] {
+    static WINDOW: OnceLock<Vec<f32>> = OnceLock::new();
+    WINDOW.get_or_init(|| hann_window(FRAME_SIZE))
+}
+
+/// FFT magnitude spectrum of each windowed frame, zero-padded to `fft_size` points (a power of two
+/// no smaller than the frames). Padding samples the spectrum on finer bins without changing the
+/// peak magnitude of a tone or the frequency resolution the frame length allows.
+pub fn compute_fft(frames: &[Vec<f32>], fft_size: usize) -> Vec<Vec<f32>> {
+    log!("Starting FFT processing on {} frames ({}-point FFT)", frames.len(), fft_size);
+
+    let mut fft_results = Vec::with_capacity(frames.len());
+    for (frame_idx, frame) in frames.iter().enumerate() {
+        let magnitudes = fft_magnitudes(frame, fft_size);
+
+        // Log first frame FFT results for debugging
+        if frame_idx == 0 {
+            log!("First frame FFT magnitudes (first 10): {:?}", &magnitudes[..10]);
+            log!("First frame FFT magnitudes (bins 100-110): {:?}", &magnitudes[100..110]);
+
+            // Find peak frequency
+            let max_magnitude = magnitudes.iter().fold(0.0f32, |a, &b| a.max(b));
+            let max_index = magnitudes.iter().position(|&x| x == max_magnitude).unwrap_or(0);
+            log!("Peak frequency bin: {}, magnitude: {:.2}", max_index, max_magnitude);
+
+            // Log some frequency range statistics
+            let low_freq_sum: f32 = magnitudes[0..50].iter().sum();
+            let mid_freq_sum: f32 = magnitudes[50..200].iter().sum();
+            let high_freq_sum: f32 = magnitudes[200..512].iter().sum();
+            log!("Frequency range energies - Low (0-50): {:.2}, Mid (50-200): {:.2}, High (200-512): {:.2}",
+                 low_freq_sum, mid_freq_sum, high_freq_sum);
+        }
+
+        fft_results.push(magnitudes);
+    }
+
+    log!("FFT processing complete. Generated {} FFT results", fft_results.len());
+    fft_results
+}
+
+/// How analysis frames are turned into the spectra the bars are mapped from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Transform {
+    /// Plain FFT of each frame: linearly spaced bins, 43 Hz apart at 44.1 kHz
+    #[default]
+    Fft,
+    /// Constant-Q transform: `bins_per_octave` log-spaced bins from 20 Hz up, each as wide as a
+    /// fixed fraction of its center frequency, for more low-frequency detail at the same frame rate
+    Cqt { bins_per_octave: u32 },
+}
+
+/// Frequency weighting applied to spectra before bar mapping, so bar heights follow perceived
+/// loudness rather than raw magnitude.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Weighting {
+    /// Unweighted (Z-weighting)
+    #[default]
+    None,
+    /// IEC 61672 A-weighting: strongly attenuates lows and the very top, like hearing at low levels
+    A,
+    /// IEC 61672 C-weighting: nearly flat, rolling off only at the extremes
+    C,
+}
+
+impl Weighting {
+    /// "a", "c", or "z" / "none".
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "none" | "z" => Ok(Weighting::None),
+            "a" => Ok(Weighting::A),
+            "c" => Ok(Weighting::C),
+            _ => Err(format!("Unknown weighting '{}', expected 'a', 'c' or 'none'", name)),
+        }
+    }
+
+    /// Name accepted by `parse`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Weighting::None => "none",
+            Weighting::A => "a",
+            Weighting::C => "c",
+        }
+    }
+
+    /// Linear magnitude gain at `freq` Hz, 1.0 at 1 kHz.
+    pub fn gain(self, freq: f32) -> f32 {
+        let f2 = (freq as f64).powi(2);
+        let (response, offset_db) = match self {
+            Weighting::None => return 1.0,
+            Weighting::A => (
+                12194.0f64.powi(2) * f2 * f2
+                    / ((f2 + 20.6f64.powi(2)) * ((f2 + 107.7f64.powi(2)) * (f2 + 737.9f64.powi(2))).sqrt() * (f2 + 12194.0f64.powi(2))),
+                2.0,
+            ),
+            Weighting::C => (12194.0f64.powi(2) * f2 / ((f2 + 20.6f64.powi(2)) * (f2 + 12194.0f64.powi(2))), 0.06),
+        };
+        (response * 10f64.powf(offset_db / 20.0)) as f32
+    }
+}
+
+/// Multiply each bin of a spectrum by its weighting gain. `gains` may be shorter than the
+/// spectrum; bins past it are dropped.
+pub fn apply_weighting(spectrum: &[f32], gains: &[f32]) -> Vec<f32> {
+    spectrum.iter().zip(gains).map(|(magnitude, gain)| magnitude * gain).collect()
+}
+
+/// Largest supported number of constant-Q bins per octave.
+pub const MAX_BINS_PER_OCTAVE: u32 = 48;
+/// Samples in the longer window the constant-Q transform reads around each frame's center.
+pub const CQT_WINDOW: usize = 4 * FRAME_SIZE;
+
+impl Transform {
+    /// "fft" or "cqt"; `bins_per_octave` only applies to "cqt".
+    pub fn parse(name: &str, bins_per_octave: u32) -> Result<Self, String> {
+        match name {
+            "fft" => Ok(Transform::Fft),
+            "cqt" if (1..=MAX_BINS_PER_OCTAVE).contains(&bins_per_octave) => Ok(Transform::Cqt { bins_per_octave }),
+            "cqt" => Err(format!("Invalid bins per octave {}, expected 1-{}", bins_per_octave, MAX_BINS_PER_OCTAVE)),
+            _ => Err(format!("Unknown transform '{}', expected 'fft' or 'cqt'", name)),
+        }
+    }
+}
+
+/// Center frequencies of the constant-Q bins: `bins_per_octave` per octave from 20 Hz up to
+/// 20 kHz or the Nyquist frequency, whichever is lower.
+pub fn cqt_frequencies(sample_rate: u32, bins_per_octave: u32) -> Vec<f32> {
+    let max_freq = DEFAULT_MAX_FREQ.min(sample_rate as f32 / 2.0);
+    let octaves = (max_freq / DEFAULT_MIN_FREQ).log2().max(0.0);
+    let bin_count = (octaves * bins_per_octave as f32) as usize + 1;
+    (0..bin_count)
+        .map(|k| DEFAULT_MIN_FREQ * 2.0f32.powf(k as f32 / bins_per_octave as f32))
+        .collect()
+}
+
+/// Constant-Q magnitudes for the frames `frame_audio` produced with `hop_size`. Each frame is
+/// re-read as a `CQT_WINDOW`-sample window around its center, and every constant-Q bin sums the
+/// spectrum over its bandwidth with a triangular kernel. Below roughly 180 Hz (at 12 bins per
+/// octave) the bandwidth drops under the window's resolution and bins interpolate between FFT
+/// bins instead. Magnitudes are scaled to match `compute_fft` for a steady tone.
+pub fn compute_cqt(samples: &[i16], hop_size: usize, frame_count: usize, sample_rate: u32, bins_per_octave: u32) -> Vec<Vec<f32>> {
+    log!("Starting constant-Q processing on {} frames ({} bins per octave)", frame_count, bins_per_octave);
+
+    let resolution = sample_rate as f32 / CQT_WINDOW as f32;
+    let q = 1.0 / (2.0f32.powf(1.0 / bins_per_octave as f32) - 1.0);
+    let max_bin = (CQT_WINDOW / 2) as f32;
+
+    // Sparse kernel per constant-Q bin: first FFT bin and normalized weights
+    let kernels: Vec<(usize, Vec<f32>)> = cqt_frequencies(sample_rate, bins_per_octave)
+        .iter()
+        .map(|&freq| {
+            let center = freq / resolution;
+            let half_width = (freq / q / resolution).max(1.0);
+            let first = (center - half_width).ceil().clamp(0.0, max_bin) as usize;
+            let last = (center + half_width).floor().clamp(0.0, max_bin) as usize;
+            let weights: Vec<f32> = (first..=last).map(|bin| (1.0 - (bin as f32 - center).abs() / half_width).max(0.0)).collect();
+            let total: f32 = weights.iter().sum::<f32>().max(f32::EPSILON);
+            (first, weights.into_iter().map(|weight| weight / total).collect())
+        })
+        .collect();
+
+    let window = hann_window(CQT_WINDOW);
+    let level_scale = FRAME_SIZE as f32 / CQT_WINDOW as f32;
+    let mut cqt_results = Vec::with_capacity(frame_count);
+    for frame_idx in 0..frame_count {
+        // Window centered on the frame's center, zero-padded past either end of the signal
+        let start = (frame_idx * hop_size + FRAME_SIZE / 2) as isize - (CQT_WINDOW / 2) as isize;
+        let mut real_data: Vec<f32> = (0..CQT_WINDOW)
+            .map(|n| {
+                let sample = usize::try_from(start + n as isize).ok().and_then(|index| samples.get(index)).copied().unwrap_or(0);
+                sample as f32 / i16::MAX as f32 * window[n]
+            })
+            .collect();
+        let mut imag_data = vec![0.0; CQT_WINDOW];
+        phastft::fft_32(&mut real_data, &mut imag_data, Direction::Forward);
+
+        let power: Vec<f32> = real_data.iter().zip(&imag_data).map(|(r, i)| r * r + i * i).collect();
+        let magnitudes = kernels
+            .iter()
+            .map(|(first, weights)| {
+                let energy: f32 = weights.iter().zip(&power[*first..]).map(|(weight, p)| weight * p).sum();
+                energy.sqrt() * level_scale
+            })
+            .collect();
+        cqt_results.push(magnitudes);
+    }
+
+    log!("Constant-Q processing complete. Generated {} frames", cqt_results.len());
+    cqt_results
+}
+
+/// Raw magnitudes of `num_bars` bars from one constant-Q frame (bins centered on `frequencies`),
+/// on the same level as `fft_bar_magnitudes`. Bars narrower than a bin are interpolated at their
+/// center on the log-frequency axis.
+pub fn cqt_bar_magnitudes(cqt_frame: &[f32], frequencies: &[f32], freq_boundaries: &[f32], num_bars: usize) -> Vec<f32> {
+    if freq_boundaries.len() < num_bars + 1 || frequencies.is_empty() {
+        log!("Warning: insufficient frequency boundaries for {} bars", num_bars);
+        return vec![0.0; num_bars];
+    }
+
+    freq_boundaries
+        .windows(2)
+        .take(num_bars)
+        .map(|range| {
+            let inside: Vec<f32> = frequencies
+                .iter()
+                .zip(cqt_frame)
+                .filter(|(&freq, _)| freq >= range[0] && freq < range[1])
+                .map(|(_, &magnitude)| magnitude)
+                .collect();
+            if !inside.is_empty() {
+                return inside.iter().sum::<f32>() / inside.len() as f32;
+            }
+            let center = (range[0] * range[1]).sqrt();
+            let above = frequencies.partition_point(|&freq| freq < center).min(frequencies.len() - 1);
+            let below = above.saturating_sub(1);
+            if above == below {
+                return cqt_frame.get(above).copied().unwrap_or(0.0);
+            }
+            let blend = ((center / frequencies[below]).ln() / (frequencies[above] / frequencies[below]).ln()).clamp(0.0, 1.0);
+            cqt_frame[below] * (1.0 - blend) + cqt_frame[above] * blend
+        })
+        .collect()
+}
+
+/// Whether a range uses the hand-tuned perceptual tables, which only cover the full 20 Hz - 20 kHz range.
+pub fn is_perceptual_range(min_freq: f32, max_freq: f32) -> bool {
+    min_freq == DEFAULT_MIN_FREQ && max_freq == DEFAULT_MAX_FREQ
+}
+
+/// `min_freq..max_freq` limited to what audio at `sample_rate` can hold: the top stops at the
+/// Nyquist frequency, and the bottom stays at least an octave below it.
+pub fn nyquist_clamped_range(min_freq: f32, max_freq: f32, sample_rate: u32) -> (f32, f32) {
+    let max_freq = max_freq.min(sample_rate as f32 / 2.0);
+    (min_freq.min(max_freq / 2.0), max_freq)
+}
+
+/// `num_bars + 1` bar edge frequencies. The default range uses perceptual tables for 16, 32 and 64
+/// bars; everything else is spaced logarithmically.
+pub fn frequency_boundaries(min_freq: f32, max_freq: f32, num_bars: usize) -> Vec<f32> {
+    let mut frequencies = Vec::with_capacity(num_bars + 1);
+    let perceptual = is_perceptual_range(min_freq, max_freq);
+
+    // Perceptual frequency distribution strategy
+    // More resolution in mid-range where music content is dense
+    match num_bars {
+        64 if perceptual => {
+            // Sub-bass (20-100Hz): 4 bins
+            for i in 0..=4 {
+                let freq = 20.0 + (i as f32 / 4.0) * 80.0;
+                frequencies.push(freq);
+            }
+            // Bass (100-500Hz): 20 bins
+            for i in 1..=20 {
+                let freq = 100.0 * (500.0f32 / 100.0f32).powf(i as f32 / 20.0);
+                frequencies.push(freq);
+            }
+            // Mid-range (500-4000Hz): 24 bins
+            for i in 1..=24 {
+                let freq = 500.0 * (4000.0f32 / 500.0f32).powf(i as f32 / 24.0);
+                frequencies.push(freq);
+            }
+            // High frequencies (4000-20000Hz): 16 bins
+            for i in 1..=16 {
+                let freq = 4000.0 * (20000.0f32 / 4000.0f32).powf(i as f32 / 16.0);
+                frequencies.push(freq);
+            }
+        }
+        32 if perceptual => {
+            // Sub-bass (20-100Hz): 2 bins
+            for i in 0..=2 {
+                let freq = 20.0 + (i as f32 / 2.0) * 80.0;
+                frequencies.push(freq);
+            }
+            // Bass (100-500Hz): 10 bins
+            for i in 1..=10 {
+                let freq = 100.0 * (500.0f32 / 100.0f32).powf(i as f32 / 10.0);
+                frequencies.push(freq);
+            }
+            // Mid-range (500-4000Hz): 12 bins
+            for i in 1..=12 {
+                let freq = 500.0 * (4000.0f32 / 500.0f32).powf(i as f32 / 12.0);
+                frequencies.push(freq);
+            }
+            // High frequencies (4000-20000Hz): 8 bins
+            for i in 1..=8 {
+                let freq = 4000.0 * (20000.0f32 / 4000.0f32).powf(i as f32 / 8.0);
+                frequencies.push(freq);
+            }
+        }
+        16 if perceptual => {
+            // Sub-bass (20-100Hz): 1 bin
+            frequencies.push(20.0);
+            frequencies.push(100.0);
+            // Bass (100-500Hz): 5 bins
+            for i in 1..=5 {
+                let freq = 100.0 * (500.0f32 / 100.0f32).powf(i as f32 / 5.0);
+                frequencies.push(freq);
+            }
+            // Mid-range (500-4000Hz): 6 bins
+            for i in 1..=6 {
+                let freq = 500.0 * (4000.0f32 / 500.0f32).powf(i as f32 / 6.0);
+                frequencies.push(freq);
+            }
+            // High frequencies (4000-20000Hz): 4 bins
+            for i in 1..=4 {
+                let freq = 4000.0 * (20000.0f32 / 4000.0f32).powf(i as f32 / 4.0);
+                frequencies.push(freq);
+            }
+        }
+        _ => {
+            // Fallback to logarithmic distribution
+            let log_min = min_freq.ln();
+            let log_max = max_freq.ln();
+            let log_step = (log_max - log_min) / num_bars as f32;
+
+            for i in 0..=num_bars {
+                let freq = (log_min + i as f32 * log_step).exp();
+                frequencies.push(freq);
+            }
+        }
+    }
+
+    frequencies
+}
+
+/// Raw magnitudes of `num_bars` bars from one `fft_size`-point FFT magnitude frame, relative to
+/// `FULL_SCALE_MAGNITUDE`.
+pub fn fft_bar_magnitudes(fft_frame: &[f32], sample_rate: u32, fft_size: usize, freq_boundaries: &[f32], num_bars: usize) -> Vec<f32> {
+    if freq_boundaries.len() < num_bars + 1 {
+        log!("Warning: insufficient frequency boundaries for {} bars", num_bars);
+        return vec![0.0; num_bars];
+    }
+
+    freq_boundaries
+        .windows(2)
+        .take(num_bars)
+        .map(|range| bar_magnitude(fft_frame, sample_rate, fft_size, range[0], range[1]))
+        .collect()
+}
+
+/// Most frames `averaged_spectra` averages over.
+pub const MAX_AVERAGED_FRAMES: usize = 32;
+
+/// Welch-style averages of `spectra`: each frame's magnitudes become the RMS over the `frames`
+/// frames centered on it (fewer at the ends of the track), so bars mapped from them jitter less
+/// without lagging behind the audio. Power is summed over a sliding window, so the cost doesn't
+/// grow with `frames`.
+pub fn averaged_spectra(spectra: &[Vec<f32>], frames: usize) -> impl Iterator<Item = Vec<f32>> + '_ {
+    let (before, after) = ((frames.max(1) - 1) / 2, frames.max(1) / 2);
+    let bins = spectra.first().map_or(0, Vec::len);
+    let mut power = vec![0.0f64; bins];
+    let mut window = 0..0;
+    (0..spectra.len()).map(move |frame_index| {
+        let wanted = frame_index.saturating_sub(before)..(frame_index + after + 1).min(spectra.len());
+        while window.end < wanted.end {
+            for (sum, &magnitude) in power.iter_mut().zip(&spectra[window.end]) {
+                *sum += magnitude as f64 * magnitude as f64;
+            }
+            window.end += 1;
+        }
+        while window.start < wanted.start {
+            for (sum, &magnitude) in power.iter_mut().zip(&spectra[window.start]) {
+                *sum -= magnitude as f64 * magnitude as f64;
+            }
+            window.start += 1;
+        }
+        let count = window.len() as f64;
+        power.iter().map(|&sum| (sum.max(0.0) / count).sqrt() as f32).collect()
+    })
+}
+
+/// Widest window `median_filter_frames` takes.
+pub const MAX_MEDIAN_FRAMES: usize = 9;
+
+/// Every bar's magnitude replaced by its median over the `width` frames centered on its frame
+/// (fewer at the ends of the track, where the lower of the two middle values is taken). A spike
+/// shorter than half the window, like a click or pop on a vinyl rip, disappears, while lasting
+/// changes in level keep their sharp edges.
+pub fn median_filter_frames(frames: Vec<Vec<f32>>, width: usize) -> Vec<Vec<f32>> {
+    let half = width / 2;
+    let mut window = Vec::with_capacity(width);
+    (0..frames.len())
+        .map(|frame_index| {
+            let neighbours = &frames[frame_index.saturating_sub(half)..(frame_index + half + 1).min(frames.len())];
+            (0..frames[frame_index].len())
+                .map(|bar| {
+                    window.clear();
+                    window.extend(neighbours.iter().filter_map(|frame| frame.get(bar)));
+                    // The windows cut off at the ends can have an even length; the upper middle
+                    // value would let a spike in the first or last frame through
+                    let middle = (window.len() - 1) / 2;
+                    *window.select_nth_unstable_by(middle, f32::total_cmp).1
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Scale one frame of raw bar magnitudes to display values in 0.0-1.0 by their percentiles within
+/// the frame. Bars below `noise_floor` (see `db_to_magnitude`; 0.0 keeps everything) are zeroed
+/// first.
+pub fn scale_bars(raw_magnitudes: Vec<f32>, noise_floor: f32) -> Vec<f32> {
+    let num_bars = raw_magnitudes.len();
+    let mut bars = vec![0.0; num_bars];
+
+    // Apply dynamic range compression and power expansion for better variance
+    apply_dynamic_scaling(&gate_magnitudes(raw_magnitudes, noise_floor), &mut bars, num_bars);
+
+    bars
+}
+
+/// Automatic gain control for the bars: an alternative to the per-frame percentile scaling that
+/// follows the loudest bar with a slow envelope, so quiet passages still move and loud ones keep
+/// their shape instead of saturating.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AutoGain {
+    /// Height the envelope of the loudest bar is scaled to (0.0-1.0]
+    pub target: f32,
+    /// Time for the gain to come down when the level rises
+    pub attack_seconds: f32,
+    /// Time for the gain to come back up when the level falls
+    pub release_seconds: f32,
+}
+
+// Level the AGC treats as silence: below it the gain stops rising, so noise isn't blown up
+const AUTO_GAIN_MIN_LEVEL_DB: f32 = -60.0;
+
+impl AutoGain {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.target > 0.0 && self.target <= 1.0) {
+            return Err(format!("AGC target must be in (0, 1], got {}", self.target));
+        }
+        if !(self.attack_seconds >= 0.0 && self.attack_seconds.is_finite() && self.release_seconds >= 0.0 && self.release_seconds.is_finite()) {
+            return Err(format!(
+                "AGC attack and release must be non-negative seconds, got {} and {}",
+                self.attack_seconds, self.release_seconds
+            ));
+        }
+        Ok(())
+    }
+
+    /// Scale frames of raw bar magnitudes, in playback order, to display values in 0.0-1.0.
+    pub fn apply(&self, frames: &mut [Vec<f32>], frames_per_second: f64) {
+        // One-pole smoothing coefficient for a time constant; 0 seconds follows instantly
+        let coefficient = |seconds: f32| {
+            if seconds > 0.0 {
+                (-1.0 / (seconds as f64 * frames_per_second)).exp() as f32
+            } else {
+                0.0
+            }
+        };
+        let (attack, release) = (coefficient(self.attack_seconds), coefficient(self.release_seconds));
+        let min_level = db_to_magnitude(AUTO_GAIN_MIN_LEVEL_DB);
+
+        let mut envelope: Option<f32> = None;
+        for bars in frames.iter_mut() {
+            let level = bars.iter().fold(min_level, |max, &magnitude| max.max(magnitude));
+            // Start at the first frame's level rather than ramping in from silence
+            let previous = envelope.unwrap_or(level);
+            let smoothing = if level > previous { attack } else { release };
+            let current = smoothing * previous + (1.0 - smoothing) * level;
+            envelope = Some(current);
+
+            let gain = self.target / current;
+            for bar in bars.iter_mut() {
+                *bar = (*bar * gain).min(1.0);
+            }
+        }
+    }
+}
+
+/// Bar count and frequency range of the bar mapping stage.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BarMapping {
+    pub num_bars: usize,
+    pub min_freq: f32,
+    pub max_freq: f32,
+}
+
+impl BarMapping {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.num_bars == 0 {
+            return Err("A bar mapping needs at least one bar".to_string());
+        }
+        if !(self.min_freq > 0.0 && self.max_freq > self.min_freq) {
+            return Err(format!("Invalid frequency range {} Hz - {} Hz", self.min_freq, self.max_freq));
+        }
+        Ok(())
+    }
+}
+
+/// How far two bar mappings of the same FFT frame are apart, in bar heights (0.0-1.0).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameDivergence {
+    pub mean_abs: f32,
+    pub max_abs: f32,
+    pub rms: f32,
+}
+
+/// Map every frame with both mappings and measure how far the results diverge, frame by frame.
+/// `map_frames(boundaries, num_bars)` maps all analyzed spectra with the transform and scaling in
+/// use. Different bar counts are compared by position across the display: both sets are
+/// resampled to the larger count, as they would be stretched across the same canvas.
+pub fn compare_mappings(a: BarMapping, b: BarMapping, map_frames: impl Fn(&[f32], usize) -> Vec<Vec<f32>>) -> Vec<FrameDivergence> {
+    let frames_a = map_frames(&frequency_boundaries(a.min_freq, a.max_freq, a.num_bars), a.num_bars);
+    let frames_b = map_frames(&frequency_boundaries(b.min_freq, b.max_freq, b.num_bars), b.num_bars);
+    let count = a.num_bars.max(b.num_bars);
+
+    frames_a
+        .iter()
+        .zip(&frames_b)
+        .map(|(frame_a, frame_b)| {
+            let bars_a = resample_bars(frame_a, count);
+            let bars_b = resample_bars(frame_b, count);
+            let differences: Vec<f32> = bars_a.iter().zip(&bars_b).map(|(a, b)| (a - b).abs()).collect();
+            FrameDivergence {
+                mean_abs: differences.iter().sum::<f32>() / count as f32,
+                max_abs: differences.iter().fold(0.0, |max, &difference| max.max(difference)),
+                rms: (differences.iter().map(|difference| difference * difference).sum::<f32>() / count as f32).sqrt(),
+            }
+        })
+        .collect()
+}
+
+/// Linearly resample bar values to `count` bars spanning the same display width.
+pub fn resample_bars(bars: &[f32], count: usize) -> Vec<f32> {
+    if bars.len() == count || bars.is_empty() {
+        return bars.to_vec();
+    }
+    (0..count)
+        .map(|i| {
+            // Center of output bar i, in input bar units
+            let position = ((i as f32 + 0.5) * bars.len() as f32 / count as f32 - 0.5).clamp(0.0, (bars.len() - 1) as f32);
+            let below = position.floor() as usize;
+            let above = (below + 1).min(bars.len() - 1);
+            let blend = position - below as f32;
+            bars[below] * (1.0 - blend) + bars[above] * blend
+        })
+        .collect()
+}
+
+/// Pitch classes in a chromagram, C first.
+pub const PITCH_CLASSES: usize = 12;
+// Range folded into the chromagram. Below it FFT bins are wider than a couple of semitones; above
+// it there is mostly noise and overtones
+const CHROMA_MIN_FREQ: f32 = 100.0;
+const CHROMA_MAX_FREQ: f32 = 5000.0;
+// Frames whose strongest pitch class sums to less than this are scaled down rather than
+// normalized, so near-silence doesn't light up every class
+const CHROMA_FLOOR: f32 = 1.0;
+
+/// Fold one `fft_size`-point FFT magnitude frame into 12 pitch-class energies (C, C#, ... B),
+/// relative to the strongest class (0.0-1.0). Each bin goes to the pitch class of its nearest
+/// equal-tempered note.
+pub fn compute_chroma(fft_frame: &[f32], sample_rate: u32, fft_size: usize) -> [f32; PITCH_CLASSES] {
+    let freq_resolution = sample_rate as f32 / fft_size as f32;
+    // Zero padding spreads a tone over more bins; scale the sums back to unpadded levels
+    let bin_scale = FRAME_SIZE as f32 / fft_size as f32;
+    let mut chroma = [0.0; PITCH_CLASSES];
+
+    for (bin_idx, &magnitude) in fft_frame.iter().enumerate().take(fft_size / 2) {
+        let freq = bin_idx as f32 * freq_resolution;
+        if !(CHROMA_MIN_FREQ..=CHROMA_MAX_FREQ).contains(&freq) {
+            continue;
+        }
+        // Semitones from A4 (440 Hz); A is pitch class 9
+        let semitone = (12.0 * (freq / 440.0).log2()).round() as i32;
+        chroma[(semitone + 9).rem_euclid(PITCH_CLASSES as i32) as usize] += magnitude * bin_scale;
+    }
+
+    let strongest = chroma.iter().fold(CHROMA_FLOOR, |max, &energy| max.max(energy));
+    chroma.map(|energy| energy / strongest)
+}
+
+/// Correlation between the channels of a stretch of stereo audio (1.0 identical, 0.0 unrelated,
+/// -1.0 out of phase) and its width: the share of its energy in the side (L-R) signal, from 0.0
+/// for mono through 0.5 for unrelated channels to 1.0 out of phase. Silence reads as 0.0 for both.
+pub fn stereo_correlation(left: &[i16], right: &[i16]) -> (f32, f32) {
+    let (mut left_energy, mut right_energy, mut cross) = (0.0f64, 0.0f64, 0.0f64);
+    for (&l, &r) in left.iter().zip(right) {
+        let (l, r) = (l as f64, r as f64);
+        left_energy += l * l;
+        right_energy += r * r;
+        cross += l * r;
+    }
+    let total = left_energy + right_energy;
+    if total == 0.0 {
+        return (0.0, 0.0);
+    }
+    let correlation = if left_energy > 0.0 && right_energy > 0.0 { cross / (left_energy * right_energy).sqrt() } else { 0.0 };
+    // Side energy over mid plus side energy, with mid = (L+R)/2 and side = (L-R)/2
+    let width = (total - 2.0 * cross) / (2.0 * total);
+    (correlation.clamp(-1.0, 1.0) as f32, width.clamp(0.0, 1.0) as f32)
+}
+
+/// Average magnitude of an `fft_size`-point FFT between two frequencies.
+pub fn bar_magnitude(fft_frame: &[f32], sample_rate: u32, fft_size: usize, freq_start: f32, freq_end: f32) -> f32 {
+    let freq_resolution = sample_rate as f32 / fft_size as f32;
+    let nyquist_bin = fft_size / 2;
+
+    // Convert frequencies to bin indices
+    let bin_start = ((freq_start / freq_resolution) as usize).min(nyquist_bin);
+    let bin_end = ((freq_end / freq_resolution) as usize).min(nyquist_bin);
+
+    // Ensure bin_end is at least bin_start
+    let bin_end = bin_end.max(bin_start);
+
+    // Average magnitudes in this frequency range
+    let mut magnitude_sum = 0.0;
+    let mut bin_count = 0;
+
+    for bin_idx in bin_start..=bin_end {
+        if bin_idx < nyquist_bin && bin_idx < fft_frame.len() {
+            magnitude_sum += fft_frame[bin_idx];
+            bin_count += 1;
+        }
+    }
+
+    if bin_count > 0 {
+        magnitude_sum / bin_count as f32
+    } else {
+        0.0
+    }
+}
+
+/// Zero raw bar magnitudes under the noise floor. This runs before the scaling so gated bars stay
+/// at 0.0 and don't count towards the percentiles or the AGC level as faint signal.
+pub fn gate_magnitudes(mut raw_magnitudes: Vec<f32>, noise_floor: f32) -> Vec<f32> {
+    for magnitude in raw_magnitudes.iter_mut() {
+        if *magnitude < noise_floor {
+            *magnitude = 0.0;
+        }
+    }
+    raw_magnitudes
+}
+
+fn apply_dynamic_scaling(raw_magnitudes: &[f32], output_bars: &mut [f32], num_bars: usize) {
+    // Use percentile-based normalization for better variance
+    let mut sorted_mags = raw_magnitudes.to_vec();
+    sorted_mags.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    // Find percentile thresholds
+    let p25_idx = (num_bars as f32 * 0.25) as usize;
+    let p75_idx = (num_bars as f32 * 0.75) as usize;
+    let p90_idx = (num_bars as f32 * 0.90) as usize;
+
+    let p25_val = sorted_mags.get(p25_idx).unwrap_or(&0.0);
+    let p75_val = sorted_mags.get(p75_idx).unwrap_or(&0.0);
+    let p90_val = sorted_mags.get(p90_idx).unwrap_or(&0.0);
+    let max_val = sorted_mags.last().unwrap_or(&0.0);
+
+    for i in 0..num_bars {
+        let mag = raw_magnitudes[i];
+
+        // Map to percentile-based ranges with dramatic scaling
+        let scaled = if mag <= *p25_val {
+            // Bottom 25%: Map to 0-0.2 range
+            (mag / p25_val.max(0.001)) * 0.2
+        } else if mag <= *p75_val {
+            // 25%-75%: Map to 0.2-0.6 range with power scaling
+            let normalized = (mag - p25_val) / (p75_val - p25_val).max(0.001);
+            0.2 + normalized.powf(1.5) * 0.4
+        } else if mag <= *p90_val {
+            // 75%-90%: Map to 0.6-0.85 range with strong power scaling
+            let normalized = (mag - p75_val) / (p90_val - p75_val).max(0.001);
+            0.6 + normalized.powf(2.0) * 0.25
+        } else {
+            // Top 10%: Map to 0.85-1.0 range with extreme scaling
+            let normalized = (mag - p90_val) / (max_val - p90_val).max(0.001);
+            0.85 + normalized.powf(3.0) * 0.15
+        };
+
+        output_bars[i] = scaled.min(1.0);
+    }
+}
+
+pub fn hann_window(size: usize) -> Vec<f32> {
+    let mut window = Vec::with_capacity(size);
+    for n in 0..size {
+        let value = 0.5 * (1.0 - ((2.0 * std::f32::consts::PI * n as f32) / (size - 1) as f32).cos());
+        window.push(value);
+    }
+    window
+}
+
+fn apply_hann_window(frame: &[i16], window: &[f32]) -> Vec<f32> {
+    frame.iter()
+        .zip(window.iter())
+        .map(|(&sample, &window_val)| {
+            let normalized_sample = sample as f32 / i16::MAX as f32;
+            normalized_sample * window_val
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn single_bar(values: &[f32]) -> Vec<Vec<f32>> {
+        values.iter().map(|&value| vec![value]).collect()
+    }
+
+    #[test]
+    fn median_filter_removes_spikes_at_the_ends() {
+        let filtered = median_filter_frames(single_bar(&[9.0, 1.0, 1.0, 1.0, 9.0]), 3);
+        assert_eq!(filtered, single_bar(&[1.0, 1.0, 1.0, 1.0, 1.0]));
+
+        let filtered = median_filter_frames(single_bar(&[9.0, 1.0, 2.0, 3.0, 4.0, 5.0]), 5);
+        assert_eq!(filtered, single_bar(&[2.0, 2.0, 3.0, 3.0, 3.0, 4.0]));
+    }
+
+    #[test]
+    fn frame_layout_at_tiny_sample_rates() {
+        for sample_rate in [0, 1, 50, 119] {
+            let (frame_count, hop_size) = frame_layout(FRAME_SIZE * 4, sample_rate, None);
+            assert!(hop_size >= 1, "{} Hz", sample_rate);
+            assert_eq!(frame_count, (FRAME_SIZE * 3) / hop_size + 1);
+        }
+        assert_eq!(frame_layout(FRAME_SIZE * 4, 44_100, Some(0)).1, 1);
+    }
+
+    #[test]
+    fn interleaved_pcm_needs_a_usable_sample_rate() {
+        let samples = vec![0.5; 2 * FRAME_SIZE];
+        for sample_rate in [0, 1, 119, MIN_SAMPLE_RATE - 1] {
+            assert!(DecodedAudio::from_interleaved(&samples, sample_rate, 2).is_err(), "{} Hz", sample_rate);
+        }
+        assert!(DecodedAudio::from_interleaved(&samples, 44_100, 0).is_err());
+
+        let audio = DecodedAudio::from_interleaved(&samples, MIN_SAMPLE_RATE, 2).unwrap();
+        assert_eq!((audio.samples.len(), audio.right.map(|right| right.len())), (FRAME_SIZE, Some(FRAME_SIZE)));
+    }
+
+    #[test]
+    fn median_filter_keeps_steps() {
+        let step = single_bar(&[0.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+        assert_eq!(median_filter_frames(step.clone(), 3), step);
+        assert_eq!(median_filter_frames(single_bar(&[0.0, 5.0, 0.0]), 1), single_bar(&[0.0, 5.0, 0.0]));
+    }
+}
@@ -0,0 +1,1677 @@
+use std::borrow::Cow;
+use std::ops::Range;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+
+use crate::analysis::{self, AutoGain, Preprocessing, Transform, Weighting, DEFAULT_MAX_FREQ, DEFAULT_MIN_FREQ};
+use crate::audio_info::AudioInfo;
+use crate::bar_storage::{BarFrames, BarPrecision};
+use crate::batch::{self, BatchResult};
+use crate::browser_decode;
+use crate::frame_cache::FrameCache;
+use crate::interaction::magnitude_to_dbfs;
+use crate::key::{self, KeyEstimate};
+use crate::loudness::KWeightedEnergy;
+use crate::mapping_diff::{MappingConfig, MappingDiff};
+use crate::markers::{Marker, Markers};
+use crate::metadata::{self, TrackMetadata};
+use crate::mfcc;
+use crate::pitch;
+use crate::structure::{self, Section};
+use crate::time::{FrameIndex, TimeBase, Timestamp};
+
+// Band edges (Hz) for the bass/mid/treble features
+const BASS_RANGE: (f32, f32) = (20.0, 250.0);
+const MID_RANGE: (f32, f32) = (250.0, 4000.0);
+const TREBLE_RANGE: (f32, f32) = (4000.0, 20000.0);
+// Band levels are reported on a linear 0.0-1.0 scale spanning this many dB below full scale
+const LEVEL_FLOOR_DB: f32 = 60.0;
+// Frames kept by lazy analysis, two seconds at the default 120 fps
+const LAZY_CACHE_FRAMES: usize = 240;
+// Spectra computed between yields by `process_cancellable`, a few milliseconds' work
+const PROCESSING_CHUNK_FRAMES: usize = 256;
+
+/// Per-frame audio features for driving non-canvas visuals (DOM/CSS animations and the like).
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameFeatures {
+    /// RMS level of the windowed frame, 0.0-1.0
+    pub rms: f32,
+    /// Magnitude-weighted mean frequency in Hz ("brightness")
+    pub spectral_centroid: f32,
+    /// Positive spectral change since the previous frame, a cheap onset indicator
+    pub spectral_flux: f32,
+    /// Frequency of the strongest FFT bin in Hz
+    pub peak_frequency: f32,
+    /// 20-250 Hz level, 0.0-1.0
+    pub bass: f32,
+    /// 250 Hz-4 kHz level, 0.0-1.0
+    pub mid: f32,
+    /// 4-20 kHz level, 0.0-1.0
+    pub treble: f32,
+}
+
+/// Stereo image of a frame, for a correlation meter. Mono files read as fully correlated.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StereoMeter {
+    /// 1.0 for identical channels, 0.0 for unrelated ones, -1.0 for channels out of phase
+    pub correlation: f32,
+    /// Share of the energy in the side (L-R) signal: 0.0 mono, 0.5 unrelated, 1.0 out of phase
+    pub width: f32,
+}
+
+// Everything lazy analysis computes for one frame
+struct LazyFrame {
+    fft: Vec<f32>,
+    chroma: [f32; analysis::PITCH_CLASSES],
+    bars: Vec<f32>,
+    // Empty unless the right channel is analyzed
+    right_bars: Vec<f32>,
+}
+
+/// The analysis half of the visualizer: decoding, FFT and bar mapping, with no canvas or GPU.
+/// `App` drives its rendering from one of these; it can also be used on its own.
+#[wasm_bindgen]
+pub struct AudioAnalyzer {
+    samples: Vec<i16>,
+    // Right channel of a stereo file (`samples` holds the left), empty for mono
+    right_samples: Vec<i16>,
+    // Analyze the right channel separately as well
+    stereo: bool,
+    // Part of the track to analyze in seconds (the end may be infinite), `None` for all of it
+    region: Option<(f64, f64)>,
+    // Hand files that aren't WAV to the browser's decoders when processing asynchronously
+    browser_decoding: bool,
+    // Peak level in dBFS below which leading and trailing audio isn't analyzed, `None` to keep it
+    silence_trim: Option<f32>,
+    // Frame overlap in percent, `None` to space frames for `TARGET_FPS`
+    overlap: Option<f32>,
+    // FFT length as a multiple of the frame length; 1 for no zero padding
+    zero_padding: usize,
+    // Compute each frame's spectrum and bars when it's first read instead of all up front
+    lazy: bool,
+    // Frames the analyzed samples split into
+    frame_count: usize,
+    // Preprocessed analyzed samples, kept to frame on demand in lazy analysis; empty when the
+    // analysis is up front or preprocessing is off
+    preprocessed: Vec<i16>,
+    right_preprocessed: Vec<i16>,
+    lazy_frames: FrameCache<LazyFrame>,
+    // Samples that were framed, after the region and silence trim
+    analyzed_range: Range<usize>,
+    transform: Transform,
+    preprocessing: Preprocessing,
+    weighting: Weighting,
+    // Raw bar magnitude below which bars are zeroed; 0.0 when the gate is off
+    noise_floor: f32,
+    // Frames each spectrum is averaged over before bar mapping; 1 maps them as they are
+    spectrum_averaging: usize,
+    // Frames the raw bar magnitudes are median filtered over; 1 leaves them unfiltered
+    median_filter: usize,
+    // Replaces the per-frame percentile scaling when set
+    auto_gain: Option<AutoGain>,
+    // Weighting gain per bin of the spectra the bars are mapped from; empty when unweighted
+    weighting_gains: Vec<f32>,
+    fft_results: Vec<Vec<f32>>,
+    cqt_frequencies: Vec<f32>,
+    cqt_results: Vec<Vec<f32>>,
+    bar_precision: BarPrecision,
+    frequency_bars: BarFrames,
+    // Right channel spectra and bars; empty unless stereo analysis is on and the file is stereo
+    right_fft_results: Vec<Vec<f32>>,
+    right_cqt_results: Vec<Vec<f32>>,
+    right_bars: BarFrames,
+    chroma: Vec<[f32; analysis::PITCH_CLASSES]>,
+    stereo_meter: Vec<StereoMeter>,
+    loudness: Vec<f32>,
+    integrated_lufs: Option<f32>,
+    freq_boundaries: Vec<f32>,
+    waveform: Vec<f32>,
+    sample_rate: u32,
+    hop_size: usize,
+    bin_size: usize,
+    min_freq: f32,
+    max_freq: f32,
+    audio_info: Option<AudioInfo>,
+    metadata: TrackMetadata,
+    markers: Markers,
+    // Sample offsets at which each file of an appended mix starts, the first at 0
+    track_starts: Vec<usize>,
+    processed: bool,
+}
+
+impl Default for AudioAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl AudioAnalyzer {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            samples: Vec::new(),
+            right_samples: Vec::new(),
+            stereo: false,
+            region: None,
+            browser_decoding: false,
+            silence_trim: None,
+            overlap: None,
+            zero_padding: 1,
+            lazy: false,
+            frame_count: 0,
+            preprocessed: Vec::new(),
+            right_preprocessed: Vec::new(),
+            lazy_frames: FrameCache::new(LAZY_CACHE_FRAMES),
+            analyzed_range: 0..0,
+            transform: Transform::Fft,
+            preprocessing: Preprocessing::default(),
+            weighting: Weighting::None,
+            noise_floor: 0.0,
+            spectrum_averaging: 1,
+            median_filter: 1,
+            auto_gain: None,
+            weighting_gains: Vec::new(),
+            fft_results: Vec::new(),
+            cqt_frequencies: Vec::new(),
+            cqt_results: Vec::new(),
+            bar_precision: BarPrecision::F32,
+            frequency_bars: BarFrames::default(),
+            right_fft_results: Vec::new(),
+            right_cqt_results: Vec::new(),
+            right_bars: BarFrames::default(),
+            chroma: Vec::new(),
+            stereo_meter: Vec::new(),
+            loudness: Vec::new(),
+            integrated_lufs: None,
+            freq_boundaries: Vec::new(),
+            waveform: Vec::new(),
+            sample_rate: 44100,
+            hop_size: 0,
+            bin_size: 64,
+            min_freq: DEFAULT_MIN_FREQ,
+            max_freq: DEFAULT_MAX_FREQ,
+            audio_info: None,
+            metadata: TrackMetadata::default(),
+            markers: Markers::default(),
+            track_starts: Vec::new(),
+            processed: false,
+        }
+    }
+
+    /// Decode and analyze a WAV file.
+    #[wasm_bindgen]
+    pub fn process_audio_file(&mut self, file_data: &[u8]) -> Result<(), JsValue> {
+        self.process(file_data).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Analyze PCM the host already has (from WebAudio, a game engine, synthesis...) instead
+    /// of a file: `samples` are interleaved, -1.0..1.0, with `channels` channels. Stereo is
+    /// analyzed like a stereo WAV file; of more channels only the first is. Sample rates below
+    /// 8 kHz are rejected.
+    #[wasm_bindgen]
+    pub fn process_samples(&mut self, samples: &[f32], sample_rate: u32, channels: u32) -> Result<(), JsValue> {
+        self.process_pcm(samples, sample_rate, channels).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Append a WAV file to the processed audio and re-analyze the whole, so a gapless mix of
+    /// several files plays as one track with one contiguous frame timeline. The file must have
+    /// the sample rate of the audio so far; mixing mono and stereo files repeats the mono one on
+    /// both channels. Markers and the first file's metadata are kept. Without processed audio this
+    /// is `process_audio_file`.
+    #[wasm_bindgen]
+    pub fn append_audio_file(&mut self, file_data: &[u8]) -> Result<(), JsValue> {
+        self.append(file_data).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Seconds at which each file of the track starts: 0.0 alone for a single file, one more
+    /// for every `append_audio_file`. Empty before a file has been processed.
+    #[wasm_bindgen]
+    pub fn get_track_boundaries(&self) -> Vec<f64> {
+        if !self.processed {
+            return Vec::new();
+        }
+        self.track_starts.iter().map(|&start| start as f64 / self.sample_rate as f64).collect()
+    }
+
+    /// Change the number of bars; already processed audio is re-mapped.
+    #[wasm_bindgen]
+    pub fn set_bin_size(&mut self, bin_size: usize) {
+        self.bin_size = bin_size;
+        if self.processed {
+            self.map_to_frequency_bars();
+        }
+    }
+
+    /// Spread the bars over `min_hz..max_hz` only, e.g. 20-200 Hz to inspect the bass. Already
+    /// processed spectra are re-mapped onto the new bars without reanalyzing the audio, so the
+    /// range can follow a zoom control. The default is 20 Hz to 20 kHz. The top is limited to the
+    /// Nyquist frequency (half the sample rate) of the loaded audio, so e.g. a 22.05 kHz file
+    /// shows up to 11.025 kHz rather than bars with nothing in them.
+    #[wasm_bindgen]
+    pub fn set_display_range(&mut self, min_hz: f32, max_hz: f32) -> Result<(), JsValue> {
+        if !(min_hz > 0.0 && max_hz > min_hz && max_hz.is_finite()) {
+            return Err(JsValue::from_str(&format!("Invalid display range {}-{} Hz", min_hz, max_hz)));
+        }
+        self.set_frequency_range(min_hz, max_hz);
+        Ok(())
+    }
+
+    /// The range the bars are spread over as `[min_hz, max_hz]`, after limiting it to the
+    /// loaded audio.
+    #[wasm_bindgen]
+    pub fn get_display_range(&self) -> Vec<f32> {
+        let (min_freq, max_freq) = self.frequency_range();
+        vec![min_freq, max_freq]
+    }
+
+    /// Choose the spectra the bars are mapped from: "fft" (the default) or "cqt", a constant-Q
+    /// transform with `bins_per_octave` log-spaced bins (1-48, ignored for "fft") that resolves
+    /// bass notes far better. Already processed audio is re-analyzed.
+    #[wasm_bindgen]
+    pub fn set_transform(&mut self, name: &str, bins_per_octave: u32) -> Result<(), JsValue> {
+        let transform = Transform::parse(name, bins_per_octave).map_err(|e| JsValue::from_str(&e))?;
+        self.apply_transform(transform);
+        Ok(())
+    }
+
+    /// Analyze only `start_seconds..end_seconds` of the track (`end_seconds` may be `Infinity` for
+    /// the rest of it), so nothing outside is framed or transformed. Frames, bars and the time
+    /// base then cover just the region, with frame 0 at `start_seconds`; playback positions before
+    /// it map to frame 0 and after it past the last frame. Already processed audio is re-analyzed.
+    #[wasm_bindgen]
+    pub fn set_analysis_region(&mut self, start_seconds: f64, end_seconds: f64) -> Result<(), JsValue> {
+        if !(start_seconds.is_finite() && start_seconds >= 0.0 && end_seconds > start_seconds) {
+            return Err(JsValue::from_str(&format!("Invalid analysis region {}s - {}s", start_seconds, end_seconds)));
+        }
+        self.region = Some((start_seconds, end_seconds));
+        self.reanalyze();
+        Ok(())
+    }
+
+    /// Analyze the whole track again after `set_analysis_region`.
+    #[wasm_bindgen]
+    pub fn clear_analysis_region(&mut self) {
+        if self.region.take().is_some() {
+            self.reanalyze();
+        }
+    }
+
+    /// Overlap consecutive 1024-sample analysis frames by `percent` (0 to 93.75) instead of
+    /// spacing them for 120 frames per second of audio, so the spectral resolution over time no
+    /// longer depends on the sample rate. 50-75% suits most music. The frame rate then follows
+    /// from the overlap (`get_time_base().frames_per_second()`); `render_at_time` interpolates
+    /// between frames when the display runs faster. Already processed audio is re-analyzed.
+    #[wasm_bindgen]
+    pub fn set_overlap(&mut self, percent: f32) -> Result<(), JsValue> {
+        if !(0.0..=analysis::MAX_OVERLAP_PERCENT).contains(&percent) {
+            return Err(JsValue::from_str(&format!("Overlap must be 0-{}%, got {}", analysis::MAX_OVERLAP_PERCENT, percent)));
+        }
+        if self.overlap != Some(percent) {
+            self.overlap = Some(percent);
+            self.reanalyze();
+        }
+        Ok(())
+    }
+
+    /// Space frames for 120 frames per second of audio again after `set_overlap`.
+    #[wasm_bindgen]
+    pub fn clear_overlap(&mut self) {
+        if self.overlap.take().is_some() {
+            self.reanalyze();
+        }
+    }
+
+    /// Zero-pad each 1024-sample frame to `factor` times its length before the FFT (1, 2, 4 or 8;
+    /// 1 turns padding off). The bins get proportionally narrower, so low bars that shared one
+    /// 43 Hz bin each get their own interpolated values, at the cost of a longer FFT and more
+    /// memory for the spectra. It doesn't sharpen the spectrum itself, which the frame length
+    /// limits; the constant-Q transform does that. Already processed audio is re-analyzed.
+    #[wasm_bindgen]
+    pub fn set_fft_zero_padding(&mut self, factor: usize) -> Result<(), JsValue> {
+        if !factor.is_power_of_two() || factor > analysis::MAX_ZERO_PADDING {
+            return Err(JsValue::from_str(&format!("Zero padding factor must be 1, 2, 4 or 8, got {}", factor)));
+        }
+        if factor != self.zero_padding {
+            self.zero_padding = factor;
+            self.reanalyze();
+        }
+        Ok(())
+    }
+
+    /// Analyze frames on demand: processing then only decodes the file and lays out its frames,
+    /// and each frame's FFT, chromagram and bars are computed the first time they're read, with
+    /// the last 240 frames cached. Playback can start as soon as the file is loaded, and the
+    /// spectra of a long track are never all held in memory. Results over the whole track
+    /// (`get_key`, `get_sections`, `compare_mappings`) compute every frame on each call. The
+    /// constant-Q transform and auto gain need the whole track, so with either of them the
+    /// analysis still runs up front. Already processed audio is re-analyzed.
+    #[wasm_bindgen]
+    pub fn set_lazy_analysis(&mut self, enabled: bool) {
+        if enabled != self.lazy {
+            self.lazy = enabled;
+            self.reanalyze();
+        }
+    }
+
+    /// Decode files that aren't WAV (AAC/M4A, Ogg, WebM audio, whatever the browser can play)
+    /// with `AudioContext.decodeAudioData`. The browser decodes asynchronously, so only
+    /// `App.process_audio_file_async` and `App.load_from_url` fall back to it; the audio arrives
+    /// resampled to the browser's output rate.
+    #[wasm_bindgen]
+    pub fn set_browser_decoding(&mut self, enabled: bool) {
+        self.browser_decoding = enabled;
+    }
+
+    /// Leave out leading and trailing audio whose peak stays below `threshold_db` dBFS (-60 is a
+    /// good start), within the analysis region if one is set. Long silent intros and tails then
+    /// cost no processing or memory; the time base's `start_sample` says where analysis begins.
+    /// Already processed audio is re-analyzed.
+    #[wasm_bindgen]
+    pub fn set_silence_trim(&mut self, enabled: bool, threshold_db: f32) -> Result<(), JsValue> {
+        if enabled && !(threshold_db.is_finite() && threshold_db < 0.0) {
+            return Err(JsValue::from_str(&format!("Silence threshold must be below 0 dB, got {}", threshold_db)));
+        }
+        let silence_trim = enabled.then_some(threshold_db);
+        if silence_trim != self.silence_trim {
+            self.silence_trim = silence_trim;
+            self.reanalyze();
+        }
+        Ok(())
+    }
+
+    /// Filter the samples before they are windowed: `dc_block` removes a DC offset (which otherwise
+    /// smears energy into the lowest bins of every frame) and `pre_emphasis` applies a first-order
+    /// pre-emphasis with that coefficient (0.0 = off, 0.97 is typical). Both are off by default.
+    /// Waveform, loudness and peak levels always use the unfiltered audio. Already processed audio
+    /// is re-analyzed.
+    #[wasm_bindgen]
+    pub fn set_preprocessing(&mut self, dc_block: bool, pre_emphasis: f32) -> Result<(), JsValue> {
+        let preprocessing = Preprocessing { dc_block, pre_emphasis };
+        preprocessing.validate().map_err(|e| JsValue::from_str(&e))?;
+        if preprocessing == self.preprocessing {
+            return Ok(());
+        }
+        self.preprocessing = preprocessing;
+        if self.processed {
+            self.compute_spectra();
+            self.compute_transform();
+            self.map_to_frequency_bars();
+        }
+        Ok(())
+    }
+
+    /// Store the bars at "f32" (the default), "f16" or "u8" precision, cutting their memory by 2x
+    /// or 4x for long tracks. 8-bit bars are relative to the track's loudest bar, so steps stay
+    /// well under a pixel at typical bar heights. Bars are converted back to floats when read.
+    /// Already processed audio is re-mapped.
+    #[wasm_bindgen]
+    pub fn set_bar_precision(&mut self, name: &str) -> Result<(), JsValue> {
+        let precision = BarPrecision::parse(name).map_err(|e| JsValue::from_str(&e))?;
+        if precision != self.bar_precision {
+            self.bar_precision = precision;
+            if self.processed {
+                self.map_to_frequency_bars();
+            }
+        }
+        Ok(())
+    }
+
+    /// Weight magnitudes by frequency before mapping them to bars so heights follow perceived
+    /// loudness: "a" (A-weighting, tames the lows and the top end), "c" (C-weighting, only the
+    /// extremes) or "none" (the default). Already processed audio is re-mapped.
+    #[wasm_bindgen]
+    pub fn set_weighting(&mut self, name: &str) -> Result<(), JsValue> {
+        let weighting = Weighting::parse(name).map_err(|e| JsValue::from_str(&e))?;
+        self.apply_weighting(weighting);
+        Ok(())
+    }
+
+    /// Zero bars quieter than `db` relative to a full-scale sine, so silence and codec noise don't
+    /// shimmer. The gate applies to raw magnitudes, before the bars are scaled. `-Infinity` (the
+    /// default) turns it off. Already processed audio is re-mapped.
+    #[wasm_bindgen]
+    pub fn set_noise_floor(&mut self, db: f32) -> Result<(), JsValue> {
+        if db.is_nan() || db > 0.0 {
+            return Err(JsValue::from_str(&format!("Noise floor must be at most 0 dB, got {}", db)));
+        }
+        self.noise_floor = analysis::db_to_magnitude(db);
+        if self.processed {
+            self.map_to_frequency_bars();
+        }
+        Ok(())
+    }
+
+    /// Average the magnitudes of each spectrum with its neighbours before mapping them to bars,
+    /// Welch-style: `frames` (1-32) consecutive frames centered on it, in power, for a steadier
+    /// measurement-style analyzer. Unlike the display smoothing this changes the stored bars and
+    /// doesn't lag, since later frames are averaged in as well. 1 (the default) turns it off.
+    /// Averaging needs the whole track's spectra, so it analyzes lazily loaded audio up front.
+    /// Already processed audio is re-mapped.
+    #[wasm_bindgen]
+    pub fn set_spectrum_averaging(&mut self, frames: usize) -> Result<(), JsValue> {
+        if !(1..=analysis::MAX_AVERAGED_FRAMES).contains(&frames) {
+            return Err(JsValue::from_str(&format!("Averaged frames must be 1-{}, got {}", analysis::MAX_AVERAGED_FRAMES, frames)));
+        }
+        if frames == self.spectrum_averaging {
+            return Ok(());
+        }
+        self.spectrum_averaging = frames;
+        if self.lazy {
+            // Averaging turns lazy analysis on or off
+            self.reanalyze();
+        } else if self.processed {
+            self.map_to_frequency_bars();
+        }
+        Ok(())
+    }
+
+    /// Median filter each bar over `frames` consecutive frames (an odd number, 1-9) before the
+    /// bars are scaled, so single-frame clicks and pops, common on vinyl rips, no longer flash
+    /// bars to full height. 3 removes one-frame spikes; 1 (the default) turns it off. The filter
+    /// needs the neighbouring frames, so it analyzes lazily loaded audio up front. Already
+    /// processed audio is re-mapped.
+    #[wasm_bindgen]
+    pub fn set_median_filter(&mut self, frames: usize) -> Result<(), JsValue> {
+        if frames.is_multiple_of(2) || frames > analysis::MAX_MEDIAN_FRAMES {
+            return Err(JsValue::from_str(&format!("Median filter width must be odd and 1-{}, got {}", analysis::MAX_MEDIAN_FRAMES, frames)));
+        }
+        if frames == self.median_filter {
+            return Ok(());
+        }
+        self.median_filter = frames;
+        if self.lazy {
+            // Filtering turns lazy analysis on or off
+            self.reanalyze();
+        } else if self.processed {
+            self.map_to_frequency_bars();
+        }
+        Ok(())
+    }
+
+    /// Scale the bars with automatic gain control instead of per-frame percentiles: the overall
+    /// gain follows the loudest bar so its envelope sits at `target` (0.0-1.0), coming down over
+    /// `attack_s` seconds when the level rises and back up over `release_s` when it falls. Quiet
+    /// intros still move and drops keep their shape instead of pinning every bar at 1.0. With
+    /// `enabled` false the percentile scaling is restored and the other arguments are ignored.
+    /// Already processed audio is re-mapped.
+    #[wasm_bindgen]
+    pub fn set_agc(&mut self, enabled: bool, target: f32, attack_s: f32, release_s: f32) -> Result<(), JsValue> {
+        let auto_gain = if enabled {
+            let auto_gain = AutoGain {
+                target,
+                attack_seconds: attack_s,
+                release_seconds: release_s,
+            };
+            auto_gain.validate().map_err(|e| JsValue::from_str(&e))?;
+            Some(auto_gain)
+        } else {
+            None
+        };
+        self.auto_gain = auto_gain;
+        if self.lazy {
+            // Auto gain turns lazy analysis on or off
+            self.reanalyze();
+        } else if self.processed {
+            self.map_to_frequency_bars();
+        }
+        Ok(())
+    }
+
+    /// Analyze the left and right channels of stereo files separately, for the "stereo" mode and
+    /// `get_stereo_bars`. The bars stay those of the left channel, but both channels are scaled
+    /// together so their balance shows. Off by default since it doubles the analysis work and
+    /// memory; mono files are unaffected. Already processed audio is re-analyzed.
+    #[wasm_bindgen]
+    pub fn set_stereo_analysis(&mut self, enabled: bool) {
+        if enabled == self.stereo {
+            return;
+        }
+        self.stereo = enabled;
+        if self.processed {
+            self.compute_spectra();
+            self.compute_transform();
+            self.map_to_frequency_bars();
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn get_bin_size(&self) -> usize {
+        self.bin_size
+    }
+
+    #[wasm_bindgen]
+    pub fn get_frequency_bars(&self, frame_index: usize) -> Vec<f32> {
+        match self.frame_bars(frame_index) {
+            Some(bars) => bars.into_owned(),
+            None => vec![0.0; self.bin_size], // Return empty bars if index out of bounds or no audio processed
+        }
+    }
+
+    /// Bars at a playback position in seconds, linearly interpolated between the frames either
+    /// side of it, for displays running faster than the analysis. Zeros past the last frame.
+    #[wasm_bindgen]
+    pub fn get_frequency_bars_at(&self, playback_seconds: f64) -> Vec<f32> {
+        self.bars_at(playback_seconds).unwrap_or_else(|| vec![0.0; self.bin_size])
+    }
+
+    /// Lower and upper edge in Hz of bar `bar_index`'s frequency range, e.g. for tooltips;
+    /// `undefined` past the last bar. Follows `set_bin_size` and the frequency range, with or
+    /// without a processed file.
+    #[wasm_bindgen]
+    pub fn get_bar_frequency_range(&self, bar_index: usize) -> Option<Vec<f32>> {
+        if bar_index >= self.bin_size {
+            return None;
+        }
+        let (min_freq, max_freq) = self.frequency_range();
+        let boundaries = analysis::frequency_boundaries(min_freq, max_freq, self.bin_size);
+        boundaries.get(bar_index..bar_index + 2).map(<[f32]>::to_vec)
+    }
+
+    /// Left then right channel bars for a frame (`2 * bin_size` values), or `undefined` unless
+    /// stereo analysis is on and the processed file is stereo.
+    #[wasm_bindgen]
+    pub fn get_stereo_bars(&self, frame_index: usize) -> Option<Vec<f32>> {
+        let left = self.frame_bars(frame_index)?;
+        let right = self.frame_right_bars(frame_index)?;
+        Some([left, right].concat())
+    }
+
+    /// A frame's samples as interleaved (left, right) pairs in -1.0..1.0, e.g. for a phase scope;
+    /// mono files repeat their one channel. Empty if the frame doesn't exist.
+    #[wasm_bindgen]
+    pub fn get_scope_samples(&self, frame_index: usize) -> Vec<f32> {
+        self.frame_stereo_samples(frame_index).concat()
+    }
+
+    /// Unscaled FFT magnitudes for a frame, up to the Nyquist bin (512 values, times the
+    /// `set_fft_zero_padding` factor).
+    #[wasm_bindgen]
+    pub fn get_fft_frame(&self, frame_index: usize) -> Vec<f32> {
+        let nyquist_bin = self.fft_size() / 2;
+        match self.fft_frame(frame_index) {
+            Some(magnitudes) => magnitudes[..nyquist_bin.min(magnitudes.len())].to_vec(),
+            None => vec![0.0; nyquist_bin], // Return empty magnitudes if index out of bounds or no audio processed
+        }
+    }
+
+    /// Mel-frequency cepstral coefficients for a frame: 13 values from a 40-band mel filterbank
+    /// over the frame's FFT, for timbre features such as genre classification or similarity.
+    /// Computed on each call from the stored spectrum; empty if the frame doesn't exist.
+    #[wasm_bindgen]
+    pub fn get_mfcc(&self, frame_index: usize) -> Vec<f32> {
+        self.fft_frame(frame_index).map(|fft_frame| mfcc::compute_mfcc(&fft_frame, self.sample_rate, self.fft_size())).unwrap_or_default()
+    }
+
+    /// Pitch-class energies for a frame: 12 values (C, C#, ... B) relative to the strongest
+    /// class, 0.0-1.0.
+    #[wasm_bindgen]
+    pub fn get_chroma(&self, frame_index: usize) -> Vec<f32> {
+        self.frame_chroma(frame_index).unwrap_or_default().to_vec()
+    }
+
+    /// Correlation and width of the left and right channels over a frame's window; zeros if the
+    /// frame doesn't exist.
+    #[wasm_bindgen]
+    pub fn get_stereo_meter(&self, frame_index: usize) -> StereoMeter {
+        self.frame_stereo_meter(frame_index).unwrap_or_default()
+    }
+
+    /// Fundamental frequency in Hz at a frame (YIN, 40 Hz-2 kHz), or `undefined` when the frame is
+    /// quiet, unpitched or doesn't exist. Estimated from the unfiltered (left) channel on each
+    /// call rather than stored, so it costs nothing until asked for.
+    #[wasm_bindgen]
+    pub fn get_pitch(&self, frame_index: usize) -> Option<f32> {
+        self.frame_pitch(frame_index)
+    }
+
+    /// Estimated key of the track (or the analysis region) from its spectra, e.g. to pick a
+    /// palette: minor keys cooler, major ones warmer. `undefined` before a file has been
+    /// processed or when it has no pitched content.
+    #[wasm_bindgen]
+    pub fn get_key(&self) -> Option<KeyEstimate> {
+        if !self.processed {
+            return None;
+        }
+        key::estimate_key(self.all_fft_frames(), self.sample_rate, self.fft_size())
+    }
+
+    /// Sections of the track (or the analysis region) split where the bars change character most,
+    /// such as verse to chorus, found from the novelty of a self-similarity matrix over the bar
+    /// frames. Sections are at least 8 seconds long and cover the whole track; a track too short
+    /// or too uniform to split is one section. Empty before a file has been processed.
+    #[wasm_bindgen]
+    pub fn get_sections(&self) -> Vec<Section> {
+        if !self.processed || self.frame_count == 0 {
+            return Vec::new();
+        }
+        let time_base = self.analysis_time_base();
+        let boundaries = structure::section_boundaries(&self.all_frame_bars(), time_base.frames_per_second());
+        let starts = std::iter::once((0, 0.0)).chain(boundaries.iter().copied());
+        let ends = boundaries.iter().map(|&(frame, _)| frame).chain(std::iter::once(self.frame_count));
+        starts
+            .zip(ends)
+            .map(|((start, strength), end)| Section {
+                start_seconds: time_base.frame_to_seconds(start),
+                end_seconds: time_base.frame_to_seconds(end),
+                strength,
+            })
+            .collect()
+    }
+
+    /// Momentary loudness (LUFS over the preceding 400 ms, EBU R128) at every frame, floored at
+    /// -70 LUFS for silence. Empty before a file has been processed.
+    #[wasm_bindgen]
+    pub fn get_loudness_curve(&self) -> Vec<f32> {
+        if self.processed {
+            self.loudness.clone()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Gated integrated loudness of the whole track in LUFS (EBU R128), or `undefined` before a
+    /// file has been processed or when it is shorter than 400 ms or silent.
+    #[wasm_bindgen]
+    pub fn get_integrated_lufs(&self) -> Option<f32> {
+        self.integrated_lufs.filter(|_| self.processed)
+    }
+
+    /// Loudness, brightness, onset and band-level features for a frame.
+    #[wasm_bindgen]
+    pub fn get_features(&self, frame_index: usize) -> FrameFeatures {
+        let Some(fft_frame) = self.fft_frame(frame_index) else {
+            return FrameFeatures::default();
+        };
+        let previous = frame_index.checked_sub(1).and_then(|index| self.fft_frame(index));
+        let bins = &fft_frame[..(self.fft_size() / 2).min(fft_frame.len())];
+        let bin_hz = self.sample_rate as f32 / self.fft_size() as f32;
+
+        // Parseval over the one-sided spectrum of the windowed frame, zero padding included
+        let power: f32 = bins.iter().enumerate().map(|(i, m)| if i == 0 { m * m } else { 2.0 * m * m }).sum();
+        let rms = (power / (self.fft_size() * analysis::FRAME_SIZE) as f32).sqrt();
+
+        let magnitude_sum: f32 = bins.iter().sum();
+        let spectral_centroid = if magnitude_sum > 0.0 {
+            bins.iter().enumerate().map(|(i, m)| i as f32 * bin_hz * m).sum::<f32>() / magnitude_sum
+        } else {
+            0.0
+        };
+
+        let spectral_flux = match previous {
+            Some(previous) => {
+                let rise: f32 = bins.iter().zip(previous.iter()).map(|(m, p)| (m - p).max(0.0)).sum();
+                rise / bins.len().max(1) as f32
+            }
+            None => 0.0,
+        };
+
+        let peak_bin = bins
+            .iter()
+            .enumerate()
+            .fold((0, 0.0f32), |best, (i, &m)| if m > best.1 { (i, m) } else { best })
+            .0;
+
+        let band_level = |(start, end): (f32, f32)| {
+            let magnitude = analysis::bar_magnitude(&fft_frame, self.sample_rate, self.fft_size(), start, end);
+            ((magnitude_to_dbfs(magnitude) + LEVEL_FLOOR_DB) / LEVEL_FLOOR_DB).clamp(0.0, 1.0)
+        };
+
+        FrameFeatures {
+            rms: rms.min(1.0),
+            spectral_centroid,
+            spectral_flux,
+            peak_frequency: peak_bin as f32 * bin_hz,
+            bass: band_level(BASS_RANGE),
+            mid: band_level(MID_RANGE),
+            treble: band_level(TREBLE_RANGE),
+        }
+    }
+
+    /// Map the analyzed spectra with two bar configurations and report how far the results
+    /// diverge per frame, to gauge the effect of a bar count or frequency range change before
+    /// applying it. The current mapping is left untouched.
+    #[wasm_bindgen]
+    pub fn compare_mappings(&self, a: &MappingConfig, b: &MappingConfig) -> Result<MappingDiff, JsValue> {
+        if !self.processed {
+            return Err(JsValue::from_str("Cannot compare mappings: no audio has been processed"));
+        }
+        let a = a.to_bar_mapping().map_err(|e| JsValue::from_str(&e))?;
+        let b = b.to_bar_mapping().map_err(|e| JsValue::from_str(&e))?;
+        let frames = analysis::compare_mappings(a, b, |boundaries, num_bars| self.map_frames(boundaries, num_bars));
+        Ok(MappingDiff::from_frames(&frames))
+    }
+
+    /// Analyze several WAV files (e.g. a whole playlist) with this analyzer's bar count, frequency
+    /// range and transform, leaving its own audio untouched. Files are processed one at a time,
+    /// yielding to the event loop in between; after each one `progress(index, completed, total)`
+    /// is called, and returning (or resolving to) `false` cancels the rest. Files that fail to
+    /// decode are reported in their result instead of failing the batch.
+    #[wasm_bindgen]
+    pub async fn analyze_batch(
+        &self,
+        files: Vec<js_sys::Uint8Array>,
+        #[wasm_bindgen(unchecked_param_type = "((index: number, completed: number, total: number) => boolean | void | Promise<boolean | void>) | null")]
+        progress: Option<js_sys::Function>,
+    ) -> Result<BatchResult, JsValue> {
+        batch::analyze_batch(self, &files, progress.as_ref()).await
+    }
+
+    /// Analysis frame for a playback position in seconds: the frame starting at or before it.
+    #[wasm_bindgen]
+    pub fn frame_index_at(&self, playback_seconds: f64) -> usize {
+        self.time_base().map_or(0, |time_base| time_base.frame_at(Timestamp(playback_seconds)).0)
+    }
+
+    /// Sample rate, hop size and start of the analysis, for converting between seconds, samples
+    /// and frame indices; `undefined` before a file has been processed.
+    #[wasm_bindgen]
+    pub fn get_time_base(&self) -> Option<TimeBase> {
+        self.time_base()
+    }
+
+    #[wasm_bindgen]
+    pub fn get_total_frames(&self) -> usize {
+        if self.processed {
+            self.frame_count
+        } else {
+            0
+        }
+    }
+
+    /// Min/max waveform columns, interleaved as (min, max) pairs in -1.0..1.0.
+    #[wasm_bindgen]
+    pub fn get_waveform(&self) -> Vec<f32> {
+        self.waveform.clone()
+    }
+
+    /// Min/max peaks of the whole file in `buckets` equal slices, interleaved as (min, max) pairs in
+    /// -1.0..1.0, for drawing a seek bar without handing the samples to JS. Files shorter than
+    /// `buckets` samples get one bucket per sample; empty before a file has been processed.
+    #[wasm_bindgen]
+    pub fn get_waveform_peaks(&self, buckets: usize) -> Vec<f32> {
+        analysis::compute_waveform(&self.samples, buckets)
+    }
+
+    /// RMS level (0.0-1.0) of each of `buckets` equal slices of the whole file; a smoother
+    /// alternative to `get_waveform_peaks`.
+    #[wasm_bindgen]
+    pub fn get_waveform_rms(&self, buckets: usize) -> Vec<f32> {
+        analysis::compute_waveform_rms(&self.samples, buckets)
+    }
+
+    /// Metadata about the processed audio, or `undefined` before a file has been processed.
+    #[wasm_bindgen]
+    pub fn get_audio_info(&self) -> Option<AudioInfo> {
+        if self.processed {
+            self.audio_info
+        } else {
+            None
+        }
+    }
+
+    /// Title/artist/album tags and embedded cover art of the last processed file,
+    /// or `undefined` if the file carried no recognised tags.
+    #[wasm_bindgen]
+    pub fn get_metadata(&self) -> Option<TrackMetadata> {
+        if self.metadata.is_empty() {
+            None
+        } else {
+            Some(self.metadata.clone())
+        }
+    }
+
+    /// Add a named marker at `seconds`, returning its index in time order. Markers belong to the
+    /// loaded track: processing another file clears them.
+    #[wasm_bindgen]
+    pub fn add_marker(&mut self, seconds: f64, label: String) -> Result<usize, JsValue> {
+        self.markers.add(seconds, label).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Remove the marker at `index` (in time order).
+    #[wasm_bindgen]
+    pub fn remove_marker(&mut self, index: usize) -> Result<(), JsValue> {
+        self.markers.remove(index).map(|_| ()).map_err(|e| JsValue::from_str(&e))
+    }
+
+    #[wasm_bindgen]
+    pub fn clear_markers(&mut self) {
+        self.markers.clear();
+    }
+
+    /// All markers in time order.
+    #[wasm_bindgen]
+    pub fn get_markers(&self) -> Vec<Marker> {
+        self.markers.all().to_vec()
+    }
+
+    /// The bars, waveform and markers of the processed audio as an analysis blob (the format
+    /// `analyze_batch` produces).
+    #[wasm_bindgen]
+    pub fn export_analysis(&self) -> Result<Vec<u8>, JsValue> {
+        if !self.processed {
+            return Err(JsValue::from_str("Cannot export analysis: no audio has been processed"));
+        }
+        Ok(batch::analysis_blob(self))
+    }
+
+    /// Replace the markers with those stored in an analysis blob from `export_analysis`, returning
+    /// how many were read. Blobs from before markers were stored carry none.
+    #[wasm_bindgen]
+    pub fn import_markers(&mut self, blob: &[u8]) -> Result<usize, JsValue> {
+        let markers = batch::markers_from_blob(blob).map_err(|e| JsValue::from_str(&e))?;
+        let count = markers.len();
+        self.markers.replace(markers);
+        Ok(count)
+    }
+}
+
+impl AudioAnalyzer {
+    /// Decode and analyze a WAV file, replacing any previous analysis.
+    pub fn process(&mut self, file_data: &[u8]) -> Result<(), String> {
+        self.decode(file_data)?;
+        self.analyze_samples();
+        self.finish_processing();
+        Ok(())
+    }
+
+    /// See `append_audio_file`.
+    pub fn append(&mut self, file_data: &[u8]) -> Result<(), String> {
+        let Some(mut audio) = self.decoded_audio() else {
+            return self.process(file_data);
+        };
+        let appended = analysis::decode_wav(file_data)?;
+        if appended.sample_rate != audio.sample_rate {
+            return Err(format!(
+                "Cannot append audio at {} Hz to audio at {} Hz",
+                appended.sample_rate, audio.sample_rate
+            ));
+        }
+        log!("Appending {} samples at {:.2}s", appended.samples.len(), audio.samples.len() as f64 / audio.sample_rate as f64);
+
+        // Keep the channels the same length, a mono side standing in for its missing right channel
+        match (&mut audio.right, appended.right) {
+            (Some(right), Some(appended_right)) => right.extend(appended_right),
+            (Some(right), None) => right.extend_from_slice(&appended.samples),
+            (None, Some(appended_right)) => {
+                let mut right = audio.samples.clone();
+                right.extend(appended_right);
+                audio.right = Some(right);
+            }
+            (None, None) => {}
+        }
+        let offset = audio.samples.len();
+        audio.samples.extend(appended.samples);
+        audio.channels = audio.channels.max(appended.channels);
+        audio.bits_per_sample = audio.bits_per_sample.max(appended.bits_per_sample);
+
+        let metadata = std::mem::take(&mut self.metadata);
+        let markers = std::mem::take(&mut self.markers);
+        let mut track_starts = std::mem::take(&mut self.track_starts);
+        track_starts.push(offset);
+        self.process_decoded(audio);
+        self.metadata = metadata;
+        self.markers = markers;
+        self.track_starts = track_starts;
+        Ok(())
+    }
+
+    /// `process` for interleaved samples rather than a file; see `process_samples`.
+    pub fn process_pcm(&mut self, samples: &[f32], sample_rate: u32, channels: u32) -> Result<(), String> {
+        let audio = analysis::DecodedAudio::from_interleaved(samples, sample_rate, channels)?;
+        log!("Processing {} samples of {}-channel PCM at {} Hz", samples.len(), channels, sample_rate);
+        self.process_decoded(audio);
+        Ok(())
+    }
+
+    /// `process` for audio that's already decoded, such as `decoded_audio` saved earlier.
+    pub fn process_decoded(&mut self, audio: analysis::DecodedAudio) {
+        self.metadata = TrackMetadata::default();
+        self.load_decoded(audio);
+        self.analyze_samples();
+        self.finish_processing();
+    }
+
+    /// The decoded samples the analysis was computed from, `None` before audio is processed.
+    pub fn decoded_audio(&self) -> Option<analysis::DecodedAudio> {
+        let info = self.audio_info.filter(|_| self.processed)?;
+        Some(analysis::DecodedAudio {
+            samples: self.samples.clone(),
+            right: (!self.right_samples.is_empty()).then(|| self.right_samples.clone()),
+            sample_rate: self.sample_rate,
+            channels: info.channels,
+            bits_per_sample: info.bits_per_sample,
+        })
+    }
+
+    /// `process` in steps, yielding to the event loop between them (in the browser) so the page
+    /// stays responsive, and giving up once `cancelled` returns true. The analysis only replaces
+    /// this one when it completes: a cancelled or failed run leaves the previous track loaded.
+    /// `progress` is called with the fraction of the spectra computed so far after each step.
+    pub async fn process_cancellable(&mut self, file_data: &[u8], cancelled: impl Fn() -> bool, progress: impl Fn(f64)) -> Result<(), String> {
+        let mut analyzer = self.empty_like();
+        analyzer.read_metadata(file_data);
+        let audio = match analysis::decode_wav(file_data) {
+            Err(e) if analyzer.browser_decoding => {
+                log!("Not a WAV file, decoding it with the browser");
+                browser_decode::decode_audio_data(file_data)
+                    .await
+                    .map_err(|browser| format!("{}; {}", e, browser))?
+            }
+            decoded => decoded?,
+        };
+        analyzer.load_decoded(audio);
+        processing_step(&cancelled).await?;
+
+        analyzer.update_analyzed_range();
+        analyzer.lay_out_frames();
+        if !analyzer.is_lazy() {
+            while analyzer.compute_spectra_chunk(PROCESSING_CHUNK_FRAMES) {
+                progress(analyzer.fft_results.len() as f64 / analyzer.frame_count as f64);
+                processing_step(&cancelled).await?;
+            }
+            analyzer.release_preprocessed();
+        }
+        processing_step(&cancelled).await?;
+
+        analyzer.analyze_spectra();
+        processing_step(&cancelled).await?;
+        analyzer.finish_processing();
+        progress(1.0);
+        *self = analyzer;
+        Ok(())
+    }
+
+    // Read the tags and samples of a WAV file, the start of `process`
+    fn decode(&mut self, file_data: &[u8]) -> Result<(), String> {
+        self.read_metadata(file_data);
+        let audio = analysis::decode_wav(file_data)?;
+        self.load_decoded(audio);
+        Ok(())
+    }
+
+    // Pull tags and cover art, before decoding
+    fn read_metadata(&mut self, file_data: &[u8]) {
+        log!("Processing audio file, size: {} bytes", file_data.len());
+        self.metadata = metadata::extract(file_data);
+        if !self.metadata.is_empty() {
+            log!("Metadata: title={:?}, artist={:?}, album={:?}, cover art: {} bytes",
+                 self.metadata.title, self.metadata.artist, self.metadata.album,
+                 self.metadata.cover_art.as_ref().map_or(0, |art| art.len()));
+        }
+    }
+
+    // Take in decoded samples, summarizing them for `audio_info`
+    fn load_decoded(&mut self, audio: analysis::DecodedAudio) {
+        let mono_samples = audio.samples;
+        self.right_samples = audio.right.unwrap_or_default();
+        self.markers.clear();
+        self.track_starts = vec![0];
+
+        // Precompute min/max columns for the waveform strip
+        self.waveform = analysis::compute_waveform(&mono_samples, analysis::WAVEFORM_COLUMNS);
+
+        // The samples are kept so the region, preprocessing and transform can be switched without
+        // decoding again
+        let peak_sample = mono_samples.iter().map(|&s| (s as i32).abs()).max().unwrap_or(0);
+        let sample_count = mono_samples.len();
+        self.samples = mono_samples;
+        self.sample_rate = audio.sample_rate;
+
+        // Summarize the input for get_audio_info; the frames are filled in once analyzed
+        let peak_level = (peak_sample as f32 / i16::MAX as f32).min(1.0);
+        self.audio_info = Some(AudioInfo {
+            duration_seconds: sample_count as f64 / audio.sample_rate as f64,
+            sample_rate: audio.sample_rate,
+            channels: audio.channels,
+            bits_per_sample: audio.bits_per_sample,
+            total_frames: 0,
+            analysis_fps: 0.0,
+            peak_level,
+            peak_db: 20.0 * peak_level.max(1e-6).log10(),
+        });
+    }
+
+    // The end of `process`, once the samples are analyzed
+    fn finish_processing(&mut self) {
+        self.update_frame_info();
+        self.processed = true;
+        log!("Audio processing complete! Ready for visualization.");
+    }
+
+    /// An analyzer without audio that maps bars the same way as this one.
+    pub fn empty_like(&self) -> Self {
+        let mut analyzer = Self::new();
+        analyzer.bin_size = self.bin_size;
+        analyzer.min_freq = self.min_freq;
+        analyzer.max_freq = self.max_freq;
+        analyzer.stereo = self.stereo;
+        analyzer.browser_decoding = self.browser_decoding;
+        analyzer.region = self.region;
+        analyzer.silence_trim = self.silence_trim;
+        analyzer.overlap = self.overlap;
+        analyzer.zero_padding = self.zero_padding;
+        analyzer.lazy = self.lazy;
+        analyzer.bar_precision = self.bar_precision;
+        analyzer.transform = self.transform;
+        analyzer.preprocessing = self.preprocessing;
+        analyzer.weighting = self.weighting;
+        analyzer.noise_floor = self.noise_floor;
+        analyzer.spectrum_averaging = self.spectrum_averaging;
+        analyzer.median_filter = self.median_filter;
+        analyzer.auto_gain = self.auto_gain;
+        analyzer
+    }
+
+    pub fn is_processed(&self) -> bool {
+        self.processed
+    }
+
+    /// Bars for a frame, if audio has been processed and the frame exists; dequantized unless
+    /// stored at full precision.
+    pub fn frame_bars(&self, frame_index: usize) -> Option<Cow<'_, [f32]>> {
+        if self.is_lazy() {
+            return self.lazy_frame(frame_index).map(|frame| Cow::Owned(frame.bars.clone()));
+        }
+        self.frequency_bars.frame(frame_index).filter(|_| self.processed)
+    }
+
+    /// Right channel bars for a frame, if the right channel was analyzed and the frame exists.
+    pub fn frame_right_bars(&self, frame_index: usize) -> Option<Cow<'_, [f32]>> {
+        if self.is_lazy() {
+            let frame = self.lazy_frame(frame_index)?;
+            return (!frame.right_bars.is_empty()).then(|| Cow::Owned(frame.right_bars.clone()));
+        }
+        self.right_bars.frame(frame_index).filter(|_| self.processed)
+    }
+
+    // Whether audio has been processed and the frame exists
+    fn has_frame(&self, frame_index: usize) -> bool {
+        self.processed && frame_index < self.frame_count
+    }
+
+    /// (left, right) sample pairs of a frame's window in -1.0..1.0, before preprocessing; mono
+    /// files repeat their one channel. Empty if audio hasn't been processed or the frame doesn't
+    /// exist.
+    pub fn frame_stereo_samples(&self, frame_index: usize) -> Vec<[f32; 2]> {
+        if !self.has_frame(frame_index) {
+            return Vec::new();
+        }
+        let start = self.analysis_time_base().frame_start(FrameIndex(frame_index)).0;
+        let end = (start + analysis::FRAME_SIZE).min(self.samples.len());
+        let right = if self.right_samples.is_empty() { &self.samples } else { &self.right_samples };
+        let scale = 1.0 / i16::MAX as f32;
+        self.samples[start..end]
+            .iter()
+            .zip(&right[start..end])
+            .map(|(&left, &right)| [left as f32 * scale, right as f32 * scale])
+            .collect()
+    }
+
+    /// Bars at a playback position, interpolated between the frame starting at or before it and
+    /// the next one; `None` if that first frame doesn't exist.
+    pub fn bars_at(&self, playback_seconds: f64) -> Option<Vec<f32>> {
+        let position = self.time_base()?.frame_position(Timestamp(playback_seconds));
+        let frame_index = position.floor() as usize;
+        let current = self.frame_bars(frame_index)?;
+        let Some(next) = self.frame_bars(frame_index + 1) else {
+            return Some(current.into_owned());
+        };
+        let fraction = position.fract() as f32;
+        Some(current.iter().zip(next.iter()).map(|(a, b)| a + (b - a) * fraction).collect())
+    }
+
+    /// Full FFT magnitude frame, if audio has been processed and the frame exists.
+    pub fn fft_frame(&self, frame_index: usize) -> Option<Cow<'_, [f32]>> {
+        if self.is_lazy() {
+            return self.lazy_frame(frame_index).map(|frame| Cow::Owned(frame.fft.clone()));
+        }
+        self.fft_results.get(frame_index).filter(|_| self.processed).map(|fft_frame| Cow::Borrowed(fft_frame.as_slice()))
+    }
+
+    /// Pitch-class energies for a frame, if audio has been processed and the frame exists.
+    pub fn frame_chroma(&self, frame_index: usize) -> Option<[f32; analysis::PITCH_CLASSES]> {
+        if self.is_lazy() {
+            return self.lazy_frame(frame_index).map(|frame| frame.chroma);
+        }
+        self.chroma.get(frame_index).filter(|_| self.processed).copied()
+    }
+
+    // Lazy analysis needs every frame to stand alone; the constant-Q transform, auto gain,
+    // spectrum averaging and the median filter work across frames, so they're analyzed up front
+    fn is_lazy(&self) -> bool {
+        self.lazy && self.transform == Transform::Fft && self.auto_gain.is_none() && self.spectrum_averaging == 1 && self.median_filter == 1
+    }
+
+    // A frame of lazy analysis, computed unless it's cached
+    fn lazy_frame(&self, frame_index: usize) -> Option<Rc<LazyFrame>> {
+        self.has_frame(frame_index).then(|| self.lazy_frames.get_or_insert_with(frame_index, || self.compute_lazy_frame(frame_index)))
+    }
+
+    // Spectrum, chromagram and bars of one frame, the same as the up-front analysis gives
+    fn compute_lazy_frame(&self, frame_index: usize) -> LazyFrame {
+        let fft = analysis::frame_spectrum(self.framed_samples(), frame_index, self.hop_size, self.fft_size());
+        let chroma = analysis::compute_chroma(&fft, self.sample_rate, self.fft_size());
+        let mut raw = self.frame_magnitudes(&fft, &self.freq_boundaries, self.bin_size);
+        // Both channels are scaled together, as in `map_stereo_frames`
+        let right = self.framed_right_samples().map(|right| analysis::frame_spectrum(right, frame_index, self.hop_size, self.fft_size()));
+        if let Some(right) = &right {
+            raw.extend(self.frame_magnitudes(right, &self.freq_boundaries, self.bin_size));
+        }
+        let mut bars = analysis::scale_bars(raw, self.noise_floor);
+        let right_bars = if right.is_some() { bars.split_off(self.bin_size) } else { Vec::new() };
+        LazyFrame { fft, chroma, bars, right_bars }
+    }
+
+    // The samples frames are cut from: preprocessed if preprocessing is on
+    fn framed_samples(&self) -> &[i16] {
+        if self.preprocessed.is_empty() {
+            &self.samples[self.analyzed_range.clone()]
+        } else {
+            &self.preprocessed
+        }
+    }
+
+    fn framed_right_samples(&self) -> Option<&[i16]> {
+        self.right_channel().map(|right| if self.right_preprocessed.is_empty() { right } else { &self.right_preprocessed })
+    }
+
+    // Every frame's FFT in order; lazy analysis computes them without going through the cache
+    fn all_fft_frames(&self) -> impl Iterator<Item = Cow<'_, [f32]>> {
+        (0..self.frame_count).map(|frame_index| match self.fft_results.get(frame_index) {
+            Some(fft_frame) => Cow::Borrowed(fft_frame.as_slice()),
+            None => Cow::Owned(analysis::frame_spectrum(self.framed_samples(), frame_index, self.hop_size, self.fft_size())),
+        })
+    }
+
+    // Every frame's bars; lazy analysis computes them without going through the cache
+    fn all_frame_bars(&self) -> Vec<Vec<f32>> {
+        if self.is_lazy() {
+            (0..self.frame_count).map(|frame_index| self.compute_lazy_frame(frame_index).bars).collect()
+        } else {
+            self.frequency_bars.to_frames()
+        }
+    }
+
+    /// Stereo correlation and width for a frame, if audio has been processed and the frame exists.
+    pub fn frame_stereo_meter(&self, frame_index: usize) -> Option<StereoMeter> {
+        self.stereo_meter.get(frame_index).filter(|_| self.processed).copied()
+    }
+
+    /// Fundamental frequency at a frame, from the samples starting at the frame.
+    pub fn frame_pitch(&self, frame_index: usize) -> Option<f32> {
+        if !self.has_frame(frame_index) {
+            return None;
+        }
+        let start = self.analysis_time_base().frame_start(FrameIndex(frame_index)).0;
+        let span = pitch::PITCH_WINDOW + (self.sample_rate as f32 / pitch::MIN_PITCH_HZ).ceil() as usize + 1;
+        let end = (start + span).min(self.samples.len());
+        pitch::detect_pitch(&self.samples[start..end], self.sample_rate)
+    }
+
+    /// Edge frequencies of the current bars (`bin_size + 1` values once processed).
+    pub fn freq_boundaries(&self) -> &[f32] {
+        &self.freq_boundaries
+    }
+
+    pub fn markers(&self) -> &Markers {
+        &self.markers
+    }
+
+    pub fn set_markers(&mut self, markers: Vec<Marker>) {
+        self.markers.replace(markers);
+    }
+
+    pub fn waveform(&self) -> &[f32] {
+        &self.waveform
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn audio_info(&self) -> Option<AudioInfo> {
+        self.audio_info.filter(|_| self.processed)
+    }
+
+    pub fn time_base(&self) -> Option<TimeBase> {
+        self.processed.then(|| self.analysis_time_base())
+    }
+
+    fn analysis_time_base(&self) -> TimeBase {
+        TimeBase::new(self.sample_rate, self.hop_size, self.analyzed_range.start)
+    }
+
+    pub fn hop_size(&self) -> usize {
+        self.hop_size
+    }
+
+    /// Points in the FFT of each frame, zero padding included.
+    pub fn fft_size(&self) -> usize {
+        analysis::FRAME_SIZE * self.zero_padding
+    }
+
+    /// Bytes held by the samples and the cached spectra, bars, chromagram, loudness and waveform.
+    pub fn memory_estimate(&self) -> usize {
+        let nested = |frames: &[Vec<f32>]| frames.iter().map(Vec::len).sum::<usize>();
+        let floats: usize = nested(&self.fft_results)
+            + nested(&self.cqt_results)
+            + nested(&self.right_fft_results)
+            + nested(&self.right_cqt_results)
+            + self.chroma.len() * analysis::PITCH_CLASSES
+            + self.stereo_meter.len() * 2
+            + self.loudness.len()
+            + self.waveform.len()
+            + self.lazy_frames.len() * (self.fft_size() + 2 * self.bin_size + analysis::PITCH_CLASSES);
+        floats * std::mem::size_of::<f32>()
+            + self.frequency_bars.memory_bytes()
+            + self.right_bars.memory_bytes()
+            + (self.samples.len() + self.right_samples.len() + self.preprocessed.len() + self.right_preprocessed.len()) * std::mem::size_of::<i16>()
+    }
+
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    /// Switch the transform the bars are mapped from, re-analyzing processed audio.
+    pub fn apply_transform(&mut self, transform: Transform) {
+        if transform == self.transform {
+            return;
+        }
+        self.transform = transform;
+        if self.lazy {
+            // The constant-Q transform turns lazy analysis on or off
+            self.reanalyze();
+        } else if self.processed {
+            self.compute_transform();
+            self.update_weighting_gains();
+            self.map_to_frequency_bars();
+        }
+    }
+
+    pub fn weighting(&self) -> Weighting {
+        self.weighting
+    }
+
+    /// Switch the frequency weighting applied before bar mapping, re-mapping processed audio.
+    pub fn apply_weighting(&mut self, weighting: Weighting) {
+        if weighting == self.weighting {
+            return;
+        }
+        self.weighting = weighting;
+        if self.processed {
+            self.update_weighting_gains();
+            self.map_to_frequency_bars();
+        }
+    }
+
+    fn update_weighting_gains(&mut self) {
+        if self.weighting == Weighting::None {
+            self.weighting_gains = Vec::new();
+            return;
+        }
+        let bin_hz = self.sample_rate as f32 / self.fft_size() as f32;
+        self.weighting_gains = match self.transform {
+            Transform::Fft => (0..=self.fft_size() / 2).map(|bin| self.weighting.gain(bin as f32 * bin_hz)).collect(),
+            Transform::Cqt { .. } => self.cqt_frequencies.iter().map(|&freq| self.weighting.gain(freq)).collect(),
+        };
+    }
+
+    // Samples inside the analysis region, less leading and trailing silence if trimming is on
+    fn update_analyzed_range(&mut self) {
+        let length = self.samples.len();
+        let to_sample = |seconds: f64| ((seconds * self.sample_rate as f64).round().max(0.0) as usize).min(length);
+        let region = match self.region {
+            Some((start, end)) => to_sample(start)..if end.is_finite() { to_sample(end) } else { length },
+            None => 0..length,
+        };
+        self.analyzed_range = match self.silence_trim {
+            Some(threshold_db) => {
+                let audible = analysis::audible_range(&self.samples[region.clone()], threshold_db);
+                region.start + audible.start..region.start + audible.end
+            }
+            None => region,
+        };
+        log!("Analyzing samples {}..{} of {}", self.analyzed_range.start, self.analyzed_range.end, length);
+    }
+
+    // Everything derived from the samples: spectra, loudness and bars
+    fn analyze_samples(&mut self) {
+        self.update_analyzed_range();
+        self.compute_spectra();
+        self.analyze_spectra();
+    }
+
+    // Everything derived once the frames are laid out (and their spectra computed, unless the
+    // analysis is lazy): loudness, stereo image and bars
+    fn analyze_spectra(&mut self) {
+        // Momentary loudness at each frame's center, and the whole track's integrated loudness,
+        // over both channels of stereo audio
+        let energy = if self.right_samples.is_empty() {
+            KWeightedEnergy::new(&[&self.samples], self.sample_rate)
+        } else {
+            KWeightedEnergy::new(&[&self.samples, &self.right_samples], self.sample_rate)
+        };
+        let time_base = self.analysis_time_base();
+        self.loudness = (0..self.frame_count)
+            .map(|i| energy.momentary(time_base.frame_start(FrameIndex(i)).0 + analysis::FRAME_SIZE / 2))
+            .collect();
+        self.integrated_lufs = energy.integrated();
+
+        // Stereo image over each frame's window, from the unfiltered channels
+        let right = if self.right_samples.is_empty() { &self.samples } else { &self.right_samples };
+        self.stereo_meter = (0..self.frame_count)
+            .map(|i| {
+                let start = time_base.frame_start(FrameIndex(i)).0;
+                let window = start..(start + analysis::FRAME_SIZE).min(self.samples.len());
+                let (correlation, width) = analysis::stereo_correlation(&self.samples[window.clone()], &right[window]);
+                StereoMeter { correlation, width }
+            })
+            .collect();
+
+        self.compute_transform();
+        self.update_weighting_gains();
+
+        // Map FFT results to frequency bars
+        self.map_to_frequency_bars();
+    }
+
+    // Re-run the analysis of processed audio after the region or trim changed
+    fn reanalyze(&mut self) {
+        if !self.processed {
+            return;
+        }
+        self.analyze_samples();
+        self.update_frame_info();
+    }
+
+    fn update_frame_info(&mut self) {
+        let (total_frames, analysis_fps) = (self.frame_count, self.analysis_time_base().frames_per_second());
+        if let Some(info) = &mut self.audio_info {
+            info.total_frames = total_frames;
+            info.analysis_fps = analysis_fps;
+        }
+    }
+
+    // Frame, window and FFT the preprocessed samples, and fold the spectra into pitch classes;
+    // unlike the bars the chromagram doesn't depend on the mapping. Lazy analysis only lays the
+    // frames out.
+    fn compute_spectra(&mut self) {
+        self.lay_out_frames();
+        if !self.is_lazy() {
+            self.compute_spectra_chunk(self.frame_count);
+            self.release_preprocessed();
+        }
+    }
+
+    // Count the frames and keep the preprocessed samples to cut them from, dropping any spectra
+    fn lay_out_frames(&mut self) {
+        self.lazy_frames.clear();
+        let samples = self.preprocessing.apply(&self.samples[self.analyzed_range.clone()], self.sample_rate);
+        (self.frame_count, self.hop_size) = analysis::frame_layout(samples.len(), self.sample_rate, self.overlap.map(analysis::overlap_hop_size));
+        log!("Framing {} samples into {} frames (hop size: {})", samples.len(), self.frame_count, self.hop_size);
+        self.preprocessed = match samples {
+            Cow::Owned(samples) => samples,
+            Cow::Borrowed(_) => Vec::new(),
+        };
+        self.right_preprocessed = match self.right_channel().map(|right| self.preprocessing.apply(right, self.sample_rate)) {
+            Some(Cow::Owned(right)) => right,
+            _ => Vec::new(),
+        };
+        self.fft_results = Vec::new();
+        self.right_fft_results = Vec::new();
+        self.chroma = Vec::new();
+    }
+
+    // Spectra and chromagram of up to `count` more frames, continuing where the last call stopped;
+    // returns whether frames remain
+    fn compute_spectra_chunk(&mut self, count: usize) -> bool {
+        let frames = self.fft_results.len()..(self.fft_results.len() + count).min(self.frame_count);
+        let (sample_rate, fft_size) = (self.sample_rate, self.fft_size());
+        let spectrum = |samples: &[i16], frame_index| analysis::frame_spectrum(samples, frame_index, self.hop_size, fft_size);
+        let spectra: Vec<Vec<f32>> = frames.clone().map(|frame_index| spectrum(self.framed_samples(), frame_index)).collect();
+        let right_spectra: Vec<Vec<f32>> = match self.framed_right_samples() {
+            Some(right) => frames.map(|frame_index| spectrum(right, frame_index)).collect(),
+            None => Vec::new(),
+        };
+        self.chroma.extend(spectra.iter().map(|fft_frame| analysis::compute_chroma(fft_frame, sample_rate, fft_size)));
+        self.fft_results.extend(spectra);
+        self.right_fft_results.extend(right_spectra);
+        self.fft_results.len() < self.frame_count
+    }
+
+    // The preprocessed samples are only kept for lazy analysis once the spectra are computed
+    fn release_preprocessed(&mut self) {
+        self.preprocessed = Vec::new();
+        self.right_preprocessed = Vec::new();
+    }
+
+    // Analyzed part of the right channel, when it is analyzed at all
+    fn right_channel(&self) -> Option<&[i16]> {
+        (self.stereo && !self.right_samples.is_empty()).then(|| &self.right_samples[self.analyzed_range.clone()])
+    }
+
+    // Constant-Q spectra for the current transform; the FFT spectra are always kept since the
+    // features, chromagram and hit testing read them
+    fn compute_transform(&mut self) {
+        match self.transform {
+            Transform::Fft => {
+                self.cqt_frequencies = Vec::new();
+                self.cqt_results = Vec::new();
+                self.right_cqt_results = Vec::new();
+            }
+            Transform::Cqt { bins_per_octave } => {
+                self.cqt_frequencies = analysis::cqt_frequencies(self.sample_rate, bins_per_octave);
+                let cqt = |samples: &[i16]| {
+                    let samples = self.preprocessing.apply(samples, self.sample_rate);
+                    analysis::compute_cqt(&samples, self.hop_size, self.fft_results.len(), self.sample_rate, bins_per_octave)
+                };
+                let cqt_results = cqt(&self.samples[self.analyzed_range.clone()]);
+                let right_cqt_results = self.right_channel().map(cqt).unwrap_or_default();
+                self.cqt_results = cqt_results;
+                self.right_cqt_results = right_cqt_results;
+            }
+        }
+    }
+
+    // Spectra the bars are mapped from, the raw bar magnitudes of one of them, and the mapping of
+    // all of them to display values
+    fn bar_spectra(&self) -> &[Vec<f32>] {
+        match self.transform {
+            Transform::Fft => &self.fft_results,
+            Transform::Cqt { .. } => &self.cqt_results,
+        }
+    }
+
+    fn right_bar_spectra(&self) -> &[Vec<f32>] {
+        match self.transform {
+            Transform::Fft => &self.right_fft_results,
+            Transform::Cqt { .. } => &self.right_cqt_results,
+        }
+    }
+
+    fn frame_magnitudes(&self, spectrum: &[f32], freq_boundaries: &[f32], num_bars: usize) -> Vec<f32> {
+        let weighted;
+        let spectrum = if self.weighting_gains.is_empty() {
+            spectrum
+        } else {
+            weighted = analysis::apply_weighting(spectrum, &self.weighting_gains);
+            &weighted
+        };
+        match self.transform {
+            Transform::Fft => analysis::fft_bar_magnitudes(spectrum, self.sample_rate, self.fft_size(), freq_boundaries, num_bars),
+            Transform::Cqt { .. } => analysis::cqt_bar_magnitudes(spectrum, &self.cqt_frequencies, freq_boundaries, num_bars),
+        }
+    }
+
+    fn map_frames(&self, freq_boundaries: &[f32], num_bars: usize) -> Vec<Vec<f32>> {
+        if self.is_lazy() {
+            let raw_frames = self.all_fft_frames().map(|spectrum| self.frame_magnitudes(&spectrum, freq_boundaries, num_bars));
+            return self.scale_frames(raw_frames);
+        }
+        let raw_frames = self.averaged_bar_spectra(self.bar_spectra()).map(|spectrum| self.frame_magnitudes(&spectrum, freq_boundaries, num_bars));
+        self.scale_frames(raw_frames)
+    }
+
+    // Both channels' raw magnitudes go through the scaling side by side in one frame, so a sound
+    // panned left stays taller on the left
+    fn map_stereo_frames(&self, freq_boundaries: &[f32], num_bars: usize) -> (Vec<Vec<f32>>, Vec<Vec<f32>>) {
+        let right_spectra = self.averaged_bar_spectra(self.right_bar_spectra());
+        let raw_frames = self.averaged_bar_spectra(self.bar_spectra()).zip(right_spectra).map(|(left, right)| {
+            let mut raw = self.frame_magnitudes(&left, freq_boundaries, num_bars);
+            raw.extend(self.frame_magnitudes(&right, freq_boundaries, num_bars));
+            raw
+        });
+        self.scale_frames(raw_frames)
+            .into_iter()
+            .map(|mut bars| {
+                let right = bars.split_off(num_bars);
+                (bars, right)
+            })
+            .unzip()
+    }
+
+    // Spectra as they are mapped to bars: averaged when spectrum averaging is on
+    fn averaged_bar_spectra<'a>(&self, spectra: &'a [Vec<f32>]) -> Box<dyn Iterator<Item = Cow<'a, [f32]>> + 'a> {
+        match self.spectrum_averaging {
+            1 => Box::new(spectra.iter().map(|spectrum| Cow::Borrowed(spectrum.as_slice()))),
+            frames => Box::new(analysis::averaged_spectra(spectra, frames).map(Cow::Owned)),
+        }
+    }
+
+    fn scale_frames(&self, raw_frames: impl Iterator<Item = Vec<f32>>) -> Vec<Vec<f32>> {
+        match self.median_filter {
+            1 => self.scale_raw_frames(raw_frames),
+            width => self.scale_raw_frames(analysis::median_filter_frames(raw_frames.collect(), width).into_iter()),
+        }
+    }
+
+    fn scale_raw_frames(&self, raw_frames: impl Iterator<Item = Vec<f32>>) -> Vec<Vec<f32>> {
+        match self.auto_gain {
+            None => raw_frames.map(|raw| analysis::scale_bars(raw, self.noise_floor)).collect(),
+            Some(auto_gain) => {
+                let mut frames: Vec<Vec<f32>> = raw_frames.map(|raw| analysis::gate_magnitudes(raw, self.noise_floor)).collect();
+                auto_gain.apply(&mut frames, self.analysis_time_base().frames_per_second());
+                frames
+            }
+        }
+    }
+
+    /// The range the bars are spread over: the one set, with its top limited to the Nyquist
+    /// frequency of the loaded audio, since nothing above it is in the spectra.
+    pub fn frequency_range(&self) -> (f32, f32) {
+        analysis::nyquist_clamped_range(self.min_freq, self.max_freq, self.sample_rate)
+    }
+
+    /// The range last set, before limiting it to the loaded audio; kept in settings so it
+    /// applies in full to audio at higher sample rates.
+    pub fn configured_frequency_range(&self) -> (f32, f32) {
+        (self.min_freq, self.max_freq)
+    }
+
+    /// Change the displayed frequency range and re-map the cached FFT results onto the bars.
+    /// Invalid ranges are ignored.
+    pub fn set_frequency_range(&mut self, min_freq: f32, max_freq: f32) {
+        if !(min_freq > 0.0 && max_freq > min_freq) {
+            return;
+        }
+        self.min_freq = min_freq;
+        self.max_freq = max_freq;
+
+        if self.processed {
+            self.map_to_frequency_bars();
+        }
+    }
+
+    fn map_to_frequency_bars(&mut self) {
+        let num_bars = self.bin_size;
+        let (min_freq, max_freq) = self.frequency_range();
+        let perceptual = analysis::is_perceptual_range(min_freq, max_freq);
+
+        log!("Mapping FFT results to {} logarithmic frequency bars", num_bars);
+        log!("Frequency range: {:.1} Hz to {:.1} Hz", min_freq, max_freq);
+
+        // Generate logarithmic frequency boundaries
+        let freq_boundaries = analysis::frequency_boundaries(min_freq, max_freq, num_bars);
+
+        // Log some frequency ranges for debugging (perceptual distribution)
+        log!("Perceptual frequency distribution:");
+        match num_bars {
+            64 if perceptual => {
+                log!("  Bins 0-3: Sub-bass (20-100 Hz)");
+                log!("  Bins 4-23: Bass (100-500 Hz)");
+                log!("  Bins 24-47: Mid-range (500-4000 Hz)");
+                log!("  Bins 48-63: High frequencies (4000-20000 Hz)");
+            }
+            32 if perceptual => {
+                log!("  Bins 0-1: Sub-bass (20-100 Hz)");
+                log!("  Bins 2-11: Bass (100-500 Hz)");
+                log!("  Bins 12-23: Mid-range (500-4000 Hz)");
+                log!("  Bins 24-31: High frequencies (4000-20000 Hz)");
+            }
+            16 if perceptual => {
+                log!("  Bin 0: Sub-bass (20-100 Hz)");
+                log!("  Bins 1-5: Bass (100-500 Hz)");
+                log!("  Bins 6-11: Mid-range (500-4000 Hz)");
+                log!("  Bins 12-15: High frequencies (4000-20000 Hz)");
+            }
+            _ => {
+                log!("  Using logarithmic distribution");
+            }
+        }
+        for i in 0..5.min(num_bars) {
+            log!("  Bar {}: {:.1} Hz - {:.1} Hz", i, freq_boundaries[i], freq_boundaries[i + 1]);
+        }
+
+        if self.is_lazy() {
+            // Frames are mapped as they're read
+            self.lazy_frames.clear();
+            self.frequency_bars = BarFrames::default();
+            self.right_bars = BarFrames::default();
+            self.freq_boundaries = freq_boundaries;
+            return;
+        }
+
+        // Map each spectrum to frequency bars
+        if self.right_bar_spectra().is_empty() {
+            self.frequency_bars = BarFrames::new(self.map_frames(&freq_boundaries, num_bars), self.bar_precision);
+            self.right_bars = BarFrames::default();
+        } else {
+            let (bars, right_bars) = self.map_stereo_frames(&freq_boundaries, num_bars);
+            self.frequency_bars = BarFrames::new(bars, self.bar_precision);
+            self.right_bars = BarFrames::new(right_bars, self.bar_precision);
+        }
+
+        // Log first frame for debugging
+        if let Some(first) = self.frequency_bars.frame(0) {
+            let log_end = (10).min(first.len());
+            log!("First frame frequency bars (first {}): {:?}", log_end, &first[..log_end]);
+
+            // Find peak bar
+            let max_bar = first.iter().fold(0.0f32, |a, &b| a.max(b));
+            let max_bar_idx = first.iter().position(|&x| x == max_bar).unwrap_or(0);
+            if max_bar_idx < freq_boundaries.len() - 1 {
+                log!("Peak bar: {} (freq range: {:.1} Hz - {:.1} Hz), magnitude: {:.2}",
+                     max_bar_idx, freq_boundaries[max_bar_idx], freq_boundaries[max_bar_idx + 1], max_bar);
+            }
+        }
+
+        log!("Frequency bar mapping complete. Generated {} bar frames", self.frequency_bars.len());
+
+        // Keep the boundaries around for hit testing
+        self.freq_boundaries = freq_boundaries;
+    }
+}
+
+// Between the steps of `process_cancellable`: let the browser run, then stop if cancelled
+async fn processing_step(cancelled: &impl Fn() -> bool) -> Result<(), String> {
+    #[cfg(target_arch = "wasm32")]
+    crate::timing::yield_to_event_loop().await?;
+    if cancelled() {
+        return Err("Processing cancelled".to_string());
+    }
+    Ok(())
+}
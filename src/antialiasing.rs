@@ -0,0 +1,194 @@
+use wasm_bindgen::prelude::*;
+use wgpu::*;
+
+/// Edge smoothing applied to the finished frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Antialiasing {
+    #[default]
+    Off,
+    /// A post pass smoothing luma edges, available on every backend including WebGL
+    Fxaa,
+}
+
+impl Antialiasing {
+    pub fn parse(name: &str) -> Result<Self, JsValue> {
+        match name {
+            "off" => Ok(Antialiasing::Off),
+            "fxaa" => Ok(Antialiasing::Fxaa),
+            _ => Err(JsValue::from_str(&format!("Unknown antialiasing '{}', expected 'off' or 'fxaa'", name))),
+        }
+    }
+
+    /// Name accepted by `parse`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Antialiasing::Off => "off",
+            Antialiasing::Fxaa => "fxaa",
+        }
+    }
+}
+
+/// FXAA post pass: the frame is drawn into an intermediate texture, then copied onto the target
+/// with its edges smoothed.
+pub struct Fxaa {
+    pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    sampler: Sampler,
+    format: TextureFormat,
+    target: Option<(Texture, TextureView, BindGroup)>,
+}
+
+impl Fxaa {
+    pub fn new(device: &Device, format: TextureFormat) -> Self {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("FXAA Shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/fxaa.wgsl").into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("FXAA Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("FXAA Sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("FXAA Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("FXAA Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: Some(Face::Back),
+                polygon_mode: PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            sampler,
+            format,
+            target: None,
+        }
+    }
+
+    /// Make sure the intermediate texture matches the size about to be drawn.
+    pub fn prepare(&mut self, device: &Device, width: u32, height: u32) {
+        let reusable = self
+            .target
+            .as_ref()
+            .is_some_and(|(texture, _, _)| texture.width() == width && texture.height() == height);
+        if reusable {
+            return;
+        }
+
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("FXAA Texture"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: self.format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor {
+            label: Some("FXAA Texture View"),
+            ..Default::default()
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("FXAA Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+        self.target = Some((texture, view, bind_group));
+    }
+
+    /// Drop the intermediate texture while antialiasing is off.
+    pub fn release(&mut self) {
+        self.target = None;
+    }
+
+    /// View the frame is drawn into, once `prepare` has run.
+    pub fn view(&self) -> Option<&TextureView> {
+        self.target.as_ref().map(|(_, view, _)| view)
+    }
+
+    /// Copy the smoothed frame into `render_pass`.
+    pub fn resolve(&self, render_pass: &mut RenderPass) {
+        let Some((_, _, bind_group)) = &self.target else {
+            return;
+        };
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,19 @@
+use wasm_bindgen::prelude::*;
+
+/// Summary of the loaded audio and its analysis, available after processing.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct AudioInfo {
+    pub duration_seconds: f64,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+    /// Number of analysis frames (same as `get_total_frames`)
+    pub total_frames: usize,
+    /// Analysis frames per second of audio (sample rate / hop size)
+    pub analysis_fps: f64,
+    /// Peak absolute sample value of the analysed channel, 0.0-1.0
+    pub peak_level: f32,
+    /// Peak level in dBFS
+    pub peak_db: f32,
+}
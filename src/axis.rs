@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::layout::{BarArrangement, BarStyle, Orientation};
+use crate::locale::Locale;
+use crate::text::{self, TextAlign, TextItem};
+
+// TypeScript shape of the options object taken by `App.set_axis`
+#[wasm_bindgen(typescript_custom_section)]
+const AXIS_OPTIONS_TS: &'static str = r#"
+export interface AxisOptions {
+    frequency_labels?: boolean;
+    grid?: boolean;
+    db_step?: number;
+    opacity?: number;
+}
+"#;
+
+/// Modes drawn with `barsLayer` in shaders/common.wgsl, whose layout the axis follows
+pub const AXIS_MODES: [&str; 2] = ["bars", "waterfall"];
+/// Gridlines drawn, the first at the height of a full bar; `AXIS_GRID_LINES` in common.wgsl
+pub const GRID_LINES: u32 = 5;
+// Frequencies labelled along the axis, where they fall inside the displayed range
+const TICK_FREQUENCIES: [f32; 4] = [10.0, 100.0, 1000.0, 10000.0];
+// Height of a full bar in bar space, as `max_height` in `barsLayer`
+const MAX_BAR_HEIGHT: f32 = 0.8;
+const LABEL_COLOR: [f32; 4] = [0.8, 0.8, 0.85, 0.8];
+
+/// Options of `App.set_axis`; fields left out take the defaults.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AxisOptions {
+    /// Label 10 Hz, 100 Hz, 1 kHz and 10 kHz along the bars
+    pub frequency_labels: bool,
+    /// Draw labelled gridlines under the bars
+    pub grid: bool,
+    /// dB between gridlines, measured down from the height of a full bar
+    pub db_step: f32,
+    /// Gridline brightness, 0.0-1.0
+    pub opacity: f32,
+}
+
+impl Default for AxisOptions {
+    fn default() -> Self {
+        Self {
+            frequency_labels: true,
+            grid: true,
+            db_step: 12.0,
+            opacity: 0.25,
+        }
+    }
+}
+
+impl AxisOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.db_step > 0.0 && self.db_step <= 60.0) {
+            return Err(format!("Axis dB step must be within (0, 60], got {}", self.db_step));
+        }
+        if !(0.0..=1.0).contains(&self.opacity) {
+            return Err(format!("Axis opacity must be within 0-1, got {}", self.opacity));
+        }
+        Ok(())
+    }
+}
+
+/// Bar layout the axis labels are placed against.
+pub struct AxisLayout {
+    pub orientation: Orientation,
+    pub arrangement: BarArrangement,
+    pub reversed: bool,
+    pub style: BarStyle,
+}
+
+/// Labels for the axis on a `width` x `height` canvas: frequencies along the bars, at the
+/// position in the bar each falls in (`boundaries` being the bars' edge frequencies), and the
+/// level of each gridline beside it.
+pub fn compose_labels(options: &AxisOptions, layout: &AxisLayout, boundaries: &[f32], width: u32, height: u32, locale: Locale) -> Vec<TextItem> {
+    let (width, height) = (width as f32, height as f32);
+    let scale = (crate::overlay::overlay_scale(height as u32) - 1.0).max(1.0);
+    let margin = text::line_height(scale);
+    let mut items = Vec::new();
+
+    if options.frequency_labels {
+        for hz in TICK_FREQUENCIES {
+            for spread in frequency_spread(hz, boundaries, layout) {
+                let label = locale.format_frequency(hz);
+                let item = match layout.orientation {
+                    Orientation::Vertical => TextItem::new(label, spread * width, height - margin * 2.0, scale, TextAlign::Center),
+                    Orientation::Horizontal => TextItem::new(label, margin, spread * height - margin * 0.5, scale, TextAlign::Left),
+                };
+                items.push(item.with_color(LABEL_COLOR));
+            }
+        }
+    }
+
+    if options.grid {
+        for line in 0..GRID_LINES {
+            let db = -(line as f32) * options.db_step;
+            let label = format!("{} dB", locale.format_number(db as f64, 0));
+            for growth in grid_growth(line, options.db_step, layout) {
+                let item = match layout.orientation {
+                    Orientation::Vertical => TextItem::new(label.clone(), width - margin, (1.0 - growth) * height - margin, scale, TextAlign::Right),
+                    Orientation::Horizontal => TextItem::new(label.clone(), growth * width, height - margin * 2.0, scale, TextAlign::Center),
+                };
+                items.push(item.with_color(LABEL_COLOR));
+            }
+        }
+    }
+    items
+}
+
+// Where `hz` is drawn across the bars (0.0-1.0 of the spread axis), twice for mirrored bars;
+// nothing outside the displayed range
+fn frequency_spread(hz: f32, boundaries: &[f32], layout: &AxisLayout) -> Vec<f32> {
+    let bin_size = boundaries.len().saturating_sub(1);
+    let Some(bar) = boundaries.windows(2).position(|edges| edges[0] <= hz && hz < edges[1]) else {
+        return Vec::new();
+    };
+    let (low, high) = (boundaries[bar], boundaries[bar + 1]);
+    let within = (hz / low).ln() / (high / low).ln();
+    // Bars are centered on their slot, so their range starts half a slot before it
+    let slot = if layout.reversed {
+        (bin_size - 1 - bar) as f32 + 0.5 - within
+    } else {
+        bar as f32 - 0.5 + within
+    };
+    match layout.arrangement {
+        BarArrangement::Mirrored => {
+            let offset = (slot + 0.5) / bin_size as f32 * 0.5;
+            vec![0.5 - offset, 0.5 + offset]
+        }
+        _ => vec![slot / bin_size as f32],
+    }
+}
+
+// Height of gridline `line` above the edge the bars grow from (0.0-1.0 of the growth axis),
+// matching `axisGrid` in common.wgsl; twice for dual-sided bars
+fn grid_growth(line: u32, db_step: f32, layout: &AxisLayout) -> Vec<f32> {
+    let level = 10f32.powf(-(line as f32) * db_step / 20.0);
+    let bar_height = layout.style.min_height + level * (MAX_BAR_HEIGHT - layout.style.min_height);
+    match layout.arrangement {
+        BarArrangement::BottomUp | BarArrangement::Mirrored => vec![bar_height],
+        BarArrangement::TopDown => vec![1.0 - bar_height],
+        BarArrangement::DualSided => vec![0.5 + bar_height * 0.5, 0.5 - bar_height * 0.5],
+    }
+}
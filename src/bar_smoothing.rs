@@ -0,0 +1,226 @@
+use wgpu::*;
+
+// Bars held per row of the state texture, as in the uniforms
+const MAX_BARS: usize = 64;
+// Row 0 holds the mono (or left) bars, row 1 the right channel's
+const ROWS: u32 = 2;
+// Packed as two 8-bit halves (see bar_smoothing.wgsl): 8-bit formats render everywhere, WebGL
+// included, where float targets need extensions
+const FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+
+/// Temporal smoothing of the bars on the GPU. The smoothed levels live in a small texture the
+/// visualizer shaders read; each frame a pass eases them towards the raw target bars, so the CPU
+/// only uploads the targets and keeps no smoothing state of its own.
+pub struct BarSmoothing {
+    pipeline: RenderPipeline,
+    params_buffer: Buffer,
+    // Smoothed levels, read by the visualizers and by the next smoothing pass
+    state: Texture,
+    state_view: TextureView,
+    // Written by the smoothing pass and copied into `state`, since a pass can't read its target
+    scratch: Texture,
+    scratch_view: TextureView,
+    bind_group: BindGroup,
+}
+
+impl BarSmoothing {
+    pub fn new(device: &Device, queue: &Queue) -> Self {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Bar Smoothing Shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/bar_smoothing.wgsl").into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Bar Smoothing Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        // 64 target bars, 64 target right channel bars, [smoothing factor, padding...]
+        let params_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Bar Smoothing Params Buffer"),
+            size: (2 * MAX_BARS as u64 + 4) * 4,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let create_texture = |label, usage| {
+            let texture = device.create_texture(&TextureDescriptor {
+                label: Some(label),
+                size: Extent3d {
+                    width: MAX_BARS as u32,
+                    height: ROWS,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: FORMAT,
+                usage,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&TextureViewDescriptor {
+                label: Some(label),
+                ..Default::default()
+            });
+            (texture, view)
+        };
+        let (state, state_view) = create_texture("Bar Smoothing State Texture", TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST);
+        let (scratch, scratch_view) = create_texture("Bar Smoothing Scratch Texture", TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC);
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Bar Smoothing Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&state_view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Bar Smoothing Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Bar Smoothing Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(ColorTargetState {
+                    format: FORMAT,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let smoothing = Self {
+            pipeline,
+            params_buffer,
+            state,
+            state_view,
+            scratch,
+            scratch_view,
+            bind_group,
+        };
+        smoothing.reset(queue, &[], &[]);
+        smoothing
+    }
+
+    /// Smoothed levels for the visualizer shaders: 64x2 texels, mono (or left) bars in row 0 and
+    /// right channel bars in row 1, packed as described in bar_smoothing.wgsl.
+    pub fn view(&self) -> &TextureView {
+        &self.state_view
+    }
+
+    /// Set the smoothed levels directly, e.g. to start an export from silence; missing bars are 0.
+    pub fn reset(&self, queue: &Queue, bars: &[f32], right_bars: &[f32]) {
+        let mut texels = vec![0u8; MAX_BARS * ROWS as usize * 4];
+        for (row, levels) in [bars, right_bars].into_iter().enumerate() {
+            for (index, &level) in levels.iter().take(MAX_BARS).enumerate() {
+                let scaled = level.clamp(0.0, 1.0) * 255.0;
+                let texel = (row * MAX_BARS + index) * 4;
+                texels[texel] = scaled.floor() as u8;
+                texels[texel + 1] = (scaled.fract() * 255.0).round() as u8;
+                texels[texel + 3] = 255;
+            }
+        }
+        queue.write_texture(
+            TexelCopyTextureInfo {
+                texture: &self.state,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            &texels,
+            TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(MAX_BARS as u32 * 4),
+                rows_per_image: Some(ROWS),
+            },
+            Extent3d {
+                width: MAX_BARS as u32,
+                height: ROWS,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Ease the smoothed levels towards this frame's targets by `factor` (0.0-1.0, already
+    /// adjusted for the frame rate).
+    pub fn advance(&self, device: &Device, queue: &Queue, bars: &[f32], right_bars: &[f32], factor: f32) {
+        let mut params = vec![0.0f32; 2 * MAX_BARS + 4];
+        for (slot, &bar) in params[..MAX_BARS].iter_mut().zip(bars) {
+            *slot = bar;
+        }
+        for (slot, &bar) in params[MAX_BARS..2 * MAX_BARS].iter_mut().zip(right_bars) {
+            *slot = bar;
+        }
+        params[2 * MAX_BARS] = factor.clamp(0.0, 1.0);
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&params));
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Bar Smoothing Encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Bar Smoothing Pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &self.scratch_view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::TRANSPARENT),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        encoder.copy_texture_to_texture(self.scratch.as_image_copy(), self.state.as_image_copy(), self.state.size());
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+}
@@ -0,0 +1,113 @@
+//! Storage for the bar frames of a track, optionally quantized: a 10-minute track at 120 fps
+//! holds 72k frames, so 16- or 8-bit bars save megabytes with no visible difference. Bars are
+//! dequantized on the fly when a frame is read.
+
+use std::borrow::Cow;
+
+use half::f16;
+
+/// Precision the bars of every frame are stored at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum BarPrecision {
+    /// Full precision, 4 bytes per bar
+    #[default]
+    F32,
+    /// Half precision floats, 2 bytes per bar; about three significant digits
+    F16,
+    /// 8-bit levels relative to the track's loudest bar, 1 byte per bar
+    U8,
+}
+
+impl BarPrecision {
+    /// "f32", "f16" or "u8".
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "f32" => Ok(BarPrecision::F32),
+            "f16" => Ok(BarPrecision::F16),
+            "u8" => Ok(BarPrecision::U8),
+            _ => Err(format!("Unknown bar precision '{}', expected 'f32', 'f16' or 'u8'", name)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Levels {
+    F32(Vec<f32>),
+    F16(Vec<f16>),
+    // Each level times `scale / 255`
+    U8 { levels: Vec<u8>, scale: f32 },
+}
+
+/// Bar frames of equal width, stored contiguously at a `BarPrecision`.
+#[derive(Clone, Debug)]
+pub struct BarFrames {
+    width: usize,
+    frames: usize,
+    levels: Levels,
+}
+
+impl Default for BarFrames {
+    fn default() -> Self {
+        Self {
+            width: 0,
+            frames: 0,
+            levels: Levels::F32(Vec::new()),
+        }
+    }
+}
+
+impl BarFrames {
+    /// Store `frames` at `precision`; frames are padded with zeros or cut to the first one's width.
+    pub fn new(frames: Vec<Vec<f32>>, precision: BarPrecision) -> Self {
+        let width = frames.first().map_or(0, Vec::len);
+        let flat = || frames.iter().flat_map(|bars| (0..width).map(|i| bars.get(i).copied().unwrap_or(0.0)));
+        let levels = match precision {
+            BarPrecision::F32 => Levels::F32(flat().collect()),
+            BarPrecision::F16 => Levels::F16(flat().map(f16::from_f32).collect()),
+            BarPrecision::U8 => {
+                let scale = flat().fold(0.0f32, f32::max);
+                let step = if scale > 0.0 { 255.0 / scale } else { 0.0 };
+                Levels::U8 {
+                    levels: flat().map(|level| (level.max(0.0) * step).round() as u8).collect(),
+                    scale,
+                }
+            }
+        };
+        Self {
+            width,
+            frames: frames.len(),
+            levels,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames
+    }
+
+    /// Bars of a frame, borrowed when stored at full precision and dequantized otherwise.
+    pub fn frame(&self, index: usize) -> Option<Cow<'_, [f32]>> {
+        if index >= self.frames {
+            return None;
+        }
+        let span = index * self.width..(index + 1) * self.width;
+        Some(match &self.levels {
+            Levels::F32(levels) => Cow::Borrowed(&levels[span]),
+            Levels::F16(levels) => Cow::Owned(levels[span].iter().map(|level| level.to_f32()).collect()),
+            Levels::U8 { levels, scale } => Cow::Owned(levels[span].iter().map(|&level| level as f32 * scale / 255.0).collect()),
+        })
+    }
+
+    /// Every frame at full precision.
+    pub fn to_frames(&self) -> Vec<Vec<f32>> {
+        (0..self.frames).filter_map(|index| self.frame(index)).map(Cow::into_owned).collect()
+    }
+
+    /// Bytes held by the stored levels.
+    pub fn memory_bytes(&self) -> usize {
+        match &self.levels {
+            Levels::F32(levels) => levels.len() * std::mem::size_of::<f32>(),
+            Levels::F16(levels) => levels.len() * std::mem::size_of::<f16>(),
+            Levels::U8 { levels, .. } => levels.len(),
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use wasm_bindgen::prelude::*;
+
+use crate::analyzer::AudioAnalyzer;
+use crate::audio_info::AudioInfo;
+use crate::markers::Marker;
+use crate::timing;
+
+/// Format version of the analysis blobs produced by batch analysis.
+pub const ANALYSIS_BLOB_VERSION: u32 = 2;
+const ANALYSIS_BLOB_MAGIC: &[u8; 4] = b"VBAB";
+// Peak level reported when no file in a batch could be analyzed
+const SILENT_PEAK_DB: f32 = -120.0;
+
+/// Analysis of one file in a batch.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct BatchFileResult {
+    /// Position of the file in the batch
+    pub index: usize,
+    /// Why the file couldn't be analyzed, `undefined` if it was
+    pub error: Option<String>,
+    pub info: Option<AudioInfo>,
+    /// Bars of every frame and the waveform, in the layout described at `analysis_blob`; empty
+    /// if the file couldn't be analyzed
+    pub blob: Vec<u8>,
+}
+
+/// Totals over the files a batch got through.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BatchSummary {
+    /// Files attempted, fewer than were passed in if the batch was cancelled
+    pub files: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub cancelled: bool,
+    pub total_duration_seconds: f64,
+    pub total_frames: usize,
+    /// Loudest peak of the analyzed files in dBFS
+    pub max_peak_db: f32,
+    /// Mean of the analyzed files' peaks in dBFS
+    pub mean_peak_db: f32,
+    /// Wall-clock time the batch took
+    pub processing_seconds: f64,
+}
+
+/// Per-file results and summary of `analyze_batch`.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct BatchResult {
+    pub files: Vec<BatchFileResult>,
+    pub summary: BatchSummary,
+}
+
+/// Analyze one file with a fresh copy of `template`'s bar settings.
+pub fn analyze_file(template: &AudioAnalyzer, index: usize, file_data: &[u8]) -> BatchFileResult {
+    let mut analyzer = template.empty_like();
+    match analyzer.process(file_data) {
+        Ok(()) => BatchFileResult {
+            index,
+            error: None,
+            info: analyzer.audio_info(),
+            blob: analysis_blob(&analyzer),
+        },
+        Err(e) => BatchFileResult {
+            index,
+            error: Some(e),
+            info: None,
+            blob: Vec::new(),
+        },
+    }
+}
+
+/// Analyze `files` one at a time with `template`'s bar settings, yielding to the event loop
+/// between files so the page stays responsive. After each file `progress(index, completed,
+/// total)` is called; returning (or resolving to) `false` cancels the remaining files.
+pub async fn analyze_batch(
+    template: &AudioAnalyzer,
+    files: &[js_sys::Uint8Array],
+    progress: Option<&js_sys::Function>,
+) -> Result<BatchResult, JsValue> {
+    let started = timing::now_seconds();
+    let mut results = Vec::with_capacity(files.len());
+    let mut cancelled = false;
+
+    for (index, file) in files.iter().enumerate() {
+        log!("Batch analysis: file {} of {}", index + 1, files.len());
+        results.push(analyze_file(template, index, &file.to_vec()));
+
+        if let Some(progress) = progress {
+            let mut response = progress.call3(
+                &JsValue::NULL,
+                &JsValue::from(index as u32),
+                &JsValue::from(results.len() as u32),
+                &JsValue::from(files.len() as u32),
+            )?;
+            if let Some(promise) = response.dyn_ref::<js_sys::Promise>() {
+                response = wasm_bindgen_futures::JsFuture::from(promise.clone()).await?;
+            }
+            if response.as_bool() == Some(false) {
+                log!("Batch analysis cancelled after {} files", results.len());
+                cancelled = results.len() < files.len();
+                break;
+            }
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        timing::yield_to_event_loop().await.map_err(|e| JsValue::from_str(&e))?;
+    }
+
+    let summary = summarize(&results, cancelled, timing::now_seconds() - started);
+    Ok(BatchResult { files: results, summary })
+}
+
+fn summarize(results: &[BatchFileResult], cancelled: bool, processing_seconds: f64) -> BatchSummary {
+    let infos: Vec<AudioInfo> = results.iter().filter_map(|result| result.info).collect();
+    let peaks = infos.iter().map(|info| info.peak_db);
+    BatchSummary {
+        files: results.len(),
+        succeeded: infos.len(),
+        failed: results.len() - infos.len(),
+        cancelled,
+        total_duration_seconds: infos.iter().map(|info| info.duration_seconds).sum(),
+        total_frames: infos.iter().map(|info| info.total_frames).sum(),
+        max_peak_db: peaks.clone().fold(SILENT_PEAK_DB, f32::max),
+        mean_peak_db: if infos.is_empty() {
+            SILENT_PEAK_DB
+        } else {
+            peaks.sum::<f32>() / infos.len() as f32
+        },
+        processing_seconds,
+    }
+}
+
+/// Pack an analyzer's bars and waveform into a blob, all little-endian:
+///
+/// | offset | contents                                              |
+/// |--------|-------------------------------------------------------|
+/// | 0      | magic `VBAB`                                          |
+/// | 4      | u32 format version (`ANALYSIS_BLOB_VERSION`)          |
+/// | 8      | u32 bars per frame                                    |
+/// | 12     | u32 frame count                                       |
+/// | 16     | f64 analysis frames per second                        |
+/// | 24     | u32 waveform length in floats ((min, max) pairs)      |
+/// | 28     | u32 padding                                           |
+/// | 32     | f32 bars, frame by frame, then the f32 waveform       |
+///
+/// followed by the markers (since version 2): a u32 count, then for each marker in time order an
+/// f64 position in seconds, a u32 label length in bytes and the UTF-8 label.
+pub fn analysis_blob(analyzer: &AudioAnalyzer) -> Vec<u8> {
+    let frames = analyzer.get_total_frames();
+    let bin_size = analyzer.get_bin_size();
+    let waveform = analyzer.waveform();
+    let analysis_fps = analyzer.audio_info().map_or(0.0, |info| info.analysis_fps);
+
+    let mut blob = Vec::with_capacity(32 + (frames * bin_size + waveform.len()) * 4);
+    blob.extend_from_slice(ANALYSIS_BLOB_MAGIC);
+    blob.extend_from_slice(&ANALYSIS_BLOB_VERSION.to_le_bytes());
+    blob.extend_from_slice(&(bin_size as u32).to_le_bytes());
+    blob.extend_from_slice(&(frames as u32).to_le_bytes());
+    blob.extend_from_slice(&analysis_fps.to_le_bytes());
+    blob.extend_from_slice(&(waveform.len() as u32).to_le_bytes());
+    blob.extend_from_slice(&0u32.to_le_bytes());
+    for bars in (0..frames).filter_map(|frame_index| analyzer.frame_bars(frame_index)) {
+        // Every frame holds exactly `bin_size` bars, padded or cut if the mapping disagreed
+        for i in 0..bin_size {
+            blob.extend_from_slice(&bars.get(i).copied().unwrap_or(0.0).to_le_bytes());
+        }
+    }
+    for value in waveform {
+        blob.extend_from_slice(&value.to_le_bytes());
+    }
+    write_markers(&mut blob, analyzer.markers().all());
+    blob
+}
+
+/// Append markers as stored in analysis blobs: a u32 count, then for each marker an f64
+/// position in seconds, a u32 label length in bytes and the UTF-8 label.
+pub fn write_markers(blob: &mut Vec<u8>, markers: &[Marker]) {
+    blob.extend_from_slice(&(markers.len() as u32).to_le_bytes());
+    for marker in markers {
+        blob.extend_from_slice(&marker.seconds.to_le_bytes());
+        blob.extend_from_slice(&(marker.label.len() as u32).to_le_bytes());
+        blob.extend_from_slice(marker.label.as_bytes());
+    }
+}
+
+/// Little-endian reads from an analysis blob (or another blob in the same style), failing past
+/// its end.
+pub struct BlobReader<'a> {
+    blob: &'a [u8],
+    offset: usize,
+    // What the blob is, for error messages
+    kind: &'static str,
+}
+
+impl<'a> BlobReader<'a> {
+    pub fn new(blob: &'a [u8], kind: &'static str) -> Self {
+        Self { blob, offset: 0, kind }
+    }
+
+    pub fn bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let end = self.offset.checked_add(count).filter(|&end| end <= self.blob.len());
+        let Some(end) = end else {
+            return Err(format!("{} truncated at byte {}", self.kind, self.blob.len()));
+        };
+        let bytes = &self.blob[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    pub fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    pub fn f64(&mut self) -> Result<f64, String> {
+        Ok(f64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    /// Markers as written by `write_markers`.
+    pub fn markers(&mut self) -> Result<Vec<Marker>, String> {
+        let count = self.u32()?;
+        let mut markers = Vec::new();
+        for _ in 0..count {
+            let seconds = self.f64()?;
+            let length = self.u32()? as usize;
+            let label = String::from_utf8(self.bytes(length)?.to_vec()).map_err(|_| "Marker label is not valid UTF-8".to_string())?;
+            markers.push(Marker { seconds, label });
+        }
+        Ok(markers)
+    }
+}
+
+/// Read the markers back from an analysis blob. Version 1 blobs have none.
+pub fn markers_from_blob(blob: &[u8]) -> Result<Vec<Marker>, String> {
+    let mut reader = BlobReader::new(blob, "Analysis blob");
+    if reader.bytes(4)? != ANALYSIS_BLOB_MAGIC {
+        return Err("Not an analysis blob".to_string());
+    }
+    let version = reader.u32()?;
+    if version > ANALYSIS_BLOB_VERSION {
+        return Err(format!("Unsupported analysis blob version {}", version));
+    }
+    let bin_size = reader.u32()? as usize;
+    let frames = reader.u32()? as usize;
+    reader.f64()?;
+    let waveform_length = reader.u32()? as usize;
+    reader.u32()?;
+    if version < 2 {
+        return Ok(Vec::new());
+    }
+
+    // Skip the bars and waveform
+    let floats = frames.checked_mul(bin_size).and_then(|bars| bars.checked_add(waveform_length));
+    reader.bytes(floats.and_then(|floats| floats.checked_mul(4)).ok_or("Analysis blob sizes overflow")?)?;
+    reader.markers()
+}
@@ -0,0 +1,265 @@
+// Flux has to exceed its running average by this factor to count as a beat
+const BEAT_THRESHOLD: f32 = 1.5;
+const BEAT_MIN_FLUX: f32 = 0.01;
+// Frames to ignore after a beat so one onset isn't reported several times
+const BEAT_COOLDOWN_FRAMES: u32 = 8;
+// Flux this many times its running average counts as a full-strength onset
+const FULL_STRENGTH_RATIO: f32 = 4.0;
+
+// How quickly each effect fades after an onset (seconds for a drop to 1/e)
+const FLASH_DECAY_SECONDS: f64 = 0.12;
+const PULSE_DECAY_SECONDS: f64 = 0.35;
+const SHAKE_DECAY_SECONDS: f64 = 0.25;
+// Flashes are at most three per second, the photosensitivity guideline limit
+const FLASH_MIN_INTERVAL: f64 = 0.34;
+// Largest shake offset as a fraction of the canvas height, and how fast it jitters (radians/second)
+const MAX_SHAKE: f32 = 0.015;
+const SHAKE_RATE: f64 = 45.0;
+
+/// Onset detection from spectral flux: a frame is a beat when its flux jumps well above the
+/// recent average.
+#[derive(Debug, Default)]
+pub struct BeatDetector {
+    flux_average: f32,
+    cooldown: u32,
+}
+
+impl BeatDetector {
+    /// Feed the next frame's flux; returns the onset strength (0.0-1.0) if it is a beat.
+    pub fn detect(&mut self, flux: f32) -> Option<f32> {
+        let beat = self.cooldown == 0 && flux > BEAT_MIN_FLUX && flux > self.flux_average * BEAT_THRESHOLD;
+        let strength = (flux / (self.flux_average * FULL_STRENGTH_RATIO).max(f32::EPSILON)).min(1.0);
+        self.flux_average = self.flux_average * 0.9 + flux * 0.1;
+        if beat {
+            self.cooldown = BEAT_COOLDOWN_FRAMES;
+            Some(strength)
+        } else {
+            self.cooldown = self.cooldown.saturating_sub(1);
+            None
+        }
+    }
+}
+
+/// Intensities (0.0-1.0) of the built-in beat effects; 0.0 turns an effect off.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BeatEffectSettings {
+    pub flash: f32,
+    pub pulse: f32,
+    pub shake: f32,
+}
+
+impl BeatEffectSettings {
+    pub fn new(flash: f32, pulse: f32, shake: f32) -> Self {
+        Self {
+            flash: flash.clamp(0.0, 1.0),
+            pulse: pulse.clamp(0.0, 1.0),
+            shake: shake.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn is_off(&self) -> bool {
+        self.flash == 0.0 && self.pulse == 0.0 && self.shake == 0.0
+    }
+}
+
+/// Screen flash, background pulse and shake envelopes kicked by detected onsets, scaled by their
+/// strength, and fading between them.
+#[derive(Debug)]
+pub struct BeatEffects {
+    settings: BeatEffectSettings,
+    detector: BeatDetector,
+    flash: f32,
+    pulse: f32,
+    shake: f32,
+    // Direction the current shake started in, radians
+    shake_angle: f64,
+    last_time: Option<f64>,
+    last_flash: Option<f64>,
+}
+
+impl BeatEffects {
+    pub fn new(settings: BeatEffectSettings) -> Self {
+        Self {
+            settings,
+            detector: BeatDetector::default(),
+            flash: 0.0,
+            pulse: 0.0,
+            shake: 0.0,
+            shake_angle: 0.0,
+            last_time: None,
+            last_flash: None,
+        }
+    }
+
+    /// Settle every effect and forget the flux history, keeping the settings.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.settings);
+    }
+
+    /// Advance to render `time` and return the effect levels for the shader: [flash, pulse,
+    /// shake x, shake y], the shake as a fraction of the canvas height. `flux` is the spectral
+    /// flux of a new analysis frame, or `None` when the frame hasn't changed since the last
+    /// render, so the onset detection runs once per frame while the effects fade every render.
+    /// Time running backwards restarts the flash rate limit.
+    pub fn update(&mut self, time: f64, flux: Option<f32>) -> [f32; 4] {
+        let delta = self.last_time.map_or(0.0, |last| (time - last).max(0.0));
+        self.last_time = Some(time);
+        let fade = |level: f32, decay_seconds: f64| level * (-delta / decay_seconds).exp() as f32;
+        self.flash = fade(self.flash, FLASH_DECAY_SECONDS);
+        self.pulse = fade(self.pulse, PULSE_DECAY_SECONDS);
+        self.shake = fade(self.shake, SHAKE_DECAY_SECONDS);
+
+        if let Some(strength) = flux.and_then(|flux| self.detector.detect(flux)) {
+            let flash_allowed = match self.last_flash {
+                Some(last) if time >= last => time - last >= FLASH_MIN_INTERVAL,
+                _ => true,
+            };
+            if flash_allowed && self.settings.flash > 0.0 {
+                self.flash = self.flash.max(strength * self.settings.flash);
+                self.last_flash = Some(time);
+            }
+            self.pulse = self.pulse.max(strength * self.settings.pulse);
+            if strength * self.settings.shake > self.shake {
+                self.shake = strength * self.settings.shake;
+                // A new direction per onset, cheaply scrambled from the time
+                self.shake_angle = (time * 7919.0).sin() * 1000.0 % std::f64::consts::TAU;
+            }
+        }
+
+        let angle = self.shake_angle + time * SHAKE_RATE;
+        let offset = self.shake * MAX_SHAKE;
+        [self.flash, self.pulse, offset * angle.cos() as f32, offset * angle.sin() as f32]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Flux of a steady passage, then an onset well above it
+    const QUIET: f32 = 0.05;
+    const ONSET: f32 = 0.5;
+
+    fn settled_detector() -> BeatDetector {
+        let mut detector = BeatDetector::default();
+        for _ in 0..50 {
+            detector.detect(QUIET);
+        }
+        detector
+    }
+
+    fn settled_effects(settings: BeatEffectSettings) -> (BeatEffects, f64) {
+        let mut effects = BeatEffects::new(settings);
+        let mut time = 0.0;
+        for _ in 0..50 {
+            effects.update(time, Some(QUIET));
+            time += 0.02;
+        }
+        (effects, time)
+    }
+
+    #[test]
+    fn steady_flux_is_no_beat() {
+        let mut detector = settled_detector();
+        assert_eq!(detector.detect(QUIET), None);
+    }
+
+    #[test]
+    fn flux_jumps_are_beats_with_capped_strength() {
+        let mut detector = settled_detector();
+        assert_eq!(detector.detect(10.0), Some(1.0));
+
+        let mut detector = settled_detector();
+        let strength = detector.detect(ONSET).expect("onset");
+        assert!(strength > 0.0 && strength <= 1.0, "strength {}", strength);
+    }
+
+    #[test]
+    fn tiny_flux_is_never_a_beat() {
+        let mut detector = BeatDetector::default();
+        assert_eq!(detector.detect(BEAT_MIN_FLUX / 2.0), None);
+    }
+
+    #[test]
+    fn cooldown_suppresses_repeated_onsets() {
+        let mut detector = settled_detector();
+        assert!(detector.detect(ONSET).is_some());
+        for frame in 0..BEAT_COOLDOWN_FRAMES {
+            assert_eq!(detector.detect(ONSET * 10.0), None, "frame {} of the cooldown", frame);
+        }
+        assert!(detector.detect(ONSET * 10.0).is_some(), "beat after the cooldown");
+    }
+
+    #[test]
+    fn settings_are_clamped() {
+        let settings = BeatEffectSettings::new(-1.0, 0.5, 3.0);
+        assert_eq!(settings, BeatEffectSettings::new(0.0, 0.5, 1.0));
+        assert!(!settings.is_off());
+        assert!(BeatEffectSettings::new(0.0, -2.0, 0.0).is_off());
+    }
+
+    #[test]
+    fn onsets_kick_the_effects() {
+        let (mut effects, time) = settled_effects(BeatEffectSettings::new(1.0, 0.5, 1.0));
+        let [flash, pulse, x, y] = effects.update(time, Some(10.0));
+        assert_eq!(flash, 1.0);
+        assert_eq!(pulse, 0.5);
+        assert!(((x * x + y * y).sqrt() - MAX_SHAKE).abs() < 1e-6, "shake ({}, {})", x, y);
+    }
+
+    #[test]
+    fn effects_fade_on_renders_without_a_new_frame() {
+        let (mut effects, time) = settled_effects(BeatEffectSettings::new(1.0, 1.0, 0.0));
+        effects.update(time, Some(10.0));
+
+        // Same frame rendered again: no new detection, only the fade
+        let [flash, pulse, _, _] = effects.update(time + PULSE_DECAY_SECONDS, None);
+        assert!((pulse - (-1.0f32).exp()).abs() < 1e-5, "pulse {}", pulse);
+        assert!(flash < pulse, "flash {} fades faster than pulse {}", flash, pulse);
+
+        let [flash, pulse, _, _] = effects.update(time + 10.0, None);
+        assert!(flash < 1e-6 && pulse < 1e-6, "settled to {} and {}", flash, pulse);
+    }
+
+    #[test]
+    fn repeated_renders_of_a_frame_dont_feed_the_detector() {
+        let (mut effects, mut time) = settled_effects(BeatEffectSettings::new(0.0, 1.0, 0.0));
+        for _ in 0..20 {
+            time += 0.004;
+            effects.update(time, None);
+        }
+        // The flux average only saw the quiet frames, so the onset still stands out
+        let [_, pulse, _, _] = effects.update(time, Some(ONSET));
+        assert!(pulse > 0.0, "onset after idle renders");
+    }
+
+    #[test]
+    fn flashes_are_rate_limited() {
+        let (mut effects, time) = settled_effects(BeatEffectSettings::new(1.0, 0.0, 0.0));
+        effects.update(time, Some(10.0));
+        let mut later = time;
+        for _ in 0..BEAT_COOLDOWN_FRAMES {
+            later += 0.01;
+            effects.update(later, Some(QUIET));
+        }
+        let [faded, _, _, _] = effects.update(later, None);
+        // Another onset before the flash interval has passed only fades
+        let [flash, _, _, _] = effects.update(later + 0.01, Some(100.0));
+        assert!(flash < faded, "flash {} not re-triggered above {}", flash, faded);
+
+        for _ in 0..BEAT_COOLDOWN_FRAMES {
+            later += 0.01;
+            effects.update(later, Some(QUIET));
+        }
+        let [flash, _, _, _] = effects.update(time + FLASH_MIN_INTERVAL + 0.5, Some(1000.0));
+        assert_eq!(flash, 1.0, "flash once the interval has passed");
+    }
+
+    #[test]
+    fn reset_settles_the_effects() {
+        let (mut effects, time) = settled_effects(BeatEffectSettings::new(1.0, 1.0, 1.0));
+        effects.update(time, Some(10.0));
+        effects.reset();
+        assert_eq!(effects.update(time, None), [0.0; 4]);
+    }
+}
@@ -0,0 +1,43 @@
+//! Decoding with the browser's own decoders through `AudioContext.decodeAudioData`, for formats
+//! the crate can't read itself (AAC/M4A, Ogg, WebM audio and whatever else the browser plays).
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::analysis::DecodedAudio;
+
+/// Decode `file_data` with a short-lived `AudioContext`. The browser resamples the audio to the
+/// context's rate (usually the output device's, 44.1 or 48 kHz).
+pub async fn decode_audio_data(file_data: &[u8]) -> Result<DecodedAudio, String> {
+    let context = web_sys::AudioContext::new().map_err(|e| format!("Failed to create an AudioContext: {:?}", e))?;
+    let decoded = decode_with(&context, file_data).await;
+    if let Ok(closed) = context.close() {
+        let _ = JsFuture::from(closed).await;
+    }
+    decoded
+}
+
+async fn decode_with(context: &web_sys::AudioContext, file_data: &[u8]) -> Result<DecodedAudio, String> {
+    // decodeAudioData takes ownership of (detaches) the buffer, so hand it a copy
+    let buffer = js_sys::Uint8Array::from(file_data).buffer();
+    let promise = context
+        .decode_audio_data(&buffer)
+        .map_err(|e| format!("Browser failed to decode audio: {:?}", e))?;
+    let audio: web_sys::AudioBuffer = JsFuture::from(promise)
+        .await
+        .map_err(|e| format!("Browser failed to decode audio: {:?}", e))?
+        .unchecked_into();
+
+    log!(
+        "Browser decoded audio: {} channels, {} Hz, {:.2} seconds",
+        audio.number_of_channels(),
+        audio.sample_rate(),
+        audio.duration()
+    );
+    let channels = (0..audio.number_of_channels())
+        .map(|channel| audio.get_channel_data(channel))
+        .collect::<Result<Vec<_>, JsValue>>()
+        .map_err(|e| format!("Failed to read decoded audio: {:?}", e))?;
+    Ok(DecodedAudio::from_float_channels(channels, audio.sample_rate().round() as u32))
+}
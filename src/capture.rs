@@ -0,0 +1,12 @@
+/// Encode tightly packed RGBA8 pixels as a PNG file.
+pub fn encode_png(rgba: &[u8], width: u32, height: u32) -> Result<Vec<u8>, String> {
+    let mut png_bytes = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut png_bytes, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(|e| format!("Failed to write PNG header: {}", e))?;
+        writer.write_image_data(rgba).map_err(|e| format!("Failed to write PNG data: {}", e))?;
+    }
+    Ok(png_bytes)
+}
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::layout::BarStyle;
+
+// TypeScript shape of the plain objects below, for `configure` and `get_config` in the
+// generated .d.ts
+#[wasm_bindgen(typescript_custom_section)]
+const VIBER_CONFIG_TS: &'static str = r#"
+export interface ViberConfig {
+    visualizer_mode?: string;
+    bin_size?: number;
+    min_freq?: number;
+    max_freq?: number;
+    weighting?: "a" | "c" | "none";
+    gpu_smoothing?: boolean;
+    latency_offset_ms?: number;
+    theme?: ThemeConfig;
+    bar_layout?: BarLayoutConfig;
+    bar_style?: BarStyleConfig;
+    render_scale?: number;
+    antialiasing?: "off" | "fxaa";
+    debug_overlay?: boolean;
+    hover_highlight?: boolean;
+    solo_on_click?: boolean;
+}
+
+export interface ThemeConfig {
+    scheme?: "dark" | "light" | "auto";
+    high_contrast?: boolean;
+}
+
+export interface BarLayoutConfig {
+    arrangement?: "bottom-up" | "top-down" | "mirrored" | "dual-sided";
+    reversed?: boolean;
+}
+
+export interface BarStyleConfig {
+    gap_fraction?: number;
+    corner_radius?: number;
+    min_height?: number;
+    orientation?: "vertical" | "horizontal";
+}
+"#;
+
+/// Settings taken by `App.configure` and returned by `App.get_config`, as a plain object. Keys
+/// left out of a config keep their current values.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ViberConfig {
+    pub visualizer_mode: Option<String>,
+    pub bin_size: Option<usize>,
+    pub min_freq: Option<f32>,
+    pub max_freq: Option<f32>,
+    pub weighting: Option<String>,
+    pub gpu_smoothing: Option<bool>,
+    pub latency_offset_ms: Option<f64>,
+    pub theme: Option<ThemeConfig>,
+    pub bar_layout: Option<BarLayoutConfig>,
+    pub bar_style: Option<BarStyleConfig>,
+    pub render_scale: Option<f32>,
+    pub antialiasing: Option<String>,
+    pub debug_overlay: Option<bool>,
+    pub hover_highlight: Option<bool>,
+    pub solo_on_click: Option<bool>,
+}
+
+/// Arguments of `App.set_theme`; fields left out take the defaults.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    pub scheme: String,
+    pub high_contrast: bool,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            scheme: "dark".to_string(),
+            high_contrast: false,
+        }
+    }
+}
+
+/// Arguments of `App.set_bar_layout`; fields left out take the defaults.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BarLayoutConfig {
+    pub arrangement: String,
+    pub reversed: bool,
+}
+
+impl Default for BarLayoutConfig {
+    fn default() -> Self {
+        Self {
+            arrangement: "bottom-up".to_string(),
+            reversed: false,
+        }
+    }
+}
+
+/// Arguments of `App.set_bar_style`; fields left out take the defaults.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BarStyleConfig {
+    pub gap_fraction: f32,
+    pub corner_radius: f32,
+    pub min_height: f32,
+    pub orientation: String,
+}
+
+impl Default for BarStyleConfig {
+    fn default() -> Self {
+        let style = BarStyle::default();
+        Self {
+            gap_fraction: style.gap_fraction,
+            corner_radius: style.corner_radius,
+            min_height: style.min_height,
+            orientation: "vertical".to_string(),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Live-editing support for a local dev loop: configuration pushed by a file watcher (see
+//! `www/dev.js`) is held back until the writes settle, so a save that touches the file several
+//! times is applied once.
+
+use serde_json::{Map, Value};
+
+// Quiet period after the last submitted config before it is applied
+const CONFIG_DEBOUNCE_SECONDS: f64 = 0.15;
+
+/// The latest submitted configuration, waiting out the debounce period.
+#[derive(Default)]
+pub struct ConfigDebouncer {
+    pending: Option<Map<String, Value>>,
+    // Render time of the first frame that saw the pending config
+    first_seen: Option<f64>,
+}
+
+impl ConfigDebouncer {
+    /// Parse a JSON object and make it the pending config, restarting the debounce period.
+    pub fn submit(&mut self, json: &str) -> Result<(), String> {
+        let config = match serde_json::from_str(json).map_err(|e| format!("Invalid config JSON: {}", e))? {
+            Value::Object(config) => config,
+            other => return Err(format!("Config must be a JSON object, got {}", other)),
+        };
+        self.pending = Some(config);
+        self.first_seen = None;
+        Ok(())
+    }
+
+    /// Take the pending config once it has been left alone for the debounce period as of render
+    /// `time`. Time running backwards restarts the period.
+    pub fn poll(&mut self, time: f64) -> Option<Map<String, Value>> {
+        self.pending.as_ref()?;
+        let first_seen = match self.first_seen {
+            Some(first_seen) if time >= first_seen => first_seen,
+            _ => *self.first_seen.insert(time),
+        };
+        if time - first_seen < CONFIG_DEBOUNCE_SECONDS {
+            return None;
+        }
+        self.first_seen = None;
+        self.pending.take()
+    }
+}
+
+pub fn number(key: &str, value: &Value) -> Result<f64, String> {
+    value.as_f64().ok_or_else(|| format!("`{}` must be a number, got {}", key, value))
+}
+
+pub fn boolean(key: &str, value: &Value) -> Result<bool, String> {
+    value.as_bool().ok_or_else(|| format!("`{}` must be a boolean, got {}", key, value))
+}
+
+pub fn string<'a>(key: &str, value: &'a Value) -> Result<&'a str, String> {
+    value.as_str().ok_or_else(|| format!("`{}` must be a string, got {}", key, value))
+}
+
+/// Optional field of an object-valued key; missing and `null` both read as absent.
+pub fn field<'a>(key: &str, value: &'a Value, name: &str) -> Result<Option<&'a Value>, String> {
+    let object = value.as_object().ok_or_else(|| format!("`{}` must be an object, got {}", key, value))?;
+    Ok(object.get(name).filter(|field| !field.is_null()))
+}
@@ -0,0 +1,111 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+
+// Event names and listener signatures for `App.on` and `App.off` in the generated .d.ts
+#[wasm_bindgen(typescript_custom_section)]
+const VIBER_EVENTS_TS: &'static str = r#"
+export interface ViberEvents {
+    "processing-started": () => void;
+    "processing-progress": (fraction: number) => void;
+    "processing-complete": (info: AudioInfo | undefined) => void;
+    "beat": (strength: number, frame_index: number) => void;
+    "section-change": (section: Section, index: number) => void;
+    "context-lost": () => void;
+    "frame-rendered": (frame_index: number) => void;
+}
+
+export type ViberEvent = keyof ViberEvents;
+"#;
+
+/// Lifecycle events hosts can listen to with `App.on`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AppEvent {
+    ProcessingStarted,
+    ProcessingProgress,
+    ProcessingComplete,
+    Beat,
+    SectionChange,
+    ContextLost,
+    FrameRendered,
+}
+
+impl AppEvent {
+    const ALL: [AppEvent; 7] = [
+        AppEvent::ProcessingStarted,
+        AppEvent::ProcessingProgress,
+        AppEvent::ProcessingComplete,
+        AppEvent::Beat,
+        AppEvent::SectionChange,
+        AppEvent::ContextLost,
+        AppEvent::FrameRendered,
+    ];
+
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        Self::ALL.into_iter().find(|event| event.name() == name).ok_or_else(|| {
+            let names: Vec<&str> = Self::ALL.iter().map(|event| event.name()).collect();
+            format!("Unknown event '{}', expected one of: {}", name, names.join(", "))
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AppEvent::ProcessingStarted => "processing-started",
+            AppEvent::ProcessingProgress => "processing-progress",
+            AppEvent::ProcessingComplete => "processing-complete",
+            AppEvent::Beat => "beat",
+            AppEvent::SectionChange => "section-change",
+            AppEvent::ContextLost => "context-lost",
+            AppEvent::FrameRendered => "frame-rendered",
+        }
+    }
+}
+
+/// Listeners registered with `App.on`, in registration order. Clones share the listeners, so
+/// processing running apart from the app can emit too.
+#[derive(Clone, Default)]
+pub struct EventEmitter {
+    listeners: Rc<RefCell<Vec<(AppEvent, js_sys::Function)>>>,
+}
+
+impl EventEmitter {
+    pub fn on(&self, event: AppEvent, callback: js_sys::Function) {
+        self.listeners.borrow_mut().push((event, callback));
+    }
+
+    /// Remove `callback` from `event`, or every listener of `event` without one. Returns whether
+    /// any was removed.
+    pub fn off(&self, event: AppEvent, callback: Option<&js_sys::Function>) -> bool {
+        let mut listeners = self.listeners.borrow_mut();
+        let before = listeners.len();
+        listeners.retain(|(listened, listener)| *listened != event || callback.is_some_and(|callback| listener != callback));
+        listeners.len() != before
+    }
+
+    /// Whether anything listens to `event`, to skip work only listeners need.
+    pub fn has_listeners(&self, event: AppEvent) -> bool {
+        self.listeners.borrow().iter().any(|(listened, _)| *listened == event)
+    }
+
+    /// Call the listeners of `event` with `args`. A listener that throws is logged and the rest
+    /// still run.
+    pub fn emit(&self, event: AppEvent, args: &[JsValue]) {
+        // Listeners may call `on` or `off`, so don't hold the borrow while calling them
+        let listeners: Vec<js_sys::Function> = self
+            .listeners
+            .borrow()
+            .iter()
+            .filter(|(listened, _)| *listened == event)
+            .map(|(_, listener)| listener.clone())
+            .collect();
+        if listeners.is_empty() {
+            return;
+        }
+        let args: js_sys::Array = args.iter().collect();
+        for listener in listeners {
+            if let Err(e) = listener.apply(&JsValue::NULL, &args) {
+                log!("'{}' listener failed: {:?}", event.name(), e);
+            }
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use wgpu::*;
+
+use crate::timing::REFERENCE_REFRESH_RATE;
+
+// Frame gaps longer than this (paused rendering, tab switches) advance the trails by one reference frame
+const MAX_FRAME_DELTA: f64 = 0.25;
+
+/// How the previous frame is carried into the next one. Values are per 60 Hz frame and are
+/// rescaled for the actual frame time, so trails look the same at any refresh rate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FeedbackSettings {
+    /// Scale about the center; above 1.0 trails stream outwards, below 1.0 they shrink inwards
+    pub zoom: f32,
+    /// Rotation about the center in radians, counter-clockwise
+    pub rotation: f32,
+    /// Brightness kept from the previous frame (0.0 for no trails, 1.0 for trails that never fade)
+    pub decay: f32,
+}
+
+impl FeedbackSettings {
+    /// Clamp to usable ranges: zoom 0.5-2.0, rotation within half a turn, decay 0.0-1.0.
+    pub fn new(zoom: f32, rotation: f32, decay: f32) -> Self {
+        Self {
+            zoom: zoom.clamp(0.5, 2.0),
+            rotation: rotation.clamp(-std::f32::consts::PI, std::f32::consts::PI),
+            decay: decay.clamp(0.0, 1.0),
+        }
+    }
+
+    // Zoom, rotation and decay for a frame drawn `delta` seconds after the previous one
+    fn for_delta(self, delta: f64) -> [f32; 3] {
+        let frames = (delta * REFERENCE_REFRESH_RATE as f64) as f32;
+        [self.zoom.powf(frames), self.rotation * frames, self.decay.powf(frames)]
+    }
+}
+
+// Textures for one target size: the freshly drawn frame and two history textures that take turns
+// being read (the previous output) and written (the new one)
+struct FeedbackTargets {
+    frame: (Texture, TextureView),
+    history: [TextureView; 2],
+    // combine_groups[i] reads history[1 - i] and writes history[i]
+    combine_groups: [BindGroup; 2],
+    copy_groups: [BindGroup; 2],
+}
+
+/// Trails effect: each frame is drawn into an intermediate texture, combined with the previous
+/// output transformed by the settings, and the result is both copied to the target and kept for
+/// the next frame.
+pub struct Feedback {
+    combine_pipeline: RenderPipeline,
+    copy_pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    sampler: Sampler,
+    params_buffer: Buffer,
+    format: TextureFormat,
+    targets: Option<FeedbackTargets>,
+    // History texture written by the latest frame
+    current: usize,
+    last_time: Option<f64>,
+    light: bool,
+}
+
+impl Feedback {
+    pub fn new(device: &Device, format: TextureFormat) -> Self {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Feedback Shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/feedback.wgsl").into()),
+        });
+
+        let texture_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: true },
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Feedback Bind Group Layout"),
+            entries: &[
+                texture_entry(0),
+                texture_entry(1),
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Feedback Sampler"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+        // [zoom, rotation, decay, light scheme], [aspect ratio, padding...]
+        let params_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Feedback Params Buffer"),
+            size: 8 * 4,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Feedback Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |label, entry_point| {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &shader,
+                    entry_point: Some(entry_point),
+                    targets: &[Some(ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: FrontFace::Ccw,
+                    cull_mode: Some(Face::Back),
+                    polygon_mode: PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: None,
+                multisample: MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+                cache: None,
+            })
+        };
+
+        Self {
+            combine_pipeline: create_pipeline("Feedback Combine Pipeline", "fs_main"),
+            copy_pipeline: create_pipeline("Feedback Copy Pipeline", "fs_copy"),
+            bind_group_layout,
+            sampler,
+            params_buffer,
+            format,
+            targets: None,
+            current: 0,
+            last_time: None,
+            light: false,
+        }
+    }
+
+    /// Advance to the frame at renderer `time`: make sure the textures match the size about to be
+    /// drawn and upload this frame's transform. New textures and scheme changes start the trails
+    /// over.
+    #[allow(clippy::too_many_arguments)]
+    pub fn prepare(&mut self, device: &Device, queue: &Queue, width: u32, height: u32, time: f64, settings: FeedbackSettings, light: bool) {
+        let reusable = self
+            .targets
+            .as_ref()
+            .is_some_and(|targets| targets.frame.0.width() == width && targets.frame.0.height() == height);
+        if !reusable {
+            self.targets = Some(self.create_targets(device, width, height));
+            self.last_time = None;
+        }
+
+        let delta = match self.last_time {
+            Some(last) if time > last && time - last < MAX_FRAME_DELTA => time - last,
+            _ => 1.0 / REFERENCE_REFRESH_RATE as f64,
+        };
+        let restart = self.last_time.is_none() || light != self.light;
+        self.last_time = Some(time);
+        self.light = light;
+        self.current = 1 - self.current;
+
+        let [zoom, rotation, decay] = settings.for_delta(delta);
+        let params = [
+            zoom,
+            rotation,
+            if restart { 0.0 } else { decay },
+            if light { 1.0 } else { 0.0 },
+            width as f32 / height.max(1) as f32,
+            0.0,
+            0.0,
+            0.0,
+        ];
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&params));
+    }
+
+    fn create_targets(&self, device: &Device, width: u32, height: u32) -> FeedbackTargets {
+        let create_texture = |label| {
+            let texture = device.create_texture(&TextureDescriptor {
+                label: Some(label),
+                size: Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: self.format,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&TextureViewDescriptor {
+                label: Some(label),
+                ..Default::default()
+            });
+            (texture, view)
+        };
+        let frame = create_texture("Feedback Frame Texture");
+        let history = [create_texture("Feedback History Texture A").1, create_texture("Feedback History Texture B").1];
+
+        let create_bind_group = |label, source: &TextureView, previous: &TextureView| {
+            device.create_bind_group(&BindGroupDescriptor {
+                label: Some(label),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(source),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::TextureView(previous),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: BindingResource::Sampler(&self.sampler),
+                    },
+                    BindGroupEntry {
+                        binding: 3,
+                        resource: self.params_buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+        let combine_groups = [
+            create_bind_group("Feedback Combine Bind Group A", &frame.1, &history[1]),
+            create_bind_group("Feedback Combine Bind Group B", &frame.1, &history[0]),
+        ];
+        let copy_groups = [
+            create_bind_group("Feedback Copy Bind Group A", &history[0], &history[0]),
+            create_bind_group("Feedback Copy Bind Group B", &history[1], &history[1]),
+        ];
+
+        FeedbackTargets {
+            frame,
+            history,
+            combine_groups,
+            copy_groups,
+        }
+    }
+
+    /// Drop the textures once the effect is turned off.
+    pub fn release(&mut self) {
+        self.targets = None;
+        self.last_time = None;
+    }
+
+    /// View the frame is drawn into, once `prepare` has run.
+    pub fn frame_view(&self) -> Option<&TextureView> {
+        self.targets.as_ref().map(|targets| &targets.frame.1)
+    }
+
+    /// Combine the drawn frame with the previous output into the current history texture.
+    pub fn accumulate(&self, encoder: &mut CommandEncoder) {
+        let Some(targets) = &self.targets else {
+            return;
+        };
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Feedback Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &targets.history[self.current],
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::TRANSPARENT),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(&self.combine_pipeline);
+        render_pass.set_bind_group(0, &targets.combine_groups[self.current], &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    /// Copy the combined frame into `render_pass`.
+    pub fn composite(&self, render_pass: &mut RenderPass) {
+        let Some(targets) = &self.targets else {
+            return;
+        };
+        render_pass.set_pipeline(&self.copy_pipeline);
+        render_pass.set_bind_group(0, &targets.copy_groups[self.current], &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
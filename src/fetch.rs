@@ -0,0 +1,84 @@
+//! Downloading audio with `fetch`, reading the body as a stream where the host supports it so
+//! progress can be reported while the bytes arrive.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+// Most bytes reserved up front from Content-Length, which the server controls; larger bodies
+// grow the buffer as they arrive
+const MAX_PREALLOCATION: usize = 64 << 20;
+
+/// Fetch `url` and return its body. `progress(loaded, total)` is called as each chunk arrives,
+/// with `total` taken from Content-Length, or `None` when the server doesn't send one or the
+/// body turns out longer (as when it was compressed in transit); an error from it stops the
+/// download. Stops with an error as soon as `cancelled` returns true. The body is collected in
+/// full: nothing is decoded until it has all arrived.
+pub async fn fetch_bytes(
+    url: &str,
+    mut progress: impl FnMut(usize, Option<usize>) -> Result<(), String>,
+    cancelled: impl Fn() -> bool,
+) -> Result<Vec<u8>, String> {
+    let response = fetch(url).await?;
+    if !response.ok() {
+        return Err(format!("Failed to fetch '{}': HTTP {}", url, response.status()));
+    }
+    let total: Option<usize> = response
+        .headers()
+        .get("Content-Length")
+        .ok()
+        .flatten()
+        .and_then(|length| length.trim().parse().ok());
+
+    // Without streamed bodies, progress is only known once the whole file is in
+    let Some(body) = response.body() else {
+        let promise = response.array_buffer().map_err(|e| format!("Failed to read '{}': {:?}", url, e))?;
+        let buffer = JsFuture::from(promise).await.map_err(|e| format!("Failed to read '{}': {:?}", url, e))?;
+        let bytes = js_sys::Uint8Array::new(&buffer).to_vec();
+        progress(bytes.len(), total.filter(|&total| total >= bytes.len()))?;
+        return Ok(bytes);
+    };
+
+    let reader: web_sys::ReadableStreamDefaultReader = body.get_reader().unchecked_into();
+    let mut bytes = Vec::with_capacity(total.unwrap_or(0).min(MAX_PREALLOCATION));
+    loop {
+        if cancelled() {
+            let _ = reader.cancel();
+            return Err(format!("Download of '{}' cancelled", url));
+        }
+        let chunk: web_sys::ReadableStreamReadResult = JsFuture::from(reader.read())
+            .await
+            .map_err(|e| format!("Failed to read '{}': {:?}", url, e))?
+            .unchecked_into();
+        if chunk.get_done().unwrap_or(false) {
+            break;
+        }
+        let chunk = js_sys::Uint8Array::new(&chunk.get_value());
+        let start = bytes.len();
+        bytes.resize(start + chunk.length() as usize, 0);
+        chunk.copy_to(&mut bytes[start..]);
+        if let Err(e) = progress(bytes.len(), total.filter(|&total| total >= bytes.len())) {
+            let _ = reader.cancel();
+            return Err(e);
+        }
+    }
+    log!("Downloaded {} bytes from '{}'", bytes.len(), url);
+    Ok(bytes)
+}
+
+// Call the global `fetch`, so this also works in workers and Node, where there is no `window`
+async fn fetch(url: &str) -> Result<web_sys::Response, String> {
+    let global = js_sys::global();
+    let fetch = js_sys::Reflect::get(&global, &JsValue::from_str("fetch"))
+        .ok()
+        .and_then(|fetch| fetch.dyn_into::<js_sys::Function>().ok())
+        .ok_or_else(|| "fetch is not available in this environment".to_string())?;
+    let promise = fetch
+        .call1(&global, &JsValue::from_str(url))
+        .map_err(|e| format!("Failed to fetch '{}': {:?}", url, e))?;
+    JsFuture::from(js_sys::Promise::from(promise))
+        .await
+        .map_err(|e| format!("Failed to fetch '{}': {:?}", url, e))?
+        .dyn_into::<web_sys::Response>()
+        .map_err(|_| format!("Failed to fetch '{}': not a Response", url))
+}
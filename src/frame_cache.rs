@@ -0,0 +1,49 @@
+//! A small least-recently-used cache of per-frame results, for analysis computed on demand.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// Values computed per frame index, keeping the `capacity` most recently used. Lookups only need
+/// a shared reference, so read-only accessors can fill it.
+pub struct FrameCache<T> {
+    capacity: usize,
+    // Most recently used first
+    entries: RefCell<VecDeque<(usize, Rc<T>)>>,
+}
+
+impl<T> FrameCache<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: RefCell::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// The value for frame `index`, computed with `compute` and cached unless it already is.
+    pub fn get_or_insert_with(&self, index: usize, compute: impl FnOnce() -> T) -> Rc<T> {
+        {
+            let mut entries = self.entries.borrow_mut();
+            let position = entries.iter().position(|(cached, _)| *cached == index);
+            if let Some(entry) = position.and_then(|position| entries.remove(position)) {
+                let value = Rc::clone(&entry.1);
+                entries.push_front(entry);
+                return value;
+            }
+        }
+        // Not borrowed while computing, in case that reads other frames
+        let value = Rc::new(compute());
+        let mut entries = self.entries.borrow_mut();
+        entries.push_front((index, Rc::clone(&value)));
+        entries.truncate(self.capacity);
+        value
+    }
+
+    pub fn clear(&self) {
+        self.entries.borrow_mut().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+}
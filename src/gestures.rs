@@ -0,0 +1,184 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use web_sys::{HtmlCanvasElement, TouchEvent};
+
+// Thresholds in CSS pixels / milliseconds
+const SWIPE_MIN_DISTANCE: f64 = 60.0;
+const SWIPE_MAX_DURATION: f64 = 500.0;
+const TAP_MAX_DURATION: f64 = 300.0;
+const TAP_MAX_MOVEMENT: f64 = 15.0;
+const PINCH_MIN_CHANGE: f64 = 0.05;
+
+/// A completed gesture, queued by the touch listeners and consumed by `App` on the next render.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gesture {
+    /// Two-finger pinch; `scale` > 1.0 when spreading, `center` is the normalized x position (0.0-1.0)
+    Pinch { scale: f32, center: f32 },
+    /// Horizontal single-finger swipe; `direction` is +1 for right, -1 for left
+    Swipe { direction: i32 },
+    TwoFingerTap,
+}
+
+impl Gesture {
+    /// Event name passed to the host gesture callback.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Gesture::Pinch { .. } => "pinch",
+            Gesture::Swipe { direction } if *direction > 0 => "swipe-right",
+            Gesture::Swipe { .. } => "swipe-left",
+            Gesture::TwoFingerTap => "two-finger-tap",
+        }
+    }
+}
+
+#[derive(Default)]
+struct TouchTracker {
+    start_time: f64,
+    start_points: Vec<(f64, f64)>,
+    last_points: Vec<(f64, f64)>,
+    max_touches: usize,
+    pending: Vec<Gesture>,
+}
+
+impl TouchTracker {
+    fn touch_start(&mut self, points: Vec<(f64, f64)>, time: f64) {
+        // A new gesture begins when the first finger lands; later fingers extend it
+        if self.start_points.is_empty() || self.max_touches == 0 {
+            self.start_time = time;
+            self.max_touches = 0;
+        }
+        self.max_touches = self.max_touches.max(points.len());
+        self.start_points = points.clone();
+        self.last_points = points;
+    }
+
+    fn touch_move(&mut self, points: Vec<(f64, f64)>) {
+        if points.len() == self.start_points.len() {
+            self.last_points = points;
+        }
+    }
+
+    fn touch_end(&mut self, remaining: usize, time: f64, canvas_width: f64) {
+        if remaining > 0 {
+            return;
+        }
+
+        let duration = time - self.start_time;
+        if self.max_touches == 2 && self.start_points.len() == 2 && self.last_points.len() == 2 {
+            let start_distance = distance(self.start_points[0], self.start_points[1]);
+            let end_distance = distance(self.last_points[0], self.last_points[1]);
+            let scale = if start_distance > 0.0 { end_distance / start_distance } else { 1.0 };
+            let movement = self
+                .start_points
+                .iter()
+                .zip(&self.last_points)
+                .map(|(&a, &b)| distance(a, b))
+                .fold(0.0, f64::max);
+
+            if (scale - 1.0).abs() > PINCH_MIN_CHANGE {
+                let center_x = (self.last_points[0].0 + self.last_points[1].0) * 0.5;
+                self.pending.push(Gesture::Pinch {
+                    scale: scale as f32,
+                    center: (center_x / canvas_width.max(1.0)).clamp(0.0, 1.0) as f32,
+                });
+            } else if duration < TAP_MAX_DURATION && movement < TAP_MAX_MOVEMENT {
+                self.pending.push(Gesture::TwoFingerTap);
+            }
+        } else if self.max_touches == 1 && self.start_points.len() == 1 && self.last_points.len() == 1 {
+            let dx = self.last_points[0].0 - self.start_points[0].0;
+            let dy = self.last_points[0].1 - self.start_points[0].1;
+            if duration < SWIPE_MAX_DURATION && dx.abs() > SWIPE_MIN_DISTANCE && dx.abs() > dy.abs() * 2.0 {
+                self.pending.push(Gesture::Swipe { direction: if dx > 0.0 { 1 } else { -1 } });
+            }
+        }
+
+        self.start_points.clear();
+        self.last_points.clear();
+        self.max_touches = 0;
+    }
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+type TouchListener = Closure<dyn FnMut(TouchEvent)>;
+
+/// Touch listeners attached to the canvas. Listeners are removed when the controller is dropped.
+pub struct GestureController {
+    canvas: HtmlCanvasElement,
+    tracker: Rc<RefCell<TouchTracker>>,
+    listeners: Vec<(&'static str, TouchListener)>,
+}
+
+impl GestureController {
+    pub fn attach(canvas: &HtmlCanvasElement) -> Result<Self, JsValue> {
+        let tracker = Rc::new(RefCell::new(TouchTracker::default()));
+        let mut listeners: Vec<(&'static str, TouchListener)> = Vec::new();
+
+        let start = {
+            let tracker = tracker.clone();
+            let canvas = canvas.clone();
+            TouchListener::new(move |event: TouchEvent| {
+                event.prevent_default();
+                tracker.borrow_mut().touch_start(touch_points(&canvas, &event), event.time_stamp());
+            })
+        };
+        listeners.push(("touchstart", start));
+
+        let moved = {
+            let tracker = tracker.clone();
+            let canvas = canvas.clone();
+            TouchListener::new(move |event: TouchEvent| {
+                event.prevent_default();
+                tracker.borrow_mut().touch_move(touch_points(&canvas, &event));
+            })
+        };
+        listeners.push(("touchmove", moved));
+
+        for name in ["touchend", "touchcancel"] {
+            let tracker = tracker.clone();
+            let canvas = canvas.clone();
+            let end = TouchListener::new(move |event: TouchEvent| {
+                event.prevent_default();
+                let width = canvas.get_bounding_client_rect().width();
+                tracker.borrow_mut().touch_end(event.touches().length() as usize, event.time_stamp(), width);
+            });
+            listeners.push((name, end));
+        }
+
+        for (name, listener) in &listeners {
+            canvas.add_event_listener_with_callback(name, listener.as_ref().unchecked_ref())?;
+        }
+
+        Ok(Self {
+            canvas: canvas.clone(),
+            tracker,
+            listeners,
+        })
+    }
+
+    /// Take all gestures completed since the last call.
+    pub fn drain(&self) -> Vec<Gesture> {
+        std::mem::take(&mut self.tracker.borrow_mut().pending)
+    }
+}
+
+impl Drop for GestureController {
+    fn drop(&mut self) {
+        for (name, listener) in &self.listeners {
+            let _ = self.canvas.remove_event_listener_with_callback(name, listener.as_ref().unchecked_ref());
+        }
+    }
+}
+
+// Touch positions relative to the canvas' top-left corner, in CSS pixels
+fn touch_points(canvas: &HtmlCanvasElement, event: &TouchEvent) -> Vec<(f64, f64)> {
+    let rect = canvas.get_bounding_client_rect();
+    let touches = event.touches();
+    (0..touches.length())
+        .filter_map(|i| touches.get(i))
+        .map(|touch| (touch.client_x() as f64 - rect.left(), touch.client_y() as f64 - rect.top()))
+        .collect()
+}
@@ -0,0 +1,30 @@
+//! A GPU device several `App`s share, for dashboards that show many visualizers at once.
+
+use wasm_bindgen::prelude::*;
+
+use crate::renderer::SharedGpu;
+
+/// Owner of a GPU device that apps initialized with `App.init_shared` (or
+/// `init_headless_shared`) render through, instead of each requesting its own. The device lives
+/// as long as any app using it or this context.
+#[wasm_bindgen]
+pub struct GpuContext {
+    shared: SharedGpu,
+}
+
+#[wasm_bindgen]
+impl GpuContext {
+    /// Request the shared device. In the browser this needs WebGPU, since a WebGL device is tied
+    /// to a single canvas; it rejects without it.
+    #[wasm_bindgen]
+    pub async fn create() -> Result<GpuContext, JsValue> {
+        let shared = SharedGpu::connect().await.map_err(|e| JsValue::from_str(&e))?;
+        Ok(Self { shared })
+    }
+}
+
+impl GpuContext {
+    pub(crate) fn shared(&self) -> &SharedGpu {
+        &self.shared
+    }
+}
@@ -0,0 +1,94 @@
+use wasm_bindgen::prelude::*;
+
+// Texels in the gradient lookup texture
+pub const GRADIENT_WIDTH: u32 = 256;
+
+/// What a bar's position along the gradient is taken from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GradientMode {
+    /// Bar index across the spectrum: lowest frequency at 0.0, highest at 1.0
+    ByIndex,
+    /// Bar magnitude: silent at 0.0, full height at 1.0
+    ByMagnitude,
+}
+
+impl GradientMode {
+    pub fn parse(name: &str) -> Result<Self, JsValue> {
+        match name {
+            "index" => Ok(GradientMode::ByIndex),
+            "magnitude" => Ok(GradientMode::ByMagnitude),
+            _ => Err(JsValue::from_str(&format!("Unknown gradient mode '{}', expected 'index' or 'magnitude'", name))),
+        }
+    }
+
+    /// Value of the mode in the shader's bar style uniform (0 means no gradient).
+    pub fn shader_value(self) -> f32 {
+        match self {
+            GradientMode::ByIndex => 1.0,
+            GradientMode::ByMagnitude => 2.0,
+        }
+    }
+}
+
+/// Color stops at positions 0.0-1.0, linearly interpolated and held flat beyond the first and
+/// last stop.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    stops: Vec<(f32, [f32; 4])>,
+}
+
+impl Gradient {
+    pub fn new(mut stops: Vec<(f32, [f32; 4])>) -> Result<Self, String> {
+        if stops.is_empty() {
+            return Err("A gradient needs at least one stop".to_string());
+        }
+        if let Some((position, _)) = stops.iter().find(|(position, _)| !(0.0..=1.0).contains(position)) {
+            return Err(format!("Gradient stop position {} is outside 0.0-1.0", position));
+        }
+        if stops.iter().any(|(_, color)| color.iter().any(|channel| !channel.is_finite())) {
+            return Err("Gradient stop colors must be finite numbers".to_string());
+        }
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(Self { stops })
+    }
+
+    /// Parse stops flattened as `[position, r, g, b, a, position, r, g, b, a, ...]`, channels 0.0-1.0.
+    pub fn from_flat(values: &[f32]) -> Result<Self, String> {
+        if !values.len().is_multiple_of(5) {
+            return Err(format!(
+                "Gradient stops must be groups of 5 numbers (position, r, g, b, a), got {} numbers",
+                values.len()
+            ));
+        }
+        let stops = values
+            .chunks_exact(5)
+            .map(|stop| (stop[0], [stop[1], stop[2], stop[3], stop[4]]))
+            .collect();
+        Self::new(stops)
+    }
+
+    /// Color at `t` (0.0-1.0).
+    pub fn color_at(&self, t: f32) -> [f32; 4] {
+        let next = self.stops.iter().position(|(position, _)| *position > t);
+        match next {
+            Some(0) => self.stops[0].1,
+            None => self.stops[self.stops.len() - 1].1,
+            Some(next) => {
+                let (start, from) = self.stops[next - 1];
+                let (end, to) = self.stops[next];
+                let blend = (t - start) / (end - start);
+                std::array::from_fn(|channel| from[channel] + (to[channel] - from[channel]) * blend)
+            }
+        }
+    }
+
+    /// RGBA8 texels for the gradient lookup texture.
+    pub fn rasterize(&self) -> Vec<u8> {
+        (0..GRADIENT_WIDTH)
+            .flat_map(|texel| {
+                let t = texel as f32 / (GRADIENT_WIDTH - 1) as f32;
+                self.color_at(t).map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+            })
+            .collect()
+    }
+}
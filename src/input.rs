@@ -0,0 +1,404 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use web_sys::{HtmlCanvasElement, KeyboardEvent, MouseEvent, WheelEvent};
+
+// TypeScript shape of the bindings object taken by `App.set_mouse_input`
+#[wasm_bindgen(typescript_custom_section)]
+const INPUT_BINDINGS_TS: &'static str = r#"
+export interface InputBindings {
+    wheel?: "zoom" | "pan" | "none";
+    drag?: "pan" | "zoom" | "none";
+    click?: "marker" | "solo" | "none";
+    keyboard?: boolean;
+}
+"#;
+
+// Movement in CSS pixels past which a press is a drag rather than a click
+const CLICK_MAX_MOVEMENT: f64 = 5.0;
+// Wheel pixels per notch, for wheels reporting lines or pages
+const WHEEL_LINE_PIXELS: f64 = 40.0;
+const WHEEL_PAGE_PIXELS: f64 = 800.0;
+
+/// Zoom factor per wheel notch
+pub const WHEEL_ZOOM_STEP: f32 = 1.15;
+/// Fraction of the displayed range panned per wheel notch
+pub const WHEEL_PAN_STEP: f32 = 0.1;
+/// Zoom per canvas height dragged, as the exponent of the zoom factor
+pub const DRAG_ZOOM_RATE: f32 = 2.0;
+
+/// What a mouse input does to the view.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputAction {
+    /// Zoom the displayed frequency range about the pointer
+    Zoom,
+    /// Move the displayed frequency range along the bars
+    Pan,
+    /// Add a marker at the playback position, or at the time under the pointer on the waveform strip
+    Marker,
+    /// Toggle the solo on the bar under the pointer, as `App.click` with click-to-solo
+    Solo,
+    None,
+}
+
+impl InputAction {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "zoom" => Ok(InputAction::Zoom),
+            "pan" => Ok(InputAction::Pan),
+            "marker" => Ok(InputAction::Marker),
+            "solo" => Ok(InputAction::Solo),
+            "none" => Ok(InputAction::None),
+            _ => Err(format!("Unknown input action '{}', expected zoom, pan, marker, solo or none", name)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            InputAction::Zoom => "zoom",
+            InputAction::Pan => "pan",
+            InputAction::Marker => "marker",
+            InputAction::Solo => "solo",
+            InputAction::None => "none",
+        }
+    }
+}
+
+/// Arguments of `App.set_mouse_input`; fields left out take the defaults.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputBindings {
+    pub wheel: String,
+    pub drag: String,
+    pub click: String,
+    /// Whether the keys listed under `KeyCommand` work while the canvas has focus
+    pub keyboard: bool,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        Self {
+            wheel: "zoom".to_string(),
+            drag: "pan".to_string(),
+            click: "marker".to_string(),
+            keyboard: true,
+        }
+    }
+}
+
+/// Bindings parsed and checked against what each input can drive: the wheel and drags carry an
+/// amount for zooming or panning, clicks a position for a marker or solo.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ActionBindings {
+    pub wheel: InputAction,
+    pub drag: InputAction,
+    pub click: InputAction,
+    pub keyboard: bool,
+}
+
+impl TryFrom<&InputBindings> for ActionBindings {
+    type Error = String;
+
+    fn try_from(bindings: &InputBindings) -> Result<Self, String> {
+        let bound = |input: &str, name: &str, allowed: [InputAction; 3]| {
+            let action = InputAction::parse(name)?;
+            if !allowed.contains(&action) {
+                let names: Vec<&str> = allowed.iter().map(|action| action.name()).collect();
+                return Err(format!("The {} can't be bound to '{}', expected one of: {}", input, name, names.join(", ")));
+            }
+            Ok(action)
+        };
+        Ok(Self {
+            wheel: bound("wheel", &bindings.wheel, [InputAction::Zoom, InputAction::Pan, InputAction::None])?,
+            drag: bound("drag", &bindings.drag, [InputAction::Pan, InputAction::Zoom, InputAction::None])?,
+            click: bound("click", &bindings.click, [InputAction::Marker, InputAction::Solo, InputAction::None])?,
+            keyboard: bindings.keyboard,
+        })
+    }
+}
+
+/// What a key pressed while the canvas has focus does. Keys held with Ctrl, Alt or Meta are left
+/// to the browser, so its own zoom shortcuts keep working.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyCommand {
+    /// "+" or "=": zoom in on the middle of the displayed range by one wheel notch
+    ZoomIn,
+    /// "-": zoom out by one wheel notch
+    ZoomOut,
+    /// Right or up arrow: pan towards higher frequencies by one wheel notch
+    PanUp,
+    /// Left or down arrow: pan towards lower frequencies
+    PanDown,
+    /// "0": zoom all the way out
+    Reset,
+    /// "m": add a marker at the playback position
+    Marker,
+}
+
+impl KeyCommand {
+    fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "+" | "=" => Some(KeyCommand::ZoomIn),
+            "-" => Some(KeyCommand::ZoomOut),
+            "ArrowRight" | "ArrowUp" => Some(KeyCommand::PanUp),
+            "ArrowLeft" | "ArrowDown" => Some(KeyCommand::PanDown),
+            "0" => Some(KeyCommand::Reset),
+            "m" | "M" => Some(KeyCommand::Marker),
+            _ => None,
+        }
+    }
+}
+
+/// A mouse or keyboard input on the canvas, queued by the listeners and consumed by `App` on the
+/// next render. Positions and drag distances are fractions of the canvas size, from its top-left
+/// corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CanvasInput {
+    /// Wheel turned by `notches`, positive towards the user (scrolling down)
+    Wheel { notches: f32, x: f32, y: f32 },
+    /// Pointer moved by `dx`, `dy` with the button held, since the last drag input
+    Drag { dx: f32, dy: f32 },
+    Click { x: f32, y: f32 },
+    Key(KeyCommand),
+}
+
+#[derive(Default)]
+struct PointerTracker {
+    // Where the button went down and where the pointer was last seen, in CSS pixels
+    press: Option<(f64, f64)>,
+    last: (f64, f64),
+    dragging: bool,
+    pending: Vec<CanvasInput>,
+}
+
+impl PointerTracker {
+    fn down(&mut self, point: (f64, f64)) {
+        self.press = Some(point);
+        self.last = point;
+        self.dragging = false;
+    }
+
+    fn moved(&mut self, point: (f64, f64), size: (f64, f64)) {
+        let Some(press) = self.press else {
+            return;
+        };
+        if !self.dragging && (point.0 - press.0).hypot(point.1 - press.1) > CLICK_MAX_MOVEMENT {
+            self.dragging = true;
+        }
+        if self.dragging {
+            self.pending.push(CanvasInput::Drag {
+                dx: ((point.0 - self.last.0) / size.0.max(1.0)) as f32,
+                dy: ((point.1 - self.last.1) / size.1.max(1.0)) as f32,
+            });
+            self.last = point;
+        }
+    }
+
+    fn up(&mut self, point: (f64, f64), size: (f64, f64)) {
+        if self.press.take().is_some() && !self.dragging {
+            self.pending.push(CanvasInput::Click {
+                x: (point.0 / size.0.max(1.0)) as f32,
+                y: (point.1 / size.1.max(1.0)) as f32,
+            });
+        }
+        self.dragging = false;
+    }
+
+    fn cancel(&mut self) {
+        self.press = None;
+        self.dragging = false;
+    }
+}
+
+type MouseListener = Closure<dyn FnMut(MouseEvent)>;
+type WheelListener = Closure<dyn FnMut(WheelEvent)>;
+type KeyListener = Closure<dyn FnMut(KeyboardEvent)>;
+
+/// Mouse and keyboard listeners attached to the canvas, which is made focusable so it can take
+/// key presses. Listeners are removed when the controller is dropped.
+pub struct InputController {
+    canvas: HtmlCanvasElement,
+    tracker: Rc<RefCell<PointerTracker>>,
+    listeners: Vec<(&'static str, MouseListener)>,
+    wheel: WheelListener,
+    keydown: KeyListener,
+    // Whether the canvas had no tabindex before, so dropping the controller removes the one added
+    added_tab_index: bool,
+}
+
+impl InputController {
+    pub fn attach(canvas: &HtmlCanvasElement) -> Result<Self, JsValue> {
+        let tracker = Rc::new(RefCell::new(PointerTracker::default()));
+        let mut listeners: Vec<(&'static str, MouseListener)> = Vec::new();
+
+        let down = {
+            let tracker = tracker.clone();
+            let canvas = canvas.clone();
+            MouseListener::new(move |event: MouseEvent| {
+                if event.button() == 0 {
+                    tracker.borrow_mut().down(mouse_point(&canvas, &event));
+                }
+            })
+        };
+        listeners.push(("mousedown", down));
+
+        let moved = {
+            let tracker = tracker.clone();
+            let canvas = canvas.clone();
+            MouseListener::new(move |event: MouseEvent| {
+                tracker.borrow_mut().moved(mouse_point(&canvas, &event), canvas_size(&canvas));
+            })
+        };
+        listeners.push(("mousemove", moved));
+
+        let up = {
+            let tracker = tracker.clone();
+            let canvas = canvas.clone();
+            MouseListener::new(move |event: MouseEvent| {
+                if event.button() == 0 {
+                    tracker.borrow_mut().up(mouse_point(&canvas, &event), canvas_size(&canvas));
+                }
+            })
+        };
+        listeners.push(("mouseup", up));
+
+        let leave = {
+            let tracker = tracker.clone();
+            MouseListener::new(move |_: MouseEvent| tracker.borrow_mut().cancel())
+        };
+        listeners.push(("mouseleave", leave));
+
+        let wheel = {
+            let tracker = tracker.clone();
+            let canvas = canvas.clone();
+            WheelListener::new(move |event: WheelEvent| {
+                // Keep the page from scrolling while the canvas takes the wheel
+                event.prevent_default();
+                let pixels = match event.delta_mode() {
+                    WheelEvent::DOM_DELTA_LINE => event.delta_y() * WHEEL_LINE_PIXELS,
+                    WheelEvent::DOM_DELTA_PAGE => event.delta_y() * WHEEL_PAGE_PIXELS,
+                    _ => event.delta_y(),
+                };
+                let (x, y) = mouse_point(&canvas, &event);
+                let (width, height) = canvas_size(&canvas);
+                tracker.borrow_mut().pending.push(CanvasInput::Wheel {
+                    notches: (pixels / WHEEL_LINE_PIXELS / 2.5) as f32,
+                    x: (x / width.max(1.0)) as f32,
+                    y: (y / height.max(1.0)) as f32,
+                });
+            })
+        };
+
+        let keydown = {
+            let tracker = tracker.clone();
+            KeyListener::new(move |event: KeyboardEvent| {
+                if event.ctrl_key() || event.alt_key() || event.meta_key() {
+                    return;
+                }
+                if let Some(command) = KeyCommand::from_key(&event.key()) {
+                    // Keep the arrows from scrolling the page
+                    event.prevent_default();
+                    tracker.borrow_mut().pending.push(CanvasInput::Key(command));
+                }
+            })
+        };
+
+        for (name, listener) in &listeners {
+            canvas.add_event_listener_with_callback(name, listener.as_ref().unchecked_ref())?;
+        }
+        canvas.add_event_listener_with_callback("wheel", wheel.as_ref().unchecked_ref())?;
+        canvas.add_event_listener_with_callback("keydown", keydown.as_ref().unchecked_ref())?;
+
+        // A canvas only receives key presses once it can take focus, which a click then gives it
+        let added_tab_index = !canvas.has_attribute("tabindex");
+        if added_tab_index {
+            canvas.set_tab_index(0);
+        }
+
+        Ok(Self {
+            canvas: canvas.clone(),
+            tracker,
+            listeners,
+            wheel,
+            keydown,
+            added_tab_index,
+        })
+    }
+
+    /// Take all inputs queued since the last call.
+    pub fn drain(&self) -> Vec<CanvasInput> {
+        std::mem::take(&mut self.tracker.borrow_mut().pending)
+    }
+}
+
+impl Drop for InputController {
+    fn drop(&mut self) {
+        for (name, listener) in &self.listeners {
+            let _ = self.canvas.remove_event_listener_with_callback(name, listener.as_ref().unchecked_ref());
+        }
+        let _ = self.canvas.remove_event_listener_with_callback("wheel", self.wheel.as_ref().unchecked_ref());
+        let _ = self.canvas.remove_event_listener_with_callback("keydown", self.keydown.as_ref().unchecked_ref());
+        if self.added_tab_index {
+            let _ = self.canvas.remove_attribute("tabindex");
+        }
+    }
+}
+
+// Pointer position relative to the canvas' top-left corner, in CSS pixels
+fn mouse_point(canvas: &HtmlCanvasElement, event: &MouseEvent) -> (f64, f64) {
+    let rect = canvas.get_bounding_client_rect();
+    (event.client_x() as f64 - rect.left(), event.client_y() as f64 - rect.top())
+}
+
+// Canvas size on the page in CSS pixels
+fn canvas_size(canvas: &HtmlCanvasElement) -> (f64, f64) {
+    let rect = canvas.get_bounding_client_rect();
+    (rect.width(), rect.height())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_map_to_commands() {
+        assert_eq!(KeyCommand::from_key("+"), Some(KeyCommand::ZoomIn));
+        assert_eq!(KeyCommand::from_key("="), Some(KeyCommand::ZoomIn));
+        assert_eq!(KeyCommand::from_key("-"), Some(KeyCommand::ZoomOut));
+        assert_eq!(KeyCommand::from_key("ArrowUp"), Some(KeyCommand::PanUp));
+        assert_eq!(KeyCommand::from_key("ArrowLeft"), Some(KeyCommand::PanDown));
+        assert_eq!(KeyCommand::from_key("0"), Some(KeyCommand::Reset));
+        assert_eq!(KeyCommand::from_key("M"), Some(KeyCommand::Marker));
+        assert_eq!(KeyCommand::from_key("Enter"), None);
+    }
+
+    #[test]
+    fn bindings_are_checked_per_input() {
+        let defaults = ActionBindings::try_from(&InputBindings::default()).unwrap();
+        assert_eq!(defaults.wheel, InputAction::Zoom);
+        assert!(defaults.keyboard);
+
+        let bindings = InputBindings {
+            click: "zoom".to_string(),
+            ..InputBindings::default()
+        };
+        assert!(ActionBindings::try_from(&bindings).unwrap_err().contains("click can't be bound to 'zoom'"));
+    }
+
+    #[test]
+    fn short_presses_are_clicks_and_longer_moves_drags() {
+        let size = (200.0, 100.0);
+        let mut tracker = PointerTracker::default();
+        tracker.down((50.0, 50.0));
+        tracker.moved((52.0, 51.0), size);
+        tracker.up((52.0, 51.0), size);
+        assert_eq!(tracker.pending, [CanvasInput::Click { x: 0.26, y: 0.51 }]);
+
+        tracker.pending.clear();
+        tracker.down((50.0, 50.0));
+        tracker.moved((70.0, 50.0), size);
+        tracker.up((70.0, 50.0), size);
+        assert_eq!(tracker.pending, [CanvasInput::Drag { dx: 0.1, dy: 0.0 }]);
+    }
+}
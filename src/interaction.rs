@@ -0,0 +1,61 @@
+use wasm_bindgen::prelude::*;
+
+use crate::layout::BarArrangement;
+
+/// Result of a pointer hit test against the bar layout.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct BarHit {
+    /// Index of the bar under the pointer
+    pub bar_index: usize,
+    /// Lower edge of the bar's frequency range in Hz
+    pub freq_start: f32,
+    /// Upper edge of the bar's frequency range in Hz
+    pub freq_end: f32,
+    /// Currently displayed (smoothed) bar value, 0.0-1.0
+    pub value: f32,
+    /// Unscaled level of the bar's frequency range in dBFS for the current frame
+    pub db: f32,
+    /// Whether the pointer is inside the drawn bar rather than just its column
+    pub inside_bar: bool,
+}
+
+/// Map a position in bar space (`spread` across the bars, `growth` from the bottom edge, both
+/// 0.0-1.0) to the nearest bar and the position along that bar measured from its base, undoing
+/// the arrangement and order the same way as `barsLayer` in shaders/common.wgsl.
+pub fn bar_at_position(spread: f32, growth: f32, bin_size: usize, arrangement: BarArrangement, reversed: bool) -> (usize, f32) {
+    if bin_size == 0 {
+        return (0, growth);
+    }
+    let spread = spread.clamp(0.0, 1.0);
+    let slot = match arrangement {
+        // Mirrored bars sit at `(slot + 0.5) / bin_size` of each half, measured from the center
+        BarArrangement::Mirrored => ((spread - 0.5).abs() * 2.0 * bin_size as f32).floor() as usize,
+        // Otherwise at `slot / bin_size` across the canvas
+        _ => (spread * bin_size as f32).round() as usize,
+    }
+    .min(bin_size - 1);
+    let index = if reversed { bin_size - 1 - slot } else { slot };
+
+    let along_bar = match arrangement {
+        BarArrangement::BottomUp | BarArrangement::Mirrored => growth,
+        BarArrangement::TopDown => 1.0 - growth,
+        // Each half is drawn at half height, so rescale to compare against `bar_top`
+        BarArrangement::DualSided => (growth - 0.5).abs() * 2.0,
+    };
+    (index, along_bar)
+}
+
+/// Height of a bar's top above the bottom of the canvas (0.0-1.0), matching `barsLayer` in shaders/common.wgsl.
+pub fn bar_top(value: f32, min_height: f32) -> f32 {
+    const MAX_HEIGHT: f32 = 0.8;
+    let amplitude = (value * 2.0).clamp(0.0, 1.0);
+    min_height + amplitude * (MAX_HEIGHT - min_height)
+}
+
+/// Convert an FFT magnitude of a Hann-windowed 1024-sample frame to dBFS.
+pub fn magnitude_to_dbfs(magnitude: f32) -> f32 {
+    // A full-scale sine peaks at N/2 * 0.5 (Hann coherent gain) = 256
+    const FULL_SCALE: f32 = 256.0;
+    20.0 * (magnitude / FULL_SCALE).max(1e-6).log10()
+}
@@ -0,0 +1,95 @@
+//! Musical key estimation: the track's pitch-class distribution correlated with the
+//! Krumhansl-Kessler key profiles for all 24 major and minor keys.
+
+use wasm_bindgen::prelude::*;
+
+use crate::analysis::PITCH_CLASSES;
+use crate::pitch::NOTE_NAMES;
+
+// Range of spectral peaks counted towards the pitch-class distribution
+const KEY_MIN_FREQ: f32 = 80.0;
+const KEY_MAX_FREQ: f32 = 5000.0;
+
+// Probe-tone ratings of each pitch class relative to the tonic (Krumhansl & Kessler, 1982)
+const MAJOR_PROFILE: [f32; PITCH_CLASSES] = [6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88];
+const MINOR_PROFILE: [f32; PITCH_CLASSES] = [6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17];
+
+/// Estimated key of a track.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyEstimate {
+    /// Pitch class of the tonic, 0 = C ... 11 = B
+    pub tonic: u8,
+    pub minor: bool,
+    /// Tonic and mode, e.g. "F# minor"
+    pub name: String,
+    /// Correlation of the track's pitch-class distribution with the key's profile, up to 1.0;
+    /// low values mean the music isn't clearly in any one key
+    pub confidence: f32,
+}
+
+/// The best matching key for a track's `fft_size`-point FFT magnitude frames, or `None` if they
+/// hold no pitched energy.
+pub fn estimate_key(fft_frames: impl IntoIterator<Item = impl AsRef<[f32]>>, sample_rate: u32, fft_size: usize) -> Option<KeyEstimate> {
+    let mut totals = [0.0f32; PITCH_CLASSES];
+    for fft_frame in fft_frames {
+        add_peak_chroma(&mut totals, fft_frame.as_ref(), sample_rate, fft_size);
+    }
+    if totals.iter().all(|&total| total <= 0.0) {
+        return None;
+    }
+
+    (0..PITCH_CLASSES)
+        .flat_map(|tonic| [(tonic, false), (tonic, true)])
+        .map(|(tonic, minor)| {
+            let profile = if minor { &MINOR_PROFILE } else { &MAJOR_PROFILE };
+            let rotated: [f32; PITCH_CLASSES] = std::array::from_fn(|pitch_class| profile[(pitch_class + PITCH_CLASSES - tonic) % PITCH_CLASSES]);
+            (tonic, minor, correlation(&totals, &rotated))
+        })
+        .max_by(|a, b| a.2.total_cmp(&b.2))
+        .map(|(tonic, minor, confidence)| KeyEstimate {
+            tonic: tonic as u8,
+            minor,
+            name: format!("{} {}", NOTE_NAMES[tonic], if minor { "minor" } else { "major" }),
+            confidence,
+        })
+}
+
+// Add the spectral peaks of a frame to pitch-class totals. Unlike the display chromagram, which
+// sums every bin, each local maximum's frequency is refined by parabolic interpolation first,
+// so a low note's leakage into the bins either side doesn't count towards the neighbouring classes.
+fn add_peak_chroma(totals: &mut [f32; PITCH_CLASSES], fft_frame: &[f32], sample_rate: u32, fft_size: usize) {
+    let bin_hz = sample_rate as f32 / fft_size as f32;
+    let bins = &fft_frame[..(fft_size / 2).min(fft_frame.len())];
+    for bin in 1..bins.len().saturating_sub(1) {
+        let (before, peak, after) = (bins[bin - 1], bins[bin], bins[bin + 1]);
+        if peak <= before || peak < after {
+            continue;
+        }
+        let curvature = before - 2.0 * peak + after;
+        let shift = if curvature < 0.0 { 0.5 * (before - after) / curvature } else { 0.0 };
+        let freq = (bin as f32 + shift) * bin_hz;
+        if !(KEY_MIN_FREQ..=KEY_MAX_FREQ).contains(&freq) {
+            continue;
+        }
+        // Semitones from A4 (440 Hz); A is pitch class 9
+        let semitone = (12.0 * (freq / 440.0).log2()).round() as i32;
+        totals[(semitone + 9).rem_euclid(PITCH_CLASSES as i32) as usize] += peak;
+    }
+}
+
+// Pearson correlation of two pitch-class distributions
+fn correlation(a: &[f32; PITCH_CLASSES], b: &[f32; PITCH_CLASSES]) -> f32 {
+    let mean = |values: &[f32; PITCH_CLASSES]| values.iter().sum::<f32>() / PITCH_CLASSES as f32;
+    let (mean_a, mean_b) = (mean(a), mean(b));
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    for (&x, &y) in a.iter().zip(b) {
+        covariance += (x - mean_a) * (y - mean_b);
+        variance_a += (x - mean_a).powi(2);
+        variance_b += (y - mean_b).powi(2);
+    }
+    if variance_a <= 0.0 || variance_b <= 0.0 {
+        return 0.0;
+    }
+    covariance / (variance_a * variance_b).sqrt()
+}
@@ -0,0 +1,239 @@
+use wasm_bindgen::prelude::*;
+
+/// Direction the bars grow in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Orientation {
+    /// Bars spread left-to-right and grow upward from the bottom edge
+    #[default]
+    Vertical,
+    /// Bars spread top-to-bottom and grow rightward from the left edge
+    Horizontal,
+}
+
+impl Orientation {
+    pub fn parse(name: &str) -> Result<Self, JsValue> {
+        match name {
+            "vertical" => Ok(Orientation::Vertical),
+            "horizontal" => Ok(Orientation::Horizontal),
+            _ => Err(JsValue::from_str(&format!("Unknown orientation '{}', expected 'vertical' or 'horizontal'", name))),
+        }
+    }
+
+    /// Name accepted by `parse`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Orientation::Vertical => "vertical",
+            Orientation::Horizontal => "horizontal",
+        }
+    }
+}
+
+/// How the bars are arranged within their orientation (described here for vertical bars).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum BarArrangement {
+    /// Bars grow upward from the bottom edge, lowest frequency on the left
+    #[default]
+    BottomUp,
+    /// Bars hang down from the top edge
+    TopDown,
+    /// The spectrum is mirrored about the vertical center line, lowest frequency in the middle
+    Mirrored,
+    /// Bars grow both up and down from the horizontal center line
+    DualSided,
+}
+
+impl BarArrangement {
+    pub fn parse(name: &str) -> Result<Self, JsValue> {
+        match name {
+            "bottom-up" => Ok(BarArrangement::BottomUp),
+            "top-down" => Ok(BarArrangement::TopDown),
+            "mirrored" => Ok(BarArrangement::Mirrored),
+            "dual-sided" => Ok(BarArrangement::DualSided),
+            _ => Err(JsValue::from_str(&format!(
+                "Unknown bar arrangement '{}', expected 'bottom-up', 'top-down', 'mirrored' or 'dual-sided'",
+                name
+            ))),
+        }
+    }
+
+    /// Name accepted by `parse`.
+    pub fn name(self) -> &'static str {
+        match self {
+            BarArrangement::BottomUp => "bottom-up",
+            BarArrangement::TopDown => "top-down",
+            BarArrangement::Mirrored => "mirrored",
+            BarArrangement::DualSided => "dual-sided",
+        }
+    }
+
+    /// Value of the arrangement in the shader's layout uniform.
+    pub fn shader_value(self) -> f32 {
+        match self {
+            BarArrangement::BottomUp => 0.0,
+            BarArrangement::TopDown => 1.0,
+            BarArrangement::Mirrored => 2.0,
+            BarArrangement::DualSided => 3.0,
+        }
+    }
+}
+
+/// Shape of the bars drawn by the bars mode.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BarStyle {
+    /// Fraction of each bar's slot left empty between neighbouring bars (0.0-0.95)
+    pub gap_fraction: f32,
+    /// Corner radius as a fraction of the bar width (0.0 = square, 0.5 = fully rounded ends)
+    pub corner_radius: f32,
+    /// Height of a silent bar as a fraction of the canvas height (0.0-0.8)
+    pub min_height: f32,
+}
+
+impl BarStyle {
+    pub fn new(gap_fraction: f32, corner_radius: f32, min_height: f32) -> Self {
+        Self {
+            gap_fraction: gap_fraction.clamp(0.0, 0.95),
+            corner_radius: corner_radius.clamp(0.0, 0.5),
+            min_height: min_height.clamp(0.0, 0.8),
+        }
+    }
+}
+
+impl Default for BarStyle {
+    // Thin rounded lines, as the bars were drawn before they could be styled
+    fn default() -> Self {
+        Self {
+            gap_fraction: 0.8,
+            corner_radius: 0.5,
+            min_height: 0.05,
+        }
+    }
+}
+
+/// Where the HUD (waveform strip) is placed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum HudPosition {
+    #[default]
+    Top,
+    Bottom,
+}
+
+impl HudPosition {
+    pub fn parse(name: &str) -> Result<Self, JsValue> {
+        match name {
+            "top" => Ok(HudPosition::Top),
+            "bottom" => Ok(HudPosition::Bottom),
+            _ => Err(JsValue::from_str(&format!("Unknown HUD position '{}', expected 'top' or 'bottom'", name))),
+        }
+    }
+}
+
+/// How a split view divides the canvas between its two views.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplitLayout {
+    /// The first view on the left, the second on the right
+    SideBySide,
+    /// The first view on top, the second below
+    Stacked,
+}
+
+impl SplitLayout {
+    /// "side-by-side" or "stacked"; "none" for no split.
+    pub fn parse(name: &str) -> Result<Option<Self>, JsValue> {
+        match name {
+            "none" => Ok(None),
+            "side-by-side" => Ok(Some(SplitLayout::SideBySide)),
+            "stacked" => Ok(Some(SplitLayout::Stacked)),
+            _ => Err(JsValue::from_str(&format!(
+                "Unknown split layout '{}', expected 'none', 'side-by-side' or 'stacked'",
+                name
+            ))),
+        }
+    }
+
+    /// (x, y, width, height) of the first and second views in a `width` x `height` target; the
+    /// second view gets the odd pixel.
+    pub fn viewports(self, width: u32, height: u32) -> [(u32, u32, u32, u32); 2] {
+        match self {
+            SplitLayout::SideBySide => {
+                let first = (width / 2).max(1);
+                [(0, 0, first, height), (first, 0, width.saturating_sub(first).max(1), height)]
+            }
+            SplitLayout::Stacked => {
+                let first = (height / 2).max(1);
+                [(0, 0, width, first), (0, first, width, height.saturating_sub(first).max(1))]
+            }
+        }
+    }
+}
+
+/// Canvas shape buckets that can each have their own layout variant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AspectClass {
+    Portrait,
+    Landscape,
+    Ultrawide,
+}
+
+impl AspectClass {
+    pub fn parse(name: &str) -> Result<Self, JsValue> {
+        match name {
+            "portrait" => Ok(AspectClass::Portrait),
+            "landscape" => Ok(AspectClass::Landscape),
+            "ultrawide" => Ok(AspectClass::Ultrawide),
+            _ => Err(JsValue::from_str(&format!(
+                "Unknown aspect class '{}', expected 'portrait', 'landscape' or 'ultrawide'",
+                name
+            ))),
+        }
+    }
+
+    /// Classify a canvas size: taller than wide is portrait, wider than 2:1 is ultrawide.
+    pub fn from_size(width: u32, height: u32) -> Self {
+        let aspect = width as f32 / height.max(1) as f32;
+        if aspect < 0.9 {
+            AspectClass::Portrait
+        } else if aspect > 2.0 {
+            AspectClass::Ultrawide
+        } else {
+            AspectClass::Landscape
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LayoutVariant {
+    pub bin_size: usize,
+    pub orientation: Orientation,
+    pub hud_position: HudPosition,
+}
+
+/// Layout variants per aspect class, picked automatically on resize when enabled.
+#[derive(Clone, Debug, Default)]
+pub struct ResponsiveLayout {
+    pub enabled: bool,
+    portrait: Option<LayoutVariant>,
+    landscape: Option<LayoutVariant>,
+    ultrawide: Option<LayoutVariant>,
+}
+
+impl ResponsiveLayout {
+    pub fn set_variant(&mut self, class: AspectClass, variant: LayoutVariant) {
+        match class {
+            AspectClass::Portrait => self.portrait = Some(variant),
+            AspectClass::Landscape => self.landscape = Some(variant),
+            AspectClass::Ultrawide => self.ultrawide = Some(variant),
+        }
+    }
+
+    /// Variant for a canvas size, if responsive layout is enabled and one is defined.
+    pub fn variant_for(&self, width: u32, height: u32) -> Option<LayoutVariant> {
+        if !self.enabled {
+            return None;
+        }
+        match AspectClass::from_size(width, height) {
+            AspectClass::Portrait => self.portrait,
+            AspectClass::Landscape => self.landscape,
+            AspectClass::Ultrawide => self.ultrawide,
+        }
+    }
+}
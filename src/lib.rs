@@ -1,27 +1,198 @@
 use wasm_bindgen::prelude::*;
-use web_sys::console;
-use std::io::Cursor;
-use phastft::planner::Direction;
-
-mod renderer;
-use renderer::Renderer;
 
 // A macro to provide `println!(..)`-style syntax for `console.log` logging.
+// Falls back to stdout on native targets, where there is no console to call into.
 macro_rules! log {
-    ( $( $t:tt )* ) => {
-        console::log_1(&format!( $( $t )* ).into());
-    }
+    ( $( $t:tt )* ) => {{
+        #[cfg(target_arch = "wasm32")]
+        web_sys::console::log_1(&format!( $( $t )* ).into());
+        #[cfg(not(target_arch = "wasm32"))]
+        println!( $( $t )* );
+    }}
 }
 
+pub mod analysis;
+mod analyzer;
+mod antialiasing;
+mod axis;
+mod audio_info;
+mod bar_smoothing;
+mod bar_storage;
+mod batch;
+mod beat;
+mod browser_decode;
+mod capture;
+mod config;
+#[cfg(feature = "dev")]
+mod dev;
+mod events;
+mod feedback;
+mod fetch;
+mod frame_cache;
+mod gestures;
+mod gpu_context;
+mod gradient;
+mod input;
+mod interaction;
+mod key;
+mod layout;
+mod lifecycle;
+mod live;
+mod locale;
+mod lyrics;
+pub mod loudness;
+mod mapping_diff;
+mod markers;
+mod metadata;
+pub mod mfcc;
+#[cfg(feature = "native")]
+pub mod native;
+mod overlay;
+mod pipeline_cache;
+pub mod pitch;
+#[cfg(feature = "presets")]
+mod preset;
+pub mod reference;
+mod renderer;
+mod state;
+mod split_view;
+mod structure;
+mod telemetry;
+mod text;
+mod theme;
+pub mod time;
+mod timing;
+mod transition;
+mod underrun;
+mod uniforms;
+mod visualizer;
+pub use analyzer::{AudioAnalyzer, FrameFeatures, StereoMeter};
+pub use audio_info::AudioInfo;
+pub use batch::{BatchFileResult, BatchResult, BatchSummary};
+pub use gpu_context::GpuContext;
+pub use interaction::BarHit;
+pub use key::KeyEstimate;
+pub use live::LiveAnalyzer;
+pub use mapping_diff::{MappingConfig, MappingDiff};
+pub use markers::Marker;
+pub use metadata::TrackMetadata;
+pub use structure::Section;
+pub use telemetry::TelemetryReport;
+pub use time::TimeBase;
+#[cfg(feature = "dev")]
+pub use visualizer::ShaderDiagnostic;
+use analysis::{DEFAULT_MAX_FREQ, DEFAULT_MIN_FREQ, PITCH_CLASSES};
+use antialiasing::Antialiasing;
+use axis::{AxisLayout, AxisOptions};
+use beat::{BeatDetector, BeatEffectSettings, BeatEffects};
+use config::{BarLayoutConfig, BarStyleConfig, ThemeConfig, ViberConfig};
+#[cfg(feature = "dev")]
+use dev::ConfigDebouncer;
+use events::{AppEvent, EventEmitter};
+use feedback::FeedbackSettings;
+use gestures::{Gesture, GestureController};
+use gradient::{Gradient, GradientMode};
+use input::{ActionBindings, CanvasInput, InputAction, InputBindings, InputController, KeyCommand, DRAG_ZOOM_RATE, WHEEL_PAN_STEP, WHEEL_ZOOM_STEP};
+use layout::{AspectClass, BarArrangement, BarStyle, HudPosition, LayoutVariant, Orientation, ResponsiveLayout, SplitLayout};
+use lifecycle::{InitTarget, LifecycleState, PendingInit, PendingProcessing};
+use live::SharedBars;
+use locale::Locale;
+use lyrics::Lyrics;
+use overlay::OverlayText;
+#[cfg(feature = "presets")]
+use preset::Preset;
+use renderer::{GpuTarget, Renderer};
+use state::AppState;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::ops::Range;
+use std::rc::Rc;
+use telemetry::Telemetry;
+use theme::{ColorScheme, ThemeSettings};
+use timing::{FramePlan, LoopRegion, PauseTracker, PlaybackClock, PlaybackDirection, PowerSaving, RefreshRateEstimator};
+use transition::Easing;
+use underrun::UnderrunHold;
+
 #[wasm_bindgen]
 pub struct App {
+    state: LifecycleState,
+    init_target: Option<InitTarget>,
+    pending_init: Option<PendingInit>,
     renderer: Renderer,
-    audio_frames: Vec<Vec<f32>>,
-    fft_results: Vec<Vec<f32>>,
-    frequency_bars: Vec<Vec<f32>>,
+    analyzer: AudioAnalyzer,
+    pending_processing: Option<PendingProcessing>,
+    // Bars a `LiveAnalyzer` publishes, drawn instead of the loaded track's while it has any
+    live_bars: Option<SharedBars>,
+    // Live bars by the time they were read, kept for `latency_offset`
+    live_history: VecDeque<(f64, Vec<f32>)>,
+    // Seconds the bars are drawn behind the playback position (or live input), to line up with
+    // what is heard
+    latency_offset: f64,
+    // Speed the host plays the track at, 1.0 for normal speed
+    playback_rate: f32,
+    direction: PlaybackDirection,
+    loop_region: Option<LoopRegion>,
+    // Smoothed bars, or with GPU smoothing the raw targets the GPU eases towards
     previous_bars: Vec<f32>,
-    audio_processed: bool,
-    bin_size: usize,
+    gpu_smoothing: bool,
+    previous_chroma: [f32; PITCH_CLASSES],
+    // Smoothed right channel bars, empty while there is no right channel to show
+    previous_right_bars: Vec<f32>,
+    // Smoothed correlation and width for the HUD stereo meter
+    previous_stereo_meter: [f32; 2],
+    split_view: bool,
+    // Second track for the split view, which shows the right channel while there is none
+    split_track: Option<AudioAnalyzer>,
+    // Smoothed bars of the split view's second view
+    previous_split_bars: Vec<f32>,
+    stereo_meter: bool,
+    current_frame: usize,
+    hover_highlight: bool,
+    solo_on_click: bool,
+    soloed_bar: Option<usize>,
+    solo_callback: Option<js_sys::Function>,
+    gestures: Option<GestureController>,
+    gesture_callback: Option<js_sys::Function>,
+    mouse_input: Option<(InputController, ActionBindings)>,
+    // The range set with `set_display_range` or `configure`, which zooming and panning stay inside
+    display_bounds: (f32, f32),
+    responsive_layout: ResponsiveLayout,
+    overlay: OverlayText,
+    debug_overlay: bool,
+    axis: Option<AxisOptions>,
+    marker_ticks: bool,
+    lyrics: Option<Lyrics>,
+    lyrics_overlay: bool,
+    locale: Locale,
+    theme: ThemeSettings,
+    refresh_rate: RefreshRateEstimator,
+    last_rendered_frame: Option<usize>,
+    skipped_frames: u64,
+    playback_clock: PlaybackClock,
+    pause_tracker: PauseTracker,
+    time_origin: Option<f64>,
+    telemetry: Option<Telemetry>,
+    beat_effects: Option<BeatEffects>,
+    underrun: UnderrunHold,
+    events: EventEmitter,
+    // Onsets reported as "beat" events, apart from the beat effects' own detection
+    event_beats: BeatDetector,
+    // Last frame the frame events were raised for, so a frame drawn twice raises them once
+    event_frame: Option<usize>,
+    // Sections of the loaded track, computed for the first "section-change" listener
+    sections: Option<Vec<Section>>,
+    current_section: Option<usize>,
+    #[cfg(feature = "presets")]
+    preset: Option<Preset>,
+    #[cfg(feature = "dev")]
+    pending_config: ConfigDebouncer,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[wasm_bindgen]
@@ -32,511 +203,2630 @@ impl App {
         log!("Initializing music visualizer...");
 
         Self {
+            state: LifecycleState::Created,
+            init_target: None,
+            pending_init: None,
             renderer: Renderer::new(),
-            audio_frames: Vec::new(),
-            fft_results: Vec::new(),
-            frequency_bars: Vec::new(),
+            analyzer: AudioAnalyzer::new(),
+            pending_processing: None,
+            live_bars: None,
+            live_history: VecDeque::new(),
+            latency_offset: 0.0,
+            playback_rate: 1.0,
+            direction: PlaybackDirection::Forward,
+            loop_region: None,
             previous_bars: vec![0.0; 64],
-            audio_processed: false,
-            bin_size: 64,
+            gpu_smoothing: false,
+            previous_chroma: [0.0; PITCH_CLASSES],
+            previous_right_bars: Vec::new(),
+            previous_stereo_meter: [0.0; 2],
+            split_view: false,
+            split_track: None,
+            previous_split_bars: Vec::new(),
+            stereo_meter: false,
+            current_frame: 0,
+            hover_highlight: false,
+            solo_on_click: false,
+            soloed_bar: None,
+            solo_callback: None,
+            gestures: None,
+            gesture_callback: None,
+            mouse_input: None,
+            display_bounds: (DEFAULT_MIN_FREQ, DEFAULT_MAX_FREQ),
+            responsive_layout: ResponsiveLayout::default(),
+            overlay: OverlayText::default(),
+            debug_overlay: false,
+            axis: None,
+            marker_ticks: false,
+            lyrics: None,
+            lyrics_overlay: false,
+            locale: Locale::default(),
+            theme: ThemeSettings::default(),
+            refresh_rate: RefreshRateEstimator::default(),
+            last_rendered_frame: None,
+            skipped_frames: 0,
+            playback_clock: PlaybackClock::default(),
+            pause_tracker: PauseTracker::default(),
+            time_origin: None,
+            telemetry: None,
+            beat_effects: None,
+            underrun: UnderrunHold::default(),
+            events: EventEmitter::default(),
+            event_beats: BeatDetector::default(),
+            event_frame: None,
+            sections: None,
+            current_section: None,
+            #[cfg(feature = "presets")]
+            preset: None,
+            #[cfg(feature = "dev")]
+            pending_config: ConfigDebouncer::default(),
         }
     }
 
+    /// Current lifecycle state: "created", "initialized" (after `init`/`init_headless`),
+    /// "loaded" (after `process_audio_file`) or "playing" (once a loaded frame has been rendered).
+    #[wasm_bindgen]
+    pub fn get_state(&mut self) -> String {
+        self.finish_init();
+        self.state.name().to_string()
+    }
+
+    /// Initialize against the canvas with id `canvas_id`. Safe to call repeatedly (e.g. from React
+    /// strict-mode double effects): calls while an init is in flight share its promise, and calls
+    /// for the same canvas afterwards resolve immediately. A call for a different canvas rejects
+    /// with an error named "AlreadyInitialized".
+    #[wasm_bindgen(unchecked_return_type = "Promise<void>")]
+    pub fn init(&mut self, canvas_id: &str) -> js_sys::Promise {
+        let connect = Renderer::connect_canvas(canvas_id.to_string());
+        self.start_init(InitTarget::Canvas(canvas_id.to_string()), connect)
+    }
+
+    /// Initialize without a canvas, for offscreen use (golden-image tests, server-side thumbnails).
+    /// Frames are retrieved with `render_to_pixels` or `capture_frame`. Requires WebGPU or a native
+    /// GPU backend, since WebGL cannot run without a canvas. Repeated calls behave as for `init`.
+    #[wasm_bindgen(unchecked_return_type = "Promise<void>")]
+    pub fn init_headless(&mut self, width: u32, height: u32) -> js_sys::Promise {
+        self.start_init(InitTarget::Headless(width, height), Renderer::connect_headless(width, height))
+    }
+
+    /// `init` through `context`'s GPU device, shared with every other app initialized with it,
+    /// so several visualizers on a page cost one device. Needs WebGPU, as `GpuContext.create` does.
+    #[wasm_bindgen(unchecked_return_type = "Promise<void>")]
+    pub fn init_shared(&mut self, canvas_id: &str, context: &GpuContext) -> js_sys::Promise {
+        let (canvas, shared) = (canvas_id.to_string(), context.shared().clone());
+        let connect = async move { Renderer::connect_shared_canvas(&canvas, &shared) };
+        self.start_init(InitTarget::Canvas(canvas_id.to_string()), connect)
+    }
+
+    /// `init_headless` through `context`'s shared GPU device; see `init_shared`.
+    #[wasm_bindgen(unchecked_return_type = "Promise<void>")]
+    pub fn init_headless_shared(&mut self, width: u32, height: u32, context: &GpuContext) -> js_sys::Promise {
+        let shared = context.shared().clone();
+        let connect = async move { Renderer::connect_shared_headless(width, height, &shared) };
+        self.start_init(InitTarget::Headless(width, height), connect)
+    }
+
+    /// Draw a frame. Before audio is loaded this draws the idle (silent) visualization.
+    #[wasm_bindgen]
+    pub fn render(&mut self, time: f64, frame_index: usize, smoothing_factor: f32) -> Result<(), JsValue> {
+        self.require(LifecycleState::Initialized, "render")?;
+        self.draw(time, frame_index, None, smoothing_factor)
+    }
+
+    /// Same as `render`, but draws into an offscreen texture at the current size and resolves to
+    /// its RGBA8 pixels instead of presenting. Works both with a canvas and after `init_headless`.
+    #[wasm_bindgen]
+    pub async fn render_to_pixels(&mut self, time: f64, frame_index: usize, smoothing_factor: f32) -> Result<Vec<u8>, JsValue> {
+        self.require(LifecycleState::Initialized, "render_to_pixels")?;
+        let Some((width, height)) = self.renderer.surface_size() else {
+            return Err(JsValue::from_str("Renderer not initialized"));
+        };
+        let (shader_time, playhead) = self.prepare_frame(time, frame_index, None, smoothing_factor);
+        let pixels = self
+            .renderer
+            .render_offscreen(shader_time, &self.previous_bars, self.analyzer.get_bin_size(), playhead, width, height)
+            .await
+            .map_err(|e| JsValue::from_str(&e))?;
+        self.mark_playing();
+        Ok(pixels)
+    }
+
+    /// Same as `render_to_pixels`, but draws at `canvas`'s size and copies the frame into its 2D
+    /// context, so another scene (a game engine, a WebGL or WebGPU app) can composite it as a
+    /// texture source without the visualizer owning that scene's canvas. The canvas must not have
+    /// a different kind of context already; an `OffscreenCanvas` isn't supported.
     #[wasm_bindgen]
-    pub async fn init(&mut self, canvas_id: &str) -> Result<(), JsValue> {
-        self.renderer.init(canvas_id).await?;
+    pub async fn render_to_canvas(
+        &mut self,
+        time: f64,
+        frame_index: usize,
+        smoothing_factor: f32,
+        canvas: web_sys::HtmlCanvasElement,
+    ) -> Result<(), JsValue> {
+        self.require(LifecycleState::Initialized, "render_to_canvas")?;
+        let context = canvas
+            .get_context("2d")?
+            .and_then(|context| context.dyn_into::<web_sys::CanvasRenderingContext2d>().ok())
+            .ok_or_else(|| JsValue::from_str("Canvas has no 2D context; it may already have another kind"))?;
+        let (width, height) = (canvas.width(), canvas.height());
+        let (shader_time, playhead) = self.prepare_frame(time, frame_index, None, smoothing_factor);
+        let pixels = self
+            .renderer
+            .render_offscreen(shader_time, &self.previous_bars, self.analyzer.get_bin_size(), playhead, width, height)
+            .await
+            .map_err(|e| JsValue::from_str(&e))?;
+        let image = web_sys::ImageData::new_with_u8_clamped_array_and_sh(wasm_bindgen::Clamped(&pixels), width, height)?;
+        context.put_image_data(&image, 0.0, 0.0)?;
+        self.mark_playing();
         Ok(())
     }
 
+    /// Render using the audio playback position in seconds instead of a precomputed frame index.
+    /// The analysis frame is derived from the actual analysis rate, so this works regardless of
+    /// the display refresh rate; when the display runs faster than the analysis (e.g. with a low
+    /// `set_overlap`), bars are interpolated between frames rather than held. The position is
+    /// accumulated in f64 from frame timestamps and continuously corrected against
+    /// `playback_seconds`, so coarse audio clock updates don't make the visuals stutter or drift.
     #[wasm_bindgen]
-    pub fn render(&mut self, time: f64, frame_index: usize, smoothing_factor: f32) {
-        let bin_size = self.bin_size;
-        
-        if self.audio_processed {
-            let target_bars = if frame_index < self.frequency_bars.len() {
-                self.frequency_bars[frame_index].clone()
-            } else {
-                vec![0.0; bin_size]
-            };
-            let smoothed_bars = self.smooth_interpolate(&target_bars, smoothing_factor);
-            self.renderer.render(time, &smoothed_bars, bin_size);
-        } else {
-            // Render empty bars or default animation when no audio is loaded
-            let empty_bars = vec![0.0; bin_size];
-            self.renderer.render(time, &empty_bars, bin_size);
+    pub fn render_at_time(&mut self, time: f64, playback_seconds: f64, smoothing_factor: f32) -> Result<(), JsValue> {
+        self.require(LifecycleState::Initialized, "render_at_time")?;
+        let position = self.playback_clock.update(time, playback_seconds, self.track_rate(), self.loop_region);
+        let position = self.loop_shift(position, -self.latency_offset * self.track_rate());
+        let frame_index = self.analyzer.frame_index_at(position);
+        let analysis_fps = self.analyzer.time_base().map_or(analysis::TARGET_FPS, |time_base| time_base.frames_per_second());
+        // Frames go by `playback_rate` times as fast as they were analyzed
+        let interpolated = (analysis_fps * (self.playback_rate as f64) < self.refresh_rate.refresh_rate() as f64).then_some(position);
+        match self.pause_tracker.plan(time, self.playback_clock.is_playing()) {
+            FramePlan::Animate => self.draw(time, frame_index, interpolated, smoothing_factor),
+            FramePlan::Poster => self.render_poster(time, frame_index),
+            FramePlan::Skip => Ok(()),
         }
     }
 
+    /// Render the track at exactly `time_seconds`, with the bars interpolated between the two
+    /// analysis frames around that time instead of snapped to the one before it, so motion stays
+    /// even at any display refresh rate (60, 90, 144 Hz) against the 120 fps analysis. The same
+    /// time drives the shader animation and refresh rate measurement; use `render_at_time` when
+    /// following an audio element's clock, which is corrected for drift and pauses.
     #[wasm_bindgen]
-    pub fn resize(&mut self, width: u32, height: u32) {
-        self.renderer.resize(width, height);
+    pub fn render_interpolated(&mut self, time_seconds: f64, smoothing_factor: f32) -> Result<(), JsValue> {
+        self.require(LifecycleState::Initialized, "render_interpolated")?;
+        let position = self.loop_shift(time_seconds, -self.latency_offset * self.track_rate());
+        let frame_index = self.analyzer.frame_index_at(position);
+        self.draw(time_seconds, frame_index, Some(position), smoothing_factor)
     }
 
+    /// Tell the visualizer the track plays at `rate` times normal speed (0.0625-16, the range
+    /// browsers allow for `HTMLMediaElement.playbackRate`), so `render_at_time` predicts the
+    /// playback position at that speed, the latency offset covers the track time heard in that
+    /// latency, and the bars ease as much faster or slower as they change. Live bars are unaffected.
     #[wasm_bindgen]
-    pub fn get_frequency_bars(&self, frame_index: usize) -> Vec<f32> {
-        if self.audio_processed && frame_index < self.frequency_bars.len() {
-            self.frequency_bars[frame_index].clone()
-        } else {
-            vec![0.0; self.bin_size] // Return empty bars if index out of bounds or no audio processed
+    pub fn set_playback_rate(&mut self, rate: f32) -> Result<(), JsValue> {
+        const MIN_PLAYBACK_RATE: f32 = 0.0625;
+        const MAX_PLAYBACK_RATE: f32 = 16.0;
+
+        if !(MIN_PLAYBACK_RATE..=MAX_PLAYBACK_RATE).contains(&rate) {
+            return Err(JsValue::from_str(&format!(
+                "Playback rate must be within {}-{}, got {}",
+                MIN_PLAYBACK_RATE, MAX_PLAYBACK_RATE, rate
+            )));
         }
+        self.playback_rate = rate;
+        Ok(())
     }
 
     #[wasm_bindgen]
-    pub fn get_total_frames(&self) -> usize {
-        if self.audio_processed {
-            self.frequency_bars.len()
-        } else {
-            0
-        }
+    pub fn get_playback_rate(&self) -> f32 {
+        self.playback_rate
     }
 
+    /// Tell the visualizer which way the host plays the track, "forward" (the default) or
+    /// "reverse", for scrubbing and rewind effects: `render_at_time` then predicts the position
+    /// running backwards, the latency offset looks ahead in the track instead of behind, and
+    /// frames skipped between renders are caught up going down rather than up. Pass frames or
+    /// playback positions that decrease over time, as a reversed clock gives them.
     #[wasm_bindgen]
-    pub fn set_bin_size(&mut self, bin_size: usize) {
-        self.bin_size = bin_size;
-        self.previous_bars = vec![0.0; bin_size];
+    pub fn set_direction(&mut self, direction: &str) -> Result<(), JsValue> {
+        self.direction = PlaybackDirection::parse(direction)?;
+        Ok(())
     }
 
     #[wasm_bindgen]
-    pub fn process_audio_file(&mut self, file_data: &[u8]) -> Result<(), JsValue> {
-        log!("Processing audio file, size: {} bytes", file_data.len());
-        
-        // Create a cursor from the byte data
-        let cursor = Cursor::new(file_data);
-        
-        // Try to read the WAV file
-        match hound::WavReader::new(cursor) {
-            Ok(reader) => {
-                let spec = reader.spec();
-                log!("WAV file info:");
-                log!("  Channels: {}", spec.channels);
-                log!("  Sample rate: {} Hz", spec.sample_rate);
-                log!("  Bits per sample: {}", spec.bits_per_sample);
-                log!("  Sample format: {:?}", spec.sample_format);
-                log!("  Duration: {:.2} seconds", reader.duration() as f64 / spec.sample_rate as f64);
-                
-                // Read all samples
-                let samples: Result<Vec<i16>, _> = reader.into_samples().collect();
-                match samples {
-                    Ok(sample_vec) => {
-                        log!("Total samples: {}", sample_vec.len());
-                        
-                        // Convert to mono if stereo (take left channel only)
-                        let mono_samples = if spec.channels == 2 {
-                            sample_vec.iter().step_by(2).cloned().collect::<Vec<i16>>()
-                        } else {
-                            sample_vec
-                        };
-                        
-                        log!("Mono samples: {}", mono_samples.len());
-                        
-                        // Process audio with framing and windowing
-                        self.process_audio_frames(&mono_samples);
-                        
-                        // Process FFT on windowed frames
-                        self.process_fft();
-                        
-                        // Map FFT results to frequency bars
-                        self.map_to_frequency_bars(spec.sample_rate);
-                        
-                        // Mark audio as processed
-                        self.audio_processed = true;
-                        log!("Audio processing complete! Ready for visualization.");
-                        
-                        Ok(())
-                    }
-                    Err(e) => {
-                        log!("Error reading samples: {:?}", e);
-                        Err(JsValue::from_str(&format!("Failed to read samples: {:?}", e)))
-                    }
-                }
-            }
-            Err(e) => {
-                log!("Error reading WAV file: {:?}", e);
-                Err(JsValue::from_str(&format!("Failed to read WAV file: {:?}", e)))
-            }
+    pub fn get_direction(&self) -> String {
+        self.direction.name().to_string()
+    }
+
+    // Seconds of track time that pass per second, negative in reverse
+    fn track_rate(&self) -> f64 {
+        self.playback_rate as f64 * self.direction.sign()
+    }
+
+    /// Repeat `start_seconds..end_seconds` of the track: `render_at_time` and
+    /// `render_interpolated` wrap positions past the end back into the loop, and the playback
+    /// clock follows the host's audio across the loop point instead of snapping. With `crossfade_seconds`
+    /// the bars blend into those at the loop start over that long before the end (in reverse,
+    /// into the end's after the start), so a loop whose ends differ doesn't flicker at the wrap.
+    #[wasm_bindgen]
+    pub fn set_loop(&mut self, start_seconds: f64, end_seconds: f64, crossfade_seconds: Option<f64>) -> Result<(), JsValue> {
+        let crossfade = crossfade_seconds.unwrap_or(0.0);
+        if !(start_seconds.is_finite() && end_seconds.is_finite() && 0.0 <= start_seconds && start_seconds < end_seconds) {
+            return Err(JsValue::from_str(&format!("Invalid loop {}-{}s", start_seconds, end_seconds)));
+        }
+        if !(0.0..=end_seconds - start_seconds).contains(&crossfade) {
+            return Err(JsValue::from_str(&format!("Loop crossfade must be within 0-{}s, got {}", end_seconds - start_seconds, crossfade)));
         }
+        self.loop_region = Some(LoopRegion {
+            start: start_seconds,
+            end: end_seconds,
+            crossfade,
+        });
+        Ok(())
     }
 
-    fn process_audio_frames(&mut self, samples: &[i16]) {
-        const FRAME_SIZE: usize = 1024;
-        const TARGET_FPS: f64 = 120.0;
-        const SAMPLE_RATE: f64 = 44100.0;
-        
-        // Calculate hop size for 120fps synchronization
-        let duration_seconds = samples.len() as f64 / SAMPLE_RATE;
-        let target_frames = (duration_seconds * TARGET_FPS) as usize;
-        let hop_size = if target_frames > 0 {
-            samples.len() / target_frames
-        } else {
-            FRAME_SIZE
-        };
-        
-        // Calculate number of frames with calculated hop size
-        let frame_count = if samples.len() >= FRAME_SIZE {
-            (samples.len() - FRAME_SIZE) / hop_size + 1
-        } else {
-            0
+    /// Stop looping; see `set_loop`.
+    #[wasm_bindgen]
+    pub fn clear_loop(&mut self) {
+        self.loop_region = None;
+    }
+
+    // `position` moved by `seconds`, within the loop if there is one
+    fn loop_shift(&self, position: f64, seconds: f64) -> f64 {
+        match self.loop_region {
+            Some(region) => region.shift(position, seconds),
+            None => position + seconds,
+        }
+    }
+
+    // Blend `bars` into those across the loop point over the crossfade before it
+    fn crossfade_loop(&self, mut bars: Vec<f32>, frame_index: usize, position: Option<f64>) -> Vec<f32> {
+        let (Some(region), Some(time_base)) = (self.loop_region, self.analyzer.time_base()) else {
+            return bars;
         };
-        
-        log!("Audio duration: {:.2} seconds", duration_seconds);
-        log!("Target frames for 60fps: {}", target_frames);
-        log!("Calculated hop size: {} samples", hop_size);
-        log!("Processing {} frames (hop size: {})", frame_count, hop_size);
-        
-        // Generate Hann window
-        let hann_window = self.generate_hann_window(FRAME_SIZE);
-        
-        // Clear previous audio frames
-        self.audio_frames.clear();
-        
-        // Process each frame with calculated hop size
-        for frame_idx in 0..frame_count {
-            let start_idx = frame_idx * hop_size;
-            let end_idx = start_idx + FRAME_SIZE;
-            
-            if end_idx <= samples.len() {
-                let frame = &samples[start_idx..end_idx];
-                let windowed_frame = self.apply_hann_window(frame, &hann_window);
-                
-                // Store the windowed frame
-                self.audio_frames.push(windowed_frame);
-                
-                // Log first frame details for debugging
-                if frame_idx == 0 {
-                    log!("First frame raw samples (first 10): {:?}", &frame[..10]);
-                    log!("First frame windowed samples (first 10): {:?}", &self.audio_frames[0][..10]);
-                }
-            }
+        let seconds = position.unwrap_or_else(|| time_base.frame_to_seconds(frame_index));
+        let (weight, across) = region.crossfade(seconds, self.direction);
+        if weight == 0.0 {
+            return bars;
         }
-        
-        log!("Stored {} windowed frames for 120fps visualization", self.audio_frames.len());
+        let across_bars = self.analyzer.bars_at(across).unwrap_or_default();
+        for (bar, across) in bars.iter_mut().zip(across_bars) {
+            *bar += (across - *bar) * weight;
+        }
+        bars
     }
-    
-    fn process_fft(&mut self) {
-        log!("Starting FFT processing on {} frames", self.audio_frames.len());
-        
-        // Clear previous FFT results
-        self.fft_results.clear();
-        
-        for (frame_idx, frame) in self.audio_frames.iter().enumerate() {
-            // Prepare data for FFT (real and imaginary parts)
-            let mut real_data: Vec<f32> = frame.clone();
-            let mut imag_data: Vec<f32> = vec![0.0; frame.len()];
-            
-            // Perform FFT
-            phastft::fft_32(&mut real_data, &mut imag_data, Direction::Forward);
-            
-            // Calculate magnitudes (sqrt(real^2 + imag^2))
-            let magnitudes: Vec<f32> = real_data.iter()
-                .zip(imag_data.iter())
-                .map(|(r, i)| (r * r + i * i).sqrt())
-                .collect();
-            
-            // Log first frame FFT results for debugging
-            if frame_idx == 0 {
-                log!("First frame FFT magnitudes (first 10): {:?}", &magnitudes[..10]);
-                log!("First frame FFT magnitudes (bins 100-110): {:?}", &magnitudes[100..110]);
-                
-                // Find peak frequency
-                let max_magnitude = magnitudes.iter().fold(0.0f32, |a, &b| a.max(b));
-                let max_index = magnitudes.iter().position(|&x| x == max_magnitude).unwrap_or(0);
-                log!("Peak frequency bin: {}, magnitude: {:.2}", max_index, max_magnitude);
-                
-                // Log some frequency range statistics
-                let low_freq_sum: f32 = magnitudes[0..50].iter().sum();
-                let mid_freq_sum: f32 = magnitudes[50..200].iter().sum();
-                let high_freq_sum: f32 = magnitudes[200..512].iter().sum();
-                log!("Frequency range energies - Low (0-50): {:.2}, Mid (50-200): {:.2}, High (200-512): {:.2}", 
-                     low_freq_sum, mid_freq_sum, high_freq_sum);
-            }
-            
-            // Store magnitudes
-            self.fft_results.push(magnitudes);
+
+    /// Draw the bars `ms` milliseconds behind the playback position, so they line up with the
+    /// audio actually heard: output latency runs from 20 to 200 ms depending on the device (the
+    /// Web Audio `AudioContext.outputLatency` is a good start). Negative values draw them ahead.
+    /// Applies to `render_at_time`, `render_interpolated` and, positive values only, live bars;
+    /// `render` takes its frame as given.
+    #[wasm_bindgen]
+    pub fn set_latency_offset(&mut self, ms: f64) -> Result<(), JsValue> {
+        const MAX_LATENCY_OFFSET_MS: f64 = 1000.0;
+
+        if !(ms.is_finite() && ms.abs() <= MAX_LATENCY_OFFSET_MS) {
+            return Err(JsValue::from_str(&format!("Latency offset must be within ±{} ms, got {}", MAX_LATENCY_OFFSET_MS, ms)));
         }
-        
-        log!("FFT processing complete. Generated {} FFT results", self.fft_results.len());
+        self.latency_offset = ms / 1000.0;
+        Ok(())
     }
-    
-    fn map_to_frequency_bars(&mut self, sample_rate: u32) {
-        let num_bars = self.bin_size;
-        const MIN_FREQ: f32 = 20.0;    // 20 Hz
-        const MAX_FREQ: f32 = 20000.0; // 20 kHz
-        
-        log!("Mapping FFT results to {} logarithmic frequency bars", num_bars);
-        log!("Frequency range: {:.1} Hz to {:.1} Hz", MIN_FREQ, MAX_FREQ);
-        
-        // Generate logarithmic frequency boundaries
-        let freq_boundaries = self.generate_log_frequencies(MIN_FREQ, MAX_FREQ, num_bars);
-        
-        // Log some frequency ranges for debugging (perceptual distribution)
-        log!("Perceptual frequency distribution:");
-        match num_bars {
-            64 => {
-                log!("  Bins 0-3: Sub-bass (20-100 Hz)");
-                log!("  Bins 4-23: Bass (100-500 Hz)");
-                log!("  Bins 24-47: Mid-range (500-4000 Hz)");
-                log!("  Bins 48-63: High frequencies (4000-20000 Hz)");
-            }
-            32 => {
-                log!("  Bins 0-1: Sub-bass (20-100 Hz)");
-                log!("  Bins 2-11: Bass (100-500 Hz)");
-                log!("  Bins 12-23: Mid-range (500-4000 Hz)");
-                log!("  Bins 24-31: High frequencies (4000-20000 Hz)");
-            }
-            16 => {
-                log!("  Bin 0: Sub-bass (20-100 Hz)");
-                log!("  Bins 1-5: Bass (100-500 Hz)");
-                log!("  Bins 6-11: Mid-range (500-4000 Hz)");
-                log!("  Bins 12-15: High frequencies (4000-20000 Hz)");
-            }
-            _ => {
-                log!("  Using logarithmic distribution");
-            }
+
+    /// Ease the bars towards each frame's levels on the GPU instead of the CPU: only the target
+    /// bars are uploaded and a small render pass smooths them by the frame's smoothing factor.
+    /// The drawn bars look the same, but hover values and the CPU-drawn layers (the waterfall)
+    /// then follow the unsmoothed levels.
+    #[wasm_bindgen]
+    pub fn set_gpu_smoothing(&mut self, enabled: bool) {
+        if enabled && !self.gpu_smoothing {
+            // Carry on from the CPU-smoothed levels
+            self.renderer.reset_bar_smoothing(&self.previous_bars, &self.previous_right_bars);
+        }
+        self.gpu_smoothing = enabled;
+        self.renderer.set_gpu_smoothing(enabled);
+    }
+
+    /// Save power in paused players: once the audio clock passed to `render_at_time` has stood
+    /// still for `idle_seconds`, one still poster frame is drawn with the bars fully settled, and
+    /// later calls draw nothing until playback moves again. `motion_blur_samples` frames (1-32)
+    /// are averaged into the poster so animated modes come out blurred rather than frozen
+    /// mid-movement; 1 draws a plain frame.
+    #[wasm_bindgen]
+    pub fn set_power_saving(&mut self, enabled: bool, idle_seconds: f64, motion_blur_samples: u32) -> Result<(), JsValue> {
+        if !idle_seconds.is_finite() || idle_seconds < 0.0 {
+            return Err(JsValue::from_str(&format!("Invalid idle time {} seconds", idle_seconds)));
         }
-        for i in 0..5.min(num_bars) {
-            log!("  Bar {}: {:.1} Hz - {:.1} Hz", i, freq_boundaries[i], freq_boundaries[i + 1]);
+        let settings = enabled.then_some(PowerSaving {
+            idle_seconds,
+            motion_blur_samples: motion_blur_samples.clamp(1, timing::MAX_POSTER_SAMPLES),
+        });
+        self.pause_tracker.configure(settings);
+        Ok(())
+    }
+
+    /// Whether the poster frame is showing and `render_at_time` is skipping frames. Hosts can
+    /// stop requesting animation frames while this is true and restart them, after calling
+    /// `wake`, when playback resumes.
+    #[wasm_bindgen]
+    pub fn is_render_suspended(&self) -> bool {
+        self.pause_tracker.is_suspended()
+    }
+
+    /// Animate again after the poster frame, e.g. when playback starts (before the audio clock
+    /// has moved) or settings change while paused. If playback stays paused, a new poster frame
+    /// is drawn after the idle time.
+    #[wasm_bindgen]
+    pub fn wake(&mut self) {
+        self.pause_tracker.wake();
+    }
+
+    /// Drift-corrected playback position in seconds used by the last `render_at_time` call.
+    #[wasm_bindgen]
+    pub fn get_playback_position(&self) -> f64 {
+        self.playback_clock.position()
+    }
+
+    /// Display refresh rate in Hz estimated from the timestamps passed to `render`.
+    #[wasm_bindgen]
+    pub fn get_display_refresh_rate(&self) -> f32 {
+        self.refresh_rate.refresh_rate()
+    }
+
+    /// Number of analysis frames skipped because rendering fell behind the playhead.
+    #[wasm_bindgen]
+    pub fn get_skipped_frames(&self) -> f64 {
+        self.skipped_frames as f64
+    }
+
+    /// Texture format frames are drawn in, e.g. "Bgra8UnormSrgb". An sRGB format is preferred
+    /// where the surface offers one, so blending is gamma-correct; empty before `init`.
+    #[wasm_bindgen]
+    pub fn get_surface_format(&self) -> String {
+        self.renderer.target_format().map_or_else(String::new, |format| format!("{:?}", format))
+    }
+
+    #[wasm_bindgen]
+    pub fn reset_skipped_frames(&mut self) {
+        self.skipped_frames = 0;
+    }
+
+    /// Show or hide the waveform strip along the HUD edge of the canvas (the top unless moved by
+    /// `set_layout_variant`): a static overview of the whole track with a playhead marker moving
+    /// across it, like a seek bar. It doesn't scroll. `height` is the strip height as a fraction
+    /// of the canvas height.
+    #[wasm_bindgen]
+    pub fn set_waveform_strip(&mut self, enabled: bool, height: f32) {
+        self.renderer.set_waveform_strip(enabled, height);
+    }
+
+    /// Find the bar under a pointer position given in canvas pixels.
+    /// Returns `undefined` when the position is outside the canvas or the renderer isn't initialized.
+    /// With hover highlighting enabled, the hit bar is also highlighted on the next render.
+    #[wasm_bindgen]
+    pub fn hit_test(&mut self, x: f32, y: f32) -> Option<BarHit> {
+        self.finish_init();
+        let (width, height) = self.renderer.surface_size()?;
+        let bin_size = self.analyzer.get_bin_size();
+        if x < 0.0 || y < 0.0 || x > width as f32 || y > height as f32 || bin_size == 0 {
+            self.renderer.set_highlighted_bar(None);
+            return None;
         }
         
-        // Clear previous frequency bars
-        self.frequency_bars.clear();
+        // Position along the axis the bars spread over, and along the axis they grow in
+        let (spread_position, growth_position) = match self.renderer.orientation() {
+            Orientation::Vertical => (x / width as f32, 1.0 - y / height as f32),
+            Orientation::Horizontal => (y / height as f32, x / width as f32),
+        };
+        let (arrangement, reversed) = self.renderer.bar_layout();
+        let (bar_index, along_bar) = interaction::bar_at_position(spread_position, growth_position, bin_size, arrangement, reversed);
+        let value = self.previous_bars.get(bar_index).copied().unwrap_or(0.0);
+        let bar_top = interaction::bar_top(value, self.renderer.bar_style().min_height);
         
-        // Map each FFT frame to frequency bars
-        for (frame_idx, fft_frame) in self.fft_results.iter().enumerate() {
-            let bars = self.map_fft_to_bars(fft_frame, sample_rate, &freq_boundaries, num_bars);
-            self.frequency_bars.push(bars);
-            
-            // Log first frame for debugging
-            if frame_idx == 0 {
-                let log_end = (10).min(self.frequency_bars[0].len());
-                log!("First frame frequency bars (first {}): {:?}", log_end, &self.frequency_bars[0][..log_end]);
-                
-                // Find peak bar
-                let max_bar = self.frequency_bars[0].iter().fold(0.0f32, |a, &b| a.max(b));
-                let max_bar_idx = self.frequency_bars[0].iter().position(|&x| x == max_bar).unwrap_or(0);
-                if max_bar_idx < freq_boundaries.len() - 1 {
-                    log!("Peak bar: {} (freq range: {:.1} Hz - {:.1} Hz), magnitude: {:.2}", 
-                         max_bar_idx, freq_boundaries[max_bar_idx], freq_boundaries[max_bar_idx + 1], max_bar);
-                }
+        let (freq_start, freq_end) = match self.analyzer.freq_boundaries().get(bar_index..bar_index + 2) {
+            Some(range) => (range[0], range[1]),
+            None => (0.0, 0.0),
+        };
+        let db = match self.analyzer.fft_frame(self.current_frame) {
+            Some(fft_frame) => {
+                interaction::magnitude_to_dbfs(analysis::bar_magnitude(&fft_frame, self.analyzer.sample_rate(), self.analyzer.fft_size(), freq_start, freq_end))
             }
+            None => f32::NEG_INFINITY,
+        };
+        
+        if self.hover_highlight {
+            self.renderer.set_highlighted_bar(Some(bar_index));
         }
         
-        log!("Frequency bar mapping complete. Generated {} bar frames", self.frequency_bars.len());
+        Some(BarHit {
+            bar_index,
+            freq_start,
+            freq_end,
+            value,
+            db,
+            inside_bar: along_bar <= bar_top,
+        })
     }
-    
-    fn generate_log_frequencies(&self, min_freq: f32, max_freq: f32, num_bars: usize) -> Vec<f32> {
-        let mut frequencies = Vec::with_capacity(num_bars + 1);
-        
-        // Perceptual frequency distribution strategy
-        // More resolution in mid-range where music content is dense
-        match num_bars {
-            64 => {
-                // Sub-bass (20-100Hz): 4 bins
-                for i in 0..=4 {
-                    let freq = 20.0 + (i as f32 / 4.0) * 80.0;
-                    frequencies.push(freq);
-                }
-                // Bass (100-500Hz): 20 bins  
-                for i in 1..=20 {
-                    let freq = 100.0 * (500.0f32 / 100.0f32).powf(i as f32 / 20.0);
-                    frequencies.push(freq);
-                }
-                // Mid-range (500-4000Hz): 24 bins
-                for i in 1..=24 {
-                    let freq = 500.0 * (4000.0f32 / 500.0f32).powf(i as f32 / 24.0);
-                    frequencies.push(freq);
-                }
-                // High frequencies (4000-20000Hz): 16 bins
-                for i in 1..=16 {
-                    let freq = 4000.0 * (20000.0f32 / 4000.0f32).powf(i as f32 / 16.0);
-                    frequencies.push(freq);
-                }
-            }
-            32 => {
-                // Sub-bass (20-100Hz): 2 bins
-                for i in 0..=2 {
-                    let freq = 20.0 + (i as f32 / 2.0) * 80.0;
-                    frequencies.push(freq);
-                }
-                // Bass (100-500Hz): 10 bins
-                for i in 1..=10 {
-                    let freq = 100.0 * (500.0f32 / 100.0f32).powf(i as f32 / 10.0);
-                    frequencies.push(freq);
-                }
-                // Mid-range (500-4000Hz): 12 bins
-                for i in 1..=12 {
-                    let freq = 500.0 * (4000.0f32 / 500.0f32).powf(i as f32 / 12.0);
-                    frequencies.push(freq);
-                }
-                // High frequencies (4000-20000Hz): 8 bins
-                for i in 1..=8 {
-                    let freq = 4000.0 * (20000.0f32 / 4000.0f32).powf(i as f32 / 8.0);
-                    frequencies.push(freq);
-                }
-            }
-            16 => {
-                // Sub-bass (20-100Hz): 1 bin
-                frequencies.push(20.0);
-                frequencies.push(100.0);
-                // Bass (100-500Hz): 5 bins
-                for i in 1..=5 {
-                    let freq = 100.0 * (500.0f32 / 100.0f32).powf(i as f32 / 5.0);
-                    frequencies.push(freq);
-                }
-                // Mid-range (500-4000Hz): 6 bins
-                for i in 1..=6 {
-                    let freq = 500.0 * (4000.0f32 / 500.0f32).powf(i as f32 / 6.0);
-                    frequencies.push(freq);
-                }
-                // High frequencies (4000-20000Hz): 4 bins
-                for i in 1..=4 {
-                    let freq = 4000.0 * (20000.0f32 / 4000.0f32).powf(i as f32 / 4.0);
-                    frequencies.push(freq);
-                }
-            }
-            _ => {
-                // Fallback to logarithmic distribution
-                let log_min = min_freq.ln();
-                let log_max = max_freq.ln();
-                let log_step = (log_max - log_min) / num_bars as f32;
-                
-                for i in 0..=num_bars {
-                    let freq = (log_min + i as f32 * log_step).exp();
-                    frequencies.push(freq);
-                }
-            }
+
+    /// Bar drawn at `x_normalized` (0.0-1.0) of the way across the axis the bars spread over,
+    /// left to right (top to bottom for horizontal bars), with the current arrangement and
+    /// order; for tooltips over a host-drawn axis. See `get_bar_frequency_range` for its range.
+    #[wasm_bindgen]
+    pub fn get_bar_at_position(&self, x_normalized: f32) -> usize {
+        let (arrangement, reversed) = self.renderer.bar_layout();
+        interaction::bar_at_position(x_normalized, 0.0, self.analyzer.get_bin_size(), arrangement, reversed).0
+    }
+
+    /// Frequency range of a bar as `[low_hz, high_hz]`; see `AudioAnalyzer.get_bar_frequency_range`.
+    #[wasm_bindgen]
+    pub fn get_bar_frequency_range(&self, bar_index: usize) -> Option<Vec<f32>> {
+        self.analyzer.get_bar_frequency_range(bar_index)
+    }
+
+    /// Enable or disable highlighting of the bar last returned by `hit_test`.
+    #[wasm_bindgen]
+    pub fn set_hover_highlight(&mut self, enabled: bool) {
+        self.hover_highlight = enabled;
+        if !enabled {
+            self.renderer.set_highlighted_bar(None);
         }
-        
-        frequencies
     }
-    
-    fn map_fft_to_bars(&self, fft_frame: &[f32], sample_rate: u32, freq_boundaries: &[f32], num_bars: usize) -> Vec<f32> {
-        let mut bars = vec![0.0; num_bars];
-        
-        if freq_boundaries.len() < num_bars + 1 {
-            log!("Warning: insufficient frequency boundaries for {} bars", num_bars);
-            return bars;
+
+    /// Clear the hover highlight, e.g. when the pointer leaves the canvas.
+    #[wasm_bindgen]
+    pub fn clear_highlight(&mut self) {
+        self.renderer.set_highlighted_bar(None);
+    }
+
+    /// Enable or disable click-to-solo: clicking a bar dims all other bands until it is clicked again.
+    #[wasm_bindgen]
+    pub fn set_solo_on_click(&mut self, enabled: bool) {
+        self.solo_on_click = enabled;
+        if !enabled {
+            self.clear_solo();
         }
+    }
+
+    /// Register a callback invoked with the soloed `BarHit`, or `null` when the solo is cleared.
+    #[wasm_bindgen]
+    pub fn set_solo_callback(&mut self, #[wasm_bindgen(unchecked_param_type = "((hit: BarHit | null) => void) | null")] callback: Option<js_sys::Function>) {
+        self.solo_callback = callback;
+    }
+
+    /// Handle a click at a canvas pixel position. When click-to-solo is enabled this toggles
+    /// the solo on the clicked bar. Returns the hit bar, if any.
+    #[wasm_bindgen]
+    pub fn click(&mut self, x: f32, y: f32) -> Option<BarHit> {
+        let hit = self.hit_test(x, y)?;
         
-        let freq_resolution = sample_rate as f32 / 1024.0; // 1024 is FFT size
-        let nyquist_bin = 512; // Only use first half of FFT (Nyquist frequency)
-        
-        // First pass: collect raw magnitudes
-        let mut raw_magnitudes = vec![0.0; num_bars];
-        for bar_idx in 0..num_bars {
-            let freq_start = freq_boundaries[bar_idx];
-            let freq_end = freq_boundaries[bar_idx + 1];
-            
-            // Convert frequencies to bin indices
-            let bin_start = ((freq_start / freq_resolution) as usize).min(nyquist_bin);
-            let bin_end = ((freq_end / freq_resolution) as usize).min(nyquist_bin);
-            
-            // Ensure bin_end is at least bin_start
-            let bin_end = bin_end.max(bin_start);
-            
-            // Sum magnitudes in this frequency range
-            let mut magnitude_sum = 0.0;
-            let mut bin_count = 0;
-            
-            for bin_idx in bin_start..=bin_end {
-                if bin_idx < nyquist_bin && bin_idx < fft_frame.len() {
-                    magnitude_sum += fft_frame[bin_idx];
-                    bin_count += 1;
-                }
-            }
-            
-            raw_magnitudes[bar_idx] = if bin_count > 0 {
-                magnitude_sum / bin_count as f32
-            } else {
-                0.0
-            };
+        if self.solo_on_click {
+            self.toggle_solo(hit);
         }
         
-        // Apply dynamic range compression and power expansion for better variance
-        self.apply_dynamic_scaling(&raw_magnitudes, &mut bars, num_bars);
-        
-        bars
+        Some(hit)
     }
-    
-    fn apply_dynamic_scaling(&self, raw_magnitudes: &[f32], output_bars: &mut [f32], num_bars: usize) {
-        // Use percentile-based normalization for better variance
-        let mut sorted_mags = raw_magnitudes.to_vec();
-        sorted_mags.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        
-        // Find percentile thresholds
-        let p25_idx = (num_bars as f32 * 0.25) as usize;
-        let p75_idx = (num_bars as f32 * 0.75) as usize;
-        let p90_idx = (num_bars as f32 * 0.90) as usize;
-        
-        let p25_val = sorted_mags.get(p25_idx).unwrap_or(&0.0);
-        let p75_val = sorted_mags.get(p75_idx).unwrap_or(&0.0);
-        let p90_val = sorted_mags.get(p90_idx).unwrap_or(&0.0);
-        let max_val = sorted_mags.last().unwrap_or(&0.0);
-        
-        for i in 0..num_bars {
-            let mag = raw_magnitudes[i];
-            
-            // Map to percentile-based ranges with dramatic scaling
-            let scaled = if mag <= *p25_val {
-                // Bottom 25%: Map to 0-0.2 range
-                (mag / p25_val.max(0.001)) * 0.2
-            } else if mag <= *p75_val {
-                // 25%-75%: Map to 0.2-0.6 range with power scaling
-                let normalized = (mag - p25_val) / (p75_val - p25_val).max(0.001);
-                0.2 + normalized.powf(1.5) * 0.4
-            } else if mag <= *p90_val {
-                // 75%-90%: Map to 0.6-0.85 range with strong power scaling
-                let normalized = (mag - p75_val) / (p90_val - p75_val).max(0.001);
-                0.6 + normalized.powf(2.0) * 0.25
-            } else {
-                // Top 10%: Map to 0.85-1.0 range with extreme scaling
-                let normalized = (mag - p90_val) / (max_val - p90_val).max(0.001);
-                0.85 + normalized.powf(3.0) * 0.15
-            };
-            
-            output_bars[i] = scaled.min(1.0);
+
+    // Solo the hit bar, or clear the solo when it is already soloed
+    fn toggle_solo(&mut self, hit: BarHit) {
+        if self.soloed_bar == Some(hit.bar_index) {
+            self.clear_solo();
+        } else {
+            self.soloed_bar = Some(hit.bar_index);
+            self.renderer.set_soloed_bar(self.soloed_bar);
+            self.emit_solo_event(JsValue::from(hit));
         }
     }
-    
-    fn smooth_interpolate(&mut self, target_bars: &[f32], smoothing_factor: f32) -> Vec<f32> {
-        let mut smoothed = vec![0.0; self.bin_size];
-        
-        // Ensure previous_bars has correct size
-        if self.previous_bars.len() != self.bin_size {
-            self.previous_bars = vec![0.0; self.bin_size];
+
+    /// Remove any active solo and restore all bands.
+    #[wasm_bindgen]
+    pub fn clear_solo(&mut self) {
+        if self.soloed_bar.take().is_some() {
+            self.renderer.set_soloed_bar(None);
+            self.emit_solo_event(JsValue::NULL);
         }
-        
-        let actual_size = self.bin_size.min(target_bars.len());
-        
-        for i in 0..actual_size {
-            let target = target_bars.get(i).unwrap_or(&0.0);
-            let previous = self.previous_bars.get(i).unwrap_or(&0.0);
-            
-            // Linear interpolation with smoothing
-            smoothed[i] = previous * (1.0 - smoothing_factor) + target * smoothing_factor;
+    }
+
+    /// Composite an RGBA8 image (e.g. decoded cover art) behind the bars.
+    #[wasm_bindgen]
+    pub fn set_background_image(&mut self, rgba: &[u8], width: u32, height: u32) -> Result<(), JsValue> {
+        self.renderer
+            .set_background_image(rgba, width, height)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    #[wasm_bindgen]
+    pub fn clear_background_image(&mut self) {
+        self.renderer.clear_background_image();
+    }
+
+    /// Configure the background image: `blur` is the blur radius as a fraction of the image (0.0-0.1),
+    /// `dim` darkens the image (0.0 = unchanged, 1.0 = black).
+    #[wasm_bindgen]
+    pub fn set_background_style(&mut self, blur: f32, dim: f32) {
+        self.renderer.set_background_style(blur, dim);
+    }
+
+    /// Opt in to built-in touch gestures on the canvas: pinch zooms the displayed frequency range,
+    /// horizontal swipes and two-finger taps are reported to the gesture callback
+    /// (as "swipe-left"/"swipe-right" for switching presets and "two-finger-tap" for toggling pause).
+    #[wasm_bindgen]
+    pub fn set_touch_gestures(&mut self, enabled: bool) -> Result<(), JsValue> {
+        if !enabled {
+            self.gestures = None;
+            return Ok(());
         }
-        
-        // Update previous bars for next frame
-        self.previous_bars = smoothed.clone();
-        
-        smoothed
+        self.finish_init();
+        if self.gestures.is_none() {
+            let canvas = self
+                .renderer
+                .canvas()
+                .ok_or_else(|| JsValue::from_str("Touch gestures require init() to be called first"))?;
+            self.gestures = Some(GestureController::attach(canvas)?);
+        }
+        Ok(())
     }
-    
-    fn generate_hann_window(&self, size: usize) -> Vec<f32> {
-        let mut window = Vec::with_capacity(size);
-        for n in 0..size {
-            let value = 0.5 * (1.0 - ((2.0 * std::f32::consts::PI * n as f32) / (size - 1) as f32).cos());
-            window.push(value);
+
+    /// Opt in to built-in mouse and keyboard input on the canvas. By default the wheel zooms the
+    /// displayed frequency range about the pointer, dragging pans it and a click adds a marker at
+    /// the playback position (at the time under the pointer on the waveform strip). `bindings`
+    /// rebinds the wheel to "zoom", "pan" or "none", dragging to "pan", "zoom" or "none" and
+    /// clicks to "marker", "solo" or "none"; see `InputBindings`. Positions are hit-tested against
+    /// the current bar orientation, arrangement and order. The canvas is made focusable, and once
+    /// focused (e.g. by a click) "+" and "-" zoom, the arrow keys pan, "0" zooms all the way out
+    /// and "m" adds a marker at the playback position, unless `keyboard` is false. Zooming and
+    /// panning stay within the range set with `set_display_range`.
+    #[wasm_bindgen]
+    pub fn set_mouse_input(&mut self, enabled: bool, #[wasm_bindgen(unchecked_param_type = "InputBindings | undefined")] bindings: JsValue) -> Result<(), JsValue> {
+        if !enabled {
+            self.mouse_input = None;
+            return Ok(());
+        }
+        let bindings = if bindings.is_undefined() || bindings.is_null() {
+            InputBindings::default()
+        } else {
+            serde_wasm_bindgen::from_value::<InputBindings>(bindings).map_err(|e| JsValue::from_str(&format!("Invalid input bindings: {}", e)))?
+        };
+        let bindings = ActionBindings::try_from(&bindings).map_err(|e| JsValue::from_str(&e))?;
+        self.finish_init();
+        match &mut self.mouse_input {
+            Some((_, current)) => *current = bindings,
+            None => {
+                let canvas = self
+                    .renderer
+                    .canvas()
+                    .ok_or_else(|| JsValue::from_str("Mouse input requires init() to be called first"))?;
+                self.mouse_input = Some((InputController::attach(canvas)?, bindings));
+            }
         }
-        window
+        Ok(())
     }
-    
-    fn apply_hann_window(&self, frame: &[i16], window: &[f32]) -> Vec<f32> {
-        frame.iter()
-            .zip(window.iter())
-            .map(|(&sample, &window_val)| {
-                let normalized_sample = sample as f32 / i16::MAX as f32;
-                normalized_sample * window_val
-            })
-            .collect()
+
+    /// Register a callback invoked as `callback(name, value)` for each recognised gesture.
+    /// For "pinch" the value is the new displayed range as `[min_hz, max_hz]`.
+    #[wasm_bindgen]
+    pub fn set_gesture_callback(
+        &mut self,
+        #[wasm_bindgen(unchecked_param_type = "((name: string, value: [number, number] | number | undefined) => void) | null")] callback: Option<js_sys::Function>,
+    ) {
+        self.gesture_callback = callback;
+    }
+
+    /// When rendering needs a frame that has no analysis (e.g. playback running past the end of
+    /// the track), the last analyzed bars are held and fade out with a time constant of
+    /// `decay_seconds` (0.5 by default; 0 drops straight to silence).
+    #[wasm_bindgen]
+    pub fn set_underrun_decay(&mut self, decay_seconds: f64) -> Result<(), JsValue> {
+        if !decay_seconds.is_finite() || decay_seconds < 0.0 {
+            return Err(JsValue::from_str(&format!("Invalid underrun decay {} seconds", decay_seconds)));
+        }
+        self.underrun.set_decay(decay_seconds);
+        Ok(())
+    }
+
+    /// Opt in to periodic performance reports: every `interval_seconds` of rendering (at least 10)
+    /// `callback` is called with a `TelemetryReport` holding the graphics backend, a frame rate
+    /// histogram and analysis time per minute of audio. Nothing identifying is included. Pass
+    /// `undefined` to stop reporting.
+    #[wasm_bindgen]
+    pub fn set_telemetry_callback(
+        &mut self,
+        #[wasm_bindgen(unchecked_param_type = "((report: TelemetryReport) => void) | null")] callback: Option<js_sys::Function>,
+        interval_seconds: f64,
+    ) {
+        self.telemetry = callback.map(|callback| Telemetry::new(callback, interval_seconds));
+    }
+
+    /// Call `callback` on every `event`, after any listeners registered before it:
+    ///
+    /// - "processing-started" when analysis of a new track starts (for `load_from_url`, once the
+    ///   download completes)
+    /// - "processing-progress" with the fraction (0.0-1.0) of the analysis done, during
+    ///   `process_audio_file_async` and `load_from_url`
+    /// - "processing-complete" with the new track's `AudioInfo` once it is analyzed; for
+    ///   `process_audio_file_async` and `load_from_url`, just before their Promise resolves
+    /// - "beat" with the onset strength (0.0-1.0) and frame index of a rendered frame that is an onset
+    /// - "section-change" with the `Section` playback entered and its index (see `get_sections`)
+    /// - "context-lost" when the GPU device or the canvas context is lost
+    /// - "frame-rendered" with the frame index after each frame drawn to the canvas
+    #[wasm_bindgen]
+    pub fn on(
+        &mut self,
+        #[wasm_bindgen(unchecked_param_type = "ViberEvent")] event: &str,
+        #[wasm_bindgen(unchecked_param_type = "ViberEvents[ViberEvent]")] callback: js_sys::Function,
+    ) -> Result<(), JsValue> {
+        let event = AppEvent::from_name(event).map_err(|e| JsValue::from_str(&e))?;
+        self.events.on(event, callback);
+        Ok(())
+    }
+
+    /// Remove `callback` from `event`, or every listener of `event` when no callback is given.
+    /// Returns whether a listener was removed.
+    #[wasm_bindgen]
+    pub fn off(
+        &mut self,
+        #[wasm_bindgen(unchecked_param_type = "ViberEvent")] event: &str,
+        #[wasm_bindgen(unchecked_param_type = "ViberEvents[ViberEvent] | null")] callback: Option<js_sys::Function>,
+    ) -> Result<bool, JsValue> {
+        let event = AppEvent::from_name(event).map_err(|e| JsValue::from_str(&e))?;
+        Ok(self.events.off(event, callback.as_ref()))
+    }
+
+    /// Configure the on-canvas text overlay: track title (top-left), elapsed/remaining time
+    /// and BPM (top-right). Pass `undefined` to hide the title or BPM.
+    #[wasm_bindgen]
+    pub fn set_overlay_text(&mut self, title: Option<String>, show_time: bool, bpm: Option<f32>) {
+        self.overlay = OverlayText { title, show_time, bpm };
+    }
+
+    /// Load timed lyrics from LRC text, replacing any loaded before, and return the number of
+    /// timed lines. `[offset:ms]` tags are honored; enhanced LRC word times are ignored.
+    #[wasm_bindgen]
+    pub fn load_lyrics(&mut self, text: &str) -> Result<usize, JsValue> {
+        let lyrics = Lyrics::parse(text).map_err(|e| JsValue::from_str(&e))?;
+        let count = lyrics.line_count();
+        self.lyrics = Some(lyrics);
+        self.update_overlay();
+        Ok(count)
+    }
+
+    #[wasm_bindgen]
+    pub fn clear_lyrics(&mut self) {
+        self.lyrics = None;
+        self.update_overlay();
+    }
+
+    /// The lyric line at `seconds` into the track, or `undefined` before the first line, during
+    /// instrumental breaks and when no lyrics are loaded.
+    #[wasm_bindgen]
+    pub fn get_current_line(&self, seconds: f64) -> Option<String> {
+        self.lyrics.as_ref()?.current_line(seconds).map(String::from)
+    }
+
+    /// Draw the current lyric line centered along the bottom of the canvas, following the
+    /// analysis clock (also in exported video).
+    #[wasm_bindgen]
+    pub fn set_lyrics_overlay(&mut self, enabled: bool) {
+        self.lyrics_overlay = enabled;
+        self.update_overlay();
+    }
+
+    /// Switch the visualization mode: "bars" (the default), "waveform", "spectrogram", "particles",
+    /// "waterfall", "chroma-wheel", "chroma-bars" (pitch classes as a circle-of-fifths wheel or 12
+    /// bars), "stereo" (left channel bars above the center line, right below; needs
+    /// `set_stereo_analysis`, otherwise both halves show the mono bars), "goniometer" (a phase
+    /// scope of the current frame's left and right samples) or "tuner" (the note nearest the
+    /// frame's pitch and how many cents off it is).
+    #[wasm_bindgen]
+    pub fn set_visualizer_mode(&mut self, name: &str) -> Result<(), JsValue> {
+        self.renderer.set_visualizer_mode(name).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Crossfade between modes over `duration_ms` when `set_visualizer_mode` switches (0, the
+    /// default, cuts immediately). `easing` is "linear", "ease-in", "ease-out" or "ease-in-out".
+    #[wasm_bindgen]
+    pub fn set_mode_transition(&mut self, duration_ms: f64, easing: &str) -> Result<(), JsValue> {
+        self.renderer.set_mode_transition(duration_ms, Easing::parse(easing)?);
+        Ok(())
+    }
+
+    /// Split the canvas between the loaded track and a second view drawn in mode `mode`:
+    /// `layout` is "side-by-side", "stacked" or "none" to go back to one view. The second view
+    /// shows the track loaded with `process_split_track`, or without one the right channel (with
+    /// `set_stereo_analysis`, otherwise the mono bars again). Modes with their own data (the
+    /// goniometer, particles) show the loaded track's in either view.
+    #[wasm_bindgen]
+    pub fn set_split_view(&mut self, layout: &str, mode: &str) -> Result<(), JsValue> {
+        let split = SplitLayout::parse(layout)?;
+        self.renderer.set_split_view(split.map(|layout| (layout, mode))).map_err(|e| JsValue::from_str(&e))?;
+        self.split_view = split.is_some();
+        self.previous_split_bars.clear();
+        Ok(())
+    }
+
+    /// Analyze a second track for the split view with the current analysis settings, drawn frame
+    /// for frame alongside the loaded one. Replaces any previous second track.
+    #[wasm_bindgen]
+    pub fn process_split_track(&mut self, file_data: &[u8]) -> Result<(), JsValue> {
+        self.require(LifecycleState::Initialized, "process_split_track")?;
+        let mut analyzer = self.analyzer.empty_like();
+        analyzer.process(file_data).map_err(|e| JsValue::from_str(&e))?;
+        self.split_track = Some(analyzer);
+        Ok(())
+    }
+
+    /// Drop the second track; the split view goes back to the right channel.
+    #[wasm_bindgen]
+    pub fn clear_split_track(&mut self) {
+        self.split_track = None;
+    }
+
+    #[wasm_bindgen]
+    pub fn get_visualizer_mode(&self) -> String {
+        self.renderer.visualizer_mode().to_string()
+    }
+
+    /// Names accepted by `set_visualizer_mode`.
+    #[wasm_bindgen]
+    pub fn get_visualizer_modes(&self) -> Vec<String> {
+        self.renderer.visualizer_modes().into_iter().map(String::from).collect()
+    }
+
+    /// Show a diagnostic panel (frame, timing, analysis parameters, backend, memory) on the canvas,
+    /// so screenshots attached to bug reports carry the relevant state.
+    #[wasm_bindgen]
+    pub fn set_debug_overlay(&mut self, enabled: bool) {
+        self.debug_overlay = enabled;
+        self.update_overlay();
+    }
+
+    /// Draw a frequency axis and dB grid with the bars and waterfall modes: 10 Hz, 100 Hz, 1 kHz
+    /// and 10 kHz labelled where they fall along the bars, and labelled gridlines under the bars.
+    /// Gridlines sit at the height of a full bar and `db_step` (default 12) dB steps below it, so
+    /// they read true with automatic gain control on; otherwise bars are scaled to the frame's
+    /// loudest and the grid is a guide to relative level. `options` default as in `AxisOptions`.
+    #[wasm_bindgen]
+    pub fn set_axis(&mut self, enabled: bool, #[wasm_bindgen(unchecked_param_type = "AxisOptions | undefined")] options: JsValue) -> Result<(), JsValue> {
+        let options = if options.is_undefined() || options.is_null() {
+            AxisOptions::default()
+        } else {
+            serde_wasm_bindgen::from_value::<AxisOptions>(options).map_err(|e| JsValue::from_str(&format!("Invalid axis options: {}", e)))?
+        };
+        options.validate().map_err(|e| JsValue::from_str(&e))?;
+        self.axis = enabled.then_some(options);
+        let grid = self.axis.filter(|axis| axis.grid).map(|axis| [axis.db_step, axis.opacity]);
+        self.renderer.set_axis_grid(grid);
+        self.update_overlay();
+        Ok(())
+    }
+
+    /// Arrange the bars: "bottom-up" (the default), "top-down", "mirrored" (the spectrum mirrored
+    /// about the center, lowest frequency in the middle) or "dual-sided" (bars growing both ways from
+    /// the center line). `reversed` puts the highest frequency first. With horizontal orientation the
+    /// arrangement is rotated along with the bars.
+    #[wasm_bindgen]
+    pub fn set_bar_layout(&mut self, arrangement: &str, reversed: bool) -> Result<(), JsValue> {
+        self.renderer.set_bar_layout(BarArrangement::parse(arrangement)?, reversed);
+        Ok(())
+    }
+
+    /// Shape the bars: `gap_fraction` of each bar's slot is left empty (0.0-0.95, default 0.8),
+    /// `corner_radius` is a fraction of the bar width (0.0 square to 0.5 fully rounded, the default),
+    /// `min_height` is the height of a silent bar as a fraction of the canvas (default 0.05) and
+    /// `orientation` is "vertical" or "horizontal".
+    #[wasm_bindgen]
+    pub fn set_bar_style(&mut self, gap_fraction: f32, corner_radius: f32, min_height: f32, orientation: &str) -> Result<(), JsValue> {
+        self.renderer.set_orientation(Orientation::parse(orientation)?);
+        self.renderer.set_bar_style(BarStyle::new(gap_fraction, corner_radius, min_height));
+        Ok(())
+    }
+
+    /// Format numbers drawn on the canvas (BPM, debug panel) for a BCP 47 locale tag such as
+    /// "de-DE" or `navigator.language`: decimal and digit group separators and Hz/kHz labels.
+    /// The default is "en-US".
+    #[wasm_bindgen]
+    pub fn set_locale(&mut self, tag: &str) -> Result<(), JsValue> {
+        self.locale = Locale::parse(tag)?;
+        self.update_overlay();
+        Ok(())
+    }
+
+    /// Color the bars from a gradient instead of the built-in hue cycle. `stops` are flattened
+    /// groups of `position, r, g, b, a` (all 0.0-1.0; alpha scales the bar's brightness) and `mode`
+    /// is "index" (position across the spectrum, lowest frequency at 0.0) or "magnitude" (bar level).
+    #[wasm_bindgen]
+    pub fn set_gradient(&mut self, stops: &[f32], mode: &str) -> Result<(), JsValue> {
+        let mode = GradientMode::parse(mode)?;
+        let gradient = Gradient::from_flat(stops).map_err(|e| JsValue::from_str(&e))?;
+        self.renderer.set_gradient(gradient, mode);
+        Ok(())
+    }
+
+    /// Go back to the built-in bar colors.
+    #[wasm_bindgen]
+    pub fn clear_gradient(&mut self) {
+        self.renderer.clear_gradient();
+    }
+
+    /// Draw trails: each frame the previous output is zoomed by `zoom` and rotated by `rotate`
+    /// radians about the center, dimmed by `decay` and shown under the new frame. Values are per
+    /// 60 Hz frame, e.g. `set_feedback(1.02, 0.01, 0.9)` for trails spiralling slowly outwards.
+    /// Zoom is limited to 0.5-2.0 and decay to 0.0-1.0.
+    #[wasm_bindgen]
+    pub fn set_feedback(&mut self, zoom: f32, rotate: f32, decay: f32) -> Result<(), JsValue> {
+        if ![zoom, rotate, decay].iter().all(|value| value.is_finite()) {
+            return Err(JsValue::from_str(&format!("Invalid feedback settings: zoom {}, rotate {}, decay {}", zoom, rotate, decay)));
+        }
+        self.renderer.set_feedback(Some(FeedbackSettings::new(zoom, rotate, decay)));
+        Ok(())
+    }
+
+    /// Turn the trails off.
+    #[wasm_bindgen]
+    pub fn clear_feedback(&mut self) {
+        self.renderer.set_feedback(None);
+    }
+
+    /// Draw at `scale` times the canvas resolution and stretch the result onto the canvas: 0.5
+    /// quarters the fragment work on weak GPUs, 2.0 supersamples for quality. Text stays at the
+    /// canvas resolution. Applies to exports too. Limited to 0.25-2.0; 1.0, the default, draws
+    /// straight to the canvas.
+    #[wasm_bindgen]
+    pub fn set_render_scale(&mut self, scale: f32) -> Result<(), JsValue> {
+        if !(0.25..=2.0).contains(&scale) {
+            return Err(JsValue::from_str(&format!("Render scale must be within 0.25-2.0, got {}", scale)));
+        }
+        self.renderer.set_render_scale(scale);
+        Ok(())
+    }
+
+    /// Smooth the stair steps along bar edges and thin lines, which stand out on low-DPI screens:
+    /// "fxaa" runs an edge-smoothing pass over each finished frame (text excepted), on every
+    /// backend including WebGL; "off" (the default) skips it.
+    #[wasm_bindgen]
+    pub fn set_antialiasing(&mut self, mode: &str) -> Result<(), JsValue> {
+        self.renderer.set_antialiasing(Antialiasing::parse(mode)?);
+        Ok(())
+    }
+
+    /// React to detected onsets with built-in effects, each scaled by the onset strength:
+    /// `flash` brightens the whole frame (at most three times a second), `pulse` swells the
+    /// background and `shake` jolts the visualization. Intensities are 0.0-1.0; all zero turns
+    /// the effects off.
+    #[wasm_bindgen]
+    pub fn set_beat_effects(&mut self, flash: f32, pulse: f32, shake: f32) -> Result<(), JsValue> {
+        if ![flash, pulse, shake].iter().all(|value| value.is_finite()) {
+            return Err(JsValue::from_str(&format!("Invalid beat effect intensities: flash {}, pulse {}, shake {}", flash, pulse, shake)));
+        }
+        let settings = BeatEffectSettings::new(flash, pulse, shake);
+        self.beat_effects = (!settings.is_off()).then(|| BeatEffects::new(settings));
+        self.renderer.set_beat_levels([0.0; 4]);
+        Ok(())
+    }
+
+    /// Choose the palette for every mode: `scheme` is "dark" (the default, glow on black), "light"
+    /// (dark ink on white) or "auto" (follows the browser's `prefers-color-scheme`). `high_contrast`
+    /// drops faint glow and keeps a wide luminance gap between the visualization and the background.
+    #[wasm_bindgen]
+    pub fn set_theme(&mut self, scheme: &str, high_contrast: bool) -> Result<(), JsValue> {
+        self.theme = ThemeSettings::new(ColorScheme::parse(scheme)?, high_contrast);
+        Ok(())
+    }
+
+    /// Define the layout used when the canvas falls into an aspect class
+    /// ("portrait", "landscape" or "ultrawide"): bar count, bar orientation
+    /// ("vertical"/"horizontal") and HUD placement ("top"/"bottom").
+    #[wasm_bindgen]
+    pub fn set_layout_variant(&mut self, aspect_class: &str, bin_size: usize, orientation: &str, hud_position: &str) -> Result<(), JsValue> {
+        let class = AspectClass::parse(aspect_class)?;
+        let variant = LayoutVariant {
+            bin_size: bin_size.clamp(1, 64),
+            orientation: Orientation::parse(orientation)?,
+            hud_position: HudPosition::parse(hud_position)?,
+        };
+        self.responsive_layout.set_variant(class, variant);
+        self.apply_responsive_layout();
+        Ok(())
+    }
+
+    /// Enable automatic layout selection on resize based on the variants from `set_layout_variant`.
+    #[wasm_bindgen]
+    pub fn set_responsive_layout(&mut self, enabled: bool) {
+        self.responsive_layout.enabled = enabled;
+        self.apply_responsive_layout();
+    }
+
+    /// Apply several settings at once from a plain object, e.g. `{ visualizer_mode: "waterfall",
+    /// bin_size: 32, theme: { scheme: "light" } }`, for hosts restoring persisted settings. Keys:
+    /// `visualizer_mode`, `bin_size`, `weighting`, `gpu_smoothing`, `latency_offset_ms`, `theme`
+    /// (`{scheme, high_contrast}`), `bar_layout` (`{arrangement, reversed}`), `bar_style`
+    /// (`{gap_fraction, corner_radius, min_height, orientation}`), `render_scale`, `antialiasing`,
+    /// `debug_overlay`, `hover_highlight` and `solo_on_click`, each taking its setter's values.
+    /// Keys left out keep their current values. Unknown keys and values of the wrong type reject
+    /// the whole object; a value its setter rejects stops there, with the keys before it applied.
+    #[wasm_bindgen]
+    pub fn configure(&mut self, #[wasm_bindgen(unchecked_param_type = "ViberConfig")] config: JsValue) -> Result<(), JsValue> {
+        let config: ViberConfig = serde_wasm_bindgen::from_value(config).map_err(|e| JsValue::from_str(&format!("Invalid config: {}", e)))?;
+        self.apply_config(&config)
+    }
+
+    /// Current settings as the object `configure` takes, with every key set.
+    #[wasm_bindgen(unchecked_return_type = "ViberConfig")]
+    pub fn get_config(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.config()).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Save the settings of `get_config`, the loaded track and its markers, for `restore_state`
+    /// to bring the visualizer back after a page reload or in another window. The track is kept
+    /// as its decoded samples (about 10 MB per minute of stereo audio at 44.1 kHz) rather than
+    /// its analysis, so any later settings change can re-analyze it; its tags aren't kept.
+    #[wasm_bindgen]
+    pub fn serialize_state(&self) -> Vec<u8> {
+        AppState {
+            config: self.config(),
+            audio: self.analyzer.decoded_audio(),
+            markers: self.analyzer.get_markers(),
+        }
+        .to_bytes()
+    }
+
+    /// Restore a state saved by `serialize_state`: its settings are applied as by `configure`,
+    /// then its track, if it had one, is analyzed with them and its markers put back. A state
+    /// without a track leaves the loaded one. Analysis settings outside `get_config` (region,
+    /// overlap, transform and the like) are this app's, so set them before restoring.
+    #[wasm_bindgen]
+    pub fn restore_state(&mut self, state: &[u8]) -> Result<(), JsValue> {
+        let state = AppState::from_bytes(state).map_err(|e| JsValue::from_str(&e))?;
+        if state.audio.is_some() {
+            self.require(LifecycleState::Initialized, "restore_state")?;
+        }
+        self.apply_config(&state.config)?;
+        if let Some(audio) = state.audio {
+            self.cancel_processing();
+            let started = timing::now_seconds();
+            self.events.emit(AppEvent::ProcessingStarted, &[]);
+            self.analyzer.process_decoded(audio);
+            self.analyzer.set_markers(state.markers);
+            self.track_loaded(started);
+        }
+        Ok(())
+    }
+
+    fn apply_config(&mut self, config: &ViberConfig) -> Result<(), JsValue> {
+        if let Some(mode) = &config.visualizer_mode {
+            self.set_visualizer_mode(mode)?;
+        }
+        if let Some(bin_size) = config.bin_size {
+            if bin_size == 0 {
+                return Err(JsValue::from_str("`bin_size` must be a positive integer"));
+            }
+            self.set_bin_size(bin_size);
+        }
+        if config.min_freq.is_some() || config.max_freq.is_some() {
+            let (min_freq, max_freq) = self.display_bounds;
+            self.set_display_range(config.min_freq.unwrap_or(min_freq), config.max_freq.unwrap_or(max_freq))?;
+        }
+        if let Some(weighting) = &config.weighting {
+            self.set_weighting(weighting)?;
+        }
+        if let Some(enabled) = config.gpu_smoothing {
+            self.set_gpu_smoothing(enabled);
+        }
+        if let Some(ms) = config.latency_offset_ms {
+            self.set_latency_offset(ms)?;
+        }
+        if let Some(theme) = &config.theme {
+            self.set_theme(&theme.scheme, theme.high_contrast)?;
+        }
+        if let Some(layout) = &config.bar_layout {
+            self.set_bar_layout(&layout.arrangement, layout.reversed)?;
+        }
+        if let Some(style) = &config.bar_style {
+            self.set_bar_style(style.gap_fraction, style.corner_radius, style.min_height, &style.orientation)?;
+        }
+        if let Some(scale) = config.render_scale {
+            self.set_render_scale(scale)?;
+        }
+        if let Some(mode) = &config.antialiasing {
+            self.set_antialiasing(mode)?;
+        }
+        if let Some(enabled) = config.debug_overlay {
+            self.set_debug_overlay(enabled);
+        }
+        if let Some(enabled) = config.hover_highlight {
+            self.set_hover_highlight(enabled);
+        }
+        if let Some(enabled) = config.solo_on_click {
+            self.set_solo_on_click(enabled);
+        }
+        Ok(())
+    }
+
+    fn config(&self) -> ViberConfig {
+        let (arrangement, reversed) = self.renderer.bar_layout();
+        let style = self.renderer.bar_style();
+        let (min_freq, max_freq) = self.display_bounds;
+        ViberConfig {
+            visualizer_mode: Some(self.renderer.visualizer_mode().to_string()),
+            bin_size: Some(self.analyzer.get_bin_size()),
+            min_freq: Some(min_freq),
+            max_freq: Some(max_freq),
+            weighting: Some(self.analyzer.weighting().name().to_string()),
+            gpu_smoothing: Some(self.gpu_smoothing),
+            latency_offset_ms: Some(self.latency_offset * 1000.0),
+            theme: Some(ThemeConfig {
+                scheme: self.theme.scheme.name().to_string(),
+                high_contrast: self.theme.high_contrast,
+            }),
+            bar_layout: Some(BarLayoutConfig {
+                arrangement: arrangement.name().to_string(),
+                reversed,
+            }),
+            bar_style: Some(BarStyleConfig {
+                gap_fraction: style.gap_fraction,
+                corner_radius: style.corner_radius,
+                min_height: style.min_height,
+                orientation: self.renderer.orientation().name().to_string(),
+            }),
+            render_scale: Some(self.renderer.render_scale()),
+            antialiasing: Some(self.renderer.antialiasing().name().to_string()),
+            debug_overlay: Some(self.debug_overlay),
+            hover_highlight: Some(self.hover_highlight),
+            solo_on_click: Some(self.solo_on_click),
+        }
+    }
+
+    /// Render the current visualization state offscreen and return it as PNG bytes.
+    #[wasm_bindgen]
+    pub async fn capture_frame(&mut self) -> Result<Vec<u8>, JsValue> {
+        self.require(LifecycleState::Initialized, "capture_frame")?;
+        let (pixels, width, height) = self.renderer.capture_frame().await?;
+        capture::encode_png(&pixels, width, height).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Render every analysis frame of the loaded track offscreen at `width`x`height` and pass each
+    /// one to `frame_callback(pixels, frame_index, timestamp_seconds)`, where `pixels` is a
+    /// `Uint8Array` of RGBA8 rows (suitable for `new VideoFrame` / WebCodecs `VideoEncoder`).
+    /// Frames are produced as fast as the GPU allows, independent of realtime playback. If the
+    /// callback returns a Promise it is awaited before the next frame, so encoders can apply
+    /// backpressure; returning (or resolving to) `false` stops the export early.
+    /// Resolves to the number of frames exported.
+    #[wasm_bindgen]
+    pub async fn export_video(
+        &mut self,
+        width: u32,
+        height: u32,
+        smoothing_factor: f32,
+        #[wasm_bindgen(unchecked_param_type = "(pixels: Uint8Array, frame_index: number, timestamp: number) => boolean | void | Promise<boolean | void>")]
+        frame_callback: js_sys::Function,
+    ) -> Result<usize, JsValue> {
+        self.require(LifecycleState::Loaded, "export_video")?;
+        let (Some(info), Some(time_base)) = (self.analyzer.audio_info(), self.analyzer.time_base()) else {
+            return Err(JsValue::from_str("No audio processed"));
+        };
+        let fps = time_base.frames_per_second();
+        let total_frames = self.analyzer.get_total_frames();
+        let bin_size = self.analyzer.get_bin_size();
+        log!("Exporting {} frames at {}x{} ({:.1} fps)", total_frames, width, height, fps);
+        
+        // Export starts from silence with its own smoothing state; the live state is restored afterwards
+        let live_bars = std::mem::replace(&mut self.previous_bars, vec![0.0; bin_size]);
+        let live_chroma = std::mem::replace(&mut self.previous_chroma, [0.0; PITCH_CLASSES]);
+        let live_right_bars = std::mem::take(&mut self.previous_right_bars);
+        let live_stereo_meter = std::mem::take(&mut self.previous_stereo_meter);
+        self.renderer.reset_bar_smoothing(&[], &[]);
+        let smoothing_factor = timing::frame_rate_independent_smoothing(smoothing_factor, fps as f32);
+        let top_inset = self.renderer.overlay_top_inset(height);
+        
+        let mut exported = 0;
+        let result = async {
+            for frame_index in 0..total_frames {
+                let timestamp = time_base.frame_to_seconds(frame_index);
+                let target_bars = self.analyzer.get_frequency_bars(frame_index);
+                self.smooth_interpolate(&target_bars, smoothing_factor);
+                self.smooth_chroma(frame_index, smoothing_factor);
+                self.smooth_right_bars(frame_index, smoothing_factor);
+                self.smooth_stereo_meter(frame_index, smoothing_factor);
+                self.renderer.set_scope_samples(&self.analyzer.frame_stereo_samples(frame_index));
+                let playhead = frame_index as f64 / total_frames as f64;
+                
+                let mut items = self.overlay.compose(timestamp.min(info.duration_seconds), info.duration_seconds, width, height, top_inset, self.locale);
+                items.extend(self.lyric_item(timestamp, width, height));
+                items.extend(self.tuner_items(frame_index, width, height));
+                self.renderer.set_text_items(items);
+                let pixels = self
+                    .renderer
+                    .render_offscreen(timestamp, &self.previous_bars, bin_size, playhead, width, height)
+                    .await?;
+                
+                let array = js_sys::Uint8Array::from(&pixels[..]);
+                let mut response = frame_callback.call3(
+                    &JsValue::NULL,
+                    &array,
+                    &JsValue::from(frame_index as u32),
+                    &JsValue::from(timestamp),
+                )?;
+                if let Some(promise) = response.dyn_ref::<js_sys::Promise>() {
+                    response = wasm_bindgen_futures::JsFuture::from(promise.clone()).await?;
+                }
+                exported += 1;
+                
+                if response.as_bool() == Some(false) {
+                    log!("Export stopped by callback after {} frames", exported);
+                    break;
+                }
+            }
+            Ok::<(), JsValue>(())
+        }
+        .await;
+        
+        self.previous_bars = live_bars;
+        self.previous_chroma = live_chroma;
+        self.renderer.set_chroma(live_chroma);
+        self.renderer.set_right_bars(&live_right_bars);
+        self.previous_right_bars = live_right_bars;
+        // The GPU-smoothed levels restart from the live targets
+        self.renderer.reset_bar_smoothing(&self.previous_bars, &self.previous_right_bars);
+        self.previous_stereo_meter = live_stereo_meter;
+        self.renderer.set_stereo_meter(self.stereo_meter.then_some(live_stereo_meter));
+        self.renderer.release_offscreen();
+        self.update_overlay();
+        result?;
+        
+        log!("Export complete: {} frames", exported);
+        Ok(exported)
+    }
+
+    #[wasm_bindgen]
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), JsValue> {
+        self.require(LifecycleState::Initialized, "resize")?;
+        if width == 0 || height == 0 {
+            return Err(JsValue::from_str(&format!("Invalid size {}x{}", width, height)));
+        }
+        self.renderer.resize(width, height);
+        self.apply_responsive_layout();
+        // Resizing clears the canvas, so a poster frame has to be drawn again
+        self.pause_tracker.wake();
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn get_frequency_bars(&self, frame_index: usize) -> Vec<f32> {
+        self.analyzer.get_frequency_bars(frame_index)
+    }
+
+    /// Unscaled FFT magnitudes for a frame, up to the Nyquist bin (512 values, times the
+    /// `set_fft_zero_padding` factor).
+    #[wasm_bindgen]
+    pub fn get_fft_frame(&self, frame_index: usize) -> Vec<f32> {
+        self.analyzer.get_fft_frame(frame_index)
+    }
+
+    /// Bars interpolated at a playback position; see `AudioAnalyzer.get_frequency_bars_at`.
+    #[wasm_bindgen]
+    pub fn get_frequency_bars_at(&self, playback_seconds: f64) -> Vec<f32> {
+        self.analyzer.get_frequency_bars_at(playback_seconds)
+    }
+
+    /// Bars for a seek bar hover preview at `seconds` of the track: the per-bar peak over the
+    /// 50 ms around it, so the snapshot shows nearby transients, resampled to `bar_count` bars
+    /// (the current count when not given). Only the analysis is read; the playhead, its
+    /// smoothing and the drawn frame are left as they are. Zeros outside the track, empty before
+    /// a file has been processed.
+    #[wasm_bindgen]
+    pub fn preview_at_time(&self, seconds: f64, bar_count: Option<usize>) -> Vec<f32> {
+        const PREVIEW_WINDOW_SECONDS: f64 = 0.05;
+
+        let Some(time_base) = self.analyzer.time_base() else {
+            return Vec::new();
+        };
+        let mut preview = vec![0.0f32; self.analyzer.get_bin_size()];
+        let half_window = PREVIEW_WINDOW_SECONDS / 2.0;
+        if seconds + half_window >= time_base.frame_to_seconds(0) {
+            let first = self.analyzer.frame_index_at(seconds - half_window);
+            let last = self.analyzer.frame_index_at(seconds + half_window);
+            for bars in (first..=last).filter_map(|frame_index| self.analyzer.frame_bars(frame_index)) {
+                for (peak, &bar) in preview.iter_mut().zip(bars.iter()) {
+                    *peak = peak.max(bar);
+                }
+            }
+        }
+        match bar_count {
+            Some(count) => analysis::resample_bars(&preview, count),
+            None => preview,
+        }
+    }
+
+    /// Mel-frequency cepstral coefficients for a frame; see `AudioAnalyzer.get_mfcc`.
+    #[wasm_bindgen]
+    pub fn get_mfcc(&self, frame_index: usize) -> Vec<f32> {
+        self.analyzer.get_mfcc(frame_index)
+    }
+
+    /// Loudness, brightness, onset and band-level features for a frame.
+    #[wasm_bindgen]
+    pub fn get_features(&self, frame_index: usize) -> FrameFeatures {
+        self.analyzer.get_features(frame_index)
+    }
+
+    /// Sample rate and hop size of the loaded analysis, for converting between seconds, samples
+    /// and frame indices; `undefined` before a file has been processed.
+    #[wasm_bindgen]
+    pub fn get_time_base(&self) -> Option<TimeBase> {
+        self.analyzer.time_base()
+    }
+
+    /// Momentary loudness in LUFS at every frame; see `AudioAnalyzer.get_loudness_curve`.
+    #[wasm_bindgen]
+    pub fn get_loudness_curve(&self) -> Vec<f32> {
+        self.analyzer.get_loudness_curve()
+    }
+
+    /// Add a named marker at `seconds`, returning its index in time order; see
+    /// `AudioAnalyzer.add_marker`.
+    #[wasm_bindgen]
+    pub fn add_marker(&mut self, seconds: f64, label: String) -> Result<usize, JsValue> {
+        let index = self.analyzer.add_marker(seconds, label)?;
+        self.sync_marker_ticks();
+        Ok(index)
+    }
+
+    #[wasm_bindgen]
+    pub fn remove_marker(&mut self, index: usize) -> Result<(), JsValue> {
+        self.analyzer.remove_marker(index)?;
+        self.sync_marker_ticks();
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn clear_markers(&mut self) {
+        self.analyzer.clear_markers();
+        self.sync_marker_ticks();
+    }
+
+    #[wasm_bindgen]
+    pub fn get_markers(&self) -> Vec<Marker> {
+        self.analyzer.get_markers()
+    }
+
+    /// Bars, waveform and markers of the loaded track as an analysis blob; see
+    /// `AudioAnalyzer.export_analysis`.
+    #[wasm_bindgen]
+    pub fn export_analysis(&self) -> Result<Vec<u8>, JsValue> {
+        self.analyzer.export_analysis()
+    }
+
+    /// Replace the markers with those in an exported analysis blob; see
+    /// `AudioAnalyzer.import_markers`.
+    #[wasm_bindgen]
+    pub fn import_markers(&mut self, blob: &[u8]) -> Result<usize, JsValue> {
+        let count = self.analyzer.import_markers(blob)?;
+        self.sync_marker_ticks();
+        Ok(count)
+    }
+
+    /// Estimated key of the track; see `AudioAnalyzer.get_key`.
+    #[wasm_bindgen]
+    pub fn get_key(&self) -> Option<KeyEstimate> {
+        self.analyzer.get_key()
+    }
+
+    /// Detected sections of the track; see `AudioAnalyzer.get_sections`.
+    #[wasm_bindgen]
+    pub fn get_sections(&self) -> Vec<Section> {
+        self.analyzer.get_sections()
+    }
+
+    /// Fundamental frequency in Hz at a frame; see `AudioAnalyzer.get_pitch`.
+    #[wasm_bindgen]
+    pub fn get_pitch(&self, frame_index: usize) -> Option<f32> {
+        self.analyzer.get_pitch(frame_index)
+    }
+
+    /// Correlation and width of the left and right channels over a frame; see
+    /// `AudioAnalyzer.get_stereo_meter`.
+    #[wasm_bindgen]
+    pub fn get_stereo_meter(&self, frame_index: usize) -> StereoMeter {
+        self.analyzer.get_stereo_meter(frame_index)
+    }
+
+    /// Show a small correlation and width meter in the bottom-right corner, following playback
+    /// (also in exported video).
+    #[wasm_bindgen]
+    pub fn set_stereo_meter(&mut self, enabled: bool) {
+        self.stereo_meter = enabled;
+        self.renderer.set_stereo_meter(enabled.then_some(self.previous_stereo_meter));
+    }
+
+    /// Draw the markers as ticks on the waveform strip (see `set_waveform_strip`).
+    #[wasm_bindgen]
+    pub fn set_marker_ticks(&mut self, enabled: bool) {
+        self.marker_ticks = enabled;
+        self.sync_marker_ticks();
+    }
+
+    fn sync_marker_ticks(&mut self) {
+        let positions = match self.analyzer.audio_info() {
+            Some(info) if self.marker_ticks => self.analyzer.markers().positions(info.duration_seconds),
+            _ => Vec::new(),
+        };
+        self.renderer.set_marker_ticks(&positions);
+    }
+
+    /// Min/max peaks of the loaded track for a seek bar; see `AudioAnalyzer.get_waveform_peaks`.
+    #[wasm_bindgen]
+    pub fn get_waveform_peaks(&self, buckets: usize) -> Vec<f32> {
+        self.analyzer.get_waveform_peaks(buckets)
+    }
+
+    /// RMS levels of the loaded track for a seek bar; see `AudioAnalyzer.get_waveform_rms`.
+    #[wasm_bindgen]
+    pub fn get_waveform_rms(&self, buckets: usize) -> Vec<f32> {
+        self.analyzer.get_waveform_rms(buckets)
+    }
+
+    /// Integrated loudness of the loaded track in LUFS; see `AudioAnalyzer.get_integrated_lufs`.
+    #[wasm_bindgen]
+    pub fn get_integrated_lufs(&self) -> Option<f32> {
+        self.analyzer.get_integrated_lufs()
+    }
+
+    /// Pitch-class energies for a frame: 12 values (C, C#, ... B) relative to the strongest class.
+    #[wasm_bindgen]
+    pub fn get_chroma(&self, frame_index: usize) -> Vec<f32> {
+        self.analyzer.get_chroma(frame_index)
+    }
+
+    /// Analyze several WAV files with the current bar settings without loading them; see
+    /// `AudioAnalyzer.analyze_batch`. The app is busy until the batch resolves, so to keep
+    /// rendering meanwhile run the batch on a separate `AudioAnalyzer` instead.
+    #[wasm_bindgen]
+    pub async fn analyze_batch(
+        &self,
+        files: Vec<js_sys::Uint8Array>,
+        #[wasm_bindgen(unchecked_param_type = "((index: number, completed: number, total: number) => boolean | void | Promise<boolean | void>) | null")]
+        progress: Option<js_sys::Function>,
+    ) -> Result<BatchResult, JsValue> {
+        self.analyzer.analyze_batch(files, progress).await
+    }
+
+    /// Divergence between two bar mappings of the loaded audio; see `AudioAnalyzer.compare_mappings`.
+    #[wasm_bindgen]
+    pub fn compare_mappings(&self, a: &MappingConfig, b: &MappingConfig) -> Result<MappingDiff, JsValue> {
+        self.analyzer.compare_mappings(a, b)
+    }
+
+    #[wasm_bindgen]
+    pub fn get_total_frames(&self) -> usize {
+        self.analyzer.get_total_frames()
+    }
+
+    /// Metadata about the processed audio, or `undefined` before a file has been processed.
+    #[wasm_bindgen]
+    pub fn get_audio_info(&self) -> Option<AudioInfo> {
+        self.analyzer.get_audio_info()
+    }
+
+    /// Title/artist/album tags and embedded cover art of the last processed file,
+    /// or `undefined` if the file carried no recognised tags.
+    #[wasm_bindgen]
+    pub fn get_metadata(&self) -> Option<TrackMetadata> {
+        self.analyzer.get_metadata()
+    }
+
+    #[wasm_bindgen]
+    pub fn set_bin_size(&mut self, bin_size: usize) {
+        self.analyzer.set_bin_size(bin_size);
+        self.previous_bars = vec![0.0; bin_size];
+        self.clear_solo();
+    }
+
+    /// Spread the bars over `min_hz..max_hz` only, re-mapping the processed spectra; see
+    /// `AudioAnalyzer.set_display_range`. Zoom gestures and mouse and keyboard input then zoom
+    /// and pan within this range (up to the Nyquist frequency of the loaded audio). Clears any
+    /// solo, since the bars cover new bands.
+    #[wasm_bindgen]
+    pub fn set_display_range(&mut self, min_hz: f32, max_hz: f32) -> Result<(), JsValue> {
+        self.analyzer.set_display_range(min_hz, max_hz)?;
+        self.display_bounds = (min_hz, max_hz);
+        self.clear_solo();
+        Ok(())
+    }
+
+    /// The range the bars are spread over as `[min_hz, max_hz]`, following zoom gestures and
+    /// mouse and keyboard input too.
+    #[wasm_bindgen]
+    pub fn get_display_range(&self) -> Vec<f32> {
+        self.analyzer.get_display_range()
+    }
+
+    /// Analyze the channels of stereo files separately for the "stereo" mode; see
+    /// `AudioAnalyzer.set_stereo_analysis`.
+    #[wasm_bindgen]
+    pub fn set_stereo_analysis(&mut self, enabled: bool) {
+        self.analyzer.set_stereo_analysis(enabled);
+    }
+
+    /// Scale the bars with automatic gain control instead of per-frame percentiles; see
+    /// `AudioAnalyzer.set_agc`.
+    #[wasm_bindgen]
+    pub fn set_agc(&mut self, enabled: bool, target: f32, attack_s: f32, release_s: f32) -> Result<(), JsValue> {
+        self.analyzer.set_agc(enabled, target, attack_s, release_s)
+    }
+
+    /// Zero bars quieter than `db` relative to a full-scale sine; see `AudioAnalyzer.set_noise_floor`.
+    #[wasm_bindgen]
+    pub fn set_noise_floor(&mut self, db: f32) -> Result<(), JsValue> {
+        self.analyzer.set_noise_floor(db)
+    }
+
+    /// Average each spectrum over `frames` consecutive frames before bar mapping; see
+    /// `AudioAnalyzer.set_spectrum_averaging`.
+    #[wasm_bindgen]
+    pub fn set_spectrum_averaging(&mut self, frames: usize) -> Result<(), JsValue> {
+        self.analyzer.set_spectrum_averaging(frames)
+    }
+
+    /// Median filter each bar over `frames` consecutive frames to drop clicks and pops; see
+    /// `AudioAnalyzer.set_median_filter`.
+    #[wasm_bindgen]
+    pub fn set_median_filter(&mut self, frames: usize) -> Result<(), JsValue> {
+        self.analyzer.set_median_filter(frames)
+    }
+
+    /// Weight magnitudes by frequency before bar mapping: "a", "c" or "none"; see
+    /// `AudioAnalyzer.set_weighting`.
+    #[wasm_bindgen]
+    pub fn set_weighting(&mut self, name: &str) -> Result<(), JsValue> {
+        self.analyzer.set_weighting(name)
+    }
+
+    /// Analyze only part of the track; see `AudioAnalyzer.set_analysis_region`.
+    #[wasm_bindgen]
+    pub fn set_analysis_region(&mut self, start_seconds: f64, end_seconds: f64) -> Result<(), JsValue> {
+        self.analyzer.set_analysis_region(start_seconds, end_seconds)
+    }
+
+    #[wasm_bindgen]
+    pub fn clear_analysis_region(&mut self) {
+        self.analyzer.clear_analysis_region();
+    }
+
+    /// Overlap analysis frames by a fixed percentage; see `AudioAnalyzer.set_overlap`.
+    #[wasm_bindgen]
+    pub fn set_overlap(&mut self, percent: f32) -> Result<(), JsValue> {
+        self.analyzer.set_overlap(percent)
+    }
+
+    #[wasm_bindgen]
+    pub fn clear_overlap(&mut self) {
+        self.analyzer.clear_overlap();
+    }
+
+    /// Zero-pad frames for a longer FFT; see `AudioAnalyzer.set_fft_zero_padding`.
+    #[wasm_bindgen]
+    pub fn set_fft_zero_padding(&mut self, factor: usize) -> Result<(), JsValue> {
+        self.analyzer.set_fft_zero_padding(factor)
+    }
+
+    /// Store the bars at lower precision for long tracks; see `AudioAnalyzer.set_bar_precision`.
+    #[wasm_bindgen]
+    pub fn set_bar_precision(&mut self, name: &str) -> Result<(), JsValue> {
+        self.analyzer.set_bar_precision(name)
+    }
+
+    /// Analyze frames when they're first drawn instead of up front, so playback can start right
+    /// after loading; see `AudioAnalyzer.set_lazy_analysis`.
+    #[wasm_bindgen]
+    pub fn set_lazy_analysis(&mut self, enabled: bool) {
+        self.analyzer.set_lazy_analysis(enabled);
+    }
+
+    /// Decode formats other than WAV with the browser when processing asynchronously; see
+    /// `AudioAnalyzer.set_browser_decoding`.
+    #[wasm_bindgen]
+    pub fn set_browser_decoding(&mut self, enabled: bool) {
+        self.analyzer.set_browser_decoding(enabled);
+    }
+
+    /// Skip leading and trailing silence when analyzing; see `AudioAnalyzer.set_silence_trim`.
+    #[wasm_bindgen]
+    pub fn set_silence_trim(&mut self, enabled: bool, threshold_db: f32) -> Result<(), JsValue> {
+        self.analyzer.set_silence_trim(enabled, threshold_db)
+    }
+
+    /// DC blocking and pre-emphasis of the samples before windowing; see
+    /// `AudioAnalyzer.set_preprocessing`.
+    #[wasm_bindgen]
+    pub fn set_preprocessing(&mut self, dc_block: bool, pre_emphasis: f32) -> Result<(), JsValue> {
+        self.analyzer.set_preprocessing(dc_block, pre_emphasis)
+    }
+
+    /// Map the bars from "fft" (the default) or "cqt" spectra; see `AudioAnalyzer.set_transform`.
+    #[wasm_bindgen]
+    pub fn set_transform(&mut self, name: &str, bins_per_octave: u32) -> Result<(), JsValue> {
+        self.analyzer.set_transform(name, bins_per_octave)
+    }
+
+    #[wasm_bindgen]
+    pub fn process_audio_file(&mut self, file_data: &[u8]) -> Result<(), JsValue> {
+        self.require(LifecycleState::Initialized, "process_audio_file")?;
+        self.cancel_processing();
+        let started = timing::now_seconds();
+        self.events.emit(AppEvent::ProcessingStarted, &[]);
+        self.analyzer.process_audio_file(file_data)?;
+        self.track_loaded(started);
+        Ok(())
+    }
+
+    /// Append a WAV file to the loaded track for a gapless mix; see
+    /// `AudioAnalyzer.append_audio_file`. Playback carries on where it is, the frames of the
+    /// files before keeping their indices.
+    #[wasm_bindgen]
+    pub fn append_audio_file(&mut self, file_data: &[u8]) -> Result<(), JsValue> {
+        self.require(LifecycleState::Initialized, "append_audio_file")?;
+        self.cancel_processing();
+        let started = timing::now_seconds();
+        let continuing = self.analyzer.is_processed();
+        self.events.emit(AppEvent::ProcessingStarted, &[]);
+        self.analyzer.append_audio_file(file_data)?;
+        if continuing {
+            self.track_changed(started);
+        } else {
+            self.track_loaded(started);
+        }
+        Ok(())
+    }
+
+    /// Where each file of an appended mix starts, in seconds; see
+    /// `AudioAnalyzer.get_track_boundaries`.
+    #[wasm_bindgen]
+    pub fn get_track_boundaries(&self) -> Vec<f64> {
+        self.analyzer.get_track_boundaries()
+    }
+
+    /// Analyze PCM samples instead of a file; see `AudioAnalyzer.process_samples`.
+    #[wasm_bindgen]
+    pub fn process_samples(&mut self, samples: &[f32], sample_rate: u32, channels: u32) -> Result<(), JsValue> {
+        self.require(LifecycleState::Initialized, "process_samples")?;
+        self.cancel_processing();
+        let started = timing::now_seconds();
+        self.events.emit(AppEvent::ProcessingStarted, &[]);
+        self.analyzer.process_samples(samples, sample_rate, channels)?;
+        self.track_loaded(started);
+        Ok(())
+    }
+
+    /// Decode and analyze a WAV file (or, after `set_browser_decoding`, any file the browser can
+    /// play) without blocking: the analysis runs in steps, yielding to the event loop in between,
+    /// and the current track keeps playing meanwhile. Once the returned Promise resolves, the
+    /// next render (or other call that checks the app's state) switches to the new track.
+    /// Processing another file cancels this one, as does `cancel_processing`; a cancelled run
+    /// rejects with an "AbortError" and a failed one with its error, both leaving the current
+    /// track loaded. The analysis settings in effect when processing starts are used.
+    #[wasm_bindgen(unchecked_return_type = "Promise<void>")]
+    pub fn process_audio_file_async(&mut self, file_data: Vec<u8>) -> js_sys::Promise {
+        self.start_processing("process_audio_file_async", |_| async move { Ok(file_data) })
+    }
+
+    /// Download a WAV file with `fetch` and process it like `process_audio_file_async`, which
+    /// this cancels and is cancelled by in turn. `progress(loaded, total)` is called with the
+    /// bytes received so far as they arrive, `total` being null when the size isn't known in
+    /// advance; throwing from it fails the load. Decoding isn't streamed: the whole file is
+    /// downloaded first and processing starts once the download completes.
+    #[wasm_bindgen(unchecked_return_type = "Promise<void>")]
+    pub fn load_from_url(
+        &mut self,
+        url: String,
+        #[wasm_bindgen(unchecked_param_type = "((loaded: number, total: number | null) => void) | null")] progress: Option<js_sys::Function>,
+    ) -> js_sys::Promise {
+        self.start_processing("load_from_url", move |cancelled| async move {
+            let report = |loaded: usize, total: Option<usize>| {
+                let Some(progress) = &progress else {
+                    return Ok(());
+                };
+                let total = total.map_or(JsValue::NULL, |total| JsValue::from(total as f64));
+                progress
+                    .call2(&JsValue::NULL, &JsValue::from(loaded as f64), &total)
+                    .map(|_| ())
+                    .map_err(|e| format!("Download progress callback failed: {:?}", e))
+            };
+            fetch::fetch_bytes(&url, report, || cancelled.get()).await
+        })
+    }
+
+    /// Stop a `process_audio_file_async` or `load_from_url` in progress, which then rejects; the current track stays
+    /// loaded. A run that has already finished is kept. Returns whether a run was cancelled.
+    #[wasm_bindgen]
+    pub fn cancel_processing(&mut self) -> bool {
+        self.finish_processing();
+        match self.pending_processing.take() {
+            Some(pending) => {
+                log!("Cancelling audio processing");
+                pending.cancelled.set(true);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Unload the track: cancel any processing, drop the analysis (the split view's second track
+    /// included) and markers, and settle the bars, chroma, stereo meter and beat effects at rest,
+    /// so the next track starts from silence. Settings are kept. Afterwards the app is back in
+    /// the "initialized" state and draws the idle visualization.
+    #[wasm_bindgen]
+    pub fn clear(&mut self) {
+        self.cancel_processing();
+        self.analyzer = self.analyzer.empty_like();
+        self.split_track = None;
+        self.live_history.clear();
+        if matches!(self.state, LifecycleState::Loaded | LifecycleState::Playing) {
+            self.state = LifecycleState::Initialized;
+        }
+
+        let bin_size = self.analyzer.get_bin_size();
+        self.previous_bars = vec![0.0; bin_size];
+        self.previous_chroma = [0.0; PITCH_CLASSES];
+        self.previous_right_bars.clear();
+        self.previous_stereo_meter = [0.0; 2];
+        self.previous_split_bars.clear();
+        if let Some(effects) = &mut self.beat_effects {
+            effects.reset();
+        }
+        self.renderer.set_waveform(&[]);
+        self.renderer.set_marker_ticks(&[]);
+        self.renderer.set_chroma(self.previous_chroma);
+        self.renderer.set_right_bars(&[]);
+        self.renderer.set_split_bars(&[]);
+        self.renderer.set_scope_samples(&[]);
+        self.renderer.set_stereo_meter(self.stereo_meter.then_some(self.previous_stereo_meter));
+        self.renderer.set_tuner(None);
+        self.renderer.set_beat_levels([0.0; 4]);
+        self.renderer.reset_bar_smoothing(&[], &[]);
+        self.reset_playback();
+    }
+
+    /// Draw the bars a `LiveAnalyzer` publishes to `buffer` (see
+    /// `LiveAnalyzer.attach_shared_buffer`) instead of the loaded track's, reading the latest
+    /// set each frame; resampled if the counts differ. Until the first set arrives the track (or
+    /// idle animation) is drawn. `None` goes back to the track.
+    #[wasm_bindgen]
+    pub fn set_live_bars(&mut self, buffer: Option<js_sys::SharedArrayBuffer>) -> Result<(), JsValue> {
+        self.live_bars = buffer.map(|buffer| SharedBars::new(&buffer)).transpose().map_err(|e| JsValue::from_str(&e))?;
+        self.live_history.clear();
+        Ok(())
+    }
+
+    // Switch to the track of a `process_audio_file_async` that has completed since the last call
+    fn finish_processing(&mut self) {
+        let completed = match &self.pending_processing {
+            Some(pending) => pending.result.borrow_mut().take(),
+            None => return,
+        };
+        // Still in flight
+        let Some((analyzer, elapsed)) = completed else {
+            return;
+        };
+        self.pending_processing = None;
+        // The listeners were told when the analysis completed
+        self.analyzer = analyzer;
+        self.state = LifecycleState::Loaded;
+        self.reset_playback();
+        self.show_analysis(elapsed);
+    }
+
+    // Analyze the file `load` resolves to without borrowing the app, like `start_init`; `load`
+    // gets the cancellation flag so it can stop early too
+    fn start_processing<F>(&mut self, action: &str, load: impl FnOnce(Rc<Cell<bool>>) -> F) -> js_sys::Promise
+    where
+        F: Future<Output = Result<Vec<u8>, String>> + 'static,
+    {
+        if let Err(e) = self.require(LifecycleState::Initialized, action) {
+            return js_sys::Promise::reject(&e);
+        }
+        self.cancel_processing();
+
+        let cancelled = Rc::new(Cell::new(false));
+        let result = Rc::new(RefCell::new(None));
+        let file_data = load(cancelled.clone());
+        let (flag, slot, events) = (cancelled.clone(), result.clone(), self.events.clone());
+        let mut analyzer = self.analyzer.empty_like();
+        let promise = wasm_bindgen_futures::future_to_promise(async move {
+            let processed = match file_data.await {
+                Ok(file_data) => {
+                    // Time the processing alone, not whatever it took to get the file
+                    let started = timing::now_seconds();
+                    events.emit(AppEvent::ProcessingStarted, &[]);
+                    let progress = |fraction: f64| events.emit(AppEvent::ProcessingProgress, &[JsValue::from(fraction)]);
+                    let processed = analyzer.process_cancellable(&file_data, || flag.get(), progress).await;
+                    processed.map(|()| timing::now_seconds() - started)
+                }
+                Err(e) => Err(e),
+            };
+            match processed {
+                Ok(elapsed) => {
+                    let info = analyzer.audio_info().map_or(JsValue::UNDEFINED, JsValue::from);
+                    *slot.borrow_mut() = Some((analyzer, elapsed));
+                    events.emit(AppEvent::ProcessingComplete, &[info]);
+                    Ok(JsValue::UNDEFINED)
+                }
+                Err(_) if flag.get() => Err(lifecycle::processing_cancelled()),
+                Err(e) => Err(JsValue::from_str(&e)),
+            }
+        });
+        self.pending_processing = Some(PendingProcessing {
+            cancelled,
+            result,
+        });
+        promise
+    }
+
+    // Start playing a newly processed track from scratch
+    fn track_loaded(&mut self, started: f64) {
+        self.state = LifecycleState::Loaded;
+        self.reset_playback();
+        self.track_changed(started);
+    }
+
+    // Take up a new or extended analysis and tell the listeners
+    fn track_changed(&mut self, started: f64) {
+        self.show_analysis(timing::now_seconds() - started);
+        let info = self.analyzer.audio_info().map_or(JsValue::UNDEFINED, JsValue::from);
+        self.events.emit(AppEvent::ProcessingComplete, &[info]);
+    }
+
+    // Record the seconds the analysis took and show its waveform and markers
+    fn show_analysis(&mut self, elapsed: f64) {
+        if let (Some(telemetry), Some(info)) = (&mut self.telemetry, self.analyzer.audio_info()) {
+            telemetry.record_processing(elapsed, info.duration_seconds);
+        }
+        self.renderer.set_waveform(self.analyzer.waveform());
+        self.sync_marker_ticks();
+        self.sections = None;
+    }
+
+    // Start playback tracking from scratch, for a new track or none
+    fn reset_playback(&mut self) {
+        self.last_rendered_frame = None;
+        self.current_frame = 0;
+        self.underrun.reset();
+        self.skipped_frames = 0;
+        self.playback_clock.reset();
+        self.event_beats = BeatDetector::default();
+        self.event_frame = None;
+        self.current_section = None;
+    }
+
+    fn start_init(&mut self, target: InitTarget, connect: impl Future<Output = Result<GpuTarget, String>> + 'static) -> js_sys::Promise {
+        self.finish_init();
+        if let Some(pending) = &self.pending_init {
+            if pending.target == target {
+                return pending.promise.clone();
+            }
+            let message = format!("Cannot initialize {}: already initializing {}", target.describe(), pending.target.describe());
+            return js_sys::Promise::reject(&lifecycle::already_initialized(&message));
+        }
+        if let Some(current) = &self.init_target {
+            if *current == target {
+                return js_sys::Promise::resolve(&JsValue::UNDEFINED);
+            }
+            let message = format!("Cannot initialize {}: already initialized with {}", target.describe(), current.describe());
+            return js_sys::Promise::reject(&lifecycle::already_initialized(&message));
+        }
+        
+        // Connect without borrowing the app, so it stays usable (and init re-callable) meanwhile;
+        // the target is attached by the next call that goes through `finish_init`
+        let result = Rc::new(RefCell::new(None));
+        let slot = result.clone();
+        let promise = wasm_bindgen_futures::future_to_promise(async move {
+            let connected = connect.await;
+            let outcome = match &connected {
+                Ok(_) => Ok(JsValue::UNDEFINED),
+                Err(e) => Err(JsValue::from_str(e)),
+            };
+            *slot.borrow_mut() = Some(connected);
+            outcome
+        });
+        self.pending_init = Some(PendingInit {
+            target,
+            promise: promise.clone(),
+            result,
+        });
+        promise
+    }
+    
+    // Attach the GPU target of an init that has completed since the last call
+    fn finish_init(&mut self) {
+        let completed = match &self.pending_init {
+            Some(pending) => pending.result.borrow_mut().take(),
+            None => return,
+        };
+        // Still in flight
+        let Some(outcome) = completed else {
+            return;
+        };
+        let Some(pending) = self.pending_init.take() else {
+            return;
+        };
+        match outcome {
+            Ok(gpu_target) => {
+                self.renderer.attach(gpu_target);
+                self.init_target = Some(pending.target);
+                self.state = LifecycleState::Initialized;
+                self.apply_responsive_layout();
+            }
+            // The init promise has already rejected with the error; a later init may retry
+            Err(e) => log!("Initialization of {} failed: {}", pending.target.describe(), e),
+        }
+    }
+    
+    fn require(&mut self, required: LifecycleState, action: &str) -> Result<(), JsValue> {
+        self.finish_init();
+        self.finish_processing();
+        self.state.require(required, action)
+    }
+
+    // Draw a frame, with bars interpolated at `position` seconds if it's given
+    fn draw(&mut self, time: f64, frame_index: usize, position: Option<f64>, smoothing_factor: f32) -> Result<(), JsValue> {
+        let (shader_time, playhead) = self.prepare_frame(time, frame_index, position, smoothing_factor);
+        self.renderer
+            .render(shader_time, &self.previous_bars, self.analyzer.get_bin_size(), playhead)
+            .map_err(|e| JsValue::from_str(&e))?;
+        self.mark_playing();
+        self.emit_render_events(frame_index);
+        Ok(())
+    }
+
+    // Draw the still frame a paused player leaves on screen, with the bars settled on their target
+    fn render_poster(&mut self, time: f64, frame_index: usize) -> Result<(), JsValue> {
+        let samples = self.pause_tracker.settings().map_or(1, |settings| settings.motion_blur_samples);
+        let (shader_time, playhead) = self.prepare_frame(time, frame_index, None, 1.0);
+        self.renderer
+            .render_poster(shader_time, &self.previous_bars, self.analyzer.get_bin_size(), playhead, samples, timing::POSTER_SHUTTER_SECONDS)
+            .map_err(|e| JsValue::from_str(&e))?;
+        self.mark_playing();
+        self.emit_render_events(frame_index);
+        Ok(())
+    }
+
+    // The first frame drawn from loaded audio starts playback
+    fn mark_playing(&mut self) {
+        if self.state == LifecycleState::Loaded {
+            self.state = LifecycleState::Playing;
+        }
+    }
+
+    // Raise the events of a frame that has just been drawn
+    fn emit_render_events(&mut self, frame_index: usize) {
+        if self.renderer.take_context_lost() {
+            self.events.emit(AppEvent::ContextLost, &[]);
+        }
+        if self.analyzer.is_processed() && self.live_bars.is_none() && self.event_frame != Some(frame_index) {
+            self.event_frame = Some(frame_index);
+            self.emit_beat(frame_index);
+            self.emit_section_change(frame_index);
+        }
+        self.events.emit(AppEvent::FrameRendered, &[JsValue::from(frame_index as u32)]);
+    }
+
+    fn emit_beat(&mut self, frame_index: usize) {
+        if !self.events.has_listeners(AppEvent::Beat) {
+            return;
+        }
+        let flux = self.analyzer.get_features(frame_index).spectral_flux;
+        if let Some(strength) = self.event_beats.detect(flux) {
+            self.events.emit(AppEvent::Beat, &[JsValue::from(strength), JsValue::from(frame_index as u32)]);
+        }
+    }
+
+    fn emit_section_change(&mut self, frame_index: usize) {
+        if !self.events.has_listeners(AppEvent::SectionChange) {
+            return;
+        }
+        let Some(time_base) = self.analyzer.time_base() else {
+            return;
+        };
+        let seconds = time_base.frame_to_seconds(frame_index);
+        let sections = self.sections.get_or_insert_with(|| self.analyzer.get_sections());
+        let Some(index) = sections.iter().position(|section| seconds < section.end_seconds) else {
+            return;
+        };
+        if self.current_section != Some(index) {
+            self.current_section = Some(index);
+            self.events.emit(AppEvent::SectionChange, &[JsValue::from(sections[index]), JsValue::from(index as u32)]);
+        }
+    }
+    
+    fn handle_gestures(&mut self) {
+        let gestures = match &self.gestures {
+            Some(controller) => controller.drain(),
+            None => return,
+        };
+        
+        for gesture in gestures {
+            let value = match gesture {
+                Gesture::Pinch { scale, center } => {
+                    let range = self.zoomed_frequency_range(self.analyzer.frequency_range(), scale, center);
+                    self.view_frequency_range(range);
+                    let (min_freq, max_freq) = self.analyzer.frequency_range();
+                    js_sys::Array::of2(&JsValue::from(min_freq), &JsValue::from(max_freq)).into()
+                }
+                Gesture::Swipe { direction } => JsValue::from(direction),
+                Gesture::TwoFingerTap => JsValue::UNDEFINED,
+            };
+            
+            if let Some(callback) = &self.gesture_callback {
+                if let Err(e) = callback.call2(&JsValue::NULL, &JsValue::from_str(gesture.name()), &value) {
+                    log!("Gesture callback failed: {:?}", e);
+                }
+            }
+        }
+    }
+    
+    // Apply the mouse and keyboard inputs queued since the last frame. Zooms and pans are gathered so the
+    // cached FFT results are re-mapped once per frame however many inputs arrived.
+    fn handle_mouse_input(&mut self) {
+        let (inputs, bindings) = match &self.mouse_input {
+            Some((controller, bindings)) => (controller.drain(), *bindings),
+            None => return,
+        };
+        let (arrangement, reversed) = self.renderer.bar_layout();
+        let horizontal = self.renderer.orientation() == Orientation::Horizontal;
+        // Fraction of the displayed range in the direction of rising frequency
+        let spread = |x: f32, y: f32| {
+            let position = if horizontal { y } else { x };
+            let position = if arrangement == BarArrangement::Mirrored { (position * 2.0 - 1.0).abs() } else { position };
+            if reversed { 1.0 - position } else { position }
+        };
+        let initial_range = self.analyzer.frequency_range();
+        let mut range = initial_range;
+
+        for input in inputs {
+            match input {
+                CanvasInput::Wheel { notches, x, y } => match bindings.wheel {
+                    InputAction::Zoom => range = self.zoomed_frequency_range(range, WHEEL_ZOOM_STEP.powf(-notches), spread(x, y)),
+                    InputAction::Pan => range = self.panned_frequency_range(range, notches * WHEEL_PAN_STEP),
+                    _ => {}
+                },
+                CanvasInput::Drag { dx, dy } => {
+                    // Dragging up zooms in; dragging moves the bars with the pointer
+                    let along = if horizontal { dy } else { dx };
+                    let along = if reversed { -along } else { along };
+                    match bindings.drag {
+                        InputAction::Zoom => range = self.zoomed_frequency_range(range, (-dy * DRAG_ZOOM_RATE).exp(), 0.5),
+                        InputAction::Pan => range = self.panned_frequency_range(range, -along),
+                        _ => {}
+                    }
+                }
+                CanvasInput::Click { x, y } => match bindings.click {
+                    InputAction::Marker => self.place_marker_at(Some((x, y))),
+                    InputAction::Solo => {
+                        let Some((width, height)) = self.renderer.surface_size() else {
+                            continue;
+                        };
+                        if let Some(hit) = self.hit_test(x * width as f32, y * height as f32) {
+                            self.toggle_solo(hit);
+                        }
+                    }
+                    _ => {}
+                },
+                CanvasInput::Key(command) if bindings.keyboard => match command {
+                    KeyCommand::ZoomIn => range = self.zoomed_frequency_range(range, WHEEL_ZOOM_STEP, 0.5),
+                    KeyCommand::ZoomOut => range = self.zoomed_frequency_range(range, WHEEL_ZOOM_STEP.recip(), 0.5),
+                    KeyCommand::PanUp => range = self.panned_frequency_range(range, WHEEL_PAN_STEP),
+                    KeyCommand::PanDown => range = self.panned_frequency_range(range, -WHEEL_PAN_STEP),
+                    KeyCommand::Reset => range = self.zoom_limits(),
+                    KeyCommand::Marker => self.place_marker_at(None),
+                },
+                CanvasInput::Key(_) => {}
+            }
+        }
+        if range != initial_range {
+            self.view_frequency_range(range);
+        }
+    }
+
+    // Show the bars over a range zoomed or panned to by an input, within the display bounds. Like
+    // `set_display_range`, clears any solo once the bars cover new bands.
+    fn view_frequency_range(&mut self, (min_freq, max_freq): (f32, f32)) {
+        if (min_freq, max_freq) == self.analyzer.frequency_range() {
+            return;
+        }
+        self.analyzer.set_frequency_range(min_freq, max_freq);
+        self.clear_solo();
+    }
+
+    // The display bounds limited to the loaded audio: how far zooming out and panning can go
+    fn zoom_limits(&self) -> (f32, f32) {
+        analysis::nyquist_clamped_range(self.display_bounds.0, self.display_bounds.1, self.analyzer.sample_rate())
+    }
+
+    // `range` zoomed in by `scale` on a log-frequency axis, keeping the frequency `center` of the
+    // way up it in place; at least an octave wide (unless the bounds are narrower) and inside them
+    fn zoomed_frequency_range(&self, (min_freq, max_freq): (f32, f32), scale: f32, center: f32) -> (f32, f32) {
+        let (lower, upper) = self.zoom_limits();
+        let log_min = min_freq.ln();
+        let log_max = max_freq.ln();
+        let anchor = log_min + center * (log_max - log_min);
+        let span = ((log_max - log_min) / scale).max(2.0f32.ln()).min(upper.ln() - lower.ln());
+        let start = (anchor - center * span).min(upper.ln() - span).max(lower.ln());
+        (start.exp(), (start + span).exp().min(upper))
+    }
+
+    // `range` moved up by `fraction` of its width on a log-frequency axis, stopping at the edges
+    // of the display bounds
+    fn panned_frequency_range(&self, (min_freq, max_freq): (f32, f32), fraction: f32) -> (f32, f32) {
+        let (lower, upper) = self.zoom_limits();
+        let (log_min, log_max) = (min_freq.ln(), max_freq.ln());
+        let span = (log_max - log_min).min(upper.ln() - lower.ln());
+        let start = (log_min + fraction * span).min(upper.ln() - span).max(lower.ln());
+        (start.exp(), (start + span).exp().min(upper))
+    }
+
+    // Add a marker for a click at `x`, `y` (fractions of the canvas) or a key press (no position):
+    // at the time under the pointer on the waveform strip, elsewhere at the playback position
+    fn place_marker_at(&mut self, position: Option<(f32, f32)>) {
+        let (Some(info), Some(time_base), Some((_, height))) = (self.analyzer.audio_info(), self.analyzer.time_base(), self.renderer.surface_size()) else {
+            return;
+        };
+        let strip = self.renderer.waveform_strip_rows(height);
+        let seconds = match (position, strip) {
+            (Some((x, y)), Some(rows)) if rows.contains(&(y * height as f32)) => x.clamp(0.0, 1.0) as f64 * info.duration_seconds,
+            _ => time_base.frame_to_seconds(self.current_frame).min(info.duration_seconds),
+        };
+        if let Err(e) = self.add_marker(seconds, overlay::format_time(seconds)) {
+            log!("Placing a marker failed: {:?}", e);
+        }
+    }
+
+    // Advance the per-frame state (gestures, timing, overlay, smoothing), leaving the bars to draw
+    // in `previous_bars`, and return the shader time and playhead. The shader time is measured from the first rendered frame in
+    // f64 so large host timestamps don't lose precision when the renderer narrows it to f32.
+    fn prepare_frame(&mut self, time: f64, frame_index: usize, position: Option<f64>, smoothing_factor: f32) -> (f64, f64) {
+        self.handle_gestures();
+        self.handle_mouse_input();
+        self.refresh_rate.observe(time);
+        self.record_telemetry(time);
+        if let Some(theme) = self.theme.poll(time) {
+            self.renderer.set_theme(theme);
+        }
+        let shader_time = time - *self.time_origin.get_or_insert(time);
+        #[cfg(feature = "dev")]
+        self.apply_pending_config(shader_time);
+        
+        self.current_frame = frame_index;
+        self.update_overlay();
+        #[cfg(feature = "presets")]
+        self.run_preset(shader_time);
+        self.run_beat_effects(shader_time);
+        
+        // Smoothing is specified per 60 Hz frame; rescale it for the measured refresh rate,
+        // or for the actual frame time when this frame was late so the bars catch up
+        let effective_rate = match self.refresh_rate.last_delta() {
+            Some(delta) if self.refresh_rate.last_frame_was_slow() => (1.0 / delta) as f32,
+            _ => self.refresh_rate.refresh_rate(),
+        };
+        // The track's bars change `playback_rate` times as fast, so they ease that much faster too
+        let playback_rate = if self.live_bars.is_none() { self.playback_rate } else { 1.0 };
+        let smoothing_factor = timing::frame_rate_independent_smoothing(smoothing_factor, effective_rate / playback_rate);
+        
+        let playhead = if let Some(bars) = self.live_target_bars(shader_time) {
+            let bars = analysis::resample_bars(&bars, self.analyzer.get_bin_size());
+            self.smooth_interpolate(&bars, smoothing_factor);
+            0.0
+        } else if self.analyzer.is_processed() {
+            self.count_skipped_frames(frame_index);
+            let target_bars = self.target_bars(shader_time, frame_index, position);
+            let target_bars = self.crossfade_loop(target_bars, frame_index, position);
+            self.last_rendered_frame = Some(frame_index);
+            self.smooth_interpolate(&target_bars, smoothing_factor);
+            self.smooth_chroma(frame_index, smoothing_factor);
+            self.smooth_right_bars(frame_index, smoothing_factor);
+            self.smooth_stereo_meter(frame_index, smoothing_factor);
+            self.renderer.set_scope_samples(&self.analyzer.frame_stereo_samples(frame_index));
+            frame_index as f64 / self.analyzer.get_total_frames().max(1) as f64
+        } else {
+            // Render empty bars or default animation when no audio is loaded
+            self.smooth_interpolate(&[], 1.0);
+            0.0
+        };
+        self.smooth_split_bars(frame_index, smoothing_factor);
+        (shader_time, playhead)
+    }
+    
+    // Ease the split view's second view towards the second track's frame, the right channel's or,
+    // with neither, the bars of the main view
+    fn smooth_split_bars(&mut self, frame_index: usize, smoothing_factor: f32) {
+        if !self.split_view {
+            return;
+        }
+        let bin_size = self.analyzer.get_bin_size();
+        let target = match &self.split_track {
+            Some(track) => track.frame_bars(frame_index).map(|bars| analysis::resample_bars(&bars, bin_size)).unwrap_or_default(),
+            None => match self.analyzer.frame_right_bars(frame_index) {
+                Some(bars) if self.live_bars.is_none() => bars.into_owned(),
+                _ => {
+                    // Already smoothed, unless the GPU smooths them
+                    let smoothing_factor = if self.gpu_smoothing { smoothing_factor } else { 1.0 };
+                    let target = self.previous_bars.clone();
+                    self.ease_split_bars(&target, smoothing_factor);
+                    return;
+                }
+            },
+        };
+        self.ease_split_bars(&target, smoothing_factor);
+    }
+
+    fn ease_split_bars(&mut self, target: &[f32], smoothing_factor: f32) {
+        self.previous_split_bars.resize(target.len(), 0.0);
+        for (previous, target) in self.previous_split_bars.iter_mut().zip(target) {
+            *previous = *previous * (1.0 - smoothing_factor) + target * smoothing_factor;
+        }
+        self.renderer.set_split_bars(&self.previous_split_bars);
+    }
+    
+    // The latest live bars, or with a latency offset the ones read that long ago
+    fn live_target_bars(&mut self, time: f64) -> Option<Vec<f32>> {
+        let bars = self.live_bars.as_ref()?.read()?;
+        if self.latency_offset <= 0.0 {
+            self.live_history.clear();
+            return Some(bars);
+        }
+        self.live_history.push_back((time, bars));
+        // Keep the last set read at or before the delayed time, and everything newer
+        let shown = time - self.latency_offset;
+        while self.live_history.get(1).is_some_and(|&(read, _)| read <= shown) {
+            self.live_history.pop_front();
+        }
+        self.live_history.front().map(|(_, bars)| bars.clone())
+    }
+
+    // Rendering never slows the visual clock down: when renders arrive late the playhead jumps
+    // ahead and the analysis frames beyond what one display interval normally covers are counted
+    fn count_skipped_frames(&mut self, frame_index: usize) {
+        let Some(passed) = self.passed_frames(frame_index) else {
+            return;
+        };
+        
+        let analysis_fps = self.analyzer.time_base().map_or(analysis::TARGET_FPS, |time_base| time_base.frames_per_second()) as f32;
+        let expected_advance = (analysis_fps / self.refresh_rate.refresh_rate()).ceil().max(1.0) as usize;
+        let advance = passed.len() + 1;
+        if advance > expected_advance {
+            self.skipped_frames += (advance - expected_advance) as u64;
+        }
+    }
+    
+    // Frames playback went through between the last rendered frame and `frame_index`, both
+    // excluded; `None` when there is no last frame or playback went against `direction`
+    fn passed_frames(&self, frame_index: usize) -> Option<Range<usize>> {
+        let last = self.last_rendered_frame?;
+        match self.direction {
+            PlaybackDirection::Forward if last < frame_index => Some(last + 1..frame_index),
+            PlaybackDirection::Reverse if last > frame_index => Some(frame_index + 1..last),
+            _ => None,
+        }
+    }
+    
+    // The passed frames to fold into the render of `frame_index`; none after a jump, like a seek
+    fn catch_up_frames(&self, frame_index: usize) -> Range<usize> {
+        const MAX_CATCH_UP_FRAMES: usize = 8;
+        
+        match self.passed_frames(frame_index) {
+            Some(passed) if passed.len() < MAX_CATCH_UP_FRAMES => passed,
+            _ => 0..0,
+        }
+    }
+    
+    // Bars to display for a frame. When the display runs slower than the analysis rate, several
+    // analysis frames elapse between renders; take the per-bar peak over them so short transients
+    // still show up at 60 Hz. With a `position` in seconds, the bars are interpolated there instead.
+    fn target_bars(&mut self, time: f64, frame_index: usize, position: Option<f64>) -> Vec<f32> {
+        if let Some(bars) = position.and_then(|position| self.analyzer.bars_at(position)) {
+            self.underrun.track(&bars);
+            return bars;
+        }
+        let Some(current) = self.analyzer.frame_bars(frame_index) else {
+            return self.underrun.hold(time, self.analyzer.get_bin_size());
+        };
+        let passed = self.catch_up_frames(frame_index);
+        
+        let mut bars = current.into_owned();
+        for frame in passed.filter_map(|index| self.analyzer.frame_bars(index)) {
+            for (bar, &value) in bars.iter_mut().zip(frame.iter()) {
+                *bar = bar.max(value);
+            }
+        }
+        self.underrun.track(&bars);
+        bars
+    }
+    
+    fn update_overlay(&mut self) {
+        let Some((width, height)) = self.renderer.surface_size() else {
+            return;
+        };
+        
+        let (elapsed, duration) = match (self.analyzer.audio_info(), self.analyzer.time_base()) {
+            (Some(info), Some(time_base)) => {
+                let elapsed = time_base.frame_to_seconds(self.current_frame);
+                (elapsed.min(info.duration_seconds), info.duration_seconds)
+            }
+            _ => (0.0, 0.0),
+        };
+        let top_inset = self.renderer.overlay_top_inset(height);
+        let mut items = self.overlay.compose(elapsed, duration, width, height, top_inset, self.locale);
+        items.extend(self.lyric_item(elapsed, width, height));
+        items.extend(self.tuner_items(self.current_frame, width, height));
+        items.extend(self.axis_items(width, height));
+        if self.debug_overlay {
+            // Start below the title line, which shares the left edge
+            let scale = overlay::overlay_scale(height);
+            let title_lines = if self.overlay.title.is_some() { 1.25 } else { 0.0 };
+            let top = top_inset + text::line_height(scale) * (0.5 + title_lines);
+            items.extend(overlay::compose_debug_panel(&self.debug_lines(), height, top));
+        }
+        self.renderer.set_text_items(items);
+    }
+    
+    // The lyric line at `seconds`, if lyrics are loaded and shown
+    fn lyric_item(&self, seconds: f64, width: u32, height: u32) -> Option<text::TextItem> {
+        let line = self.lyrics.as_ref().filter(|_| self.lyrics_overlay)?.current_line(seconds)?;
+        Some(overlay::compose_lyric(line, width, height))
+    }
+
+    // Point the tuner mode at the pitch of a frame and label it; nothing while another mode is
+    // shown, since the pitch is estimated on demand
+    fn tuner_items(&mut self, frame_index: usize, width: u32, height: u32) -> Vec<text::TextItem> {
+        let note = match self.renderer.visualizer_mode() {
+            "tuner" => self.analyzer.frame_pitch(frame_index).map(pitch::NoteReading::from_frequency),
+            _ => None,
+        };
+        self.renderer.set_tuner(note.map(|note| [note.cents, note.pitch_class() as f32]));
+        note.map_or_else(Vec::new, |note| overlay::compose_tuner(note, width, height))
+    }
+
+    // Frequency and gridline labels, while the axis is on and the mode draws the bars it follows
+    fn axis_items(&self, width: u32, height: u32) -> Vec<text::TextItem> {
+        let Some(options) = self.axis.filter(|_| axis::AXIS_MODES.contains(&self.renderer.visualizer_mode())) else {
+            return Vec::new();
+        };
+        let (arrangement, reversed) = self.renderer.bar_layout();
+        let layout = AxisLayout {
+            orientation: self.renderer.orientation(),
+            arrangement,
+            reversed,
+            style: self.renderer.bar_style(),
+        };
+        let (min_freq, max_freq) = self.analyzer.frequency_range();
+        let boundaries = analysis::frequency_boundaries(min_freq, max_freq, self.analyzer.get_bin_size());
+        axis::compose_labels(&options, &layout, &boundaries, width, height, self.locale)
+    }
+
+    fn debug_lines(&self) -> Vec<String> {
+        let frame_time_ms = self.refresh_rate.last_delta().map_or(0.0, |delta| delta * 1000.0);
+        let (min_freq, max_freq) = self.analyzer.frequency_range();
+        let analysis_fps = self.analyzer.audio_info().map_or(0.0, |info| info.analysis_fps);
+        let memory_mb = (self.analyzer.memory_estimate() + self.renderer.memory_estimate()) as f64 / (1024.0 * 1024.0);
+
+        let locale = self.locale;
+        let integer = |value: usize| locale.format_number(value as f64, 0);
+
+        vec![
+            format!(
+                "frame {}/{}  skipped {}",
+                integer(self.current_frame),
+                integer(self.analyzer.get_total_frames()),
+                integer(self.skipped_frames as usize)
+            ),
+            format!(
+                "{} fps  {} ms",
+                locale.format_number(self.refresh_rate.refresh_rate() as f64, 1),
+                locale.format_number(frame_time_ms, 1)
+            ),
+            format!(
+                "{} bars  {}-{}",
+                self.analyzer.get_bin_size(),
+                locale.format_frequency(min_freq),
+                locale.format_frequency(max_freq)
+            ),
+            format!(
+                "{}  hop {}  {} fps",
+                locale.format_frequency(self.analyzer.sample_rate() as f32),
+                self.analyzer.hop_size(),
+                locale.format_number(analysis_fps, 1)
+            ),
+            format!(
+                "mode {}  backend {}  {}",
+                self.renderer.visualizer_mode(),
+                self.renderer.backend_description(),
+                self.get_surface_format()
+            ),
+            format!("memory ~{} MB", locale.format_number(memory_mb, 1)),
+        ]
+    }
+    
+    fn record_telemetry(&mut self, time: f64) {
+        let Some(telemetry) = &mut self.telemetry else {
+            return;
+        };
+        telemetry.record_frame(self.refresh_rate.last_delta());
+        let (backend, device_type) = self.renderer.adapter_class();
+        telemetry.report_if_due(time, &backend, &device_type);
+    }
+    
+    // The onset detection works per analysis frame, so it only sees a frame playback has moved
+    // to, with the peak flux of the frames passed since the last render; the effects fade every
+    // render regardless
+    fn run_beat_effects(&mut self, time: f64) {
+        if self.beat_effects.is_none() {
+            return;
+        }
+        let frame_index = self.current_frame;
+        let advanced = self.analyzer.is_processed() && self.live_bars.is_none() && self.last_rendered_frame != Some(frame_index);
+        let flux = advanced.then(|| {
+            self.catch_up_frames(frame_index)
+                .map(|index| self.analyzer.get_features(index).spectral_flux)
+                .fold(self.analyzer.get_features(frame_index).spectral_flux, f32::max)
+        });
+        if let Some(effects) = &mut self.beat_effects {
+            self.renderer.set_beat_levels(effects.update(time, flux));
+        }
+    }
+    
+    fn apply_responsive_layout(&mut self) {
+        let Some((width, height)) = self.renderer.surface_size() else {
+            return;
+        };
+        let Some(variant) = self.responsive_layout.variant_for(width, height) else {
+            return;
+        };
+        
+        self.renderer.set_layout(variant.orientation, variant.hud_position);
+        if variant.bin_size != self.analyzer.get_bin_size() {
+            log!("Switching layout to {} bars ({:?})", variant.bin_size, variant.orientation);
+            self.set_bin_size(variant.bin_size);
+        }
+    }
+    
+    fn emit_solo_event(&self, payload: JsValue) {
+        if let Some(callback) = &self.solo_callback {
+            if let Err(e) = callback.call1(&JsValue::NULL, &payload) {
+                log!("Solo callback failed: {:?}", e);
+            }
+        }
+    }
+
+    // Ease `previous_bars` towards the target bars in place; missing targets are 0. With GPU
+    // smoothing the targets are just stored and the easing is left to the renderer.
+    fn smooth_interpolate(&mut self, target_bars: &[f32], smoothing_factor: f32) {
+        let bin_size = self.analyzer.get_bin_size();
+        self.previous_bars.resize(bin_size, 0.0);
+        
+        if self.gpu_smoothing {
+            for (i, value) in self.previous_bars.iter_mut().enumerate() {
+                *value = target_bars.get(i).copied().unwrap_or(0.0);
+            }
+            self.renderer.set_smoothing_factor(smoothing_factor);
+            return;
+        }
+        
+        for (i, value) in self.previous_bars.iter_mut().enumerate() {
+            let target = target_bars.get(i).copied().unwrap_or(0.0);
+            // Linear interpolation with smoothing
+            *value = *value * (1.0 - smoothing_factor) + target * smoothing_factor;
+        }
+    }
+    
+    // Ease the chromagram towards the frame's pitch classes at the bar smoothing rate and hand it
+    // to the renderer
+    fn smooth_chroma(&mut self, frame_index: usize, smoothing_factor: f32) {
+        let target = self.analyzer.frame_chroma(frame_index).unwrap_or_default();
+        for (previous, target) in self.previous_chroma.iter_mut().zip(target) {
+            *previous = *previous * (1.0 - smoothing_factor) + target * smoothing_factor;
+        }
+        self.renderer.set_chroma(self.previous_chroma);
+    }
+
+    // Ease the right channel bars the same way, or drop them when the frame has none so the
+    // stereo mode falls back to the mono bars
+    fn smooth_right_bars(&mut self, frame_index: usize, smoothing_factor: f32) {
+        let Some(target) = self.analyzer.frame_right_bars(frame_index) else {
+            self.previous_right_bars.clear();
+            self.renderer.set_right_bars(&[]);
+            return;
+        };
+        self.previous_right_bars.resize(target.len(), 0.0);
+        // GPU smoothing eases the targets itself, like the mono bars
+        let smoothing_factor = if self.gpu_smoothing { 1.0 } else { smoothing_factor };
+        for (previous, target) in self.previous_right_bars.iter_mut().zip(target.iter()) {
+            *previous = *previous * (1.0 - smoothing_factor) + target * smoothing_factor;
+        }
+        self.renderer.set_right_bars(&self.previous_right_bars);
+    }
+
+    // Ease the stereo meter towards the frame's correlation and width
+    fn smooth_stereo_meter(&mut self, frame_index: usize, smoothing_factor: f32) {
+        let target = self.analyzer.frame_stereo_meter(frame_index).unwrap_or_default();
+        for (previous, target) in self.previous_stereo_meter.iter_mut().zip([target.correlation, target.width]) {
+            *previous = *previous * (1.0 - smoothing_factor) + target * smoothing_factor;
+        }
+        self.renderer.set_stereo_meter(self.stereo_meter.then_some(self.previous_stereo_meter));
+    }
+}
+
+#[cfg(feature = "presets")]
+#[wasm_bindgen]
+impl App {
+    /// Load a preset: a Rhai script run every frame that reads `bass`, `mid`, `treble`, `rms`,
+    /// `flux`, `beat` and `time` and assigns `rotation`, `zoom` and `color_shift`, which are applied
+    /// to every visualizer mode. Replaces any current preset. A script that fails while running
+    /// is unloaded and the error logged.
+    #[wasm_bindgen]
+    pub fn load_preset(&mut self, source: &str) -> Result<(), JsValue> {
+        self.preset = Some(Preset::compile(source).map_err(|e| JsValue::from_str(&e))?);
+        Ok(())
+    }
+
+    /// Unload the current preset and reset rotation, zoom and color shift.
+    #[wasm_bindgen]
+    pub fn clear_preset(&mut self) {
+        self.preset = None;
+        self.renderer.set_view_transform(0.0, 1.0, 0.0);
+    }
+
+    fn run_preset(&mut self, time: f64) {
+        let Some(preset) = &mut self.preset else {
+            return;
+        };
+        let features = self.analyzer.get_features(self.current_frame);
+        match preset.evaluate(&features, time) {
+            Ok(params) => self.renderer.set_view_transform(params.rotation, params.zoom, params.color_shift),
+            Err(e) => {
+                // Unload rather than failing again on every frame
+                log!("{}; preset unloaded", e);
+                self.clear_preset();
+            }
+        }
+    }
+}
+
+#[cfg(feature = "dev")]
+#[wasm_bindgen]
+impl App {
+    /// Queue a JSON object of settings to apply once no newer config has arrived for a short
+    /// while, so a file watcher firing several times per save applies it once. Keys:
+    /// `visualizer_mode`, `mode_transition` (`{duration_ms, easing}`), `bin_size`,
+    /// `bar_layout` (`{arrangement, reversed}`),
+    /// `bar_style` (`{gap_fraction, corner_radius, min_height, orientation}`),
+    /// `gradient` (`{stops: [[position, r, g, b, a], ...], mode}`, or `null` to clear),
+    /// `feedback` (`{zoom, rotate, decay}`, or `null` to clear), `beat_effects` (`{flash, pulse, shake}`),
+    /// `theme` (`{scheme, high_contrast}`), `waveform_strip` (`{enabled, height}`), `background_style` (`{blur, dim}`),
+    /// `overlay` (`{title, show_time, bpm}`), `debug_overlay`, `locale`, `hover_highlight`, `solo_on_click`
+    /// and `preset` (script source, or `null` to clear). Malformed JSON is rejected immediately;
+    /// errors in individual keys are logged when the config is applied.
+    #[wasm_bindgen]
+    pub fn apply_config_json(&mut self, json: &str) -> Result<(), JsValue> {
+        self.pending_config.submit(json).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Replace a mode's fragment shader without reloading the page. `fragment_source` is WGSL
+    /// defining `fs_main`, written against the same prelude as the files in `src/shaders/modes`.
+    /// A shader that fails validation is rejected with a `ShaderDiagnostic` and the current one
+    /// kept; an unknown mode rejects with an error message.
+    #[wasm_bindgen]
+    pub fn hot_swap_shader(&mut self, mode: &str, fragment_source: &str) -> Result<(), JsValue> {
+        let source = visualizer::compile_mode_shader(fragment_source).map_err(|diagnostic| {
+            log!("Shader for '{}' rejected: {}", mode, diagnostic);
+            JsValue::from(diagnostic)
+        })?;
+        self.renderer.hot_swap_shader(mode, source).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Check a fragment shader as `hot_swap_shader` would, without swapping it in: the
+    /// `ShaderDiagnostic` (line, column, message) of its first error, or undefined when it is
+    /// valid. For shader editors checking as the user types.
+    #[wasm_bindgen]
+    pub fn check_shader(&self, fragment_source: &str) -> Option<ShaderDiagnostic> {
+        visualizer::compile_mode_shader(fragment_source).err()
+    }
+
+    fn apply_pending_config(&mut self, time: f64) {
+        let Some(config) = self.pending_config.poll(time) else {
+            return;
+        };
+        for (key, value) in &config {
+            if let Err(e) = self.apply_config_value(key, value) {
+                log!("Config `{}` not applied: {}", key, e);
+            }
+        }
+        log!("Applied config ({} keys)", config.len());
+    }
+
+    fn apply_config_value(&mut self, key: &str, value: &serde_json::Value) -> Result<(), String> {
+        let js_error = |e: JsValue| e.as_string().unwrap_or_default();
+        match key {
+            "visualizer_mode" => self.renderer.set_visualizer_mode(dev::string(key, value)?)?,
+            "mode_transition" => {
+                let duration_ms = dev::field(key, value, "duration_ms")?.map_or(Ok(0.0), |v| dev::number("duration_ms", v))?;
+                let easing = dev::field(key, value, "easing")?.map_or(Ok("ease-in-out"), |v| dev::string("easing", v))?;
+                self.set_mode_transition(duration_ms, easing).map_err(js_error)?;
+            }
+            "bin_size" => {
+                let bin_size = value
+                    .as_u64()
+                    .filter(|&bin_size| bin_size > 0)
+                    .ok_or_else(|| format!("`bin_size` must be a positive integer, got {}", value))?;
+                self.set_bin_size(bin_size as usize);
+            }
+            "waveform_strip" => {
+                let enabled = dev::field(key, value, "enabled")?.map_or(Ok(true), |v| dev::boolean("enabled", v))?;
+                let height = dev::field(key, value, "height")?.map_or(Ok(0.15), |v| dev::number("height", v))?;
+                self.set_waveform_strip(enabled, height as f32);
+            }
+            "background_style" => {
+                let blur = dev::field(key, value, "blur")?.map_or(Ok(0.01), |v| dev::number("blur", v))?;
+                let dim = dev::field(key, value, "dim")?.map_or(Ok(0.6), |v| dev::number("dim", v))?;
+                self.set_background_style(blur as f32, dim as f32);
+            }
+            "overlay" => {
+                let title = dev::field(key, value, "title")?.map(|v| dev::string("title", v)).transpose()?;
+                let show_time = dev::field(key, value, "show_time")?.map_or(Ok(false), |v| dev::boolean("show_time", v))?;
+                let bpm = dev::field(key, value, "bpm")?.map(|v| dev::number("bpm", v)).transpose()?;
+                self.set_overlay_text(title.map(String::from), show_time, bpm.map(|bpm| bpm as f32));
+            }
+            "bar_layout" => {
+                let arrangement = dev::field(key, value, "arrangement")?.map_or(Ok("bottom-up"), |v| dev::string("arrangement", v))?;
+                let reversed = dev::field(key, value, "reversed")?.map_or(Ok(false), |v| dev::boolean("reversed", v))?;
+                self.set_bar_layout(arrangement, reversed).map_err(js_error)?;
+            }
+            "bar_style" => {
+                let defaults = BarStyle::default();
+                let gap_fraction = dev::field(key, value, "gap_fraction")?.map_or(Ok(defaults.gap_fraction as f64), |v| dev::number("gap_fraction", v))?;
+                let corner_radius = dev::field(key, value, "corner_radius")?.map_or(Ok(defaults.corner_radius as f64), |v| dev::number("corner_radius", v))?;
+                let min_height = dev::field(key, value, "min_height")?.map_or(Ok(defaults.min_height as f64), |v| dev::number("min_height", v))?;
+                let orientation = dev::field(key, value, "orientation")?.map_or(Ok("vertical"), |v| dev::string("orientation", v))?;
+                self.set_bar_style(gap_fraction as f32, corner_radius as f32, min_height as f32, orientation).map_err(js_error)?;
+            }
+            "gradient" => match value {
+                serde_json::Value::Null => self.clear_gradient(),
+                _ => {
+                    let mode = dev::field(key, value, "mode")?.map_or(Ok("index"), |v| dev::string("mode", v))?;
+                    let stops = dev::field(key, value, "stops")?
+                        .and_then(|stops| stops.as_array())
+                        .ok_or("`gradient.stops` must be an array of [position, r, g, b, a] arrays")?
+                        .iter()
+                        .flat_map(|stop| stop.as_array().into_iter().flatten())
+                        .map(|number| dev::number("gradient.stops", number).map(|number| number as f32))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    self.set_gradient(&stops, mode).map_err(js_error)?;
+                }
+            },
+            "feedback" => match value {
+                serde_json::Value::Null => self.clear_feedback(),
+                _ => {
+                    let zoom = dev::field(key, value, "zoom")?.map_or(Ok(1.0), |v| dev::number("zoom", v))?;
+                    let rotate = dev::field(key, value, "rotate")?.map_or(Ok(0.0), |v| dev::number("rotate", v))?;
+                    let decay = dev::field(key, value, "decay")?.map_or(Ok(0.9), |v| dev::number("decay", v))?;
+                    self.set_feedback(zoom as f32, rotate as f32, decay as f32).map_err(js_error)?;
+                }
+            },
+            "beat_effects" => {
+                let flash = dev::field(key, value, "flash")?.map_or(Ok(0.0), |v| dev::number("flash", v))?;
+                let pulse = dev::field(key, value, "pulse")?.map_or(Ok(0.0), |v| dev::number("pulse", v))?;
+                let shake = dev::field(key, value, "shake")?.map_or(Ok(0.0), |v| dev::number("shake", v))?;
+                self.set_beat_effects(flash as f32, pulse as f32, shake as f32).map_err(js_error)?;
+            }
+            "theme" => {
+                let scheme = dev::field(key, value, "scheme")?.map_or(Ok("dark"), |v| dev::string("scheme", v))?;
+                let high_contrast = dev::field(key, value, "high_contrast")?.map_or(Ok(false), |v| dev::boolean("high_contrast", v))?;
+                self.set_theme(scheme, high_contrast).map_err(js_error)?;
+            }
+            "debug_overlay" => self.set_debug_overlay(dev::boolean(key, value)?),
+            "locale" => self.set_locale(dev::string(key, value)?).map_err(js_error)?,
+            "hover_highlight" => self.set_hover_highlight(dev::boolean(key, value)?),
+            "solo_on_click" => self.set_solo_on_click(dev::boolean(key, value)?),
+            #[cfg(feature = "presets")]
+            "preset" => match value {
+                serde_json::Value::Null => self.clear_preset(),
+                _ => self.preset = Some(Preset::compile(dev::string(key, value)?)?),
+            },
+            _ => return Err("unknown key".to_string()),
+        }
+        Ok(())
     }
 }
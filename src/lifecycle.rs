@@ -0,0 +1,87 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+
+use crate::analyzer::AudioAnalyzer;
+use crate::renderer::GpuTarget;
+
+/// Lifecycle of an `App`: created, then initialized against a canvas (or headless), then loaded
+/// with analyzed audio, then playing once a loaded frame has been rendered. Loading another track
+/// returns a playing app to `Loaded`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LifecycleState {
+    Created,
+    Initialized,
+    Loaded,
+    Playing,
+}
+
+impl LifecycleState {
+    /// Name reported to JS by `get_state`.
+    pub fn name(self) -> &'static str {
+        match self {
+            LifecycleState::Created => "created",
+            LifecycleState::Initialized => "initialized",
+            LifecycleState::Loaded => "loaded",
+            LifecycleState::Playing => "playing",
+        }
+    }
+
+    /// Fail with a message naming `action` and what to call first unless `required` has been reached.
+    pub fn require(self, required: LifecycleState, action: &str) -> Result<(), JsValue> {
+        if self >= required {
+            return Ok(());
+        }
+        let hint = match required {
+            LifecycleState::Created | LifecycleState::Initialized => "call init() or init_headless() first",
+            LifecycleState::Loaded | LifecycleState::Playing => "call process_audio_file() first",
+        };
+        Err(JsValue::from_str(&format!("Cannot {}: the app is {} ({})", action, self.name(), hint)))
+    }
+}
+
+/// What an `init` call connects to, so repeated calls can be matched against the first one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InitTarget {
+    Canvas(String),
+    Headless(u32, u32),
+}
+
+impl InitTarget {
+    pub fn describe(&self) -> String {
+        match self {
+            InitTarget::Canvas(canvas_id) => format!("canvas '{}'", canvas_id),
+            InitTarget::Headless(width, height) => format!("headless {}x{}", width, height),
+        }
+    }
+}
+
+/// An `init` in flight: the promise shared by every caller and the slot its GPU target lands in.
+pub struct PendingInit {
+    pub target: InitTarget,
+    pub promise: js_sys::Promise,
+    pub result: Rc<RefCell<Option<Result<GpuTarget, String>>>>,
+}
+
+/// A `process_audio_file_async` or `load_from_url` in flight: its cancellation flag and the slot
+/// the finished analysis lands in, with the seconds its processing took (after any download).
+pub struct PendingProcessing {
+    pub cancelled: Rc<Cell<bool>>,
+    pub result: Rc<RefCell<Option<(AudioAnalyzer, f64)>>>,
+}
+
+/// Error a cancelled `process_audio_file_async` or `load_from_url` rejects with; JS can tell it
+/// apart by its `name`, "AbortError", as with an aborted `fetch`.
+pub fn processing_cancelled() -> JsValue {
+    let error = js_sys::Error::new("Audio processing was cancelled");
+    error.set_name("AbortError");
+    error.into()
+}
+
+/// Error for an `init` that conflicts with the target the app is (being) initialized with.
+/// JS can tell it apart by its `name`, "AlreadyInitialized".
+pub fn already_initialized(message: &str) -> JsValue {
+    let error = js_sys::Error::new(message);
+    error.set_name("AlreadyInitialized");
+    error.into()
+}
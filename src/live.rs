@@ -0,0 +1,170 @@
+//! Realtime analysis for live input: an `AudioWorkletProcessor` pushes its 128-sample render
+//! quanta into a `LiveAnalyzer`, which frames them with overlap like a track and publishes the
+//! latest bars to a SharedArrayBuffer. The `App` on the main thread reads them from there each
+//! frame without locking, so the bars trail the audio by one frame (about 20 ms) rather than
+//! by a whole offline analysis.
+
+use wasm_bindgen::prelude::*;
+
+use crate::analysis::{self, DEFAULT_MAX_FREQ, DEFAULT_MIN_FREQ, FRAME_SIZE};
+
+/// Samples an AudioWorklet processes per call.
+pub const RENDER_QUANTUM: usize = 128;
+/// Samples between live frames: two render quanta, about 190 frames per second at 48 kHz.
+pub const LIVE_HOP_SIZE: usize = 2 * RENDER_QUANTUM;
+// Bars the shared buffer has room for, as many as the renderer draws
+const MAX_SHARED_BARS: usize = 64;
+// Tries at reading a consistent set of bars before giving up for this frame
+const SHARED_READ_ATTEMPTS: usize = 4;
+
+/// Analyzer for audio arriving in realtime, meant to live in an `AudioWorkletProcessor`.
+#[wasm_bindgen]
+pub struct LiveAnalyzer {
+    sample_rate: u32,
+    bin_size: usize,
+    freq_boundaries: Vec<f32>,
+    // The last `FRAME_SIZE` samples, oldest first
+    history: Vec<f32>,
+    // Samples pushed since the last frame was analyzed
+    pending: usize,
+    bars: Vec<f32>,
+    shared: Option<SharedBars>,
+}
+
+#[wasm_bindgen]
+impl LiveAnalyzer {
+    /// An analyzer for mono audio at `sample_rate` Hz (the worklet's global `sampleRate`),
+    /// mapped to `bin_size` bars (at most 64) like a track.
+    #[wasm_bindgen(constructor)]
+    pub fn new(sample_rate: f32, bin_size: usize) -> Result<LiveAnalyzer, JsValue> {
+        if !(sample_rate >= 1.0 && sample_rate.is_finite()) {
+            return Err(JsValue::from_str(&format!("Invalid sample rate {}", sample_rate)));
+        }
+        if !(1..=MAX_SHARED_BARS).contains(&bin_size) {
+            return Err(JsValue::from_str(&format!("Bar count must be 1-{}, got {}", MAX_SHARED_BARS, bin_size)));
+        }
+        Ok(Self {
+            sample_rate: sample_rate.round() as u32,
+            bin_size,
+            freq_boundaries: {
+                let (min_freq, max_freq) = analysis::nyquist_clamped_range(DEFAULT_MIN_FREQ, DEFAULT_MAX_FREQ, sample_rate.round() as u32);
+                analysis::frequency_boundaries(min_freq, max_freq, bin_size)
+            },
+            history: vec![0.0; FRAME_SIZE],
+            pending: 0,
+            bars: vec![0.0; bin_size],
+            shared: None,
+        })
+    }
+
+    /// Append samples (-1.0..1.0), normally one render quantum of the first input channel; any
+    /// length works. A frame is analyzed every 256 samples over the last 1024, and its bars
+    /// published. Returns whether new bars were produced.
+    #[wasm_bindgen]
+    pub fn push(&mut self, samples: &[f32]) -> bool {
+        let mut analyzed = false;
+        let mut rest = samples;
+        while !rest.is_empty() {
+            // Up to the end of the current hop, so every hop ends on a frame
+            let (chunk, remaining) = rest.split_at((LIVE_HOP_SIZE - self.pending).min(rest.len()));
+            self.history.copy_within(chunk.len().., 0);
+            self.history[FRAME_SIZE - chunk.len()..].copy_from_slice(chunk);
+            self.pending += chunk.len();
+            rest = remaining;
+            if self.pending == LIVE_HOP_SIZE {
+                self.pending = 0;
+                self.analyze();
+                analyzed = true;
+            }
+        }
+        analyzed
+    }
+
+    /// Bars of the latest frame, zeros until one has been analyzed.
+    #[wasm_bindgen]
+    pub fn bars(&self) -> Vec<f32> {
+        self.bars.clone()
+    }
+
+    /// Bytes of the SharedArrayBuffer `attach_shared_buffer` needs.
+    #[wasm_bindgen]
+    pub fn shared_buffer_size() -> u32 {
+        SharedBars::BYTES
+    }
+
+    /// Publish every new set of bars to `buffer`, which is then handed to the main thread's
+    /// `App.set_live_bars` (e.g. through the worklet's `port`).
+    #[wasm_bindgen]
+    pub fn attach_shared_buffer(&mut self, buffer: &js_sys::SharedArrayBuffer) -> Result<(), JsValue> {
+        let shared = SharedBars::new(buffer).map_err(|e| JsValue::from_str(&e))?;
+        shared.write(&self.bars);
+        self.shared = Some(shared);
+        Ok(())
+    }
+
+    /// Stop publishing to the shared buffer.
+    #[wasm_bindgen]
+    pub fn detach_shared_buffer(&mut self) {
+        self.shared = None;
+    }
+}
+
+impl LiveAnalyzer {
+    fn analyze(&mut self) {
+        let spectrum = analysis::float_frame_spectrum(&self.history, FRAME_SIZE);
+        let raw = analysis::fft_bar_magnitudes(&spectrum, self.sample_rate, FRAME_SIZE, &self.freq_boundaries, self.bin_size);
+        self.bars = analysis::scale_bars(raw, 0.0);
+        if let Some(shared) = &self.shared {
+            shared.write(&self.bars);
+        }
+    }
+}
+
+/// Bars in a SharedArrayBuffer, written by one thread and read by another as a seqlock: a
+/// 32-bit sequence number (odd while a write is in progress), the bar count, then the bars as
+/// 32-bit floats. Readers retry when the sequence changed under them, so neither side waits.
+pub struct SharedBars {
+    header: js_sys::Int32Array,
+    levels: js_sys::Float32Array,
+}
+
+impl SharedBars {
+    // Sequence number and bar count, then the bars
+    const HEADER_LENGTH: u32 = 2;
+    pub const BYTES: u32 = (Self::HEADER_LENGTH + MAX_SHARED_BARS as u32) * 4;
+
+    pub fn new(buffer: &js_sys::SharedArrayBuffer) -> Result<Self, String> {
+        if buffer.byte_length() < Self::BYTES {
+            return Err(format!("Shared bar buffer needs {} bytes, got {}", Self::BYTES, buffer.byte_length()));
+        }
+        Ok(Self {
+            header: js_sys::Int32Array::new_with_byte_offset_and_length(buffer, 0, Self::HEADER_LENGTH),
+            levels: js_sys::Float32Array::new_with_byte_offset_and_length(buffer, Self::HEADER_LENGTH * 4, MAX_SHARED_BARS as u32),
+        })
+    }
+
+    pub fn write(&self, bars: &[f32]) {
+        let bars = &bars[..bars.len().min(MAX_SHARED_BARS)];
+        let _ = js_sys::Atomics::add(&self.header, 0, 1);
+        self.levels.subarray(0, bars.len() as u32).copy_from(bars);
+        let _ = js_sys::Atomics::store(&self.header, 1, bars.len() as i32);
+        let _ = js_sys::Atomics::add(&self.header, 0, 1);
+    }
+
+    /// The latest bars, or `None` if none have been written or a write kept getting in the way.
+    pub fn read(&self) -> Option<Vec<f32>> {
+        for _ in 0..SHARED_READ_ATTEMPTS {
+            let before = js_sys::Atomics::load(&self.header, 0).ok()?;
+            if before % 2 != 0 {
+                continue;
+            }
+            let count = (js_sys::Atomics::load(&self.header, 1).ok()?.max(0) as usize).min(MAX_SHARED_BARS);
+            let mut bars = vec![0.0; count];
+            self.levels.subarray(0, count as u32).copy_to(&mut bars);
+            if js_sys::Atomics::load(&self.header, 0).ok()? == before {
+                return (count > 0).then_some(bars);
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,141 @@
+use wasm_bindgen::prelude::*;
+
+// Languages writing 1.234,5
+const COMMA_DECIMAL_DOT_GROUP: &[&str] = &["da", "de", "el", "es", "hr", "id", "it", "nl", "pt", "ro", "sl", "sr", "tr", "vi"];
+// Languages writing 1 234,5
+const COMMA_DECIMAL_SPACE_GROUP: &[&str] = &["bg", "cs", "et", "fi", "fr", "hu", "lt", "lv", "nb", "nn", "no", "pl", "ru", "sk", "sv", "uk"];
+// Integers below this are written without group separators, so "1200 Hz" doesn't read as 1.2
+const GROUPING_THRESHOLD: f64 = 10_000.0;
+
+/// Number formatting conventions for text drawn on the canvas. Only separators the bitmap font
+/// can draw are used, so thin spaces are written as plain spaces.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Locale {
+    decimal_separator: char,
+    group_separator: char,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self {
+            decimal_separator: '.',
+            group_separator: ',',
+        }
+    }
+}
+
+impl Locale {
+    /// Conventions for a BCP 47 language tag such as "en-US", "de" or "pt-BR". Languages without
+    /// specific rules use the default English conventions.
+    pub fn parse(tag: &str) -> Result<Self, JsValue> {
+        let mut subtags = tag.split(['-', '_']);
+        let language = subtags.next().unwrap_or_default().to_ascii_lowercase();
+        let well_formed = (2..=3).contains(&language.len())
+            && language.chars().all(|c| c.is_ascii_alphabetic())
+            && subtags.clone().all(|subtag| !subtag.is_empty() && subtag.chars().all(|c| c.is_ascii_alphanumeric()));
+        if !well_formed {
+            return Err(JsValue::from_str(&format!("Invalid locale tag '{}', expected e.g. 'en-US' or 'de'", tag)));
+        }
+        let region = subtags.find(|subtag| subtag.len() == 2 || (subtag.len() == 3 && subtag.chars().all(|c| c.is_ascii_digit())));
+
+        let (decimal_separator, group_separator) = match (language.as_str(), region.map(|region| region.to_ascii_uppercase())) {
+            ("de" | "it", Some(region)) if region == "CH" => ('.', '\''),
+            ("es", Some(region)) if ["MX", "US", "419"].contains(&region.as_str()) => ('.', ','),
+            ("en", Some(region)) if region == "ZA" => (',', ' '),
+            (language, _) if COMMA_DECIMAL_DOT_GROUP.contains(&language) => (',', '.'),
+            (language, _) if COMMA_DECIMAL_SPACE_GROUP.contains(&language) => (',', ' '),
+            _ => ('.', ','),
+        };
+        Ok(Self {
+            decimal_separator,
+            group_separator,
+        })
+    }
+
+    /// Format `value` with `decimals` fraction digits, grouping the integer part of large values.
+    pub fn format_number(self, value: f64, decimals: usize) -> String {
+        let formatted = format!("{:.*}", decimals, value.abs());
+        let (integer, fraction) = formatted.split_once('.').unwrap_or((&formatted, ""));
+
+        let mut result = String::new();
+        if value < 0.0 && formatted.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            result.push('-');
+        }
+        if value.abs() >= GROUPING_THRESHOLD {
+            for (i, digit) in integer.chars().enumerate() {
+                if i > 0 && (integer.len() - i) % 3 == 0 {
+                    result.push(self.group_separator);
+                }
+                result.push(digit);
+            }
+        } else {
+            result.push_str(integer);
+        }
+        if !fraction.is_empty() {
+            result.push(self.decimal_separator);
+            result.push_str(fraction);
+        }
+        result
+    }
+
+    /// Format a frequency: whole hertz below 1 kHz, kilohertz with one decimal (dropped when
+    /// zero) above, e.g. "850 Hz", "1.5 kHz", "16 kHz".
+    pub fn format_frequency(self, hz: f32) -> String {
+        let hz = hz as f64;
+        if hz.abs() < 999.5 {
+            return format!("{} Hz", self.format_number(hz, 0));
+        }
+        let khz = (hz / 100.0).round() / 10.0;
+        let decimals = if khz.fract() == 0.0 { 0 } else { 1 };
+        format!("{} kHz", self.format_number(khz, decimals))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locale(tag: &str) -> Locale {
+        Locale::parse(tag).unwrap_or_else(|_| panic!("'{}' should parse", tag))
+    }
+
+    #[test]
+    fn groups_only_large_integers() {
+        let en = Locale::default();
+        assert_eq!(en.format_number(1200.0, 0), "1200");
+        assert_eq!(en.format_number(9999.0, 0), "9999");
+        assert_eq!(en.format_number(10_000.0, 0), "10,000");
+        assert_eq!(en.format_number(1_234_567.25, 2), "1,234,567.25");
+        assert_eq!(en.format_number(123_456.0, 0), "123,456");
+    }
+
+    #[test]
+    fn negative_numbers() {
+        let en = Locale::default();
+        assert_eq!(en.format_number(-3.5, 1), "-3.5");
+        assert_eq!(en.format_number(-12_345.6, 1), "-12,345.6");
+        // Rounding to zero drops the sign rather than printing "-0.0"
+        assert_eq!(en.format_number(-0.04, 1), "0.0");
+        assert_eq!(en.format_number(-0.0, 0), "0");
+    }
+
+    #[test]
+    fn separators_per_language_and_region() {
+        assert_eq!(locale("de-DE").format_number(-1_234_567.5, 1), "-1.234.567,5");
+        assert_eq!(locale("fr").format_number(12_345.5, 1), "12 345,5");
+        assert_eq!(locale("de-CH").format_number(12_345.5, 1), "12'345.5");
+        assert_eq!(locale("es-419").format_number(12_345.5, 1), "12,345.5");
+        assert_eq!(locale("es_ES").format_number(12_345.5, 1), "12.345,5");
+        assert_eq!(locale("en-ZA").format_number(12_345.5, 1), "12 345,5");
+        assert_eq!(locale("ja-JP"), Locale::default());
+    }
+
+    #[test]
+    fn frequencies() {
+        let de = locale("de");
+        assert_eq!(de.format_frequency(850.0), "850 Hz");
+        assert_eq!(de.format_frequency(999.6), "1 kHz");
+        assert_eq!(de.format_frequency(1500.0), "1,5 kHz");
+        assert_eq!(de.format_frequency(16_020.0), "16 kHz");
+    }
+}
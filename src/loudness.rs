@@ -0,0 +1,200 @@
+//! Loudness metering after ITU-R BS.1770 / EBU R128: K-weighted mean square over 400 ms windows,
+//! reported in LUFS. Like `analysis`, this has no browser dependencies.
+
+/// Length of the momentary loudness window.
+pub const MOMENTARY_WINDOW_SECONDS: f64 = 0.4;
+/// Loudness reported for silence, and the absolute gate of the integrated measurement.
+pub const LOUDNESS_FLOOR_LUFS: f32 = -70.0;
+// Gating blocks of the integrated measurement overlap by 75%
+const GATING_STEP_SECONDS: f64 = 0.1;
+// Blocks quieter than the ungated mean by more than this are left out of the integrated loudness
+const RELATIVE_GATE_LU: f64 = 10.0;
+
+// Direct form I biquad, coefficients normalized so a0 = 1
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.b[1] * self.x[0] + self.b[2] * self.x[1] - self.a[0] * self.y[0] - self.a[1] * self.y[1];
+        self.x = [input, self.x[0]];
+        self.y = [output, self.y[0]];
+        output
+    }
+}
+
+// The two K-weighting stages for a sample rate: a high shelf modelling the head, then a high pass
+// (the "RLB" curve). Coefficients follow libebur128, which derives the BS.1770 48 kHz filters
+// for any rate.
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let rate = sample_rate as f64;
+
+    let f0 = 1681.974450955533;
+    let gain_db = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (std::f64::consts::PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        x: [0.0; 2],
+        y: [0.0; 2],
+    };
+
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (std::f64::consts::PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        x: [0.0; 2],
+        y: [0.0; 2],
+    };
+
+    [shelf, high_pass]
+}
+
+fn mean_square_to_lufs(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.max(1e-20).log10()
+}
+
+/// Running sums of the K-weighted signal's energy, for the mean square over any range in
+/// constant time.
+pub struct KWeightedEnergy {
+    cumulative: Vec<f64>,
+    sample_rate: u32,
+}
+
+impl KWeightedEnergy {
+    /// Energy of one or more channels, summed sample by sample before gating: BS.1770 weights
+    /// left and right alike, so a stereo track reads 3 LU louder than either channel alone.
+    /// Channels longer than the shortest are cut to its length.
+    pub fn new(channels: &[&[i16]], sample_rate: u32) -> Self {
+        let length = channels.iter().map(|channel| channel.len()).min().unwrap_or(0);
+        let mut cumulative = vec![0.0; length + 1];
+        for channel in channels {
+            let [mut shelf, mut high_pass] = k_weighting(sample_rate);
+            for (energy, &sample) in cumulative[1..].iter_mut().zip(&channel[..length]) {
+                let weighted = high_pass.process(shelf.process(sample as f64 / i16::MAX as f64));
+                *energy += weighted * weighted;
+            }
+        }
+        for i in 1..cumulative.len() {
+            cumulative[i] += cumulative[i - 1];
+        }
+        Self { cumulative, sample_rate }
+    }
+
+    // Mean square of samples start..end, clamped to the signal
+    fn mean_square(&self, start: usize, end: usize) -> f64 {
+        let end = end.min(self.cumulative.len() - 1);
+        let start = start.min(end);
+        if end == start {
+            return 0.0;
+        }
+        (self.cumulative[end] - self.cumulative[start]) / (end - start) as f64
+    }
+
+    fn window_samples(&self, seconds: f64) -> usize {
+        (seconds * self.sample_rate as f64).round() as usize
+    }
+
+    /// Momentary loudness in LUFS over the 400 ms ending at sample `end`, floored at
+    /// `LOUDNESS_FLOOR_LUFS`. Near the start the window covers what has played so far.
+    pub fn momentary(&self, end: usize) -> f32 {
+        let window = self.window_samples(MOMENTARY_WINDOW_SECONDS);
+        let lufs = mean_square_to_lufs(self.mean_square(end.saturating_sub(window), end));
+        (lufs as f32).max(LOUDNESS_FLOOR_LUFS)
+    }
+
+    /// Gated integrated loudness of the whole signal in LUFS, or `None` if it is shorter than a
+    /// gating block or no block is louder than the absolute gate.
+    pub fn integrated(&self) -> Option<f32> {
+        let window = self.window_samples(MOMENTARY_WINDOW_SECONDS);
+        let step = self.window_samples(GATING_STEP_SECONDS).max(1);
+        let length = self.cumulative.len() - 1;
+        if length < window {
+            return None;
+        }
+
+        let blocks: Vec<f64> = (0..=(length - window) / step)
+            .map(|i| self.mean_square(i * step, i * step + window))
+            .filter(|&mean_square| mean_square_to_lufs(mean_square) > LOUDNESS_FLOOR_LUFS as f64)
+            .collect();
+        if blocks.is_empty() {
+            return None;
+        }
+
+        let relative_gate = mean_square_to_lufs(blocks.iter().sum::<f64>() / blocks.len() as f64) - RELATIVE_GATE_LU;
+        let gated: Vec<f64> = blocks.into_iter().filter(|&mean_square| mean_square_to_lufs(mean_square) > relative_gate).collect();
+        Some(mean_square_to_lufs(gated.iter().sum::<f64>() / gated.len().max(1) as f64) as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 48_000;
+
+    fn sine(frequency: f64, dbfs: f64, seconds: f64) -> Vec<i16> {
+        let amplitude = 10f64.powf(dbfs / 20.0) * i16::MAX as f64;
+        (0..(seconds * SAMPLE_RATE as f64) as usize)
+            .map(|i| (amplitude * (std::f64::consts::TAU * frequency * i as f64 / SAMPLE_RATE as f64).sin()).round() as i16)
+            .collect()
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 0.1, "expected {} LUFS, got {}", expected, actual);
+    }
+
+    // EBU Tech 3341: a 1 kHz sine at -23 dBFS in both channels reads -23 LUFS
+    #[test]
+    fn stereo_sine_at_reference_level() {
+        let tone = sine(1000.0, -23.0, 5.0);
+        let energy = KWeightedEnergy::new(&[&tone, &tone], SAMPLE_RATE);
+        assert_close(energy.integrated().unwrap(), -23.0);
+        assert_close(energy.momentary(tone.len()), -23.0);
+    }
+
+    #[test]
+    fn mono_reads_3_lu_below_stereo() {
+        let tone = sine(1000.0, -23.0, 5.0);
+        let mono = KWeightedEnergy::new(&[&tone], SAMPLE_RATE).integrated().unwrap();
+        let stereo = KWeightedEnergy::new(&[&tone, &tone], SAMPLE_RATE).integrated().unwrap();
+        assert_close(mono, -26.01);
+        assert_close(stereo - mono, 3.01);
+
+        // A silent right channel leaves the left channel's loudness
+        let silence = vec![0; tone.len()];
+        assert_close(KWeightedEnergy::new(&[&tone, &silence], SAMPLE_RATE).integrated().unwrap(), mono);
+    }
+
+    #[test]
+    fn relative_gate_ignores_quiet_passages() {
+        let mut track = sine(1000.0, -23.0, 4.0);
+        track.extend(sine(1000.0, -50.0, 4.0));
+        // Ungated, the quiet half would pull this down by 3 LU; only the few blocks straddling the
+        // change still count
+        let integrated = KWeightedEnergy::new(&[&track, &track], SAMPLE_RATE).integrated().unwrap();
+        assert!((integrated + 23.0).abs() < 0.25, "got {} LUFS", integrated);
+    }
+
+    #[test]
+    fn silence_and_short_signals_have_no_integrated_loudness() {
+        let silence = vec![0; SAMPLE_RATE as usize];
+        let energy = KWeightedEnergy::new(&[&silence], SAMPLE_RATE);
+        assert_eq!(energy.integrated(), None);
+        assert_eq!(energy.momentary(silence.len()), LOUDNESS_FLOOR_LUFS);
+
+        let short = sine(1000.0, -23.0, 0.3);
+        assert_eq!(KWeightedEnergy::new(&[&short], SAMPLE_RATE).integrated(), None);
+    }
+}
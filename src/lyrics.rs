@@ -0,0 +1,143 @@
+/// One timed line of an LRC file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LyricLine {
+    /// Time the line starts, in seconds from the start of the track
+    pub seconds: f64,
+    pub text: String,
+}
+
+/// Timed lyrics parsed from an LRC file, in time order.
+#[derive(Clone, Debug, Default)]
+pub struct Lyrics {
+    lines: Vec<LyricLine>,
+}
+
+impl Lyrics {
+    /// Parse LRC text: lines carrying one or more `[mm:ss.xx]` time tags before the lyric.
+    /// Untimed lines and ID tags such as `[ar:...]` are skipped, `[offset:+/-ms]` moves every
+    /// line earlier (positive) or later (negative), and enhanced LRC word times (`<mm:ss.xx>`)
+    /// are dropped from the text. Fails if there are no timed lines.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut offset_seconds = 0.0;
+        let mut lines = Vec::new();
+
+        for raw_line in text.lines() {
+            let mut rest = raw_line.trim();
+            let mut times = Vec::new();
+            while let Some((content, after)) = rest.strip_prefix('[').and_then(|tagged| tagged.split_once(']')) {
+                if let Some(seconds) = parse_timestamp(content) {
+                    times.push(seconds);
+                } else if let Some(offset) = content.strip_prefix("offset:") {
+                    let milliseconds: f64 = offset.trim().parse().map_err(|_| format!("Invalid LRC offset '{}'", offset.trim()))?;
+                    offset_seconds = milliseconds / 1000.0;
+                }
+                rest = after.trim_start();
+            }
+            let text = strip_word_times(rest).trim().to_string();
+            lines.extend(times.into_iter().map(|seconds| LyricLine { seconds, text: text.clone() }));
+        }
+
+        if lines.is_empty() {
+            return Err("No timed lines found in the lyrics".to_string());
+        }
+        for line in &mut lines {
+            line.seconds = (line.seconds - offset_seconds).max(0.0);
+        }
+        // Stable, so lines sharing a time keep their file order
+        lines.sort_by(|a, b| a.seconds.total_cmp(&b.seconds));
+        Ok(Self { lines })
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// The line being sung at `seconds`: the last one starting at or before it. `None` before the
+    /// first line and during instrumental breaks (empty lines).
+    pub fn current_line(&self, seconds: f64) -> Option<&str> {
+        let index = self.lines.partition_point(|line| line.seconds <= seconds).checked_sub(1)?;
+        Some(self.lines[index].text.as_str()).filter(|text| !text.is_empty())
+    }
+}
+
+// "mm:ss", "mm:ss.xx" or "mm:ss:xx" (hundredths) to seconds
+fn parse_timestamp(tag: &str) -> Option<f64> {
+    let (minutes, seconds) = tag.split_once(':')?;
+    let minutes: u32 = minutes.trim().parse().ok()?;
+    // Some files separate the hundredths with a colon
+    let seconds: f64 = match seconds.split_once(':') {
+        Some((whole, fraction)) => format!("{}.{}", whole, fraction).parse().ok()?,
+        None => seconds.trim().parse().ok()?,
+    };
+    (0.0..60.0).contains(&seconds).then_some(minutes as f64 * 60.0 + seconds)
+}
+
+// Drop enhanced LRC `<mm:ss.xx>` word times from a lyric
+fn strip_word_times(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        match rest[start..].find('>') {
+            Some(end) if parse_timestamp(&rest[start + 1..start + end]).is_some() => {
+                result.push_str(&rest[..start]);
+                rest = &rest[start + end + 1..];
+            }
+            _ => {
+                result.push_str(&rest[..=start]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn times(lyrics: &Lyrics) -> Vec<(f64, &str)> {
+        lyrics.lines.iter().map(|line| (line.seconds, line.text.as_str())).collect()
+    }
+
+    #[test]
+    fn repeated_lines_share_one_text_line() {
+        let lyrics = Lyrics::parse("[00:30.00][00:10.00] Chorus\n[00:20.00]Verse").unwrap();
+        assert_eq!(times(&lyrics), [(10.0, "Chorus"), (20.0, "Verse"), (30.0, "Chorus")]);
+    }
+
+    #[test]
+    fn dot_and_colon_hundredths() {
+        let lyrics = Lyrics::parse("[01:02.50]dot\n[01:03:25]colon\n[01:04]whole").unwrap();
+        assert_eq!(times(&lyrics), [(62.5, "dot"), (63.25, "colon"), (64.0, "whole")]);
+    }
+
+    #[test]
+    fn offset_moves_lines_and_clamps_at_zero() {
+        let earlier = Lyrics::parse("[offset:+500]\n[00:00.20]first\n[00:02.00]second").unwrap();
+        assert_eq!(times(&earlier), [(0.0, "first"), (1.5, "second")]);
+
+        // Applies to every line, including those before the tag
+        let later = Lyrics::parse("[00:02.00]second\n[offset: -1000]").unwrap();
+        assert_eq!(times(&later), [(3.0, "second")]);
+
+        assert!(Lyrics::parse("[offset:soon]\n[00:01.00]line").is_err());
+    }
+
+    #[test]
+    fn skips_tags_and_word_times() {
+        let lyrics = Lyrics::parse("[ar:Artist]\n[ti:Title]\nuntimed\n[00:05.00]<00:05.00>Hello <00:05.50>world <3").unwrap();
+        assert_eq!(times(&lyrics), [(5.0, "Hello world <3")]);
+        assert!(Lyrics::parse("[ar:Artist]\nno times here").is_err());
+        assert!(Lyrics::parse("[00:75.00]bad seconds").is_err());
+    }
+
+    #[test]
+    fn current_line_follows_the_playhead() {
+        let lyrics = Lyrics::parse("[00:01.00]one\n[00:02.00]\n[00:03.00]three").unwrap();
+        assert_eq!(lyrics.current_line(0.5), None);
+        assert_eq!(lyrics.current_line(1.0), Some("one"));
+        assert_eq!(lyrics.current_line(2.5), None);
+        assert_eq!(lyrics.current_line(10.0), Some("three"));
+    }
+}
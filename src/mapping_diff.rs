@@ -0,0 +1,73 @@
+use wasm_bindgen::prelude::*;
+
+use crate::analysis::{BarMapping, FrameDivergence};
+
+/// A bar mapping to compare with `compare_mappings`: bar count and displayed frequency range.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct MappingConfig {
+    pub bin_size: usize,
+    pub min_freq: f32,
+    pub max_freq: f32,
+}
+
+#[wasm_bindgen]
+impl MappingConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new(bin_size: usize, min_freq: f32, max_freq: f32) -> Self {
+        Self {
+            bin_size,
+            min_freq,
+            max_freq,
+        }
+    }
+}
+
+impl MappingConfig {
+    pub fn to_bar_mapping(self) -> Result<BarMapping, String> {
+        let mapping = BarMapping {
+            num_bars: self.bin_size,
+            min_freq: self.min_freq,
+            max_freq: self.max_freq,
+        };
+        mapping.validate()?;
+        Ok(mapping)
+    }
+}
+
+/// Divergence between two bar mappings of the same analysis, in bar heights (0.0-1.0). Bar sets
+/// of different sizes are compared by position across the display.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct MappingDiff {
+    /// Mean absolute bar difference per frame
+    pub mean_abs: Vec<f32>,
+    /// Largest bar difference per frame
+    pub max_abs: Vec<f32>,
+    /// Root-mean-square bar difference per frame
+    pub rms: Vec<f32>,
+    /// Mean absolute difference over all frames
+    pub overall_mean_abs: f32,
+    /// Largest bar difference in any frame
+    pub overall_max_abs: f32,
+    /// Frame with the largest RMS difference, 0 if there are no frames
+    pub worst_frame: usize,
+}
+
+impl MappingDiff {
+    pub fn from_frames(frames: &[FrameDivergence]) -> Self {
+        let worst_frame = frames
+            .iter()
+            .enumerate()
+            .fold((0, 0.0f32), |worst, (i, frame)| if frame.rms > worst.1 { (i, frame.rms) } else { worst })
+            .0;
+        Self {
+            mean_abs: frames.iter().map(|frame| frame.mean_abs).collect(),
+            max_abs: frames.iter().map(|frame| frame.max_abs).collect(),
+            rms: frames.iter().map(|frame| frame.rms).collect(),
+            overall_mean_abs: frames.iter().map(|frame| frame.mean_abs).sum::<f32>() / frames.len().max(1) as f32,
+            overall_max_abs: frames.iter().fold(0.0, |max, frame| max.max(frame.max_abs)),
+            worst_frame,
+        }
+    }
+}
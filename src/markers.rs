@@ -0,0 +1,62 @@
+use wasm_bindgen::prelude::*;
+
+/// A named position in the track, such as a chorus or a cue for a video edit.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Marker {
+    /// Position in seconds from the start of the track
+    pub seconds: f64,
+    pub label: String,
+}
+
+/// The markers of a track, kept in time order.
+#[derive(Clone, Debug, Default)]
+pub struct Markers {
+    markers: Vec<Marker>,
+}
+
+impl Markers {
+    /// Add a marker after any others at the same time, returning its index.
+    pub fn add(&mut self, seconds: f64, label: String) -> Result<usize, String> {
+        if !seconds.is_finite() || seconds < 0.0 {
+            return Err(format!("Invalid marker position {} seconds", seconds));
+        }
+        let index = self.markers.partition_point(|marker| marker.seconds <= seconds);
+        self.markers.insert(index, Marker { seconds, label });
+        Ok(index)
+    }
+
+    pub fn remove(&mut self, index: usize) -> Result<Marker, String> {
+        if index >= self.markers.len() {
+            return Err(format!("No marker {}, there are {}", index, self.markers.len()));
+        }
+        Ok(self.markers.remove(index))
+    }
+
+    pub fn clear(&mut self) {
+        self.markers.clear();
+    }
+
+    /// Replace all markers, e.g. with ones read back from an analysis blob.
+    pub fn replace(&mut self, mut markers: Vec<Marker>) {
+        markers.sort_by(|a, b| a.seconds.total_cmp(&b.seconds));
+        self.markers = markers;
+    }
+
+    pub fn all(&self) -> &[Marker] {
+        &self.markers
+    }
+
+    /// Marker positions as fractions (0.0-1.0) of a track `duration_seconds` long, for drawing
+    /// ticks; markers past the end are left out.
+    pub fn positions(&self, duration_seconds: f64) -> Vec<f32> {
+        if duration_seconds <= 0.0 {
+            return Vec::new();
+        }
+        self.markers
+            .iter()
+            .filter(|marker| marker.seconds <= duration_seconds)
+            .map(|marker| (marker.seconds / duration_seconds) as f32)
+            .collect()
+    }
+}
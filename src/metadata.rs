@@ -0,0 +1,612 @@
+use wasm_bindgen::prelude::*;
+
+/// Track tags and embedded cover art extracted from the loaded file.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, Default)]
+pub struct TrackMetadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    /// Raw bytes of the embedded cover image (JPEG/PNG as stored in the file)
+    pub cover_art: Option<Vec<u8>>,
+    /// MIME type of the cover image, e.g. "image/jpeg"
+    pub cover_art_mime: Option<String>,
+}
+
+impl TrackMetadata {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.artist.is_none() && self.album.is_none() && self.cover_art.is_none()
+    }
+
+    // Fill in any fields that are still missing from another source
+    fn merge(&mut self, other: TrackMetadata) {
+        self.title = self.title.take().or(other.title);
+        self.artist = self.artist.take().or(other.artist);
+        self.album = self.album.take().or(other.album);
+        if self.cover_art.is_none() {
+            self.cover_art = other.cover_art;
+            self.cover_art_mime = other.cover_art_mime;
+        }
+    }
+}
+
+/// Extract tags from a file's raw bytes. Supports ID3v2.2-2.4 tags (MP3 files or `id3 ` chunks
+/// inside WAV files), RIFF `LIST`/`INFO` chunks, FLAC Vorbis comments and `PICTURE` blocks, and
+/// Vorbis comments in Ogg Vorbis and Opus files. Unknown formats yield empty metadata.
+pub fn extract(data: &[u8]) -> TrackMetadata {
+    if data.starts_with(b"ID3") {
+        return parse_id3v2(data);
+    }
+    if let Some(blocks) = data.strip_prefix(b"fLaC") {
+        return parse_flac(blocks);
+    }
+    if data.starts_with(b"OggS") {
+        return parse_ogg(data);
+    }
+
+    let mut metadata = TrackMetadata::default();
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return metadata;
+    }
+
+    // Walk the RIFF chunks: 4-byte id, little-endian u32 size, payload padded to even length
+    let mut offset = 12;
+    while offset + 8 <= data.len() {
+        let id = &data[offset..offset + 4];
+        let size = u32::from_le_bytes([data[offset + 4], data[offset + 5], data[offset + 6], data[offset + 7]]) as usize;
+        let start = offset + 8;
+        let end = start.saturating_add(size).min(data.len());
+        let payload = &data[start..end];
+
+        match id {
+            b"LIST" if payload.starts_with(b"INFO") => metadata.merge(parse_riff_info(&payload[4..])),
+            b"id3 " | b"ID3 " => metadata.merge(parse_id3v2(payload)),
+            _ => {}
+        }
+
+        let Some(next) = size.checked_add(size & 1).and_then(|padded| advance(start, padded, data.len())) else {
+            break;
+        };
+        offset = next;
+    }
+
+    metadata
+}
+
+fn parse_riff_info(data: &[u8]) -> TrackMetadata {
+    let mut metadata = TrackMetadata::default();
+    let mut offset = 0;
+    while offset + 8 <= data.len() {
+        let id = &data[offset..offset + 4];
+        let size = u32::from_le_bytes([data[offset + 4], data[offset + 5], data[offset + 6], data[offset + 7]]) as usize;
+        let start = offset + 8;
+        let end = start.saturating_add(size).min(data.len());
+        let value = latin1_or_utf8(&data[start..end]);
+
+        match id {
+            b"INAM" => metadata.title = value,
+            b"IART" => metadata.artist = value,
+            b"IPRD" => metadata.album = value,
+            _ => {}
+        }
+
+        let Some(next) = size.checked_add(size & 1).and_then(|padded| advance(start, padded, data.len())) else {
+            break;
+        };
+        offset = next;
+    }
+    metadata
+}
+
+fn parse_id3v2(data: &[u8]) -> TrackMetadata {
+    let mut metadata = TrackMetadata::default();
+    if data.len() < 10 || &data[0..3] != b"ID3" {
+        return metadata;
+    }
+
+    // v2.2 frames have 3-character ids and 24-bit sizes, v2.3 and v2.4 4-character ids
+    let version = data[3];
+    if !(2..=4).contains(&version) {
+        return metadata;
+    }
+    // The v2.2 flag in place of the extended header one marks a compression scheme that was never defined
+    if version == 2 && data[5] & 0x40 != 0 {
+        return metadata;
+    }
+    let (id_len, header_len) = if version == 2 { (3, 6) } else { (4, 10) };
+    let tag_size = syncsafe(&data[6..10]);
+    let tag_end = (10 + tag_size).min(data.len());
+
+    let mut offset = 10;
+    // Skip the extended header if present
+    if version != 2 && data[5] & 0x40 != 0 && offset + 4 <= tag_end {
+        let ext_size = if version == 4 {
+            syncsafe(&data[offset..offset + 4])
+        } else {
+            (u32::from_be_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]) as usize).saturating_add(4)
+        };
+        let Some(next) = advance(offset, ext_size, tag_end) else {
+            return metadata;
+        };
+        offset = next;
+    }
+
+    while offset + header_len <= tag_end {
+        let id = &data[offset..offset + id_len];
+        if id[0] == 0 {
+            break; // Padding
+        }
+        let size_bytes = &data[offset + id_len..offset + id_len + 4];
+        let size = match version {
+            2 => u32::from_be_bytes([0, size_bytes[0], size_bytes[1], size_bytes[2]]) as usize,
+            3 => u32::from_be_bytes([size_bytes[0], size_bytes[1], size_bytes[2], size_bytes[3]]) as usize,
+            _ => syncsafe(size_bytes),
+        };
+        let start = offset + header_len;
+        let end = start.saturating_add(size).min(tag_end);
+        let frame = &data[start..end];
+
+        match id {
+            b"TIT2" | b"TT2" => metadata.title = decode_text_frame(frame),
+            b"TPE1" | b"TP1" => metadata.artist = decode_text_frame(frame),
+            b"TALB" | b"TAL" => metadata.album = decode_text_frame(frame),
+            b"APIC" | b"PIC" if metadata.cover_art.is_none() => {
+                if let Some((mime, image)) = decode_picture_frame(frame, version == 2) {
+                    metadata.cover_art_mime = Some(mime);
+                    metadata.cover_art = Some(image);
+                }
+            }
+            _ => {}
+        }
+
+        let Some(next) = advance(start, size, tag_end) else {
+            break;
+        };
+        offset = next;
+    }
+
+    metadata
+}
+
+// `start` moved `size` bytes on, or `None` when that passes `end`. Sizes read from the file can
+// be close to `u32::MAX`, which overflows `usize` on wasm32 if added unchecked; keeping offsets
+// within `end` also keeps the `offset + header` bounds checks of the walks from overflowing.
+fn advance(start: usize, size: usize, end: usize) -> Option<usize> {
+    start.checked_add(size).filter(|&next| next <= end)
+}
+
+fn syncsafe(bytes: &[u8]) -> usize {
+    bytes.iter().take(4).fold(0usize, |acc, &b| (acc << 7) | (b & 0x7f) as usize)
+}
+
+fn decode_text_frame(frame: &[u8]) -> Option<String> {
+    let (&encoding, text) = frame.split_first()?;
+    decode_text(encoding, text).and_then(|s| non_empty(s.split('\0').next().unwrap_or("")))
+}
+
+// APIC layout: encoding, MIME (latin-1, NUL-terminated), picture type, description (NUL-terminated), image data.
+// The v2.2 PIC frame has a 3-character image format ("JPG", "PNG") in place of the MIME type.
+fn decode_picture_frame(frame: &[u8], bare_format: bool) -> Option<(String, Vec<u8>)> {
+    let (&encoding, rest) = frame.split_first()?;
+    let (mime, rest) = if bare_format {
+        (rest.get(..3)?, &rest[3..])
+    } else {
+        let mime_end = rest.iter().position(|&b| b == 0)?;
+        (&rest[..mime_end], &rest[mime_end + 1..])
+    };
+    let mime = String::from_utf8_lossy(mime).into_owned();
+    let rest = rest.get(1..)?; // Picture type
+
+    let description_end = if encoding == 1 || encoding == 2 {
+        // UTF-16 descriptions end with a double NUL on an even boundary
+        (0..rest.len().saturating_sub(1)).step_by(2).find(|&i| rest[i] == 0 && rest[i + 1] == 0)? + 2
+    } else {
+        rest.iter().position(|&b| b == 0)? + 1
+    };
+    let image = rest.get(description_end..)?;
+    if image.is_empty() {
+        return None;
+    }
+
+    Some((picture_mime(&mime), image.to_vec()))
+}
+
+// Full MIME type for a picture stored with either one or a bare image format
+fn picture_mime(mime: &str) -> String {
+    if mime.contains('/') {
+        return mime.to_string();
+    }
+    match mime.to_lowercase().as_str() {
+        "jpg" => "image/jpeg".to_string(),
+        format => format!("image/{}", format),
+    }
+}
+
+// Walk the FLAC metadata blocks: 1-byte header (last-block flag, block type), 24-bit big-endian size, payload
+fn parse_flac(data: &[u8]) -> TrackMetadata {
+    let mut metadata = TrackMetadata::default();
+    let mut offset = 0;
+    while offset + 4 <= data.len() {
+        let header = data[offset];
+        let size = u32::from_be_bytes([0, data[offset + 1], data[offset + 2], data[offset + 3]]) as usize;
+        let start = offset + 4;
+        let end = (start + size).min(data.len());
+        let block = &data[start..end];
+
+        match header & 0x7f {
+            4 => metadata.merge(parse_vorbis_comment(block)),
+            6 if metadata.cover_art.is_none() => {
+                if let Some((mime, image)) = decode_flac_picture(block) {
+                    metadata.cover_art_mime = Some(mime);
+                    metadata.cover_art = Some(image);
+                }
+            }
+            _ => {}
+        }
+
+        if header & 0x80 != 0 {
+            break; // Last metadata block, audio frames follow
+        }
+        offset = end;
+    }
+    metadata
+}
+
+// PICTURE layout, all big-endian u32s: picture type, MIME length and MIME, description length and
+// description, width, height, colour depth, palette size, image length and image data
+fn decode_flac_picture(block: &[u8]) -> Option<(String, Vec<u8>)> {
+    let mime_len = be_u32(block, 4)? as usize;
+    let mime = block.get(8..8usize.checked_add(mime_len)?)?;
+    let description_len = be_u32(block, 8 + mime_len)? as usize;
+    let image_len_offset = (12 + mime_len).checked_add(description_len)?.checked_add(16)?;
+    let image_len = be_u32(block, image_len_offset)? as usize;
+    let start = image_len_offset + 4;
+    let image = block.get(start..start.checked_add(image_len)?)?;
+
+    // A "-->" MIME type means the data is a URL to the picture, not the picture
+    let mime = String::from_utf8_lossy(mime).into_owned();
+    if image.is_empty() || mime == "-->" {
+        return None;
+    }
+    Some((picture_mime(&mime), image.to_vec()))
+}
+
+// The comment header is the second packet of both Vorbis and Opus streams
+fn parse_ogg(data: &[u8]) -> TrackMetadata {
+    let packets = ogg_packets(data, 2);
+    match packets.get(1) {
+        Some(packet) if packet.starts_with(b"\x03vorbis") => parse_vorbis_comment(&packet[7..]),
+        Some(packet) if packet.starts_with(b"OpusTags") => parse_vorbis_comment(&packet[8..]),
+        _ => TrackMetadata::default(),
+    }
+}
+
+// Reassemble up to `count` packets from the start of the first logical stream in an Ogg file.
+// A page is "OggS", version, flags, granule position, serial number, sequence number, CRC and a
+// segment table; segments of 255 bytes continue the packet into the next segment or page.
+fn ogg_packets(data: &[u8], count: usize) -> Vec<Vec<u8>> {
+    let mut packets = Vec::new();
+    let mut packet = Vec::new();
+    let mut serial = None;
+    let mut offset = 0;
+    while packets.len() < count && data[offset..].starts_with(b"OggS") {
+        let Some(header) = data.get(offset..offset + 27) else {
+            break;
+        };
+        let segments = header[26] as usize;
+        let Some(table) = data.get(offset + 27..offset + 27 + segments) else {
+            break;
+        };
+        let page_serial = &header[14..18];
+        let in_stream = *serial.get_or_insert(page_serial) == page_serial;
+
+        let mut body = offset + 27 + segments;
+        for &len in table {
+            let Some(segment) = data.get(body..body + len as usize) else {
+                return packets;
+            };
+            body += len as usize;
+            if !in_stream || packets.len() == count {
+                continue;
+            }
+            packet.extend_from_slice(segment);
+            if len < 255 {
+                packets.push(std::mem::take(&mut packet));
+            }
+        }
+        offset = body;
+    }
+    packets
+}
+
+// Vorbis comment layout, all little-endian u32s: vendor length and vendor string, comment count,
+// then each comment's length and "NAME=value" in UTF-8 with case-insensitive names
+fn parse_vorbis_comment(data: &[u8]) -> TrackMetadata {
+    let mut metadata = TrackMetadata::default();
+    let Some(vendor_len) = le_u32(data, 0) else {
+        return metadata;
+    };
+    let mut offset = 4usize.saturating_add(vendor_len as usize);
+    let Some(count) = le_u32(data, offset) else {
+        return metadata;
+    };
+    offset += 4;
+
+    for _ in 0..count {
+        let Some(len) = le_u32(data, offset) else {
+            break;
+        };
+        let start = offset + 4;
+        let Some(comment) = data.get(start..start.saturating_add(len as usize)) else {
+            break;
+        };
+        offset = start + comment.len();
+
+        let Some(split) = comment.iter().position(|&b| b == b'=') else {
+            continue;
+        };
+        let (name, value) = (comment[..split].to_ascii_uppercase(), &comment[split + 1..]);
+        let text = || non_empty(&String::from_utf8_lossy(value));
+        match name.as_slice() {
+            b"TITLE" if metadata.title.is_none() => metadata.title = text(),
+            b"ARTIST" if metadata.artist.is_none() => metadata.artist = text(),
+            b"ALBUM" if metadata.album.is_none() => metadata.album = text(),
+            // A base64-encoded FLAC PICTURE block, the Ogg way to embed cover art
+            b"METADATA_BLOCK_PICTURE" if metadata.cover_art.is_none() => {
+                if let Some((mime, image)) = decode_base64(value).and_then(|block| decode_flac_picture(&block)) {
+                    metadata.cover_art_mime = Some(mime);
+                    metadata.cover_art = Some(image);
+                }
+            }
+            _ => {}
+        }
+    }
+    metadata
+}
+
+fn decode_base64(text: &[u8]) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+    let (mut bits, mut bit_count) = (0u32, 0);
+    for &c in text {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' | b'\r' | b'\n' => continue,
+            _ => return None,
+        };
+        bits = (bits << 6 | value as u32) & 0xffff;
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            bytes.push((bits >> bit_count) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+fn be_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset.checked_add(4)?)?.try_into().ok()?))
+}
+
+fn le_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset.checked_add(4)?)?.try_into().ok()?))
+}
+
+fn decode_text(encoding: u8, text: &[u8]) -> Option<String> {
+    match encoding {
+        0 => Some(text.iter().map(|&b| b as char).collect()),
+        1 | 2 => {
+            let (big_endian, body) = match text {
+                [0xfe, 0xff, body @ ..] => (true, body),
+                [0xff, 0xfe, body @ ..] => (false, body),
+                _ => (encoding == 2, text),
+            };
+            let units: Vec<u16> = body
+                .chunks_exact(2)
+                .map(|pair| if big_endian { u16::from_be_bytes([pair[0], pair[1]]) } else { u16::from_le_bytes([pair[0], pair[1]]) })
+                .collect();
+            Some(String::from_utf16_lossy(&units))
+        }
+        3 => Some(String::from_utf8_lossy(text).into_owned()),
+        _ => None,
+    }
+}
+
+fn latin1_or_utf8(bytes: &[u8]) -> Option<String> {
+    let bytes = bytes.split(|&b| b == 0).next().unwrap_or(&[]);
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|&b| b as char).collect(),
+    };
+    non_empty(&text)
+}
+
+fn non_empty(text: &str) -> Option<String> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vorbis_comment(comments: &[&[u8]]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&6u32.to_le_bytes());
+        data.extend_from_slice(b"vendor");
+        data.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+        for comment in comments {
+            data.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            data.extend_from_slice(comment);
+        }
+        data
+    }
+
+    fn flac_picture(mime: &str, image: &[u8]) -> Vec<u8> {
+        let mut block = Vec::new();
+        block.extend_from_slice(&3u32.to_be_bytes()); // Front cover
+        block.extend_from_slice(&(mime.len() as u32).to_be_bytes());
+        block.extend_from_slice(mime.as_bytes());
+        block.extend_from_slice(&5u32.to_be_bytes());
+        block.extend_from_slice(b"cover");
+        block.extend_from_slice(&[0; 16]);
+        block.extend_from_slice(&(image.len() as u32).to_be_bytes());
+        block.extend_from_slice(image);
+        block
+    }
+
+    fn flac_block(block_type: u8, last: bool, payload: &[u8]) -> Vec<u8> {
+        let size = (payload.len() as u32).to_be_bytes();
+        let mut block = vec![block_type | if last { 0x80 } else { 0 }, size[1], size[2], size[3]];
+        block.extend_from_slice(payload);
+        block
+    }
+
+    fn ogg_page(serial: u32, packets: &[&[u8]]) -> Vec<u8> {
+        let mut table = Vec::new();
+        for packet in packets {
+            table.extend(std::iter::repeat_n(255u8, packet.len() / 255));
+            table.push((packet.len() % 255) as u8);
+        }
+        let mut page = b"OggS\0\0".to_vec();
+        page.extend_from_slice(&[0; 8]);
+        page.extend_from_slice(&serial.to_le_bytes());
+        page.extend_from_slice(&[0; 8]);
+        page.push(table.len() as u8);
+        page.extend_from_slice(&table);
+        for packet in packets {
+            page.extend_from_slice(packet);
+        }
+        page
+    }
+
+    fn base64(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut text = String::new();
+        for chunk in bytes.chunks(3) {
+            let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    text.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+                } else {
+                    text.push('=');
+                }
+            }
+        }
+        text
+    }
+
+    #[test]
+    fn flac_vorbis_comment_and_picture() {
+        let mut data = b"fLaC".to_vec();
+        data.extend(flac_block(0, false, &[0; 34])); // STREAMINFO
+        data.extend(flac_block(4, false, &vorbis_comment(&[b"title=Song", b"ARTIST=Band", b"Album=Record"])));
+        data.extend(flac_block(6, true, &flac_picture("image/png", b"png bytes")));
+        data.extend_from_slice(&[0xff, 0xf8]); // Audio frames
+
+        let metadata = extract(&data);
+        assert_eq!(metadata.title.as_deref(), Some("Song"));
+        assert_eq!(metadata.artist.as_deref(), Some("Band"));
+        assert_eq!(metadata.album.as_deref(), Some("Record"));
+        assert_eq!(metadata.cover_art_mime.as_deref(), Some("image/png"));
+        assert_eq!(metadata.cover_art.as_deref(), Some(&b"png bytes"[..]));
+    }
+
+    #[test]
+    fn ogg_vorbis_comment_spanning_pages() {
+        let mut comment = b"\x03vorbis".to_vec();
+        comment.extend(vorbis_comment(&[b"TITLE=Long", &[b'X'; 600]]));
+        let (first, rest) = comment.split_at(510);
+
+        let mut data = ogg_page(7, &[b"\x01vorbis identification"]);
+        // A page of another stream between the two halves of the comment packet
+        data.extend(ogg_page(9, &[b"\x03vorbis other stream"]));
+        let mut page = ogg_page(7, &[first]);
+        page.truncate(page.len() - first.len() - 1); // Drop the terminating segment, continuing the packet
+        page[26] -= 1;
+        page.extend_from_slice(first);
+        data.extend(page);
+        data.extend(ogg_page(7, &[rest]));
+
+        assert_eq!(extract(&data).title.as_deref(), Some("Long"));
+    }
+
+    #[test]
+    fn opus_tags_with_picture() {
+        let picture = format!("METADATA_BLOCK_PICTURE={}", base64(&flac_picture("image/jpeg", b"jpeg bytes")));
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend(vorbis_comment(&[b"ARTIST=Singer", picture.as_bytes()]));
+        let data = ogg_page(1, &[b"OpusHead", &tags]);
+
+        let metadata = extract(&data);
+        assert_eq!(metadata.artist.as_deref(), Some("Singer"));
+        assert_eq!(metadata.cover_art_mime.as_deref(), Some("image/jpeg"));
+        assert_eq!(metadata.cover_art.as_deref(), Some(&b"jpeg bytes"[..]));
+    }
+
+    #[test]
+    fn id3v22_frames() {
+        let mut frames = Vec::new();
+        for (id, payload) in [(&b"TT2"[..], &b"\0Title"[..]), (b"TAL", b"\x03Album"), (b"PIC", b"\0PNG\x03desc\0image")] {
+            let size = (payload.len() as u32).to_be_bytes();
+            frames.extend_from_slice(id);
+            frames.extend_from_slice(&size[1..]);
+            frames.extend_from_slice(payload);
+        }
+        let mut data = b"ID3\x02\0\0\0\0\0".to_vec();
+        data.push(frames.len() as u8);
+        data.extend(frames);
+
+        let metadata = extract(&data);
+        assert_eq!(metadata.title.as_deref(), Some("Title"));
+        assert_eq!(metadata.album.as_deref(), Some("Album"));
+        assert_eq!(metadata.cover_art_mime.as_deref(), Some("image/png"));
+        assert_eq!(metadata.cover_art.as_deref(), Some(&b"image"[..]));
+    }
+
+    fn riff(chunks: &[(&[u8; 4], u32, &[u8])]) -> Vec<u8> {
+        let mut data = b"RIFF\0\0\0\0WAVE".to_vec();
+        for (id, size, payload) in chunks {
+            data.extend_from_slice(*id);
+            data.extend_from_slice(&size.to_le_bytes());
+            data.extend_from_slice(payload);
+        }
+        data
+    }
+
+    #[test]
+    fn riff_chunks_with_hostile_sizes() {
+        for size in [u32::MAX, u32::MAX - 1, u32::MAX - 12, 1 << 31] {
+            assert!(extract(&riff(&[(b"junk", size, &[0; 16])])).is_empty(), "chunk size {}", size);
+
+            let mut info = b"INFOINAM".to_vec();
+            info.extend_from_slice(&size.to_le_bytes());
+            info.extend_from_slice(b"Title\0");
+            assert_eq!(extract(&riff(&[(b"LIST", info.len() as u32, &info)])).title.as_deref(), Some("Title"), "INFO size {}", size);
+        }
+    }
+
+    #[test]
+    fn id3_frames_with_hostile_sizes() {
+        for size in [u32::MAX, u32::MAX - 9, 1 << 31] {
+            // v2.3 frame and extended header sizes are plain big-endian u32s
+            let mut tag = b"ID3\x03\0\0\0\0\x01\0TIT2".to_vec();
+            tag.extend_from_slice(&size.to_be_bytes());
+            tag.extend_from_slice(b"\0\0\0Title");
+            tag.resize(138, 0);
+            assert_eq!(extract(&tag).title.as_deref(), Some("Title"), "frame size {}", size);
+
+            let mut extended = b"ID3\x03\0\x40\0\0\x01\0".to_vec();
+            extended.extend_from_slice(&size.to_be_bytes());
+            extended.resize(138, 0);
+            assert!(extract(&extended).is_empty(), "extended header size {}", size);
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! Mel-frequency cepstral coefficients: the FFT power spectrum summed through a bank of
+//! triangular mel-spaced filters, log-compressed and decorrelated with a DCT. Like `analysis`,
+//! this has no browser dependencies.
+
+use crate::analysis::{FRAME_SIZE, FULL_SCALE_MAGNITUDE};
+
+/// Triangular filters in the mel filterbank, spanning 0 Hz to Nyquist.
+pub const MEL_BANDS: usize = 40;
+/// Coefficients returned per frame, the 0th (overall log energy) included.
+pub const MFCC_COEFFICIENTS: usize = 13;
+// Floor on filter energies before taking the log, about -100 dB below a full-scale sine
+const MIN_MEL_ENERGY: f32 = 1e-10;
+
+/// MFCCs of an `fft_size`-point FFT magnitude frame (as produced by `analysis::compute_fft`).
+/// Magnitudes are taken relative to full scale and filter energies scaled back to unpadded
+/// levels, so the coefficients don't depend on the frame length or zero padding.
+pub fn compute_mfcc(fft_frame: &[f32], sample_rate: u32, fft_size: usize) -> Vec<f32> {
+    let bins = &fft_frame[..(fft_size / 2 + 1).min(fft_frame.len())];
+    let bin_scale = FRAME_SIZE as f32 / fft_size as f32;
+    let log_energies: Vec<f32> = mel_filterbank(sample_rate, fft_size)
+        .iter()
+        .map(|filter| {
+            let energy: f32 = filter.iter().map(|&(bin, weight)| weight * (bins.get(bin).copied().unwrap_or(0.0) / FULL_SCALE_MAGNITUDE).powi(2)).sum();
+            (energy * bin_scale).max(MIN_MEL_ENERGY).ln()
+        })
+        .collect();
+
+    // Orthonormal DCT-II
+    (0..MFCC_COEFFICIENTS)
+        .map(|k| {
+            let scale = if k == 0 { (1.0 / MEL_BANDS as f32).sqrt() } else { (2.0 / MEL_BANDS as f32).sqrt() };
+            let sum: f32 = log_energies
+                .iter()
+                .enumerate()
+                .map(|(n, energy)| energy * (std::f32::consts::PI * k as f32 * (n as f32 + 0.5) / MEL_BANDS as f32).cos())
+                .sum();
+            scale * sum
+        })
+        .collect()
+}
+
+// Each filter as (FFT bin, weight) pairs: triangles rising from the previous band's centre to
+// their own and falling to the next's, evenly spaced on the mel scale
+fn mel_filterbank(sample_rate: u32, fft_size: usize) -> Vec<Vec<(usize, f32)>> {
+    let bin_hz = sample_rate as f32 / fft_size as f32;
+    let max_mel = hz_to_mel(sample_rate as f32 / 2.0);
+    let edges: Vec<f32> = (0..MEL_BANDS + 2).map(|i| mel_to_hz(max_mel * i as f32 / (MEL_BANDS + 1) as f32)).collect();
+
+    edges
+        .windows(3)
+        .map(|edge| {
+            let (low, centre, high) = (edge[0], edge[1], edge[2]);
+            (0..=fft_size / 2)
+                .filter_map(|bin| {
+                    let freq = bin as f32 * bin_hz;
+                    let weight = if freq <= centre { (freq - low) / (centre - low) } else { (high - freq) / (high - centre) };
+                    (weight > 0.0).then_some((bin, weight))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn hz_to_mel(hz: f32) -> f32 {
+    2595.0 * (1.0 + hz / 700.0).log10()
+}
+
+fn mel_to_hz(mel: f32) -> f32 {
+    700.0 * (10f32.powf(mel / 2595.0) - 1.0)
+}
@@ -0,0 +1,113 @@
+//! Desktop window running the same analysis pipeline and renderer as the web build.
+
+use std::sync::Arc;
+use std::time::Instant;
+use winit::application::ApplicationHandler;
+use winit::event::WindowEvent;
+use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::window::{Window, WindowId};
+
+use crate::analyzer::AudioAnalyzer;
+use crate::renderer::Renderer;
+use crate::timing;
+
+const BIN_SIZE: usize = 64;
+const SMOOTHING_FACTOR: f32 = 0.2;
+
+/// Analyze a WAV file and show the visualization in a window until it is closed. Audio is not
+/// played back; the visualization follows the wall clock from the moment the window opens.
+pub fn run(file_data: &[u8]) -> Result<(), String> {
+    let mut analyzer = AudioAnalyzer::new();
+    analyzer.set_bin_size(BIN_SIZE);
+    analyzer.process(file_data)?;
+
+    let mut renderer = Renderer::new();
+    renderer.set_waveform_strip(true, 0.15);
+    renderer.set_waveform(analyzer.waveform());
+
+    let mut player = Player {
+        window: None,
+        renderer,
+        analyzer,
+        previous_bars: vec![0.0; BIN_SIZE],
+        start: None,
+        error: None,
+    };
+
+    let event_loop = EventLoop::new().map_err(|e| format!("Failed to create event loop: {}", e))?;
+    event_loop
+        .run_app(&mut player)
+        .map_err(|e| format!("Event loop failed: {}", e))?;
+    player.error.map_or(Ok(()), Err)
+}
+
+struct Player {
+    window: Option<Arc<Window>>,
+    renderer: Renderer,
+    analyzer: AudioAnalyzer,
+    previous_bars: Vec<f32>,
+    start: Option<Instant>,
+    error: Option<String>,
+}
+
+impl Player {
+    fn render(&mut self) -> Result<(), String> {
+        let elapsed = self.start.get_or_insert_with(Instant::now).elapsed().as_secs_f64();
+        let total_frames = self.analyzer.get_total_frames();
+        let frame_index = self.analyzer.frame_index_at(elapsed).min(total_frames.saturating_sub(1));
+
+        // The window refreshes at the monitor's rate, so interpolate between analysis frames
+        let target = self.analyzer.bars_at(elapsed).unwrap_or_else(|| self.analyzer.get_frequency_bars(frame_index));
+        let smoothing_factor = timing::frame_rate_independent_smoothing(SMOOTHING_FACTOR, timing::REFERENCE_REFRESH_RATE);
+        for (previous, target) in self.previous_bars.iter_mut().zip(&target) {
+            *previous = *previous * (1.0 - smoothing_factor) + target * smoothing_factor;
+        }
+
+        let playhead = frame_index as f64 / total_frames.max(1) as f64;
+        self.renderer.render(elapsed, &self.previous_bars, BIN_SIZE, playhead)
+    }
+}
+
+impl ApplicationHandler for Player {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.window.is_some() {
+            return;
+        }
+
+        let attributes = Window::default_attributes().with_title("viber");
+        let window = match event_loop.create_window(attributes) {
+            Ok(window) => Arc::new(window),
+            Err(e) => {
+                self.error = Some(format!("Failed to create window: {}", e));
+                event_loop.exit();
+                return;
+            }
+        };
+
+        if let Err(e) = pollster::block_on(self.renderer.init_window(window.clone())) {
+            self.error = Some(e);
+            event_loop.exit();
+            return;
+        }
+        window.request_redraw();
+        self.window = Some(window);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _window_id: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::Resized(size) => self.renderer.resize(size.width.max(1), size.height.max(1)),
+            WindowEvent::RedrawRequested => {
+                if let Err(e) = self.render() {
+                    self.error = Some(e);
+                    event_loop.exit();
+                    return;
+                }
+                if let Some(window) = &self.window {
+                    window.request_redraw();
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::locale::Locale;
+use crate::pitch::NoteReading;
+use crate::text::{self, TextAlign, TextItem};
+
+/// Host-configured overlay content drawn with the text layer.
+#[derive(Clone, Debug, Default)]
+pub struct OverlayText {
+    pub title: Option<String>,
+    pub show_time: bool,
+    pub bpm: Option<f32>,
+}
+
+impl OverlayText {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && !self.show_time && self.bpm.is_none()
+    }
+
+    /// Lay out the overlay: title in the top-left corner, elapsed/remaining time and BPM in the top-right.
+    /// `top_inset` keeps the text clear of a HUD strip along the top edge.
+    pub fn compose(&self, elapsed_seconds: f64, duration_seconds: f64, width: u32, height: u32, top_inset: f32, locale: Locale) -> Vec<TextItem> {
+        let mut items = Vec::new();
+        if self.is_empty() || width == 0 || height == 0 {
+            return items;
+        }
+
+        let scale = overlay_scale(height);
+        let margin = text::line_height(scale);
+        let mut y = top_inset + margin * 0.5;
+
+        if let Some(title) = &self.title {
+            items.push(TextItem::new(title.clone(), margin, y, scale, TextAlign::Left));
+        }
+
+        let right = width as f32 - margin;
+        if self.show_time {
+            let remaining = (duration_seconds - elapsed_seconds).max(0.0);
+            let label = format!("{} / -{}", format_time(elapsed_seconds), format_time(remaining));
+            items.push(TextItem::new(label, right, y, scale, TextAlign::Right));
+            y += text::line_height(scale) * 1.25;
+        }
+        if let Some(bpm) = self.bpm {
+            items.push(TextItem::new(format!("{} BPM", locale.format_number(bpm as f64, 0)), right, y, scale, TextAlign::Right).with_color([0.8, 0.7, 1.0, 0.9]));
+        }
+
+        items
+    }
+}
+
+/// The current lyric line centered near the bottom edge, shrunk to fit the canvas width.
+pub fn compose_lyric(line: &str, width: u32, height: u32) -> TextItem {
+    let scale = overlay_scale(height);
+    let margin = text::line_height(scale);
+    let fit = ((width as f32 - margin * 2.0) / text::measure(line, 1.0)).floor().max(1.0);
+    let scale = scale.min(fit);
+    let y = height as f32 - margin - text::line_height(scale);
+    TextItem::new(line, width as f32 * 0.5, y, scale, TextAlign::Center).with_color([1.0, 0.95, 0.8, 0.95])
+}
+
+/// The tuner mode's note name, large inside its arc, with the deviation in cents underneath.
+pub fn compose_tuner(note: NoteReading, width: u32, height: u32) -> Vec<TextItem> {
+    let scale = overlay_scale(height);
+    let center = width as f32 * 0.5;
+    // The arc's pivot is 0.3 canvas heights below the center
+    let name_y = height as f32 * 0.62 - text::line_height(scale * 4.0);
+    let cents = format!("{:+.0} cents", note.cents);
+    vec![
+        TextItem::new(note.name(), center, name_y, scale * 4.0, TextAlign::Center),
+        TextItem::new(cents, center, name_y + text::line_height(scale * 4.0) * 1.2, scale * 1.5, TextAlign::Center).with_color([0.8, 0.8, 0.8, 0.9]),
+    ]
+}
+
+/// Diagnostic lines stacked down the left edge starting at `top`, in smaller green text.
+pub fn compose_debug_panel(lines: &[String], height: u32, top: f32) -> Vec<TextItem> {
+    let scale = (overlay_scale(height) - 1.0).max(1.0);
+    let line_height = text::line_height(scale) * 1.2;
+    let margin = text::line_height(overlay_scale(height));
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            TextItem::new(line.clone(), margin, top + i as f32 * line_height, scale, TextAlign::Left)
+                .with_color([0.5, 1.0, 0.6, 0.9])
+        })
+        .collect()
+}
+
+/// Pixel scale for overlay text so it stays legible across canvas sizes.
+pub fn overlay_scale(height: u32) -> f32 {
+    (height as f32 / 240.0).round().max(1.0)
+}
+
+/// Format seconds as m:ss (or h:mm:ss for long tracks).
+pub fn format_time(seconds: f64) -> String {
+    let total = seconds.max(0.0).floor() as u64;
+    let (hours, minutes, secs) = (total / 3600, (total / 60) % 60, total % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{}:{:02}", minutes, secs)
+    }
+}
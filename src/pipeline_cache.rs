@@ -0,0 +1,32 @@
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use wgpu::*;
+
+// Shader source hash, target format and the bind group layouts after the shared uniforms
+type PipelineKey = (u64, TextureFormat, &'static str);
+
+/// Mode pipelines by what they were built from, so rebuilding a mode with a shader it has had
+/// before (reverting a hot swap, re-initializing the same device) skips the compile. Pipelines
+/// belong to one device; `clear` when the device changes.
+#[derive(Default)]
+pub struct PipelineCache {
+    pipelines: RefCell<HashMap<PipelineKey, RenderPipeline>>,
+}
+
+impl PipelineCache {
+    /// The pipeline for `source` drawing into `format`, built by `create` unless cached. `layout`
+    /// names the bind group layouts after the shared uniforms; modes passing the same name must
+    /// use layouts with identical entries.
+    pub fn get_or_create(&self, source: &str, format: TextureFormat, layout: &'static str, create: impl FnOnce() -> RenderPipeline) -> RenderPipeline {
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+        let key = (hasher.finish(), format, layout);
+        self.pipelines.borrow_mut().entry(key).or_insert_with(create).clone()
+    }
+
+    pub fn clear(&self) {
+        self.pipelines.borrow_mut().clear();
+    }
+}
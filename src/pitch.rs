@@ -0,0 +1,158 @@
+//! Fundamental frequency estimation with YIN (de Cheveigné & Kawahara, 2002), and the note names
+//! and cents deviations a tuner shows. Like `analysis`, this has no browser dependencies.
+
+/// Lowest fundamental reported: just under a bass guitar's low E.
+pub const MIN_PITCH_HZ: f32 = 40.0;
+/// Highest fundamental reported, well above a soprano's top notes.
+pub const MAX_PITCH_HZ: f32 = 2000.0;
+/// Samples the difference function is summed over. Estimating the lowest pitches needs this plus
+/// one period of `MIN_PITCH_HZ` from the signal.
+pub const PITCH_WINDOW: usize = 1024;
+// The first dip of the normalized difference below this is taken as the period; higher finds a
+// pitch in more frames but makes octave errors more likely
+const YIN_THRESHOLD: f32 = 0.15;
+// Windows quieter than this RMS (relative to full scale, about -40 dBFS) are unvoiced
+const MIN_RMS: f32 = 0.01;
+
+/// Names of the pitch classes, C first, with sharps.
+pub const NOTE_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+/// Fundamental frequency in Hz of the audio starting at `samples[0]`, or `None` when it is quiet,
+/// unpitched or outside `MIN_PITCH_HZ..=MAX_PITCH_HZ`. Lower pitches need more samples than
+/// `PITCH_WINDOW`; with fewer, only the periods that fit are searched.
+pub fn detect_pitch(samples: &[i16], sample_rate: u32) -> Option<f32> {
+    let signal: Vec<f32> = samples.iter().map(|&sample| sample as f32 / i16::MAX as f32).collect();
+    if signal.len() <= PITCH_WINDOW {
+        return None;
+    }
+    let rms = (signal[..PITCH_WINDOW].iter().map(|s| s * s).sum::<f32>() / PITCH_WINDOW as f32).sqrt();
+    if rms < MIN_RMS {
+        return None;
+    }
+
+    let min_period = ((sample_rate as f32 / MAX_PITCH_HZ).floor() as usize).max(2);
+    let max_period = ((sample_rate as f32 / MIN_PITCH_HZ).ceil() as usize).min(signal.len() - PITCH_WINDOW - 1);
+    if max_period <= min_period {
+        return None;
+    }
+
+    // Cumulative mean normalized difference, computed lag by lag until the first dip below the
+    // threshold has bottomed out
+    let difference = |lag: usize| -> f32 { (0..PITCH_WINDOW).map(|j| (signal[j] - signal[j + lag]).powi(2)).sum() };
+    let mut normalized = vec![1.0f32; max_period + 1];
+    let mut running_sum = 0.0;
+    let mut period = None;
+    for lag in 1..=max_period {
+        let d = difference(lag);
+        running_sum += d;
+        normalized[lag] = if running_sum > 0.0 { d * lag as f32 / running_sum } else { 1.0 };
+
+        let previous = lag - 1;
+        if previous >= min_period && normalized[previous] < YIN_THRESHOLD && normalized[previous] <= normalized[lag] {
+            period = Some(previous);
+            break;
+        }
+    }
+    let period = period?;
+
+    // Parabolic interpolation between the neighbouring lags for a fractional period
+    let (before, at, after) = (normalized[period - 1], normalized[period], normalized[period + 1]);
+    let curvature = before - 2.0 * at + after;
+    let shift = if curvature > 0.0 { (0.5 * (before - after) / curvature).clamp(-1.0, 1.0) } else { 0.0 };
+    let frequency = sample_rate as f32 / (period as f32 + shift);
+    (MIN_PITCH_HZ..=MAX_PITCH_HZ).contains(&frequency).then_some(frequency)
+}
+
+/// The equal-tempered note nearest a frequency (A4 = 440 Hz) and how far the frequency is from it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NoteReading {
+    /// MIDI note number: 69 is A4, 60 middle C
+    pub midi_note: i32,
+    /// Deviation from the note in cents, -50.0 to 50.0; positive is sharp
+    pub cents: f32,
+}
+
+impl NoteReading {
+    pub fn from_frequency(frequency: f32) -> Self {
+        let semitones = 69.0 + 12.0 * (frequency / 440.0).log2();
+        let midi_note = semitones.round();
+        Self {
+            midi_note: midi_note as i32,
+            cents: (semitones - midi_note) * 100.0,
+        }
+    }
+
+    /// Pitch class, 0 = C ... 11 = B.
+    pub fn pitch_class(&self) -> usize {
+        self.midi_note.rem_euclid(12) as usize
+    }
+
+    /// Scientific pitch notation with sharps, e.g. "A4" or "C#3".
+    pub fn name(&self) -> String {
+        format!("{}{}", NOTE_NAMES[self.pitch_class()], self.midi_note.div_euclid(12) - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 44_100;
+
+    fn sine(frequency: f32, amplitude: f32, length: usize) -> Vec<i16> {
+        (0..length)
+            .map(|i| {
+                let phase = std::f32::consts::TAU * frequency * i as f32 / SAMPLE_RATE as f32;
+                (phase.sin() * amplitude * i16::MAX as f32) as i16
+            })
+            .collect()
+    }
+
+    // Enough samples for the lowest pitch, as `AudioAnalyzer::frame_pitch` passes
+    fn full_span() -> usize {
+        PITCH_WINDOW + (SAMPLE_RATE as f32 / MIN_PITCH_HZ).ceil() as usize + 1
+    }
+
+    #[test]
+    fn sines_at_known_frequencies() {
+        for frequency in [55.0, 110.0, 440.0, 1046.5, 1900.0] {
+            let pitch = detect_pitch(&sine(frequency, 0.5, full_span()), SAMPLE_RATE).expect("a sine is pitched");
+            let cents = 1200.0 * (pitch / frequency).log2();
+            assert!(cents.abs() < 5.0, "{} Hz detected as {} Hz", frequency, pitch);
+        }
+    }
+
+    #[test]
+    fn silence_and_quiet_signals_are_unpitched() {
+        assert_eq!(detect_pitch(&vec![0; full_span()], SAMPLE_RATE), None);
+        assert_eq!(detect_pitch(&sine(440.0, 0.005, full_span()), SAMPLE_RATE), None);
+    }
+
+    #[test]
+    fn too_few_samples_are_unpitched() {
+        assert_eq!(detect_pitch(&sine(440.0, 0.5, PITCH_WINDOW), SAMPLE_RATE), None);
+    }
+
+    #[test]
+    fn noise_is_unpitched() {
+        let mut state = 0x2545_f491_u32;
+        let noise: Vec<i16> = (0..full_span())
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 16) as i16 / 2
+            })
+            .collect();
+        assert_eq!(detect_pitch(&noise, SAMPLE_RATE), None);
+    }
+
+    #[test]
+    fn note_readings() {
+        let a4 = NoteReading::from_frequency(440.0);
+        assert_eq!((a4.midi_note, a4.name()), (69, "A4".to_string()));
+        assert!(a4.cents.abs() < 1e-3);
+
+        let sharp_c = NoteReading::from_frequency(261.63 * 2f32.powf(20.0 / 1200.0));
+        assert_eq!(sharp_c.name(), "C4");
+        assert!((sharp_c.cents - 20.0).abs() < 0.1);
+    }
+}
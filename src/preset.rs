@@ -0,0 +1,134 @@
+//! Milkdrop-style presets: a Rhai script run once per frame that maps audio features to visual
+//! parameters applied on top of every visualizer mode.
+//!
+//! Scripts read `bass`, `mid`, `treble` (0.0-1.0), `rms`, `flux`, `beat` (true on detected onsets)
+//! and `time` (seconds), and assign `rotation` (radians), `zoom` (1.0 = unchanged) and
+//! `color_shift` (hue offset, 1.0 = full turn). The outputs keep their values between frames, so
+//! a script can accumulate:
+//!
+//! ```text
+//! rotation += 0.002 + bass * 0.01;
+//! zoom = 1.0 + treble * 0.2;
+//! if beat { color_shift += 0.1; }
+//! ```
+
+use rhai::{Dynamic, Engine, Scope, AST};
+
+use crate::analyzer::FrameFeatures;
+use crate::beat::BeatDetector;
+
+// Guard against runaway scripts stalling the render loop
+const MAX_OPERATIONS: u64 = 50_000;
+
+/// Visual parameters driven by a preset.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PresetParams {
+    pub rotation: f32,
+    pub zoom: f32,
+    pub color_shift: f32,
+}
+
+impl Default for PresetParams {
+    fn default() -> Self {
+        Self {
+            rotation: 0.0,
+            zoom: 1.0,
+            color_shift: 0.0,
+        }
+    }
+}
+
+/// A compiled preset with the state it carries between frames.
+pub struct Preset {
+    engine: Engine,
+    ast: AST,
+    params: PresetParams,
+    beats: BeatDetector,
+}
+
+impl Preset {
+    /// Compile a preset script; syntax errors are reported with their line and column.
+    pub fn compile(source: &str) -> Result<Self, String> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.set_max_expr_depths(64, 32);
+        engine.set_max_string_size(1024);
+        engine.set_max_array_size(1024);
+        engine.set_max_map_size(256);
+        engine.on_print(|text| log!("preset: {}", text));
+
+        let ast = engine.compile(source).map_err(|e| format!("Preset failed to compile: {}", e))?;
+        Ok(Self {
+            engine,
+            ast,
+            params: PresetParams::default(),
+            beats: BeatDetector::default(),
+        })
+    }
+
+    /// Run the script for one frame and return the resulting parameters. On an error, including an
+    /// output that isn't a finite number, the parameters from the previous frame are kept.
+    pub fn evaluate(&mut self, features: &FrameFeatures, time: f64) -> Result<PresetParams, String> {
+        let beat = self.beats.detect(features.spectral_flux).is_some();
+
+        let mut scope = Scope::new();
+        scope.push("bass", features.bass);
+        scope.push("mid", features.mid);
+        scope.push("treble", features.treble);
+        scope.push("rms", features.rms);
+        scope.push("flux", features.spectral_flux);
+        scope.push("beat", beat);
+        scope.push("time", time as f32);
+        scope.push("rotation", self.params.rotation);
+        scope.push("zoom", self.params.zoom);
+        scope.push("color_shift", self.params.color_shift);
+
+        self.engine
+            .run_ast_with_scope(&mut scope, &self.ast)
+            .map_err(|e| format!("Preset failed: {}", e))?;
+
+        // Only stored once every output has read back, so a failed frame can't leave NaN behind
+        self.params = PresetParams {
+            rotation: read_number(&scope, "rotation")?.rem_euclid(std::f32::consts::TAU),
+            zoom: read_number(&scope, "zoom")?.clamp(0.1, 10.0),
+            color_shift: read_number(&scope, "color_shift")?.rem_euclid(1.0),
+        };
+        Ok(self.params)
+    }
+}
+
+// Outputs may be assigned integer literals (`zoom = 1;`). NaN and infinities, e.g. from dividing
+// by a silent `rms`, would pass through the clamping unchanged and are rejected.
+fn read_number(scope: &Scope, name: &str) -> Result<f32, String> {
+    let value = scope.get_value::<Dynamic>(name).unwrap_or(Dynamic::UNIT);
+    let number = value
+        .as_float()
+        .or_else(|_| value.as_int().map(|int| int as f32))
+        .map_err(|type_name| format!("Preset output `{}` must be a number, got {}", name, type_name))?;
+    if !number.is_finite() {
+        return Err(format!("Preset output `{}` must be finite, got {}", name, number));
+    }
+    Ok(number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_finite_output_keeps_previous_params() {
+        let features = FrameFeatures::default();
+        let mut preset = Preset::compile("zoom = if time > 1.0 { 0.0 / 0.0 } else { 2.0 };").unwrap();
+        assert_eq!(preset.evaluate(&features, 0.0).unwrap().zoom, 2.0);
+
+        let error = preset.evaluate(&features, 2.0).unwrap_err();
+        assert!(error.contains("`zoom` must be finite"), "{}", error);
+        assert_eq!(preset.params.zoom, 2.0);
+    }
+
+    #[test]
+    fn integer_outputs_are_accepted() {
+        let mut preset = Preset::compile("zoom = 3;").unwrap();
+        assert_eq!(preset.evaluate(&FrameFeatures::default(), 0.0).unwrap().zoom, 3.0);
+    }
+}
@@ -0,0 +1,160 @@
+//! Reference test vectors: canonical analysis outputs for a few synthetic signals, so other
+//! implementations of the pipeline (Python ports, FFI bindings) can check they match this one.
+//!
+//! Each fixture is a JSON document holding the exact input samples alongside the bars and
+//! chromagram of every frame and the FFT magnitudes of the first, middle and last frames.
+//! Floats are written in their shortest round-trip form. Outputs should match within the
+//! fixture's `tolerance`, absolute for bars and chroma and relative to the frame's peak for FFT
+//! magnitudes. `REFERENCE_VECTOR_VERSION` goes up whenever an intentional analysis change
+//! alters the outputs.
+
+use std::fmt::Write;
+use std::io::Cursor;
+use wasm_bindgen::prelude::*;
+
+use crate::analysis::{FRAME_SIZE, NYQUIST_BIN};
+use crate::analyzer::AudioAnalyzer;
+
+/// Version of the fixture format and of the analysis outputs it records.
+pub const REFERENCE_VECTOR_VERSION: u32 = 1;
+/// Sample rate of the reference signals, the rate the framing assumes.
+pub const REFERENCE_SAMPLE_RATE: u32 = 44100;
+/// Length of each reference signal in samples.
+pub const REFERENCE_LENGTH: usize = 4096;
+/// Bars per frame in the fixtures.
+pub const REFERENCE_BIN_SIZE: usize = 64;
+/// Largest difference at which a port's output still counts as matching.
+pub const REFERENCE_TOLERANCE: f32 = 1e-4;
+
+// Peak level of the signals: half of full scale, clear of clipping
+const AMPLITUDE: f64 = 16384.0;
+const SINE_FREQ: f64 = 1000.0;
+const SWEEP_START_FREQ: f64 = 20.0;
+const SWEEP_END_FREQ: f64 = 20000.0;
+
+/// The bundled reference signals, all mono 16-bit at `REFERENCE_SAMPLE_RATE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReferenceSignal {
+    /// 1 kHz sine at half scale
+    Sine1k,
+    /// Exponential sine sweep from 20 Hz to 20 kHz at half scale
+    Sweep,
+    /// A single half-scale sample in the middle of silence
+    Impulse,
+}
+
+impl ReferenceSignal {
+    pub const ALL: [ReferenceSignal; 3] = [ReferenceSignal::Sine1k, ReferenceSignal::Sweep, ReferenceSignal::Impulse];
+
+    pub fn parse(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|signal| signal.name() == name)
+            .ok_or_else(|| format!("Unknown reference signal '{}', expected 'sine-1k', 'sweep' or 'impulse'", name))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ReferenceSignal::Sine1k => "sine-1k",
+            ReferenceSignal::Sweep => "sweep",
+            ReferenceSignal::Impulse => "impulse",
+        }
+    }
+
+    /// The signal's samples. Generated in f64 and rounded, so they are the same on every platform.
+    pub fn samples(self) -> Vec<i16> {
+        let rate = REFERENCE_SAMPLE_RATE as f64;
+        let quantize = |value: f64| (value * AMPLITUDE).round() as i16;
+        match self {
+            ReferenceSignal::Sine1k => (0..REFERENCE_LENGTH)
+                .map(|n| quantize((std::f64::consts::TAU * SINE_FREQ * n as f64 / rate).sin()))
+                .collect(),
+            ReferenceSignal::Sweep => {
+                // Phase of an exponential sweep: the frequency multiplies by a constant factor per second
+                let duration = REFERENCE_LENGTH as f64 / rate;
+                let growth = (SWEEP_END_FREQ / SWEEP_START_FREQ).ln() / duration;
+                (0..REFERENCE_LENGTH)
+                    .map(|n| {
+                        let t = n as f64 / rate;
+                        let phase = std::f64::consts::TAU * SWEEP_START_FREQ * ((growth * t).exp() - 1.0) / growth;
+                        quantize(phase.sin())
+                    })
+                    .collect()
+            }
+            ReferenceSignal::Impulse => {
+                let mut samples = vec![0; REFERENCE_LENGTH];
+                samples[REFERENCE_LENGTH / 2] = quantize(1.0);
+                samples
+            }
+        }
+    }
+
+    /// Mono 16-bit WAV file of the signal.
+    pub fn wav(self) -> Result<Vec<u8>, String> {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: REFERENCE_SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut cursor = Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut cursor, spec).map_err(|e| format!("Failed to write WAV header: {:?}", e))?;
+        for sample in self.samples() {
+            writer.write_sample(sample).map_err(|e| format!("Failed to write WAV samples: {:?}", e))?;
+        }
+        writer.finalize().map_err(|e| format!("Failed to finish WAV file: {:?}", e))?;
+        Ok(cursor.into_inner())
+    }
+}
+
+/// Run the signal through the full analysis pipeline (WAV decoding included) and return the
+/// fixture JSON.
+pub fn generate(signal: ReferenceSignal) -> Result<String, String> {
+    let mut analyzer = AudioAnalyzer::new();
+    analyzer.set_bin_size(REFERENCE_BIN_SIZE);
+    analyzer.process(&signal.wav()?)?;
+
+    let total_frames = analyzer.get_total_frames();
+    let probe_frames = [0, total_frames / 2, total_frames.saturating_sub(1)];
+
+    let mut json = String::new();
+    json.push_str("{\n");
+    let _ = writeln!(json, "  \"version\": {},", REFERENCE_VECTOR_VERSION);
+    let _ = writeln!(json, "  \"signal\": \"{}\",", signal.name());
+    let _ = writeln!(json, "  \"sample_rate\": {},", REFERENCE_SAMPLE_RATE);
+    let _ = writeln!(json, "  \"frame_size\": {},", FRAME_SIZE);
+    let _ = writeln!(json, "  \"hop_size\": {},", analyzer.hop_size());
+    let _ = writeln!(json, "  \"bin_size\": {},", REFERENCE_BIN_SIZE);
+    let _ = writeln!(json, "  \"tolerance\": {},", REFERENCE_TOLERANCE);
+    let _ = writeln!(json, "  \"total_frames\": {},", total_frames);
+    let _ = writeln!(json, "  \"samples\": {},", number_list(&signal.samples()));
+    let _ = writeln!(json, "  \"bars\": {},", frame_list((0..total_frames).map(|i| analyzer.get_frequency_bars(i))));
+    let _ = writeln!(json, "  \"chroma\": {},", frame_list((0..total_frames).map(|i| analyzer.get_chroma(i))));
+    json.push_str("  \"fft_probes\": [\n");
+    for (i, &frame) in probe_frames.iter().enumerate() {
+        let magnitudes = analyzer.fft_frame(frame).map_or(vec![0.0; NYQUIST_BIN], |m| m[..NYQUIST_BIN.min(m.len())].to_vec());
+        let separator = if i + 1 < probe_frames.len() { "," } else { "" };
+        let _ = writeln!(json, "    {{\"frame\": {}, \"magnitudes\": {}}}{}", frame, number_list(&magnitudes), separator);
+    }
+    json.push_str("  ]\n}\n");
+    Ok(json)
+}
+
+/// Canonical analysis outputs for a bundled reference signal ("sine-1k", "sweep" or "impulse")
+/// as fixture JSON, for checking other implementations of the analysis against this one.
+#[wasm_bindgen]
+pub fn reference_vectors(signal: &str) -> Result<String, JsValue> {
+    ReferenceSignal::parse(signal)
+        .and_then(generate)
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+fn number_list<T: std::fmt::Display>(values: &[T]) -> String {
+    let items: Vec<String> = values.iter().map(ToString::to_string).collect();
+    format!("[{}]", items.join(", "))
+}
+
+fn frame_list(frames: impl Iterator<Item = Vec<f32>>) -> String {
+    let rows: Vec<String> = frames.map(|frame| format!("    {}", number_list(&frame))).collect();
+    format!("[\n{}\n  ]", rows.join(",\n"))
+}
@@ -1,19 +1,181 @@
+use bytemuck::Zeroable;
 use wasm_bindgen::prelude::*;
 use web_sys::HtmlCanvasElement;
 use wgpu::*;
 use wgpu::rwh;
 use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::analysis::PITCH_CLASSES;
+use crate::antialiasing::{Antialiasing, Fxaa};
+use crate::bar_smoothing::BarSmoothing;
+use crate::feedback::{Feedback, FeedbackSettings};
+use crate::gradient::{Gradient, GradientMode, GRADIENT_WIDTH};
+use crate::layout::{BarArrangement, BarStyle, HudPosition, Orientation, SplitLayout};
+use crate::pipeline_cache::PipelineCache;
+use crate::split_view::SplitView;
+use crate::text::{TextItem, TextRenderer};
+use crate::theme::Theme;
+use crate::transition::{Crossfade, Easing, ModeTransition};
+use crate::uniforms::{Bars, Uniforms};
+use crate::visualizer::{self, Visualizer, VisualizerContext, VisualizerFrame};
+
+// The time uniform wraps around after this many seconds. f32 keeps sub-millisecond precision
+// below an hour, and shaders fit their animation rates to this period so the wrap is seamless.
+const TIME_WRAP_PERIOD: f64 = 3600.0;
+
+// Debug groups and markers make passes navigable in Chrome/RenderDoc captures. They are only
+// encoded with the `gpu-debug` feature; resource labels are always set.
+pub(crate) const GPU_DEBUG: bool = cfg!(feature = "gpu-debug");
+
+/// A device with its render target, ready to be attached to a `Renderer`. Connecting doesn't
+/// touch the renderer, so it can run while the renderer is in use elsewhere.
+pub struct GpuTarget {
+    device: Device,
+    queue: Queue,
+    surface: Option<Surface<'static>>,
+    config: SurfaceConfiguration,
+    canvas: Option<HtmlCanvasElement>,
+    adapter_info: AdapterInfo,
+}
+
+/// One GPU device for several renderers, so a page with many visualizers doesn't pay for a
+/// device (and its pipelines' driver state) per visualizer. In the browser this needs WebGPU:
+/// a WebGL device belongs to the one canvas it was created for.
+#[derive(Clone)]
+pub struct SharedGpu {
+    instance: Instance,
+    adapter: Adapter,
+    device: Device,
+    queue: Queue,
+}
+
+impl SharedGpu {
+    pub async fn connect() -> Result<Self, String> {
+        let backends = if cfg!(target_arch = "wasm32") { Backends::BROWSER_WEBGPU } else { Backends::all() };
+        let instance = Instance::new(&InstanceDescriptor {
+            backends,
+            flags: Default::default(),
+            ..Default::default()
+        });
+
+        let adapter = instance
+            .request_adapter(&RequestAdapterOptions {
+                power_preference: PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await
+            .map_err(|e| format!("No adapter available for a shared device (it needs WebGPU in the browser): {:?}", e))?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &DeviceDescriptor {
+                    label: Some("Shared Visualizer Device"),
+                    required_features: Features::empty(),
+                    required_limits: Limits::downlevel_webgl2_defaults(),
+                    memory_hints: Default::default(),
+                    trace: Default::default(),
+                },
+            )
+            .await
+            .map_err(|e| format!("Failed to create device: {:?}", e))?;
+
+        Ok(Self {
+            instance,
+            adapter,
+            device,
+            queue,
+        })
+    }
+}
 
 pub struct Renderer {
     device: Option<Device>,
     queue: Option<Queue>,
     surface: Option<Surface<'static>>,
     config: Option<SurfaceConfiguration>,
-    render_pipeline: Option<RenderPipeline>,
+    visualizers: Vec<Box<dyn Visualizer>>,
+    pipeline_cache: PipelineCache,
+    active_visualizer: usize,
+    transition: Option<ModeTransition>,
+    transition_duration: f64,
+    transition_easing: Easing,
+    crossfade: Option<Crossfade>,
+    // Sample and accumulation targets for motion-blurred poster frames, created on first use
+    poster_targets: Option<(Crossfade, Crossfade)>,
+    feedback: Option<Feedback>,
+    feedback_settings: Option<FeedbackSettings>,
+    antialiasing: Antialiasing,
+    fxaa: Option<Fxaa>,
+    // Size the frame is drawn at relative to the target; anything but 1.0 draws into
+    // `scale_target` and stretches that onto the target
+    render_scale: f32,
+    scale_target: Option<Crossfade>,
+    // Layout and mode of a second view sharing the target, `None` for a single view
+    split: Option<(SplitLayout, usize)>,
+    split_view: Option<SplitView>,
+    // Bars of the second view
+    split_bars: Vec<f32>,
     canvas: Option<HtmlCanvasElement>,
     uniform_buffer: Option<Buffer>,
     uniform_bind_group: Option<BindGroup>,
+    uniform_bind_group_layout: Option<BindGroupLayout>,
+    // Uniform floats as last uploaded, so only the span that changed is written again
+    last_uniforms: Vec<f32>,
+    // Bar levels, in a buffer of their own since most frames leave them unchanged
+    bars_buffer: Option<Buffer>,
+    last_bars: Vec<f32>,
+    bar_smoothing: Option<BarSmoothing>,
+    // Bars are uploaded as targets and smoothed on the GPU by `smoothing_factor` per frame
+    gpu_smoothing: bool,
+    smoothing_factor: f32,
+    waveform_view: Option<TextureView>,
+    background_view: Option<TextureView>,
+    background_sampler: Option<Sampler>,
+    gradient_view: Option<TextureView>,
+    gradient: Option<(Gradient, GradientMode)>,
     frame_count: u32,
+    waveform_data: Vec<f32>,
+    // Marker ticks on the waveform strip, as fractions of the track
+    marker_positions: Vec<f32>,
+    waveform_enabled: bool,
+    waveform_height: f32,
+    highlighted_bar: Option<usize>,
+    soloed_bar: Option<usize>,
+    pending_background: Option<(Vec<u8>, u32, u32)>,
+    background_enabled: bool,
+    background_aspect: f32,
+    background_blur: f32,
+    background_dim: f32,
+    orientation: Orientation,
+    hud_position: HudPosition,
+    bar_arrangement: BarArrangement,
+    bars_reversed: bool,
+    bar_style: BarStyle,
+    theme: Theme,
+    view_transform: [f32; 3],
+    beat_levels: [f32; 4],
+    chroma: [f32; PITCH_CLASSES],
+    // Right channel bars; the uploaded right bars repeat the mono ones while this is empty
+    right_bars: Vec<f32>,
+    // (left, right) sample pairs of the current frame for the goniometer
+    scope_samples: Vec<[f32; 2]>,
+    // Correlation and width shown by the HUD stereo meter, `None` to hide it
+    stereo_meter: Option<[f32; 2]>,
+    // Cents and pitch class the tuner mode points at, `None` while unpitched
+    tuner: Option<[f32; 2]>,
+    // dB between gridlines and their opacity for the axis under the bars, `None` to hide them
+    axis_grid: Option<[f32; 2]>,
+    text_renderer: Option<TextRenderer>,
+    text_items: Vec<TextItem>,
+    offscreen_texture: Option<Texture>,
+    adapter_info: Option<AdapterInfo>,
+    background_size: (u32, u32),
+    // Set when the device or the canvas context is lost, until `take_context_lost`; shared
+    // with the device lost callback, which may run on another thread natively
+    context_lost: Arc<AtomicBool>,
 }
 
 impl Renderer {
@@ -23,33 +185,131 @@ impl Renderer {
             queue: None,
             surface: None,
             config: None,
-            render_pipeline: None,
+            visualizers: visualizer::builtin_visualizers(),
+            pipeline_cache: PipelineCache::default(),
+            active_visualizer: 0,
+            transition: None,
+            transition_duration: 0.0,
+            transition_easing: Easing::default(),
+            crossfade: None,
+            poster_targets: None,
+            feedback: None,
+            feedback_settings: None,
+            antialiasing: Antialiasing::default(),
+            fxaa: None,
+            render_scale: 1.0,
+            scale_target: None,
+            split: None,
+            split_view: None,
+            split_bars: Vec::new(),
             canvas: None,
             uniform_buffer: None,
             uniform_bind_group: None,
+            uniform_bind_group_layout: None,
+            last_uniforms: Vec::new(),
+            bars_buffer: None,
+            last_bars: Vec::new(),
+            bar_smoothing: None,
+            gpu_smoothing: false,
+            smoothing_factor: 1.0,
+            waveform_view: None,
+            background_view: None,
+            background_sampler: None,
+            gradient_view: None,
+            gradient: None,
             frame_count: 0,
+            waveform_data: Vec::new(),
+            marker_positions: Vec::new(),
+            waveform_enabled: false,
+            waveform_height: 0.15,
+            highlighted_bar: None,
+            soloed_bar: None,
+            pending_background: None,
+            background_enabled: false,
+            background_aspect: 1.0,
+            background_blur: 0.01,
+            background_dim: 0.6,
+            orientation: Orientation::Vertical,
+            hud_position: HudPosition::Top,
+            bar_arrangement: BarArrangement::BottomUp,
+            bars_reversed: false,
+            bar_style: BarStyle::default(),
+            theme: Theme::default(),
+            view_transform: [0.0, 1.0, 0.0],
+            beat_levels: [0.0; 4],
+            chroma: [0.0; PITCH_CLASSES],
+            right_bars: Vec::new(),
+            scope_samples: Vec::new(),
+            stereo_meter: None,
+            tuner: None,
+            axis_grid: None,
+            text_renderer: None,
+            text_items: Vec::new(),
+            offscreen_texture: None,
+            adapter_info: None,
+            background_size: (0, 0),
+            context_lost: Arc::new(AtomicBool::new(false)),
         }
     }
 
-    pub async fn init(&mut self, canvas_id: &str) -> Result<(), JsValue> {
-        // Get canvas element
-        let window = web_sys::window().unwrap();
-        let document = window.document().unwrap();
-        let canvas = document
-            .get_element_by_id(canvas_id)
-            .unwrap()
-            .dyn_into::<HtmlCanvasElement>()
-            .unwrap();
-
-        let width = canvas.width();
-        let height = canvas.height();
-
+    /// Connect to the canvas with the given element id.
+    pub async fn connect_canvas(canvas_id: String) -> Result<GpuTarget, String> {
         // Create WGPU instance
         let instance = Instance::new(&InstanceDescriptor {
             backends: Backends::GL,
             flags: Default::default(),
             ..Default::default()
         });
+        let (surface, canvas) = Self::canvas_surface(&instance, &canvas_id)?;
+
+        // Get adapter
+        let adapter = instance
+            .request_adapter(&RequestAdapterOptions {
+                power_preference: PowerPreference::default(),
+                compatible_surface: Some(&surface),
+                force_fallback_adapter: false,
+            })
+            .await
+            .map_err(|e| format!("No adapter available: {:?}", e))?;
+
+        // Get device and queue
+        let (device, queue) = adapter
+            .request_device(
+                &DeviceDescriptor {
+                    label: Some("Visualizer Device"),
+                    required_features: Features::empty(),
+                    required_limits: Limits::downlevel_webgl2_defaults(),
+                    memory_hints: Default::default(),
+                    trace: Default::default(),
+                },
+            )
+            .await
+            .map_err(|e| format!("Failed to create device: {:?}", e))?;
+
+        Self::configure_canvas(device, queue, &adapter, surface, canvas)
+    }
+
+    /// Connect to the canvas with the given element id through `shared`'s device, which other
+    /// renderers may be using too.
+    pub fn connect_shared_canvas(canvas_id: &str, shared: &SharedGpu) -> Result<GpuTarget, String> {
+        let (surface, canvas) = Self::canvas_surface(&shared.instance, canvas_id)?;
+        if !shared.adapter.is_surface_supported(&surface) {
+            return Err(format!("Canvas '{}' is not supported by the shared GPU adapter", canvas_id));
+        }
+        Self::configure_canvas(shared.device.clone(), shared.queue.clone(), &shared.adapter, surface, canvas)
+    }
+
+    // Look up a canvas and create a surface for it
+    fn canvas_surface(instance: &Instance, canvas_id: &str) -> Result<(Surface<'static>, HtmlCanvasElement), String> {
+        // Get canvas element
+        let document = web_sys::window()
+            .and_then(|window| window.document())
+            .ok_or_else(|| "No document available; use init_headless outside the main thread".to_string())?;
+        let canvas = document
+            .get_element_by_id(canvas_id)
+            .ok_or_else(|| format!("No element with id '{}'", canvas_id))?
+            .dyn_into::<HtmlCanvasElement>()
+            .map_err(|_| format!("Element '{}' is not a canvas", canvas_id))?;
 
         // Create surface using raw handles for canvas
         let target = SurfaceTargetUnsafe::RawHandle {
@@ -65,9 +325,70 @@ impl Renderer {
         };
 
         let surface = unsafe { instance.create_surface_unsafe(target) }
-            .map_err(|e| JsValue::from_str(&format!("Failed to create surface: {:?}", e)))?;
+            .map_err(|e| format!("Failed to create surface: {:?}", e))?;
+        Ok((surface, canvas))
+    }
+
+    // Configure a canvas surface at the canvas's size
+    fn configure_canvas(device: Device, queue: Queue, adapter: &Adapter, surface: Surface<'static>, canvas: HtmlCanvasElement) -> Result<GpuTarget, String> {
+        let config = Self::surface_config(&surface.get_capabilities(adapter).formats, canvas.width(), canvas.height())?;
+        surface.configure(&device, &config);
+
+        Ok(GpuTarget {
+            device,
+            queue,
+            surface: Some(surface),
+            config,
+            canvas: Some(canvas),
+            adapter_info: adapter.get_info(),
+        })
+    }
+
+    // Prefer an sRGB format, so blending happens on linear colors and looks the same whichever
+    // format the browser lists first. WebGPU canvases only offer linear formats, which are
+    // configured with their sRGB variant as a view format and drawn through that.
+    fn surface_config(formats: &[TextureFormat], width: u32, height: u32) -> Result<SurfaceConfiguration, String> {
+        let format = formats
+            .iter()
+            .copied()
+            .find(|format| format.is_srgb())
+            .or_else(|| formats.first().copied())
+            .ok_or_else(|| "Surface is not supported by the adapter".to_string())?;
+        let srgb_view = format.add_srgb_suffix();
+        Ok(SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width,
+            height,
+            present_mode: PresentMode::Fifo,
+            alpha_mode: CompositeAlphaMode::Auto,
+            view_formats: if srgb_view == format { vec![] } else { vec![srgb_view] },
+            desired_maximum_frame_latency: 2,
+        })
+    }
+
+    /// Initialize against a native winit window.
+    #[cfg(feature = "native")]
+    pub async fn init_window(&mut self, window: std::sync::Arc<winit::window::Window>) -> Result<(), String> {
+        let target = Self::connect_window(window).await?;
+        self.attach(target);
+        Ok(())
+    }
+
+    #[cfg(feature = "native")]
+    async fn connect_window(window: std::sync::Arc<winit::window::Window>) -> Result<GpuTarget, String> {
+        let size = window.inner_size();
+        let (width, height) = (size.width.max(1), size.height.max(1));
+
+        let instance = Instance::new(&InstanceDescriptor {
+            backends: Backends::PRIMARY,
+            flags: Default::default(),
+            ..Default::default()
+        });
+        let surface = instance
+            .create_surface(window)
+            .map_err(|e| format!("Failed to create surface: {:?}", e))?;
 
-        // Get adapter
         let adapter = instance
             .request_adapter(&RequestAdapterOptions {
                 power_preference: PowerPreference::default(),
@@ -75,13 +396,61 @@ impl Renderer {
                 force_fallback_adapter: false,
             })
             .await
-            .unwrap();
+            .map_err(|e| format!("No adapter available: {:?}", e))?;
 
-        // Get device and queue
         let (device, queue) = adapter
             .request_device(
                 &DeviceDescriptor {
-                    label: None,
+                    label: Some("Visualizer Device"),
+                    required_features: Features::empty(),
+                    required_limits: Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits()),
+                    memory_hints: Default::default(),
+                    trace: Default::default(),
+                },
+            )
+            .await
+            .map_err(|e| format!("Failed to create device: {:?}", e))?;
+
+        let config = Self::surface_config(&surface.get_capabilities(&adapter).formats, width, height)?;
+        surface.configure(&device, &config);
+
+        Ok(GpuTarget {
+            device,
+            queue,
+            surface: Some(surface),
+            config,
+            canvas: None,
+            adapter_info: adapter.get_info(),
+        })
+    }
+
+    /// Connect without a canvas or surface. Frames can only be produced with `render_offscreen`
+    /// (or `capture_frame`) at the configured size. This needs an adapter that works without a
+    /// surface: WebGPU in the browser, Deno or Node with a WebGPU binding. WebGL always needs a canvas.
+    pub async fn connect_headless(width: u32, height: u32) -> Result<GpuTarget, String> {
+        if width == 0 || height == 0 {
+            return Err(format!("Invalid headless size {}x{}", width, height));
+        }
+
+        let instance = Instance::new(&InstanceDescriptor {
+            backends: Backends::all(),
+            flags: Default::default(),
+            ..Default::default()
+        });
+
+        let adapter = instance
+            .request_adapter(&RequestAdapterOptions {
+                power_preference: PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await
+            .map_err(|e| format!("No adapter available for headless rendering: {:?}", e))?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &DeviceDescriptor {
+                    label: Some("Visualizer Device"),
                     required_features: Features::empty(),
                     required_limits: Limits::downlevel_webgl2_defaults(),
                     memory_hints: Default::default(),
@@ -89,12 +458,24 @@ impl Renderer {
                 },
             )
             .await
-            .unwrap();
+            .map_err(|e| format!("Failed to create device: {:?}", e))?;
+
+        Ok(Self::headless_target(device, queue, &adapter, width, height))
+    }
+
+    /// Connect without a canvas, like `connect_headless`, through `shared`'s device.
+    pub fn connect_shared_headless(width: u32, height: u32, shared: &SharedGpu) -> Result<GpuTarget, String> {
+        if width == 0 || height == 0 {
+            return Err(format!("Invalid headless size {}x{}", width, height));
+        }
+        Ok(Self::headless_target(shared.device.clone(), shared.queue.clone(), &shared.adapter, width, height))
+    }
 
-        // Configure surface
+    fn headless_target(device: Device, queue: Queue, adapter: &Adapter, width: u32, height: u32) -> GpuTarget {
+        // No surface is ever configured; the configuration only records the target size and format
         let config = SurfaceConfiguration {
             usage: TextureUsages::RENDER_ATTACHMENT,
-            format: surface.get_capabilities(&adapter).formats[0],
+            format: TextureFormat::Rgba8Unorm,
             width,
             height,
             present_mode: PresentMode::Fifo,
@@ -102,12 +483,39 @@ impl Renderer {
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
         };
-        surface.configure(&device, &config);
 
+        GpuTarget {
+            device,
+            queue,
+            surface: None,
+            config,
+            canvas: None,
+            adapter_info: adapter.get_info(),
+        }
+    }
+
+    /// Take over a connected target and create the pipelines and resources for it.
+    pub fn attach(&mut self, target: GpuTarget) {
+        let (format, width, height) = (Self::render_format(&target.config), target.config.width, target.config.height);
+        self.surface = target.surface;
+        self.config = Some(target.config);
+        self.canvas = target.canvas;
+        self.adapter_info = Some(target.adapter_info);
+        self.create_resources(target.device, target.queue, format, width, height);
+    }
+
+    // Uniforms, textures, pipelines and the text renderer shared by the canvas and headless paths
+    fn create_resources(&mut self, device: Device, queue: Queue, format: TextureFormat, width: u32, height: u32) {
         // Create single uniform buffer (16-byte aligned)
         let uniform_buffer = device.create_buffer(&BufferDescriptor {
             label: Some("Uniform Buffer"),
-            size: (4 + 64) * 4, // (4 base floats + 64 frequency bars) * 4 bytes each = 272 bytes, aligned to 16 bytes
+            size: std::mem::size_of::<Uniforms>() as BufferAddress, // 256 bytes, laid out as `struct Uniforms` in common.wgsl
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bars_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Bars Buffer"),
+            size: std::mem::size_of::<Bars>() as BufferAddress, // 512 bytes, laid out as `struct Bars` in common.wgsl
             usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
@@ -115,176 +523,1430 @@ impl Renderer {
         // Create bind group layout for uniforms
         let uniform_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
             label: Some("Uniform Bind Group Layout"),
-            entries: &[BindGroupLayoutEntry {
-                binding: 0,
-                visibility: ShaderStages::FRAGMENT,
-                ty: BindingType::Buffer {
-                    ty: BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
+            entries: &[
+                // Vertex stages read the uniforms too, for the goniometer trace
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::VERTEX_FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
                 },
-                count: None,
-            }],
+                BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: ShaderStages::VERTEX_FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
         });
 
-        // Create bind group for uniforms
-        let uniform_bind_group = device.create_bind_group(&BindGroupDescriptor {
-            label: Some("Uniform Bind Group"),
-            layout: &uniform_bind_group_layout,
-            entries: &[BindGroupEntry {
-                binding: 0,
-                resource: uniform_buffer.as_entire_binding(),
-            }],
+        // Waveform, background and gradient textures start as 1x1 placeholders until data is provided
+        let waveform_view = Self::create_waveform_view(&device, &queue, &self.waveform_data, &self.marker_positions);
+        let background_view = match self.pending_background.take() {
+            Some((rgba, image_width, image_height)) => Self::create_background_view(&device, &queue, &rgba, image_width, image_height),
+            None => Self::create_background_view(&device, &queue, &[0, 0, 0, 0], 1, 1),
+        };
+        let gradient_view = Self::create_gradient_view(&device, &queue, self.gradient.as_ref().map(|(gradient, _)| gradient));
+        let background_sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Background Sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
         });
 
+        let bar_smoothing = BarSmoothing::new(&device, &queue);
+
+        // Create bind group for uniforms and textures
+        let uniform_bind_group = Self::create_uniform_bind_group(
+            &device,
+            &uniform_bind_group_layout,
+            &uniform_buffer,
+            &bars_buffer,
+            &waveform_view,
+            &background_view,
+            &background_sampler,
+            &gradient_view,
+            bar_smoothing.view(),
+        );
+
         // Initialize uniform buffer: [time, padding, width, height]
         let uniform_data = [0.0f32, 0.0f32, width as f32, height as f32];
         queue.write_buffer(&uniform_buffer, 0, bytemuck::cast_slice(&uniform_data));
+        self.last_uniforms.clear();
+        self.last_bars.clear();
 
-        // Create render pipeline
-        let render_pipeline = self.create_render_pipeline(&device, config.format, &uniform_bind_group_layout);
+        // Pipelines for every visualizer mode
+        // Pipelines from a previous device can't be used with this one
+        self.pipeline_cache.clear();
+        let context = VisualizerContext {
+            device: &device,
+            queue: &queue,
+            format,
+            uniform_layout: &uniform_bind_group_layout,
+            pipelines: &self.pipeline_cache,
+        };
+        for visualizer in &mut self.visualizers {
+            visualizer.init(&context);
+        }
+        let crossfade = Crossfade::new(&device, format);
+        let feedback = Feedback::new(&device, format);
+        let fxaa = Fxaa::new(&device, format);
+        let scale_target = Crossfade::new(&device, format);
+        let text_renderer = TextRenderer::new(&device, &queue, format);
+
+        let context_lost = self.context_lost.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            // Dropping the device reports `Destroyed`, which isn't a loss
+            if reason != DeviceLostReason::Destroyed {
+                log!("GPU device lost: {}", message);
+                context_lost.store(true, Ordering::Relaxed);
+            }
+        });
 
         self.device = Some(device);
         self.queue = Some(queue);
-        self.surface = Some(surface);
-        self.config = Some(config);
-        self.render_pipeline = Some(render_pipeline);
-        self.canvas = Some(canvas);
         self.uniform_buffer = Some(uniform_buffer);
+        self.bars_buffer = Some(bars_buffer);
         self.uniform_bind_group = Some(uniform_bind_group);
+        self.uniform_bind_group_layout = Some(uniform_bind_group_layout);
+        self.bar_smoothing = Some(bar_smoothing);
+        self.waveform_view = Some(waveform_view);
+        self.background_view = Some(background_view);
+        self.background_sampler = Some(background_sampler);
+        self.gradient_view = Some(gradient_view);
+        self.crossfade = Some(crossfade);
+        self.feedback = Some(feedback);
+        self.fxaa = Some(fxaa);
+        self.scale_target = Some(scale_target);
+        self.text_renderer = Some(text_renderer);
+    }
 
-        Ok(())
+    #[allow(clippy::too_many_arguments)]
+    fn create_uniform_bind_group(
+        device: &Device,
+        layout: &BindGroupLayout,
+        uniform_buffer: &Buffer,
+        bars_buffer: &Buffer,
+        waveform_view: &TextureView,
+        background_view: &TextureView,
+        background_sampler: &Sampler,
+        gradient_view: &TextureView,
+        smoothed_bars_view: &TextureView,
+    ) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Uniform Bind Group"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(waveform_view),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(background_view),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::Sampler(background_sampler),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: BindingResource::TextureView(gradient_view),
+                },
+                BindGroupEntry {
+                    binding: 5,
+                    resource: BindingResource::TextureView(smoothed_bars_view),
+                },
+                BindGroupEntry {
+                    binding: 6,
+                    resource: bars_buffer.as_entire_binding(),
+                },
+            ],
+        })
     }
 
-    fn create_render_pipeline(&self, device: &Device, format: TextureFormat, uniform_bind_group_layout: &BindGroupLayout) -> RenderPipeline {
-        let shader = device.create_shader_module(ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: ShaderSource::Wgsl(include_str!("shaders/shader.wgsl").into()),
-        });
+    fn rebuild_bind_group(&mut self) {
+        if let Some(uniform_bind_group) = self.uniform_buffer.as_ref().zip(self.bars_buffer.as_ref()).and_then(|(uniform_buffer, bars_buffer)| self.bind_group_for(uniform_buffer, bars_buffer)) {
+            self.uniform_bind_group = Some(uniform_bind_group);
+        }
+        if let Some(bind_group) = self.split_view.as_ref().and_then(|split_view| self.bind_group_for(&split_view.uniform_buffer, &split_view.bars_buffer)) {
+            if let Some(split_view) = &mut self.split_view {
+                split_view.bind_group = bind_group;
+            }
+        }
+    }
 
-        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-            label: Some("Render Pipeline Layout"),
-            bind_group_layouts: &[uniform_bind_group_layout],
-            push_constant_ranges: &[],
-        });
+    // The uniform bind group with the current textures around `uniform_buffer` and `bars_buffer`,
+    // once initialized
+    fn bind_group_for(&self, uniform_buffer: &Buffer, bars_buffer: &Buffer) -> Option<BindGroup> {
+        if let (
+            Some(device),
+            Some(layout),
+            Some(waveform_view),
+            Some(background_view),
+            Some(background_sampler),
+            Some(gradient_view),
+            Some(bar_smoothing),
+        ) = (
+            &self.device,
+            &self.uniform_bind_group_layout,
+            &self.waveform_view,
+            &self.background_view,
+            &self.background_sampler,
+            &self.gradient_view,
+            &self.bar_smoothing,
+        ) {
+            Some(Self::create_uniform_bind_group(
+                device,
+                layout,
+                uniform_buffer,
+                bars_buffer,
+                waveform_view,
+                background_view,
+                background_sampler,
+                gradient_view,
+                bar_smoothing.view(),
+            ))
+        } else {
+            None
+        }
+    }
 
-        device.create_render_pipeline(&RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                buffers: &[],
-                compilation_options: Default::default(),
+    fn create_waveform_view(device: &Device, queue: &Queue, waveform_data: &[f32], marker_positions: &[f32]) -> TextureView {
+        // Waveform min/max pairs live in the first row of an RG32F texture; a placeholder column keeps the binding valid before audio is loaded
+        let columns = (waveform_data.len() / 2).max(1) as u32;
+        let mut texels: Vec<f32> = if waveform_data.len() >= 2 {
+            waveform_data[..columns as usize * 2].to_vec()
+        } else {
+            vec![0.0, 0.0]
+        };
+        // The second row flags the columns holding a marker tick
+        let mut ticks = vec![0.0; columns as usize * 2];
+        for &position in marker_positions {
+            let column = ((position * columns as f32) as usize).min(columns as usize - 1);
+            ticks[column * 2] = 1.0;
+        }
+        texels.extend(ticks);
+
+        let size = Extent3d {
+            width: columns,
+            height: 2,
+            depth_or_array_layers: 1,
+        };
+        let waveform_texture = device.create_texture(&TextureDescriptor {
+            label: Some("Waveform Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rg32Float,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            TexelCopyTextureInfo {
+                texture: &waveform_texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
             },
-            fragment: Some(FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(ColorTargetState {
-                    format,
-                    blend: Some(BlendState::REPLACE),
-                    write_mask: ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            primitive: PrimitiveState {
-                topology: PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: FrontFace::Ccw,
-                cull_mode: Some(Face::Back),
-                polygon_mode: PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
+            bytemuck::cast_slice(&texels),
+            TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(columns * 8), // 2 floats * 4 bytes per texel
+                rows_per_image: None,
             },
-            depth_stencil: None,
-            multisample: MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
+            size,
+        );
+        waveform_texture.create_view(&TextureViewDescriptor {
+            label: Some("Waveform Texture View"),
+            ..Default::default()
+        })
+    }
+
+    fn create_background_view(device: &Device, queue: &Queue, rgba: &[u8], width: u32, height: u32) -> TextureView {
+        Self::create_rgba_view(device, queue, "Background Texture", rgba, width, height)
+    }
+
+    fn create_gradient_view(device: &Device, queue: &Queue, gradient: Option<&Gradient>) -> TextureView {
+        match gradient {
+            Some(gradient) => Self::create_rgba_view(device, queue, "Gradient Texture", &gradient.rasterize(), GRADIENT_WIDTH, 1),
+            None => Self::create_rgba_view(device, queue, "Gradient Texture", &[0, 0, 0, 0], 1, 1),
+        }
+    }
+
+    fn create_rgba_view(device: &Device, queue: &Queue, label: &str, rgba: &[u8], width: u32, height: u32) -> TextureView {
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8Unorm,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            rgba,
+            TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(width * 4),
+                rows_per_image: None,
             },
-            multiview: None,
-            cache: None,
+            size,
+        );
+        texture.create_view(&TextureViewDescriptor {
+            label: Some(&format!("{} View", label)),
+            ..Default::default()
         })
     }
 
-    pub fn render(&mut self, time: f64, frequency_bars: &[f32], bin_size: usize) {
-        if let (Some(device), Some(queue), Some(surface), Some(render_pipeline), Some(uniform_buffer), Some(uniform_bind_group), Some(config)) = (
-            &self.device,
-            &self.queue,
-            &self.surface,
-            &self.render_pipeline,
-            &self.uniform_buffer,
-            &self.uniform_bind_group,
-            &self.config,
-        ) {
-            // Use actual elapsed time for accurate animation
+    pub fn set_waveform(&mut self, waveform_data: &[f32]) {
+        self.waveform_data = waveform_data.to_vec();
+        self.rebuild_waveform_view();
+    }
+
+    /// Draw ticks on the waveform strip at these positions (fractions of the track, 0.0-1.0).
+    pub fn set_marker_ticks(&mut self, positions: &[f32]) {
+        self.marker_positions = positions.to_vec();
+        self.rebuild_waveform_view();
+    }
+
+    fn rebuild_waveform_view(&mut self) {
+        if let (Some(device), Some(queue)) = (&self.device, &self.queue) {
+            self.waveform_view = Some(Self::create_waveform_view(device, queue, &self.waveform_data, &self.marker_positions));
+            self.rebuild_bind_group();
+        }
+    }
+
+    pub fn set_background_image(&mut self, rgba: &[u8], width: u32, height: u32) -> Result<(), String> {
+        if width == 0 || height == 0 || rgba.len() != (width as usize) * (height as usize) * 4 {
+            return Err(format!(
+                "Background image data is {} bytes, expected {} for {}x{} RGBA",
+                rgba.len(),
+                width as usize * height as usize * 4,
+                width,
+                height
+            ));
+        }
+
+        let max_dimension = self
+            .device
+            .as_ref()
+            .map_or(Limits::downlevel_webgl2_defaults().max_texture_dimension_2d, |device| device.limits().max_texture_dimension_2d);
+        if width > max_dimension || height > max_dimension {
+            return Err(format!(
+                "Background image {}x{} exceeds the maximum texture size of {}",
+                width, height, max_dimension
+            ));
+        }
+
+        self.background_enabled = true;
+        self.background_aspect = width as f32 / height as f32;
+        self.background_size = (width, height);
+
+        if let (Some(device), Some(queue)) = (&self.device, &self.queue) {
+            self.background_view = Some(Self::create_background_view(device, queue, rgba, width, height));
+            self.rebuild_bind_group();
+        } else {
+            self.pending_background = Some((rgba.to_vec(), width, height));
+        }
+
+        Ok(())
+    }
+
+    /// Color bars from `gradient` instead of the built-in hue cycle.
+    pub fn set_gradient(&mut self, gradient: Gradient, mode: GradientMode) {
+        if let (Some(device), Some(queue)) = (&self.device, &self.queue) {
+            self.gradient_view = Some(Self::create_gradient_view(device, queue, Some(&gradient)));
+            self.rebuild_bind_group();
+        }
+        self.gradient = Some((gradient, mode));
+    }
+
+    pub fn clear_gradient(&mut self) {
+        self.gradient = None;
+        if let (Some(device), Some(queue)) = (&self.device, &self.queue) {
+            self.gradient_view = Some(Self::create_gradient_view(device, queue, None));
+            self.rebuild_bind_group();
+        }
+    }
+
+    pub fn clear_background_image(&mut self) {
+        self.background_enabled = false;
+        self.pending_background = None;
+        self.background_size = (0, 0);
+
+        if let (Some(device), Some(queue)) = (&self.device, &self.queue) {
+            self.background_view = Some(Self::create_background_view(device, queue, &[0, 0, 0, 0], 1, 1));
+            self.rebuild_bind_group();
+        }
+    }
+
+    pub fn set_background_style(&mut self, blur: f32, dim: f32) {
+        self.background_blur = blur.clamp(0.0, 0.1);
+        self.background_dim = dim.clamp(0.0, 1.0);
+    }
+
+    pub fn set_waveform_strip(&mut self, enabled: bool, height: f32) {
+        self.waveform_enabled = enabled;
+        self.waveform_height = height.clamp(0.02, 0.5);
+    }
+
+    pub fn set_highlighted_bar(&mut self, bar_index: Option<usize>) {
+        self.highlighted_bar = bar_index;
+    }
+
+    pub fn set_soloed_bar(&mut self, bar_index: Option<usize>) {
+        self.soloed_bar = bar_index;
+    }
+
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    pub fn set_layout(&mut self, orientation: Orientation, hud_position: HudPosition) {
+        self.orientation = orientation;
+        self.hud_position = hud_position;
+    }
+
+    pub fn set_bar_layout(&mut self, arrangement: BarArrangement, reversed: bool) {
+        self.bar_arrangement = arrangement;
+        self.bars_reversed = reversed;
+    }
+
+    /// Turn the feedback trails on with the given settings, or off with `None`.
+    pub fn set_feedback(&mut self, settings: Option<FeedbackSettings>) {
+        self.feedback_settings = settings;
+    }
+
+    /// Rotate (radians) and zoom the visualization about the canvas center and shift its hues
+    /// (in turns). The background and HUD are not affected.
+    #[cfg_attr(not(feature = "presets"), allow(dead_code))]
+    pub fn set_view_transform(&mut self, rotation: f32, zoom: f32, hue_shift: f32) {
+        self.view_transform = [rotation, zoom, hue_shift];
+    }
+
+    /// Current beat effect levels: flash, background pulse and the shake offset as a fraction of
+    /// the canvas height.
+    pub fn set_beat_levels(&mut self, levels: [f32; 4]) {
+        self.beat_levels = levels;
+    }
+
+    /// Current pitch-class energies (C, C#, ... B), 0.0-1.0.
+    pub fn set_chroma(&mut self, chroma: [f32; PITCH_CLASSES]) {
+        self.chroma = chroma;
+    }
+
+    /// Smooth the bars on the GPU: the bars passed to `render` are then targets the drawn levels
+    /// ease towards by the `set_smoothing_factor` factor each frame, instead of final levels.
+    pub fn set_gpu_smoothing(&mut self, enabled: bool) {
+        self.gpu_smoothing = enabled;
+    }
+
+    /// Fraction of the way the GPU-smoothed bars move towards their targets in the next frame.
+    pub fn set_smoothing_factor(&mut self, factor: f32) {
+        self.smoothing_factor = factor;
+    }
+
+    /// Set the GPU-smoothed levels outright, e.g. to start an export from silence or to carry on
+    /// from the CPU-smoothed bars.
+    pub fn reset_bar_smoothing(&self, bars: &[f32], right_bars: &[f32]) {
+        if let (Some(queue), Some(bar_smoothing)) = (&self.queue, &self.bar_smoothing) {
+            bar_smoothing.reset(queue, bars, right_bars);
+        }
+    }
+
+    /// Current right channel bars for the stereo mode; empty when there is no right channel, in
+    /// which case the mono bars are drawn for both.
+    pub fn set_right_bars(&mut self, bars: &[f32]) {
+        self.right_bars.clear();
+        self.right_bars.extend_from_slice(bars);
+    }
+
+    /// (left, right) sample pairs of the current frame, -1.0..1.0, traced by the goniometer.
+    pub fn set_scope_samples(&mut self, samples: &[[f32; 2]]) {
+        self.scope_samples.clear();
+        self.scope_samples.extend_from_slice(samples);
+    }
+
+    /// Correlation (-1.0 to 1.0) and width (0.0-1.0) for the HUD stereo meter, or `None` to hide it.
+    pub fn set_stereo_meter(&mut self, meter: Option<[f32; 2]>) {
+        self.stereo_meter = meter;
+    }
+
+    /// Cents from the nearest note (-50.0 to 50.0) and its pitch class for the tuner mode, or
+    /// `None` while nothing is pitched.
+    pub fn set_tuner(&mut self, reading: Option<[f32; 2]>) {
+        self.tuner = reading;
+    }
+
+    /// dB between gridlines and their opacity (0.0-1.0) for the axis drawn under the bars, or
+    /// `None` to hide the gridlines.
+    pub fn set_axis_grid(&mut self, grid: Option<[f32; 2]>) {
+        self.axis_grid = grid;
+    }
+
+    /// Replace the text drawn on top of the visualization from the next frame on.
+    pub fn set_text_items(&mut self, items: Vec<TextItem>) {
+        self.text_items = items;
+    }
+
+    /// Pixels at the top of a target `height` pixels tall covered by the HUD strip, for positioning overlay text.
+    pub fn overlay_top_inset(&self, height: u32) -> f32 {
+        match self.hud_position {
+            HudPosition::Top if self.waveform_enabled && self.waveform_data.len() >= 2 => height as f32 * self.waveform_height,
+            _ => 0.0,
+        }
+    }
+
+    /// Pixel rows of a target `height` pixels tall covered by the waveform strip, `None` while
+    /// it isn't shown.
+    pub fn waveform_strip_rows(&self, height: u32) -> Option<std::ops::Range<f32>> {
+        if !self.waveform_enabled || self.waveform_data.len() < 2 {
+            return None;
+        }
+        let strip = height as f32 * self.waveform_height;
+        Some(match self.hud_position {
+            HudPosition::Top => 0.0..strip,
+            HudPosition::Bottom => height as f32 - strip..height as f32,
+        })
+    }
+
+    /// Switch the visualization mode drawn from the next frame on.
+    pub fn set_visualizer_mode(&mut self, name: &str) -> Result<(), String> {
+        let index = self.visualizer_index(name)?;
+        if index != self.active_visualizer {
+            // Switching mid-fade starts a new fade from the mode that was fading in
+            self.transition = (self.transition_duration > 0.0 && self.device.is_some())
+                .then(|| ModeTransition::new(self.active_visualizer));
+            self.active_visualizer = index;
+        }
+        Ok(())
+    }
+
+    /// Replace a mode's shader at runtime with `source`, a fragment shader that
+    /// `visualizer::compile_mode_shader` has accepted.
+    #[cfg(feature = "dev")]
+    pub fn hot_swap_shader(&mut self, mode: &str, source: String) -> Result<(), String> {
+        let index = self.visualizer_index(mode)?;
+        let visualizer = &mut self.visualizers[index];
+        visualizer.replace_shader(source.into());
+
+        // Before init the new source is simply picked up by `create_resources`
+        if let (Some(device), Some(queue), Some(uniform_layout), Some(config)) =
+            (&self.device, &self.queue, &self.uniform_bind_group_layout, &self.config)
+        {
+            visualizer.init(&VisualizerContext {
+                device,
+                queue,
+                format: Self::render_format(config),
+                uniform_layout,
+                pipelines: &self.pipeline_cache,
+            });
+        }
+        log!("Hot-swapped shader for '{}'", mode);
+        Ok(())
+    }
+
+    fn visualizer_index(&self, name: &str) -> Result<usize, String> {
+        self.visualizers
+            .iter()
+            .position(|visualizer| visualizer.name() == name)
+            .ok_or_else(|| format!("Unknown visualizer mode '{}' (available: {})", name, self.visualizer_modes().join(", ")))
+    }
+
+    pub fn set_antialiasing(&mut self, antialiasing: Antialiasing) {
+        self.antialiasing = antialiasing;
+    }
+
+    /// Draw at `scale` times the target's resolution and stretch the result onto it.
+    pub fn set_render_scale(&mut self, scale: f32) {
+        self.render_scale = scale;
+    }
+
+    /// Share the target with a second view in mode `mode`, laid out by `layout`, showing the
+    /// bars given to `set_split_bars`; `None` goes back to a single view.
+    pub fn set_split_view(&mut self, split: Option<(SplitLayout, &str)>) -> Result<(), String> {
+        self.split = split.map(|(layout, mode)| self.visualizer_index(mode).map(|index| (layout, index))).transpose()?;
+        Ok(())
+    }
+
+    pub fn set_split_bars(&mut self, bars: &[f32]) {
+        self.split_bars.clear();
+        self.split_bars.extend_from_slice(bars);
+    }
+
+    /// Crossfade mode switches over `duration_ms` (0 for a hard cut).
+    pub fn set_mode_transition(&mut self, duration_ms: f64, easing: Easing) {
+        self.transition_duration = duration_ms.max(0.0) / 1000.0;
+        self.transition_easing = easing;
+        if self.transition_duration == 0.0 {
+            self.transition = None;
+        }
+    }
+
+    pub fn visualizer_mode(&self) -> &'static str {
+        self.visualizers[self.active_visualizer].name()
+    }
+
+    pub fn visualizer_modes(&self) -> Vec<&'static str> {
+        self.visualizers.iter().map(|visualizer| visualizer.name()).collect()
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    pub fn bar_layout(&self) -> (BarArrangement, bool) {
+        (self.bar_arrangement, self.bars_reversed)
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    pub fn set_bar_style(&mut self, style: BarStyle) {
+        self.bar_style = style;
+    }
+
+    pub fn bar_style(&self) -> BarStyle {
+        self.bar_style
+    }
+
+    pub fn antialiasing(&self) -> Antialiasing {
+        self.antialiasing
+    }
+
+    pub fn render_scale(&self) -> f32 {
+        self.render_scale
+    }
+
+    pub fn canvas(&self) -> Option<&HtmlCanvasElement> {
+        self.canvas.as_ref()
+    }
+
+    /// Graphics backend and adapter name, e.g. "Gl (ANGLE ...)", for diagnostics.
+    pub fn backend_description(&self) -> String {
+        match &self.adapter_info {
+            Some(info) if info.name.is_empty() => format!("{:?}", info.backend),
+            Some(info) => format!("{:?} ({})", info.backend, info.name),
+            None => "uninitialized".to_string(),
+        }
+    }
+
+    /// Whether the device or the canvas context has been lost since the last call.
+    pub fn take_context_lost(&self) -> bool {
+        self.context_lost.swap(false, Ordering::Relaxed)
+    }
+
+    /// Backend and adapter class, leaving out the adapter name, for anonymous reporting.
+    pub fn adapter_class(&self) -> (String, String) {
+        match &self.adapter_info {
+            Some(info) => (format!("{:?}", info.backend), format!("{:?}", info.device_type)),
+            None => ("uninitialized".to_string(), "unknown".to_string()),
+        }
+    }
+
+    /// Rough GPU memory use in bytes: surface, offscreen target and data textures.
+    pub fn memory_estimate(&self) -> usize {
+        let surface = self.config.as_ref().map_or(0, |config| {
+            // Double-buffered surface
+            config.width as usize * config.height as usize * 4 * 2
+        });
+        let offscreen = self
+            .offscreen_texture
+            .as_ref()
+            .map_or(0, |texture| texture.width() as usize * texture.height() as usize * 4);
+        let background = self.background_size.0 as usize * self.background_size.1 as usize * 4;
+        // Two rows: the (min, max) pairs and the marker tick flags
+        let waveform = self.waveform_data.len() * 2 * std::mem::size_of::<f32>();
+        surface + offscreen + background + waveform
+    }
+
+    pub fn surface_size(&self) -> Option<(u32, u32)> {
+        self.config.as_ref().map(|config| (config.width, config.height))
+    }
+
+    /// Format every pass draws in: the surface's, or its sRGB view format.
+    pub fn target_format(&self) -> Option<TextureFormat> {
+        self.config.as_ref().map(Self::render_format)
+    }
+
+    fn render_format(config: &SurfaceConfiguration) -> TextureFormat {
+        config.view_formats.first().copied().unwrap_or(config.format)
+    }
+
+    pub fn render(&mut self, time: f64, frequency_bars: &[f32], bin_size: usize, playhead: f64) -> Result<(), String> {
+        let Some((width, height)) = self.surface_size() else {
+            return Err("Renderer not initialized".to_string());
+        };
+        if !self.update_uniforms(time, frequency_bars, bin_size, playhead, width, height) {
+            return Err("Renderer not initialized".to_string());
+        }
+        self.present(|view| self.draw_to_view(view))
+    }
+
+    /// Render a still frame for a paused player. With more than one sample, frames at times
+    /// spread over `shutter` seconds from `time` are averaged, so whatever the mode animates is
+    /// motion-blurred instead of frozen mid-movement.
+    pub fn render_poster(
+        &mut self,
+        time: f64,
+        frequency_bars: &[f32],
+        bin_size: usize,
+        playhead: f64,
+        samples: u32,
+        shutter: f64,
+    ) -> Result<(), String> {
+        if samples <= 1 {
+            return self.render(time, frequency_bars, bin_size, playhead);
+        }
+        let (Some(device), Some(config)) = (&self.device, &self.config) else {
+            return Err("Renderer not initialized".to_string());
+        };
+        let (width, height, format) = (config.width, config.height, Self::render_format(config));
+        let (mut sample_target, mut accumulator) = self
+            .poster_targets
+            .take()
+            .unwrap_or_else(|| (Crossfade::new(device, format), Crossfade::new(device, format)));
+        sample_target.prepare(device, width, height);
+        accumulator.prepare(device, width, height);
+        let (Some(sample_view), Some(accumulator_view)) = (sample_target.view(), accumulator.view()) else {
+            return Err("Poster targets not available".to_string());
+        };
+
+        for sample in 0..samples {
+            let sample_time = time + shutter * sample as f64 / (samples - 1) as f64;
+            if !self.update_uniforms(sample_time, frequency_bars, bin_size, playhead, width, height) {
+                return Err("Renderer not initialized".to_string());
+            }
+            self.draw_to_view(sample_view);
+
+            let (Some(device), Some(queue)) = (&self.device, &self.queue) else {
+                return Err("Renderer not initialized".to_string());
+            };
+            let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Poster Accumulation Encoder"),
+            });
+            {
+                // Running average: sample k is blended in with weight 1 / (k + 1)
+                let load = if sample == 0 { LoadOp::Clear(Color::TRANSPARENT) } else { LoadOp::Load };
+                let mut render_pass = Self::begin_pass(&mut encoder, "Poster Accumulation Pass", accumulator_view, load);
+                sample_target.composite(&mut render_pass, 1.0 - 1.0 / (sample + 1) as f32);
+            }
+            queue.submit(std::iter::once(encoder.finish()));
+        }
+
+        let result = self.present(|view| {
+            let (Some(device), Some(queue)) = (&self.device, &self.queue) else {
+                return;
+            };
+            let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Poster Present Encoder"),
+            });
+            {
+                let mut render_pass = Self::begin_pass(&mut encoder, "Poster Present Pass", view, LoadOp::Clear(Color::TRANSPARENT));
+                accumulator.composite(&mut render_pass, 0.0);
+            }
+            queue.submit(std::iter::once(encoder.finish()));
+        });
+
+        // The poster is drawn once per pause, so don't keep two extra surface-sized textures around
+        sample_target.release();
+        accumulator.release();
+        self.poster_targets = Some((sample_target, accumulator));
+        result
+    }
+
+    // Acquire the next surface texture, let `draw` fill it and present it
+    fn present(&self, draw: impl FnOnce(&TextureView)) -> Result<(), String> {
+        if let (Some(surface), Some(device), Some(config)) = (&self.surface, &self.device, &self.config) {
+            let output = match surface.get_current_texture() {
+                Ok(output) => output,
+                // The surface changed under us (context loss, resize in flight): reconfigure and skip this frame
+                Err(error @ (SurfaceError::Lost | SurfaceError::Outdated)) => {
+                    if error == SurfaceError::Lost {
+                        self.context_lost.store(true, Ordering::Relaxed);
+                    }
+                    surface.configure(device, config);
+                    return Ok(());
+                }
+                Err(SurfaceError::Timeout) => return Ok(()),
+                Err(e) => return Err(format!("Failed to acquire surface texture: {:?}", e)),
+            };
+            let view = output
+                .texture
+                .create_view(&TextureViewDescriptor {
+                    label: Some("Surface Texture View"),
+                    format: Some(Self::render_format(config)),
+                    ..Default::default()
+                });
+
+            draw(&view);
+            output.present();
+        }
+        Ok(())
+    }
+
+    // Upload the span of `uniform_data` that differs from the previous upload; most frames only
+    // the time and a few levels change
+    fn write_changed_uniforms(queue: &Queue, uniform_buffer: &Buffer, uniform_data: &[f32], last_uniforms: &mut Vec<f32>) {
+        let changed = if last_uniforms.len() == uniform_data.len() {
+            let first = uniform_data.iter().zip(last_uniforms.iter()).position(|(new, old)| new.to_bits() != old.to_bits());
+            let last = uniform_data.iter().zip(last_uniforms.iter()).rposition(|(new, old)| new.to_bits() != old.to_bits());
+            first.zip(last).map(|(first, last)| first..last + 1)
+        } else {
+            Some(0..uniform_data.len())
+        };
+        if let Some(span) = changed {
+            let offset = (span.start * std::mem::size_of::<f32>()) as BufferAddress;
+            queue.write_buffer(uniform_buffer, offset, bytemuck::cast_slice(&uniform_data[span]));
+            last_uniforms.clear();
+            last_uniforms.extend_from_slice(uniform_data);
+        }
+    }
+
+    fn update_uniforms(&mut self, time: f64, frequency_bars: &[f32], bin_size: usize, playhead: f64, width: u32, height: u32) -> bool {
+        // Text is laid out for the target; everything else is drawn at the render scale
+        let (text_width, text_height) = (width, height);
+        let (width, height) = self.advance_render_scale(width, height);
+        self.advance_split(width, height);
+        // With a split view the main view (and its crossfade) is drawn at the size of its viewport
+        let (view_width, view_height) = self.split_view.as_ref().map_or((width, height), |split_view| {
+            let [(_, _, first_width, first_height), _] = split_view.layout.viewports(width, height);
+            (first_width, first_height)
+        });
+        self.advance_transition(time, view_width, view_height);
+        self.advance_feedback(time, width, height);
+        self.advance_antialiasing(width, height);
+        if let (Some(queue), Some(uniform_buffer), Some(bars_buffer)) = (&self.queue, &self.uniform_buffer, &self.bars_buffer) {
+            // Use actual elapsed time for accurate animation, wrapped in f64 before narrowing to f32
             self.frame_count += 1;
-            let elapsed_time = time as f32;
+            let elapsed_time = time.rem_euclid(TIME_WRAP_PERIOD) as f32;
             
-            // Create uniform data with time, bin_size, resolution, and frequency bars
-            let mut uniform_data = vec![elapsed_time, bin_size as f32, config.width as f32, config.height as f32];
-            
-            // Add frequency bars (pad to 64 bars for shader compatibility)
-            let mut bars = vec![0.0f32; 64];
-            for (i, &bar) in frequency_bars.iter().take(64).enumerate() {
-                bars[i] = bar;
-            }
+            // Frequency bars, mono and right channel padded to 64 for shader compatibility. Bars
+            // smoothed on the GPU are read from the smoothing texture instead and stay zero here.
+            let right_bars = if self.right_bars.is_empty() { frequency_bars } else { &self.right_bars };
+            let bars = if self.gpu_smoothing { Bars::zeroed() } else { Bars::padded(frequency_bars, right_bars) };
             
             // Debug logging every 120 frames (about 2 seconds)
-            if self.frame_count % 120 == 0 {
-                web_sys::console::log_1(&format!("frame: {}, time: {:.2}, width: {}, height: {}, bin_size: {}, bars[0]: {:.2}", self.frame_count, elapsed_time, config.width, config.height, bin_size, bars[0]).into());
+            if self.frame_count.is_multiple_of(120) {
+                log!("frame: {}, time: {:.2}, width: {}, height: {}, bin_size: {}, bars[0]: {:.2}", self.frame_count, elapsed_time, width, height, bin_size, bars.mono[0]);
             }
+
+            let waveform_enabled = self.waveform_enabled && self.waveform_data.len() >= 2;
+            let highlighted = self.highlighted_bar.map_or(-1.0, |index| index as f32);
+            let soloed = self.soloed_bar.map_or(-1.0, |index| index as f32);
+            let style = self.bar_style;
+            let gradient_mode = self.gradient.as_ref().map_or(0.0, |(_, mode)| mode.shader_value());
+            let srgb_target = self.target_format().is_some_and(|format| format.is_srgb());
+            let [rotation, zoom, hue_shift] = self.view_transform;
+            let [correlation, stereo_width] = self.stereo_meter.unwrap_or_default();
+            let [cents, pitch_class] = self.tuner.unwrap_or_default();
+            let [db_step, grid_opacity] = self.axis_grid.unwrap_or_default();
+            let uniforms = Uniforms {
+                time: elapsed_time,
+                bin_size: bin_size as f32,
+                resolution: [view_width as f32, view_height as f32],
+                // [enabled, strip height, playhead (0-1), column count]
+                waveform: [
+                    if waveform_enabled { 1.0 } else { 0.0 },
+                    self.waveform_height,
+                    playhead.clamp(0.0, 1.0) as f32,
+                    (self.waveform_data.len() / 2) as f32,
+                ],
+                // [highlighted bar index, soloed bar index (-1 for none), padding...]
+                interaction: [highlighted, soloed, 0.0, 0.0],
+                // [enabled, blur radius, dim amount, image aspect ratio]
+                background: [
+                    if self.background_enabled { 1.0 } else { 0.0 },
+                    self.background_blur,
+                    self.background_dim,
+                    self.background_aspect,
+                ],
+                // [orientation, HUD position, bar arrangement, reversed frequency order]
+                bar_layout: [
+                    if self.orientation == Orientation::Horizontal { 1.0 } else { 0.0 },
+                    if self.hud_position == HudPosition::Bottom { 1.0 } else { 0.0 },
+                    self.bar_arrangement.shader_value(),
+                    if self.bars_reversed { 1.0 } else { 0.0 },
+                ],
+                // [gap fraction, corner radius, minimum height, gradient mode (0 for none)]
+                bar_style: [style.gap_fraction, style.corner_radius, style.min_height, gradient_mode],
+                // [light scheme, high contrast, padding...]
+                theme: [
+                    if self.theme.light { 1.0 } else { 0.0 },
+                    if self.theme.high_contrast { 1.0 } else { 0.0 },
+                    0.0,
+                    0.0,
+                ],
+                // [time wrap period, GPU-smoothed bars, sRGB target, padding]
+                clock: [
+                    TIME_WRAP_PERIOD as f32,
+                    if self.gpu_smoothing { 1.0 } else { 0.0 },
+                    if srgb_target { 1.0 } else { 0.0 },
+                    0.0,
+                ],
+                // [rotation, zoom, hue shift, padding]
+                transform: [rotation, zoom, hue_shift, 0.0],
+                // [flash, background pulse, shake offset x, shake offset y]
+                beat: self.beat_levels,
+                // 12 pitch-class energies, C first
+                chroma: self.chroma,
+                // [enabled, correlation, width, padding]
+                stereo_meter: [if self.stereo_meter.is_some() { 1.0 } else { 0.0 }, correlation, stereo_width, 0.0],
+                // [pitched, cents, pitch class, padding]
+                tuner: [if self.tuner.is_some() { 1.0 } else { 0.0 }, cents, pitch_class, 0.0],
+                // [gridlines shown, dB step, opacity, padding]
+                axis: [if self.axis_grid.is_some() { 1.0 } else { 0.0 }, db_step, grid_opacity, 0.0],
+            };
             
-            uniform_data.extend(bars);
-            
-            queue.write_buffer(uniform_buffer, 0, bytemuck::cast_slice(&uniform_data));
-            let output = surface.get_current_texture().unwrap();
-            let view = output
-                .texture
-                .create_view(&TextureViewDescriptor::default());
+            Self::write_changed_uniforms(queue, uniform_buffer, uniforms.as_floats(), &mut self.last_uniforms);
+            Self::write_changed_uniforms(queue, bars_buffer, bars.as_floats(), &mut self.last_bars);
+            if let Some(split_view) = &mut self.split_view {
+                let [_, (_, _, second_width, second_height)] = split_view.layout.viewports(width, height);
+                let split_uniforms = Self::split_uniforms(&uniforms, second_width, second_height);
+                Self::write_changed_uniforms(queue, &split_view.uniform_buffer, split_uniforms.as_floats(), &mut split_view.last_uniforms);
+                // The stereo modes show the same bars on both sides
+                let split_bars = Bars::padded(&self.split_bars, &self.split_bars);
+                Self::write_changed_uniforms(queue, &split_view.bars_buffer, split_bars.as_floats(), &mut split_view.last_bars);
+            }
+            if let (true, Some(device), Some(bar_smoothing)) = (self.gpu_smoothing, &self.device, &self.bar_smoothing) {
+                bar_smoothing.advance(device, queue, frequency_bars, right_bars, self.smoothing_factor);
+            }
+            let frame = VisualizerFrame {
+                bars: frequency_bars,
+                bin_size,
+                time,
+                scope: &self.scope_samples,
+            };
+            for visualizer in &mut self.visualizers {
+                visualizer.update(queue, &frame);
+            }
+            if let Some(text_renderer) = &mut self.text_renderer {
+                let theme = self.theme;
+                let items: Vec<TextItem> = self
+                    .text_items
+                    .iter()
+                    .map(|item| item.clone().with_color(theme.text_color(item.color)))
+                    .collect();
+                text_renderer.prepare(queue, &items, text_width, text_height);
+            }
+
+            true
+        } else {
+            false
+        }
+    }
+
+    // The main view's uniforms with the second view's size. Its bars are always uploaded, since
+    // the GPU smoothing texture holds the main view's.
+    fn split_uniforms(uniforms: &Uniforms, width: u32, height: u32) -> Uniforms {
+        Uniforms {
+            resolution: [width as f32, height as f32],
+            clock: [uniforms.clock[0], 0.0, uniforms.clock[2], uniforms.clock[3]],
+            ..*uniforms
+        }
+    }
+
+    // Create or drop the split view to match its settings and keep its textures at the size
+    // about to be drawn
+    fn advance_split(&mut self, width: u32, height: u32) {
+        let Some((layout, mode)) = self.split else {
+            self.split_view = None;
+            return;
+        };
+        if self.split_view.is_none() {
+            if let (Some(device), Some(config)) = (&self.device, &self.config) {
+                self.split_view = SplitView::new(device, Self::render_format(config), layout, mode, |uniform_buffer, bars_buffer| {
+                    self.bind_group_for(uniform_buffer, bars_buffer)
+                });
+            }
+        }
+        if let (Some(split_view), Some(device)) = (&mut self.split_view, &self.device) {
+            split_view.layout = layout;
+            split_view.mode = mode;
+            split_view.prepare(device, width, height);
+        }
+    }
 
+    // Step the mode crossfade and keep its intermediate texture at the size about to be drawn
+    fn advance_transition(&mut self, time: f64, width: u32, height: u32) {
+        if let Some(transition) = &mut self.transition {
+            if !transition.advance(time, self.transition_duration, self.transition_easing) {
+                self.transition = None;
+            }
+        }
+        if let (Some(crossfade), Some(device)) = (&mut self.crossfade, &self.device) {
+            if self.transition.is_some() {
+                crossfade.prepare(device, width, height);
+            } else {
+                crossfade.release();
+            }
+        }
+    }
+
+    // Step the feedback trails and keep their textures at the size about to be drawn
+    fn advance_feedback(&mut self, time: f64, width: u32, height: u32) {
+        let (Some(feedback), Some(device), Some(queue)) = (&mut self.feedback, &self.device, &self.queue) else {
+            return;
+        };
+        match self.feedback_settings {
+            Some(settings) => feedback.prepare(device, queue, width, height, time, settings, self.theme.light),
+            None => feedback.release(),
+        }
+    }
+
+    // Keep the scale target at the scaled size of a `width` x `height` target, and return that size
+    fn advance_render_scale(&mut self, width: u32, height: u32) -> (u32, u32) {
+        let (Some(scale_target), Some(device)) = (&mut self.scale_target, &self.device) else {
+            return (width, height);
+        };
+        if self.render_scale == 1.0 {
+            scale_target.release();
+            return (width, height);
+        }
+        let max_dimension = device.limits().max_texture_dimension_2d;
+        let scale = |size: u32| ((size as f32 * self.render_scale).round() as u32).clamp(1, max_dimension);
+        let (scaled_width, scaled_height) = (scale(width), scale(height));
+        scale_target.prepare(device, scaled_width, scaled_height);
+        (scaled_width, scaled_height)
+    }
+
+    // Keep the FXAA texture at the size about to be drawn while antialiasing is on
+    fn advance_antialiasing(&mut self, width: u32, height: u32) {
+        let (Some(fxaa), Some(device)) = (&mut self.fxaa, &self.device) else {
+            return;
+        };
+        match self.antialiasing {
+            Antialiasing::Fxaa => fxaa.prepare(device, width, height),
+            Antialiasing::Off => fxaa.release(),
+        }
+    }
+
+    fn begin_pass<'a>(encoder: &'a mut CommandEncoder, label: &str, view: &TextureView, load: LoadOp<Color>) -> RenderPass<'a> {
+        encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        })
+    }
+
+    // Encode and submit the visualization pass into any view with the surface format
+    fn draw_to_view(&self, view: &TextureView) {
+        if let (Some(device), Some(queue), Some(uniform_bind_group)) = (&self.device, &self.queue, &self.uniform_bind_group) {
             let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
+                label: Some("Visualizer Frame Encoder"),
             });
+            if GPU_DEBUG {
+                encoder.push_debug_group(&format!("Visualizer Frame {}", self.frame_count));
+            }
+
+            // During a mode switch the outgoing mode is drawn separately and blended in below
+            let crossfade = match (&self.transition, &self.crossfade) {
+                (Some(transition), Some(crossfade)) => crossfade.view().map(|fade_view| (transition, crossfade, fade_view)),
+                _ => None,
+            };
+            if let Some((transition, _, fade_view)) = crossfade {
+                let mut render_pass = Self::begin_pass(&mut encoder, "Visualizer Transition Pass", fade_view, LoadOp::Clear(Color::TRANSPARENT));
+                let outgoing = &self.visualizers[transition.from];
+                if GPU_DEBUG {
+                    render_pass.push_debug_group(outgoing.name());
+                }
+                outgoing.encode(&mut render_pass, uniform_bind_group);
+                if GPU_DEBUG {
+                    render_pass.pop_debug_group();
+                }
+            }
+
+            // With a split view the main view is drawn into the split view's first texture and the
+            // other view into its second, and both are copied into their viewports below
+            let split = self.split_view.as_ref().and_then(|split_view| split_view.views().map(|views| (split_view, views)));
+            if let Some((split_view, (_, second_view))) = split {
+                let mut render_pass = Self::begin_pass(&mut encoder, "Split View Pass", second_view, LoadOp::Clear(Color::TRANSPARENT));
+                let visualizer = &self.visualizers[split_view.mode];
+                if GPU_DEBUG {
+                    render_pass.push_debug_group(visualizer.name());
+                }
+                visualizer.encode(&mut render_pass, &split_view.bind_group);
+                if GPU_DEBUG {
+                    render_pass.pop_debug_group();
+                }
+            }
+
+            // With a render scale everything but the text is drawn into the scale target, which is
+            // stretched onto the view under the text at the end
+            let scale = self.scale_target.as_ref().and_then(|scale_target| scale_target.view().map(|scale_view| (scale_target, scale_view)));
+            let scaled_view = scale.map_or(view, |(_, scale_view)| scale_view);
+
+            // With antialiasing everything but the text is drawn into the FXAA texture, which is
+            // smoothed onto the view (or scale target) under the text
+            let fxaa = self.fxaa.as_ref().and_then(|fxaa| fxaa.view().map(|fxaa_view| (fxaa, fxaa_view)));
+            let output_view = fxaa.map_or(scaled_view, |(_, fxaa_view)| fxaa_view);
+            let text_renderer = self.text_renderer.as_ref().filter(|_| fxaa.is_none() && scale.is_none());
 
+            // With feedback trails the modes are drawn into the feedback frame texture instead, and
+            // the combined result is copied to the output under the text
+            let feedback = self.feedback.as_ref().and_then(|feedback| feedback.frame_view().map(|frame_view| (feedback, frame_view)));
+            let mode_view = feedback.map_or(output_view, |(_, frame_view)| frame_view);
             {
-                let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-                    label: Some("Render Pass"),
-                    color_attachments: &[Some(RenderPassColorAttachment {
-                        view: &view,
-                        resolve_target: None,
-                        ops: Operations {
-                            load: LoadOp::Clear(Color {
-                                r: 0.0,
-                                g: 0.0,
-                                b: 0.0,
-                                a: 0.0,
-                            }),
-                            store: StoreOp::Store,
-                        },
-                    })],
-                    depth_stencil_attachment: None,
-                    occlusion_query_set: None,
-                    timestamp_writes: None,
-                });
+                let main_view = split.map_or(mode_view, |(_, (first_view, _))| first_view);
+                let mut render_pass = Self::begin_pass(&mut encoder, "Visualizer Pass", main_view, LoadOp::Clear(Color::TRANSPARENT));
+
+                let visualizer = &self.visualizers[self.active_visualizer];
+                if GPU_DEBUG {
+                    render_pass.push_debug_group(visualizer.name());
+                }
+                visualizer.encode(&mut render_pass, uniform_bind_group);
+                if GPU_DEBUG {
+                    render_pass.pop_debug_group();
+                }
+
+                if let Some((transition, crossfade, _)) = crossfade {
+                    if GPU_DEBUG {
+                        render_pass.insert_debug_marker("Crossfade");
+                    }
+                    crossfade.composite(&mut render_pass, transition.progress());
+                }
+
+                // Text overlay on top of the visualization
+                if let (None, None, Some(text_renderer)) = (feedback, split, text_renderer) {
+                    text_renderer.draw(&mut render_pass);
+                }
+            }
+
+            if let Some((split_view, _)) = split {
+                let mut render_pass = Self::begin_pass(&mut encoder, "Split Composite Pass", mode_view, LoadOp::Clear(Color::TRANSPARENT));
+                split_view.composite(&mut render_pass);
+                if let (None, Some(text_renderer)) = (feedback, text_renderer) {
+                    text_renderer.draw(&mut render_pass);
+                }
+            }
 
-                render_pass.set_pipeline(render_pipeline);
-                render_pass.set_bind_group(0, uniform_bind_group, &[]);
-                render_pass.draw(0..3, 0..1); // Draw a triangle
+            if let Some((feedback, _)) = feedback {
+                feedback.accumulate(&mut encoder);
+                let mut render_pass = Self::begin_pass(&mut encoder, "Feedback Composite Pass", output_view, LoadOp::Clear(Color::TRANSPARENT));
+                feedback.composite(&mut render_pass);
+                // Text stays out of the trails
+                if let Some(text_renderer) = text_renderer {
+                    text_renderer.draw(&mut render_pass);
+                }
             }
 
+            if let Some((fxaa, _)) = fxaa {
+                let mut render_pass = Self::begin_pass(&mut encoder, "FXAA Pass", scaled_view, LoadOp::Clear(Color::TRANSPARENT));
+                fxaa.resolve(&mut render_pass);
+                // The pixel font is drawn crisp, after the smoothing
+                if let (None, Some(text_renderer)) = (scale, &self.text_renderer) {
+                    text_renderer.draw(&mut render_pass);
+                }
+            }
+
+            if let Some((scale_target, _)) = scale {
+                let mut render_pass = Self::begin_pass(&mut encoder, "Render Scale Pass", view, LoadOp::Clear(Color::TRANSPARENT));
+                scale_target.composite(&mut render_pass, 0.0);
+                // Text at the target's resolution
+                if let Some(text_renderer) = &self.text_renderer {
+                    text_renderer.draw(&mut render_pass);
+                }
+            }
+
+            if GPU_DEBUG {
+                encoder.pop_debug_group();
+            }
             queue.submit(std::iter::once(encoder.finish()));
-            output.present();
         }
     }
 
-    pub fn resize(&mut self, width: u32, height: u32) {
-        if let (Some(surface), Some(device), Some(config)) =
-            (&self.surface, &self.device, &mut self.config)
+    /// Render the current state (as of the last `render` call) into an offscreen texture and
+    /// read it back as tightly packed RGBA8 rows. Resolves once the GPU readback has completed.
+    pub async fn capture_frame(&self) -> Result<(Vec<u8>, u32, u32), String> {
+        let (Some(device), Some(config)) = (&self.device, &self.config) else {
+            return Err("Renderer not initialized".to_string());
+        };
+        let (width, height) = (config.width, config.height);
+
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("Capture Texture"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: Self::render_format(config),
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        self.draw_to_view(&texture.create_view(&TextureViewDescriptor {
+            label: Some("Capture Texture View"),
+            ..Default::default()
+        }));
+
+        let pixels = self.read_texture(&texture, width, height).await?;
+        Ok((pixels, width, height))
+    }
+
+    /// Render a frame into an offscreen texture of the given size, independent of the canvas, and
+    /// read it back as tightly packed RGBA8 rows. Used for offline export; the texture is reused
+    /// between calls with the same size.
+    pub async fn render_offscreen(
+        &mut self,
+        time: f64,
+        frequency_bars: &[f32],
+        bin_size: usize,
+        playhead: f64,
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<u8>, String> {
+        self.render_to_texture(time, frequency_bars, bin_size, playhead, width, height)?;
+        let Some(texture) = &self.offscreen_texture else {
+            return Err("Offscreen texture not available".to_string());
+        };
+        self.read_texture(texture, width, height).await
+    }
+
+    /// Render a frame into the offscreen texture of `render_offscreen` and hand it over without
+    /// reading it back, for composition into another scene on the same device: it can be sampled
+    /// or copied from, and stays valid until the next offscreen render of a different size or
+    /// `release_offscreen`.
+    pub fn render_to_texture(
+        &mut self,
+        time: f64,
+        frequency_bars: &[f32],
+        bin_size: usize,
+        playhead: f64,
+        width: u32,
+        height: u32,
+    ) -> Result<&Texture, String> {
+        let (Some(device), Some(config)) = (&self.device, &self.config) else {
+            return Err("Renderer not initialized".to_string());
+        };
+
+        let max_dimension = device.limits().max_texture_dimension_2d;
+        if width == 0 || height == 0 || width > max_dimension || height > max_dimension {
+            return Err(format!(
+                "Invalid export size {}x{} (maximum {}x{})",
+                width, height, max_dimension, max_dimension
+            ));
+        }
+
+        let reusable = self
+            .offscreen_texture
+            .as_ref()
+            .is_some_and(|texture| texture.width() == width && texture.height() == height);
+        if !reusable {
+            self.offscreen_texture = Some(device.create_texture(&TextureDescriptor {
+                label: Some("Offscreen Texture"),
+                size: Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: Self::render_format(config),
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            }));
+        }
+
+        self.update_uniforms(time, frequency_bars, bin_size, playhead, width, height);
+        let Some(texture) = &self.offscreen_texture else {
+            return Err("Offscreen texture not available".to_string());
+        };
+        self.draw_to_view(&texture.create_view(&TextureViewDescriptor {
+            label: Some("Offscreen Texture View"),
+            ..Default::default()
+        }));
+        Ok(texture)
+    }
+
+    /// Drop the offscreen texture kept around by `render_offscreen`.
+    pub fn release_offscreen(&mut self) {
+        self.offscreen_texture = None;
+    }
+
+    async fn read_texture(&self, texture: &Texture, width: u32, height: u32) -> Result<Vec<u8>, String> {
+        let (Some(device), Some(queue)) = (&self.device, &self.queue) else {
+            return Err("Renderer not initialized".to_string());
+        };
+
+        let swap_red_blue = match texture.format() {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+            format => return Err(format!("Unsupported capture format {:?}", format)),
+        };
+
+        // Buffer rows must be aligned to 256 bytes for texture-to-buffer copies
+        let unpadded_bytes_per_row = width * 4;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+        let readback_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Capture Readback Buffer"),
+            size: (padded_bytes_per_row * height) as u64,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Capture Encoder"),
+        });
+        if GPU_DEBUG {
+            encoder.insert_debug_marker("Capture Readback");
+        }
+        encoder.copy_texture_to_buffer(
+            TexelCopyTextureInfo {
+                texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            TexelCopyBufferInfo {
+                buffer: &readback_buffer,
+                layout: TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        // Buffer mapping completes asynchronously; poll the device and yield to the browser until it does
+        let slice = readback_buffer.slice(..);
+        let map_result = Arc::new(Mutex::new(None));
         {
+            let map_result = map_result.clone();
+            slice.map_async(MapMode::Read, move |result| {
+                *map_result.lock().unwrap() = Some(result);
+            });
+        }
+        loop {
+            let _ = device.poll(PollType::Poll);
+            if let Some(result) = map_result.lock().unwrap().take() {
+                result.map_err(|e| format!("Failed to map capture buffer: {:?}", e))?;
+                break;
+            }
+            #[cfg(target_arch = "wasm32")]
+            crate::timing::yield_to_event_loop().await?;
+            #[cfg(not(target_arch = "wasm32"))]
+            let _ = device.poll(PollType::Wait);
+        }
+
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+        {
+            let mapped = slice.get_mapped_range();
+            for row in mapped.chunks(padded_bytes_per_row as usize).take(height as usize) {
+                pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+            }
+        }
+        readback_buffer.unmap();
+
+        if swap_red_blue {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        Ok(pixels)
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if let Some(config) = &mut self.config {
             config.width = width;
             config.height = height;
-            surface.configure(device, config);
+            if let (Some(surface), Some(device)) = (&self.surface, &self.device) {
+                surface.configure(device, config);
+            }
         }
     }
-}
\ No newline at end of file
+}
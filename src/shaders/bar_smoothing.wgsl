@@ -0,0 +1,43 @@
+// Temporal smoothing of the bars on the GPU: each texel of the 64x2 state texture (row 0 the
+// mono or left bars, row 1 the right) eases towards this frame's target by the smoothing factor.
+// Levels are packed into two 8-bit channels so small steps aren't lost to 8-bit rounding.
+
+struct Params {
+    bars: array<vec4<f32>, 16>, // 64 target bars
+    right_bars: array<vec4<f32>, 16>, // 64 target right channel bars
+    smoothing: vec4<f32>, // factor (0 keeps the previous level, 1 jumps to the target), padding
+}
+@group(0) @binding(0) var previous_bars: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> params: Params;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertexIndex: u32) -> @builtin(position) vec4<f32> {
+    var pos = array<vec2<f32>, 3>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>( 3.0, -1.0),
+        vec2<f32>(-1.0,  3.0)
+    );
+    return vec4<f32>(pos[vertexIndex], 0.0, 1.0);
+}
+
+// Level in 0.0-1.0 from the high (r) and low (g) 8-bit halves
+fn decodeBar(texel: vec4<f32>) -> f32 {
+    return texel.r + texel.g / 255.0;
+}
+
+fn encodeBar(level: f32) -> vec4<f32> {
+    let scaled = clamp(level, 0.0, 1.0) * 255.0;
+    return vec4<f32>(floor(scaled) / 255.0, fract(scaled), 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) fragCoord: vec4<f32>) -> @location(0) vec4<f32> {
+    let texel = vec2<i32>(fragCoord.xy);
+    let index = texel.x;
+    var target_level = params.bars[index / 4][index % 4];
+    if texel.y == 1 {
+        target_level = params.right_bars[index / 4][index % 4];
+    }
+    let previous = decodeBar(textureLoad(previous_bars, texel, 0));
+    return encodeBar(mix(previous, target_level, params.smoothing.x));
+}
@@ -0,0 +1,530 @@
+// Shared prelude for every visualizer mode: the full-screen vertex shader, the uniforms and
+// textures at bind group 0, and drawing helpers. Each mode appends its own `fs_main`.
+
+// Vertex shader
+@vertex
+fn vs_main(@builtin(vertex_index) vertexIndex: u32) -> @builtin(position) vec4<f32> {
+    var pos = array<vec2<f32>, 3>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>( 3.0, -1.0),
+        vec2<f32>(-1.0,  3.0)
+    );
+    return vec4<f32>(pos[vertexIndex], 0.0, 1.0);
+}
+
+// Uniforms (16-byte aligned for WebGL compatibility)
+struct Uniforms {
+    time: f32,
+    bin_size: f32,
+    resolution: vec2<f32>,
+    waveform: vec4<f32>, // enabled, strip height, playhead (0-1), column count
+    interaction: vec4<f32>, // highlighted bar index, soloed bar index (-1 for none), padding
+    background: vec4<f32>, // enabled, blur radius, dim amount, image aspect ratio
+    bar_layout: vec4<f32>, // orientation (0 vertical, 1 horizontal), HUD position (0 top, 1 bottom), arrangement (0 bottom-up, 1 top-down, 2 mirrored, 3 dual-sided), reversed order (0/1)
+    bar_style: vec4<f32>, // gap fraction, corner radius (fraction of bar width), minimum height, gradient mode (0 none, 1 by index, 2 by magnitude)
+    theme: vec4<f32>, // light scheme (0/1), high contrast (0/1), padding
+    clock: vec4<f32>, // time wrap period in seconds (0 when time does not wrap), bars smoothed on the GPU (0/1), sRGB target (0/1), padding
+    transform: vec4<f32>, // view rotation (radians), zoom, hue shift (turns), padding
+    beat: vec4<f32>, // flash, background pulse (0-1), shake offset x, y (fractions of the canvas height)
+    chroma: array<vec4<f32>, 3>, // 12 pitch-class energies (0-1, relative to the strongest), C first
+    stereo_meter: vec4<f32>, // enabled, correlation (-1 to 1), width (0-1), padding
+    tuner: vec4<f32>, // pitched (0/1), cents from the nearest note (-50 to 50), pitch class (0 = C), padding
+    axis: vec4<f32>, // gridlines shown (0/1), dB between gridlines, gridline opacity (0-1), padding
+}
+// Bar levels live in their own buffer, only written when they change, so the per-frame uniforms
+// above stay small
+struct Bars {
+    mono: array<vec4<f32>, 16>, // 64 floats as 16 vec4s for proper alignment
+    right: array<vec4<f32>, 16>, // right channel bars (the mono bars when there is no right channel)
+}
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var waveform_texture: texture_2d<f32>; // row 0: per-column (min, max) sample pairs, row 1: marker tick flags
+@group(0) @binding(2) var background_texture: texture_2d<f32>;
+@group(0) @binding(3) var background_sampler: sampler;
+@group(0) @binding(4) var gradient_texture: texture_2d<f32>; // host gradient lookup, premultiplied by its alpha when read
+@group(0) @binding(5) var smoothed_bars: texture_2d<f32>; // GPU-smoothed levels, row 0 mono/left and row 1 right, packed as in bar_smoothing.wgsl
+@group(0) @binding(6) var<uniform> bars: Bars;
+
+// Adjust an animation rate so a whole number of cycles fits into the time wrap period, keeping
+// animations continuous when the time uniform wraps around
+fn periodicRate(rate: f32, cycle: f32) -> f32 {
+    let period = uniforms.clock.x;
+    if period <= 0.0 {
+        return rate;
+    }
+    return cycle * max(round(rate * period / cycle), 1.0) / period;
+}
+
+// Distance field functions for smooth shapes
+fn sdfLine(p: vec2<f32>, a: vec2<f32>, b: vec2<f32>) -> f32 {
+    let pa = p - a;
+    let ba = b - a;
+    let h = clamp(dot(pa, ba) / dot(ba, ba), 0.0, 1.0);
+    return length(pa - ba * h);
+}
+
+fn sdfRoundedBox(p: vec2<f32>, center: vec2<f32>, half_size: vec2<f32>, radius: f32) -> f32 {
+    let q = abs(p - center) - half_size + vec2<f32>(radius);
+    return length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0) - radius;
+}
+
+fn sdfCircle(p: vec2<f32>, center: vec2<f32>, radius: f32) -> f32 {
+    return length(p - center) - radius;
+}
+
+// Bloom effect function
+fn bloom(dist: f32, intensity: f32, radius: f32) -> f32 {
+    return intensity * exp(-dist * dist / (radius * radius));
+}
+
+// HSV to RGB conversion for dynamic colors, applying the view's hue shift
+fn hsv2rgb(c: vec3<f32>) -> vec3<f32> {
+    return hsvToRgb(vec3<f32>(c.x + uniforms.transform.z, c.y, c.z));
+}
+
+fn hsvToRgb(c: vec3<f32>) -> vec3<f32> {
+    let K = vec4<f32>(1.0, 2.0 / 3.0, 1.0 / 3.0, 3.0);
+    let p = abs(fract(vec3<f32>(c.x) + K.xyz) * 6.0 - K.www);
+    return c.z * mix(K.xxx, clamp(p - K.xxx, vec3<f32>(0.0), vec3<f32>(1.0)), c.y);
+}
+
+fn rgbToHsv(c: vec3<f32>) -> vec3<f32> {
+    let K = vec4<f32>(0.0, -1.0 / 3.0, 2.0 / 3.0, -1.0);
+    let p = select(vec4<f32>(c.gb, K.xy), vec4<f32>(c.bg, K.wz), c.g < c.b);
+    let q = select(vec4<f32>(p.xyw, c.r), vec4<f32>(c.r, p.yzx), c.r >= p.x);
+    let d = q.x - min(q.w, q.y);
+    let e = 1.0e-10;
+    return vec3<f32>(abs(q.z + (q.w - q.y) / (6.0 * d + e)), d / (q.x + e), q.x);
+}
+
+fn luminance(c: vec3<f32>) -> f32 {
+    return dot(c, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+// Color for a bar from the host gradient, looked up by bar position or amplitude (0.0-1.0)
+// depending on the gradient mode; `fallback` when no gradient is set
+fn gradientColor(index: i32, amplitude: f32, fallback: vec3<f32>) -> vec3<f32> {
+    let mode = i32(uniforms.bar_style.w);
+    if (mode == 0) {
+        return fallback;
+    }
+    let t = select(f32(index) / max(uniforms.bin_size - 1.0, 1.0), amplitude, mode == 2);
+    let width = i32(textureDimensions(gradient_texture).x);
+    let texel = textureLoad(gradient_texture, vec2<i32>(clamp(i32(round(t * f32(width - 1))), 0, width - 1), 0), 0);
+    return texel.rgb * texel.a;
+}
+
+// Background image scaled to cover the canvas, box-blurred and dimmed
+fn backgroundImage(fragCoord: vec2<f32>) -> vec3<f32> {
+    let canvas_aspect = uniforms.resolution.x / uniforms.resolution.y;
+    let image_aspect = uniforms.background.w;
+    var st = fragCoord / uniforms.resolution;
+    if (canvas_aspect > image_aspect) {
+        st.y = (st.y - 0.5) * image_aspect / canvas_aspect + 0.5;
+    } else {
+        st.x = (st.x - 0.5) * canvas_aspect / image_aspect + 0.5;
+    }
+
+    // 3x3 box blur; radius is in texture coordinates
+    let radius = uniforms.background.y;
+    var color = vec3<f32>(0.0);
+    for (var dx = -1; dx <= 1; dx++) {
+        for (var dy = -1; dy <= 1; dy++) {
+            let offset = vec2<f32>(f32(dx), f32(dy)) * radius;
+            color += textureSampleLevel(background_texture, background_sampler, st + offset, 0.0).rgb;
+        }
+    }
+    color /= 9.0;
+
+    return color * (1.0 - uniforms.background.z);
+}
+
+// Static overview of the whole track's waveform along the HUD edge, with a playhead marker moving
+// across it and marker ticks
+fn waveformStrip(fragCoord: vec2<f32>, uv: vec2<f32>) -> vec3<f32> {
+    let strip_height = uniforms.waveform.y;
+    let strip_center = select(0.5 - strip_height * 0.5, -0.5 + strip_height * 0.5, uniforms.bar_layout.y > 0.5);
+    let half_height = strip_height * 0.5;
+    if (abs(uv.y - strip_center) > half_height) {
+        return vec3<f32>(0.0);
+    }
+
+    let x_norm = fragCoord.x / uniforms.resolution.x;
+    let playhead = uniforms.waveform.z;
+    let columns = i32(uniforms.waveform.w);
+    let column = clamp(i32(x_norm * f32(columns)), 0, columns - 1);
+    let min_max = textureLoad(waveform_texture, vec2<i32>(column, 0), 0).xy;
+
+    var color = vec3<f32>(0.0);
+    let sample_y = (uv.y - strip_center) / half_height;
+    if (sample_y >= min_max.x && sample_y <= min_max.y) {
+        // Played portion is brighter than the remainder
+        color = select(vec3<f32>(0.25, 0.25, 0.3), vec3<f32>(0.7, 0.6, 0.9), x_norm <= playhead);
+    }
+
+    // Marker ticks, one waveform column wide
+    if (textureLoad(waveform_texture, vec2<i32>(column, 1), 0).x > 0.5) {
+        color = mix(color, vec3<f32>(1.0, 0.8, 0.3), 0.85);
+    }
+
+    // Playhead marker, one pixel wide
+    let playhead_dist = abs(fragCoord.x - playhead * uniforms.resolution.x);
+    color += vec3<f32>(1.0) * smoothstep(1.5, 0.5, playhead_dist);
+
+    return color;
+}
+
+// Level from the GPU smoothing texture: high byte in red, low byte in green
+fn smoothedBar(index: i32, row: i32) -> f32 {
+    let texel = textureLoad(smoothed_bars, vec2<i32>(index, row), 0);
+    return texel.r + texel.g / 255.0;
+}
+
+// Bar level as uploaded (roughly 0.0-0.5 for typical material)
+fn barAmplitude(index: i32) -> f32 {
+    if uniforms.clock.y > 0.5 {
+        return smoothedBar(index, 0);
+    }
+    return bars.mono[index / 4][index % 4];
+}
+
+// Right channel bar level, same scale as `barAmplitude`
+fn rightBarAmplitude(index: i32) -> f32 {
+    if uniforms.clock.y > 0.5 {
+        return smoothedBar(index, 1);
+    }
+    return bars.right[index / 4][index % 4];
+}
+
+// Mean level across the active bars
+fn totalEnergy() -> f32 {
+    var total_energy = 0.0;
+    for (var i = 0; i < i32(uniforms.bin_size); i++) {
+        total_energy += barAmplitude(i);
+    }
+    return total_energy / max(uniforms.bin_size, 1.0);
+}
+
+// Energy of a pitch class (0 = C ... 11 = B), 0.0-1.0 relative to the strongest class
+fn pitchClassEnergy(index: i32) -> f32 {
+    return uniforms.chroma[index / 4][index % 4];
+}
+
+// Hue for a pitch class, going round the circle of fifths so related keys get neighbouring colors
+fn pitchClassHue(index: i32) -> f32 {
+    return f32((index * 7) % 12) / 12.0;
+}
+
+// Pixel coordinates rotated and zoomed about the canvas center by the view transform and moved
+// by the beat shake. Modes draw their content from these; the background and HUD stay fixed to
+// the screen.
+fn viewCoord(fragCoord: vec2<f32>) -> vec2<f32> {
+    let center = uniforms.resolution * 0.5;
+    let shake = uniforms.beat.zw * uniforms.resolution.y;
+    let offset = (fragCoord - shake - center) / max(uniforms.transform.y, 0.001);
+    let c = cos(uniforms.transform.x);
+    let s = sin(uniforms.transform.x);
+    return center + vec2<f32>(c * offset.x - s * offset.y, s * offset.x + c * offset.y);
+}
+
+// Screen-space UV centered on the canvas, y up, x scaled by the aspect ratio
+fn screenUv(fragCoord: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(
+        (fragCoord.x / uniforms.resolution.x - 0.5) * (uniforms.resolution.x / uniforms.resolution.y),
+        (uniforms.resolution.y - fragCoord.y) / uniforms.resolution.y - 0.5
+    );
+}
+
+// What modes draw on top of: the background image if one is set, otherwise black (white in the
+// light scheme)
+fn baseLayer(fragCoord: vec2<f32>) -> vec3<f32> {
+    if (uniforms.background.x > 0.5) {
+        return backgroundImage(fragCoord);
+    }
+    return vec3<f32>(select(0.0, 1.0, uniforms.theme.x > 0.5));
+}
+
+// Correlation meter in the bottom-right corner: a track from -1 (left end) to +1 (right end)
+// filled from its center to the correlation, green when positive and red when negative, with the
+// width as a blue bar underneath. Sits above the waveform strip when that is at the bottom.
+fn stereoMeter(fragCoord: vec2<f32>) -> vec3<f32> {
+    let unit = max(round(uniforms.resolution.y / 240.0), 1.0);
+    let size = vec2<f32>(64.0, 3.0) * unit;
+    let strip = select(0.0, uniforms.waveform.y * uniforms.resolution.y, uniforms.waveform.x > 0.5 && uniforms.bar_layout.y > 0.5);
+    let origin = uniforms.resolution - vec2<f32>(size.x + 8.0 * unit, strip + 2.0 * size.y + 10.0 * unit);
+    let p = fragCoord - origin;
+    if (p.x < 0.0 || p.x >= size.x) {
+        return vec3<f32>(0.0);
+    }
+
+    let t = p.x / size.x;
+    if (p.y >= 0.0 && p.y < size.y) {
+        let correlation = uniforms.stereo_meter.y;
+        let position = t * 2.0 - 1.0;
+        if (abs(position) < 1.0 / size.x) {
+            return vec3<f32>(0.8);
+        }
+        if (position * correlation > 0.0 && abs(position) <= abs(correlation)) {
+            return select(vec3<f32>(0.9, 0.25, 0.2), vec3<f32>(0.3, 0.9, 0.4), correlation > 0.0);
+        }
+        return vec3<f32>(0.15);
+    }
+    let width_y = p.y - size.y - unit;
+    if (width_y >= 0.0 && width_y < size.y) {
+        return select(vec3<f32>(0.15), vec3<f32>(0.3, 0.55, 1.0), t <= uniforms.stereo_meter.z);
+    }
+    return vec3<f32>(0.0);
+}
+
+// HUD elements drawn over every mode
+fn hudLayer(fragCoord: vec2<f32>) -> vec3<f32> {
+    var hud = vec3<f32>(0.0);
+    if (uniforms.waveform.x > 0.5) {
+        hud = waveformStrip(fragCoord, screenUv(fragCoord));
+    }
+    if (uniforms.stereo_meter.x > 0.5) {
+        hud += stereoMeter(fragCoord);
+    }
+    return hud;
+}
+
+// Light scheme: the same hue with lightness inverted, so glow on black becomes ink on white
+fn lightInk(color: vec3<f32>) -> vec3<f32> {
+    let inverted = rgbToHsv(vec3<f32>(1.0) - clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)));
+    return hsvToRgb(vec3<f32>(inverted.x + 0.5, inverted.y, inverted.z));
+}
+
+// High contrast: drop faint glow and lift what remains to at least `min_luminance`, keeping its
+// hue. Returns the lifted color and how much of the pixel it covers.
+fn highContrast(color: vec3<f32>, min_luminance: f32) -> vec4<f32> {
+    let peak = max(color.r, max(color.g, color.b));
+    let full = color / max(peak, 1.0e-4);
+    let lift = clamp((min_luminance - luminance(full)) / (1.0 - luminance(full)), 0.0, 1.0);
+    return vec4<f32>(mix(full, vec3<f32>(1.0), lift), smoothstep(0.15, 0.3, peak));
+}
+
+// Colors are worked out as display (sRGB-encoded) values. An sRGB target encodes what it is
+// given, so it gets them decoded to linear; blending then happens on linear values.
+fn targetColor(color: vec4<f32>) -> vec4<f32> {
+    if (uniforms.clock.z <= 0.5) {
+        return color;
+    }
+    let c = clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+    let linear = select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+    return vec4<f32>(linear, color.a);
+}
+
+// Final pixel for a mode: `content` (glow drawn as if on black) composed by `themedFrame`, with
+// the beat flash on top
+fn composeFrame(fragCoord: vec2<f32>, content: vec3<f32>) -> vec4<f32> {
+    let frame = themedFrame(fragCoord, content);
+
+    // Flash towards the ink color: white on the dark scheme, near-black on the light one
+    let flash_color = vec3<f32>(select(1.0, 0.1, uniforms.theme.x > 0.5));
+    return targetColor(vec4<f32>(mix(frame.rgb, flash_color, uniforms.beat.x * 0.6), frame.a));
+}
+
+// The background, swelling with the beat pulse
+fn pulsedBackground(fragCoord: vec2<f32>) -> vec3<f32> {
+    let tint = hsv2rgb(vec3<f32>(0.72, 0.6, 0.25)) * uniforms.beat.y;
+    let background = baseLayer(fragCoord);
+    if (uniforms.theme.x > 0.5) {
+        return background * (vec3<f32>(1.0) - tint);
+    }
+    return background + tint;
+}
+
+// `content` and the HUD over the background, adapted to the theme. Dark content is added to the
+// background; light content multiplies it as ink. High contrast keeps at least half the
+// luminance range between the two.
+fn themedFrame(fragCoord: vec2<f32>, content: vec3<f32>) -> vec4<f32> {
+    let light = uniforms.theme.x > 0.5;
+    var background = pulsedBackground(fragCoord);
+    let color = content + hudLayer(fragCoord);
+
+    if (uniforms.theme.y <= 0.5) {
+        if (light) {
+            return vec4<f32>(background * lightInk(color), 1.0);
+        }
+        return vec4<f32>(background + color, 1.0);
+    }
+
+    let contrast = highContrast(color, 0.6);
+    let background_luminance = luminance(background);
+    if (light) {
+        background = mix(background, vec3<f32>(1.0), clamp((0.85 - background_luminance) / (1.0 - background_luminance), 0.0, 1.0));
+        var ink = lightInk(contrast.rgb);
+        ink *= min(1.0, 0.35 / max(luminance(ink), 1.0e-4));
+        return vec4<f32>(background * mix(vec3<f32>(1.0), ink, contrast.a), 1.0);
+    }
+    background *= min(1.0, 0.1 / max(background_luminance, 1.0e-4));
+    return vec4<f32>(background + contrast.rgb * contrast.a, 1.0);
+}
+
+// Gridlines drawn under the bars, the first at the height of a full bar; `GRID_LINES` in axis.rs
+const AXIS_GRID_LINES: i32 = 5;
+
+// Faint horizontal lines at the bar heights of 0, -step, -2 step... dB, in bar space
+fn axisGrid(bar_uv: vec2<f32>, base_y: f32, height_scale: f32) -> vec3<f32> {
+    if (uniforms.axis.x < 0.5) {
+        return vec3<f32>(0.0);
+    }
+    let min_height = uniforms.bar_style.z;
+    var grid = 0.0;
+    for (var line = 0; line < AXIS_GRID_LINES; line++) {
+        let level = pow(10.0, -f32(line) * uniforms.axis.y / 20.0);
+        let y = base_y + (min_height + level * (0.8 - min_height)) * height_scale;
+        grid = max(grid, smoothstep(0.002, 0.0, abs(bar_uv.y - y)));
+    }
+    return vec3<f32>(0.6, 0.6, 0.7) * grid * uniforms.axis.z;
+}
+
+// The bars (line, cap and glow per frequency bar) as content for `composeFrame`, following the
+// orientation, arrangement, style and gradient settings
+fn barsLayer(fragCoord: vec2<f32>) -> vec3<f32> {
+    // Horizontal layouts rotate the bar space: bars spread top-to-bottom and grow from the left edge
+    let coord = viewCoord(fragCoord);
+    var bar_coord = coord;
+    var bar_resolution = uniforms.resolution;
+    if (uniforms.bar_layout.x > 0.5) {
+        bar_coord = vec2<f32>(coord.y, uniforms.resolution.x - coord.x);
+        bar_resolution = uniforms.resolution.yx;
+    }
+
+    // Convert bar coordinates to UV coordinates with explicit bottom-to-top mapping
+    let uv = vec2<f32>(
+        (bar_coord.x / bar_resolution.x - 0.5) * (bar_resolution.x / bar_resolution.y),
+        (bar_resolution.y - bar_coord.y) / bar_resolution.y - 0.5
+    );
+    let aspect = bar_resolution.x / bar_resolution.y;
+
+    // Fold or flip the bar space so every arrangement is drawn as bars growing up from `base_y`
+    var bar_uv = uv;
+    var base_y = -0.5;
+    var height_scale = 1.0;
+    let arrangement = i32(uniforms.bar_layout.z);
+    if (arrangement == 1) {
+        // Top-down
+        bar_uv.y = -uv.y;
+    } else if (arrangement == 2) {
+        // Mirrored about the vertical center line
+        bar_uv.x = abs(uv.x);
+    } else if (arrangement == 3) {
+        // Dual-sided: each half grows from the center line at half height
+        bar_uv.y = abs(uv.y);
+        base_y = 0.0;
+        height_scale = 0.5;
+    }
+    let reversed = uniforms.bar_layout.w > 0.5;
+
+    var final_color = axisGrid(bar_uv, base_y, height_scale);
+    let time = uniforms.time;
+
+    // Draw frequency bars as lines with circles and bloom
+    for (var i = 0; i < i32(uniforms.bin_size); i++) {
+        let bar_index = i;
+        if (bar_index >= i32(uniforms.bin_size)) {
+            break;
+        }
+
+        // Get amplitude for this bar
+        var amplitude = clamp(barAmplitude(bar_index) * 2.0, 0.0, 1.0);
+
+        // Solo: boost the soloed band and fade the rest
+        let soloed_index = i32(uniforms.interaction.y);
+        var solo_gain = 1.0;
+        if (soloed_index >= 0) {
+            if (soloed_index == bar_index) {
+                amplitude = clamp(amplitude * 1.5, 0.0, 1.0);
+            } else {
+                solo_gain = 0.15;
+            }
+        }
+
+        // Skip if amplitude is too low
+        // if amplitude < 0.01 {
+        //     continue;
+        // }
+
+        // Calculate line position (from bottom to top)
+        let slot = f32(select(bar_index, i32(uniforms.bin_size) - 1 - bar_index, reversed));
+        var x_pos = (slot / uniforms.bin_size - 0.5) * aspect;
+        var slot_width = aspect / uniforms.bin_size;
+        if (arrangement == 2) {
+            // Mirrored bars fill each half outward from the center
+            x_pos = (slot + 0.5) / uniforms.bin_size * 0.5 * aspect;
+            slot_width *= 0.5;
+        }
+        let min_height = uniforms.bar_style.z;
+        let max_height = 0.8; // 80% maximum height
+        let actual_amplitude = (min_height + amplitude * (max_height - min_height)) * height_scale;
+        let line_end = vec2<f32>(x_pos, base_y + actual_amplitude);  // Grow upward
+
+        // Calculate circle position at top of line
+        let circle_center = line_end;
+        let circle_radius = 0.02;
+
+        // Dynamic color based on frequency and amplitude
+        let freq_ratio = f32(bar_index) / uniforms.bin_size;
+        let hue = freq_ratio * 0.8 + time * periodicRate(0.05, 1.0); // Slowly rotating hue
+        let saturation = 0.9 + amplitude * 0.1;
+        let brightness = 0.6 + amplitude * 0.4;
+        var base_color = gradientColor(bar_index, amplitude, hsv2rgb(vec3<f32>(hue, saturation, brightness)));
+
+        // Hover highlight: wash the bar towards white
+        let highlighted = i32(uniforms.interaction.x) == bar_index;
+        if (highlighted) {
+            base_color = mix(base_color, vec3<f32>(1.0), 0.6);
+        }
+
+        // Bar body: a rounded box filling the slot minus the gap, extended below its base by the
+        // corner radius so only the top corners show
+        let half_width = slot_width * (1.0 - uniforms.bar_style.x) * 0.5 + amplitude * 0.001 + select(0.0, 0.002, highlighted);
+        let corner_radius = min(uniforms.bar_style.y * 2.0 * half_width, half_width);
+        let bar_center = vec2<f32>(x_pos, base_y + (actual_amplitude - corner_radius) * 0.5);
+        let bar_half_size = vec2<f32>(half_width, (actual_amplitude + corner_radius) * 0.5);
+        let line_dist = max(sdfRoundedBox(bar_uv, bar_center, bar_half_size, corner_radius), 0.0);
+        let line_alpha = smoothstep(0.001, 0.0, line_dist);
+
+        // Circle distance and rendering
+        let circle_dist = sdfCircle(bar_uv, circle_center, circle_radius);
+        let circle_alpha = smoothstep(0.001, 0.0, circle_dist);
+
+        // Toned down bloom effects
+        let bloom_radius = 0.02 + amplitude * 0.03;
+        let bloom_intensity = amplitude * 0.8;
+
+        // Subtle line bloom
+        let line_bloom = bloom(line_dist, bloom_intensity * 0.2, bloom_radius * 0.5);
+
+        // Single circle bloom layer
+        let circle_bloom = bloom(circle_dist, bloom_intensity * 0.5, bloom_radius);
+
+        // Combine effects with reduced bloom
+        let total_alpha = line_alpha + circle_alpha + line_bloom * 0.3 + circle_bloom * 0.5;
+
+        // Add color with additive blending
+        final_color += base_color * total_alpha * solo_gain;
+
+        // Subtle sparkle for high frequencies
+        if freq_ratio > 0.7 && amplitude > 0.5 {
+            let sparkle_dist = length(bar_uv - circle_center);
+            let sparkle = amplitude * exp(-sparkle_dist * 30.0) * (sin(time * periodicRate(8.0, 6.2831853) + f32(bar_index)) * 0.5 + 0.5);
+            final_color += vec3<f32>(1.0, 1.0, 0.8) * sparkle * 0.2;
+        }
+    }
+
+    // Add subtle background glow based on overall energy
+    let total_energy = totalEnergy();
+
+    // Subtle background glow with adaptive colors
+    let center_dist = length(uv);
+    let bg_glow = total_energy * exp(-center_dist * 2.0) * 0.02;
+    final_color += vec3<f32>(0.2, 0.1, 0.3) * bg_glow;
+
+    // Apply tone mapping and gamma correction
+    // final_color = final_color / (final_color + vec3<f32>(1.0));
+    // final_color = pow(final_color, vec3<f32>(1.0 / 2.2));
+
+    return final_color;
+}
@@ -0,0 +1,64 @@
+// Feedback trails: the previous output, zoomed, rotated and dimmed about the center, combined
+// under the freshly drawn frame
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertexIndex: u32) -> VertexOutput {
+    var pos = array<vec2<f32>, 3>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>( 3.0, -1.0),
+        vec2<f32>(-1.0,  3.0)
+    );
+    var out: VertexOutput;
+    out.position = vec4<f32>(pos[vertexIndex], 0.0, 1.0);
+    // Texture coordinates run top-down
+    out.uv = vec2<f32>(pos[vertexIndex].x * 0.5 + 0.5, 0.5 - pos[vertexIndex].y * 0.5);
+    return out;
+}
+
+struct FeedbackParams {
+    // [zoom, rotation in radians, decay, light scheme] for this frame
+    transform: vec4<f32>,
+    // [aspect ratio (width / height), padding...]
+    target_size: vec4<f32>,
+}
+
+@group(0) @binding(0) var frame_texture: texture_2d<f32>;
+@group(0) @binding(1) var previous_texture: texture_2d<f32>;
+@group(0) @binding(2) var feedback_sampler: sampler;
+@group(0) @binding(3) var<uniform> params: FeedbackParams;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let frame = textureSample(frame_texture, feedback_sampler, in.uv);
+
+    // Where this pixel was a frame ago: undo the zoom and rotation about the center, measured in
+    // units of the canvas height so rotating doesn't shear non-square canvases
+    let aspect = vec2<f32>(params.target_size.x, 1.0);
+    let offset = (in.uv - 0.5) * aspect;
+    let c = cos(-params.transform.y);
+    let s = sin(-params.transform.y);
+    let rotated = vec2<f32>(offset.x * c - offset.y * s, offset.x * s + offset.y * c);
+    let previous_uv = rotated / (params.transform.x * aspect) + 0.5;
+    let previous = textureSample(previous_texture, feedback_sampler, previous_uv);
+    let inside = all(previous_uv >= vec2<f32>(0.0)) && all(previous_uv <= vec2<f32>(1.0));
+
+    // Trails are ink on the background: brightness on the dark scheme, darkness on the light one.
+    // The extra step down keeps 8-bit rounding from leaving trails stuck just above the background.
+    let light = params.transform.w > 0.5;
+    let frame_ink = select(frame.rgb, 1.0 - frame.rgb, light);
+    let previous_ink = select(previous.rgb, 1.0 - previous.rgb, light);
+    let trail = select(vec3<f32>(0.0), max(previous_ink * params.transform.z - 1.0 / 255.0, vec3<f32>(0.0)), inside);
+    let ink = max(frame_ink, trail);
+    return vec4<f32>(select(ink, 1.0 - ink, light), frame.a);
+}
+
+// Full-screen copy of the combined frame onto the target
+@fragment
+fn fs_copy(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(frame_texture, feedback_sampler, in.uv);
+}
@@ -0,0 +1,72 @@
+// FXAA-style edge smoothing: pixels on a luma edge are blended with their neighbours along the
+// edge, softening the stair steps of bar edges and thin lines
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertexIndex: u32) -> VertexOutput {
+    var pos = array<vec2<f32>, 3>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>( 3.0, -1.0),
+        vec2<f32>(-1.0,  3.0)
+    );
+    var out: VertexOutput;
+    out.position = vec4<f32>(pos[vertexIndex], 0.0, 1.0);
+    // Texture coordinates run top-down
+    out.uv = vec2<f32>(pos[vertexIndex].x * 0.5 + 0.5, 0.5 - pos[vertexIndex].y * 0.5);
+    return out;
+}
+
+@group(0) @binding(0) var source_texture: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+
+// Edges with less contrast than this (absolute, or relative to the brightest neighbour) are left alone
+const EDGE_THRESHOLD_MIN: f32 = 0.0312;
+const EDGE_THRESHOLD: f32 = 0.125;
+const SPAN_MAX: f32 = 8.0;
+const REDUCE_MUL: f32 = 0.125;
+const REDUCE_MIN: f32 = 0.0078125;
+
+fn luma(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.299, 0.587, 0.114));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source_texture));
+    let center = textureSample(source_texture, source_sampler, in.uv);
+    let luma_nw = luma(textureSample(source_texture, source_sampler, in.uv + vec2<f32>(-1.0, -1.0) * texel).rgb);
+    let luma_ne = luma(textureSample(source_texture, source_sampler, in.uv + vec2<f32>(1.0, -1.0) * texel).rgb);
+    let luma_sw = luma(textureSample(source_texture, source_sampler, in.uv + vec2<f32>(-1.0, 1.0) * texel).rgb);
+    let luma_se = luma(textureSample(source_texture, source_sampler, in.uv + vec2<f32>(1.0, 1.0) * texel).rgb);
+    let luma_m = luma(center.rgb);
+
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+    let contrast = luma_max - luma_min;
+
+    // Direction along the edge, scaled so weak edges are sampled a short way out
+    var direction = vec2<f32>(-((luma_nw + luma_ne) - (luma_sw + luma_se)), (luma_nw + luma_sw) - (luma_ne + luma_se));
+    let reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    let scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * scale, vec2<f32>(-SPAN_MAX), vec2<f32>(SPAN_MAX)) * texel;
+
+    let near = 0.5 * (
+        textureSample(source_texture, source_sampler, in.uv + direction * (1.0 / 3.0 - 0.5)).rgb +
+        textureSample(source_texture, source_sampler, in.uv + direction * (2.0 / 3.0 - 0.5)).rgb
+    );
+    let far = near * 0.5 + 0.25 * (
+        textureSample(source_texture, source_sampler, in.uv + direction * -0.5).rgb +
+        textureSample(source_texture, source_sampler, in.uv + direction * 0.5).rgb
+    );
+    // The far samples overshoot when they cross another edge; fall back to the near ones
+    let luma_far = luma(far);
+    let blended = select(far, near, luma_far < luma_min || luma_far > luma_max);
+
+    // Flat areas keep their exact color
+    let on_edge = contrast >= max(EDGE_THRESHOLD_MIN, luma_max * EDGE_THRESHOLD);
+    return vec4<f32>(select(center.rgb, blended, on_edge), center.a);
+}
@@ -0,0 +1,5 @@
+// Bars mode: a glowing line with a cap per frequency bar
+@fragment
+fn fs_main(@builtin(position) fragCoord: vec4<f32>) -> @location(0) vec4<f32> {
+    return composeFrame(fragCoord.xy, barsLayer(fragCoord.xy));
+}
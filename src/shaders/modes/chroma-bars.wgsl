@@ -0,0 +1,35 @@
+// Chroma bars mode: one bar per pitch class, C to B from left to right, colored like the chroma
+// wheel
+
+const BAR_GAP: f32 = 0.3; // fraction of each slot left empty
+const MAX_HEIGHT: f32 = 0.7;
+
+@fragment
+fn fs_main(@builtin(position) fragCoord: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = screenUv(viewCoord(fragCoord.xy));
+    var final_color = vec3<f32>(0.0);
+
+    // The row spans most of the canvas width, but no more than 1.2 canvas heights
+    let aspect = uniforms.resolution.x / uniforms.resolution.y;
+    let row_width = min(aspect * 0.8, 1.2);
+    let slot_width = row_width / 12.0;
+    let base = -MAX_HEIGHT * 0.5;
+    let slot = (uv.x + row_width * 0.5) / slot_width;
+
+    if (slot >= 0.0 && slot < 12.0) {
+        let pitch_class = i32(slot);
+        let energy = pitchClassEnergy(pitch_class);
+        let height = 0.01 + energy * MAX_HEIGHT;
+        let half_size = vec2<f32>(slot_width * (1.0 - BAR_GAP) * 0.5, height * 0.5);
+        let center = vec2<f32>((f32(pitch_class) + 0.5) * slot_width - row_width * 0.5, base + half_size.y);
+        let dist = sdfRoundedBox(uv, center, half_size, min(half_size.x * 0.3, half_size.y));
+
+        // Brighter towards the top of the bar
+        let brightness = 0.35 + energy * 0.65 * clamp((uv.y - base) / max(height, 0.001), 0.3, 1.0);
+        let color = hsv2rgb(vec3<f32>(pitchClassHue(pitch_class), 0.75, brightness));
+        let alpha = smoothstep(0.002, 0.0, dist) + bloom(max(dist, 0.0), energy * 0.4, 0.015);
+        final_color += color * alpha;
+    }
+
+    return composeFrame(fragCoord.xy, final_color);
+}
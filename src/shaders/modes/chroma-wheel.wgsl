@@ -0,0 +1,35 @@
+// Chroma wheel mode: the 12 pitch classes around the circle of fifths, C at the top and going
+// clockwise, each sector reaching outwards with its energy
+
+const TAU: f32 = 6.2831853;
+const INNER_RADIUS: f32 = 0.08;
+
+@fragment
+fn fs_main(@builtin(position) fragCoord: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = screenUv(viewCoord(fragCoord.xy));
+    var final_color = vec3<f32>(0.0);
+    let radius = length(uv);
+
+    // Turns clockwise from the top, shifted half a sector so C is centered there
+    let turn = fract(atan2(uv.x, uv.y) / TAU + 0.5 / 12.0);
+    let sector = min(i32(turn * 12.0), 11);
+    let pitch_class = (sector * 7) % 12;
+    let energy = pitchClassEnergy(pitch_class);
+
+    // Thin gaps between sectors, measured along the arc so they keep their width outwards
+    let along = fract(turn * 12.0);
+    let edge_dist = min(along, 1.0 - along) * radius * TAU / 12.0;
+    let gap = smoothstep(0.002, 0.004, edge_dist);
+
+    let outer_radius = INNER_RADIUS + 0.03 + energy * 0.3;
+    let dist = max(INNER_RADIUS - radius, radius - outer_radius);
+    let color = hsv2rgb(vec3<f32>(pitchClassHue(pitch_class), 0.75, 0.35 + energy * 0.65));
+    let alpha = smoothstep(0.002, 0.0, dist) + bloom(max(dist, 0.0), energy * 0.5, 0.02);
+    final_color += color * alpha * gap;
+
+    // Soft core pulsing with the overall energy
+    let core = totalEnergy();
+    final_color += vec3<f32>(0.3, 0.15, 0.45) * bloom(radius, core * 0.6, INNER_RADIUS * 0.6);
+
+    return composeFrame(fragCoord.xy, final_color);
+}
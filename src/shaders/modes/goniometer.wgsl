@@ -0,0 +1,52 @@
+// Goniometer mode: the current frame's samples traced as a Lissajous figure, left against right,
+// rotated 45 degrees so mono sits on the vertical axis and out-of-phase material on the
+// horizontal. `fs_main` draws the scope grid; the trace is a line strip through one vertex per
+// sample pair, drawn over it by `vs_trace`/`fs_trace`.
+
+const SCOPE_RADIUS: f32 = 0.42; // full scale, as a fraction of the canvas height
+
+@fragment
+fn fs_main(@builtin(position) fragCoord: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = screenUv(viewCoord(fragCoord.xy));
+    var final_color = vec3<f32>(0.0);
+
+    // Outer circle, the mono (M) and side (S) axes, and the L and R diagonals
+    let radius = length(uv);
+    let line = 0.0015;
+    let grid = smoothstep(line, 0.0, abs(radius - SCOPE_RADIUS))
+        + 0.6 * smoothstep(line, 0.0, min(abs(uv.x), abs(uv.y))) * step(radius, SCOPE_RADIUS)
+        + 0.4 * smoothstep(line, 0.0, abs(abs(uv.x) - abs(uv.y)) * 0.7071) * step(radius, SCOPE_RADIUS);
+    final_color += vec3<f32>(0.12, 0.16, 0.2) * grid;
+
+    return composeFrame(fragCoord.xy, final_color);
+}
+
+struct TraceOutput {
+    @builtin(position) position: vec4<f32>,
+}
+
+// Sample pair (left, right) in -1.0..1.0 to clip space, through the inverse of `viewCoord` so the
+// trace turns and zooms with the grid
+@vertex
+fn vs_trace(@location(0) sample: vec2<f32>) -> TraceOutput {
+    let scope = vec2<f32>(sample.y - sample.x, sample.x + sample.y) * 0.5 * SCOPE_RADIUS;
+    let center = uniforms.resolution * 0.5;
+    let content = vec2<f32>(center.x + scope.x * uniforms.resolution.y, center.y - scope.y * uniforms.resolution.y);
+
+    let c = cos(uniforms.transform.x);
+    let s = sin(uniforms.transform.x);
+    let offset = content - center;
+    let unrotated = vec2<f32>(c * offset.x + s * offset.y, -s * offset.x + c * offset.y);
+    let pixel = center + uniforms.beat.zw * uniforms.resolution.y + unrotated * uniforms.transform.y;
+
+    var out: TraceOutput;
+    out.position = vec4<f32>(pixel.x / uniforms.resolution.x * 2.0 - 1.0, 1.0 - pixel.y / uniforms.resolution.y * 2.0, 0.0, 1.0);
+    return out;
+}
+
+// Translucent, so dense parts of the trace build up; dark ink on the light scheme
+@fragment
+fn fs_trace() -> @location(0) vec4<f32> {
+    let color = select(vec3<f32>(0.4, 1.0, 0.6), vec3<f32>(0.0, 0.35, 0.15), uniforms.theme.x > 0.5);
+    return targetColor(vec4<f32>(color, 0.35));
+}
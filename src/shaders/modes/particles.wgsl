@@ -0,0 +1,45 @@
+// Particles mode: each particle orbits the center and is driven by one frequency bar
+
+const PARTICLE_COUNT: i32 = 96;
+const TAU: f32 = 6.2831853;
+
+fn hash(n: f32) -> f32 {
+    return fract(sin(n * 12.9898) * 43758.5453);
+}
+
+@fragment
+fn fs_main(@builtin(position) fragCoord: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = screenUv(viewCoord(fragCoord.xy));
+    var final_color = vec3<f32>(0.0);
+    let time = uniforms.time;
+    let bar_count = max(i32(uniforms.bin_size), 1);
+    let soloed_index = i32(uniforms.interaction.y);
+
+    for (var i = 0; i < PARTICLE_COUNT; i++) {
+        let seed = f32(i);
+        let bar_index = i % bar_count;
+        let amplitude = clamp(barAmplitude(bar_index) * 2.0, 0.0, 1.0);
+        let solo_gain = select(1.0, select(0.15, 1.0, soloed_index == bar_index), soloed_index >= 0);
+
+        // Louder bands push their particles outwards; alternate particles orbit the other way
+        let orbit = 0.08 + hash(seed) * 0.3 + amplitude * 0.1;
+        let direction = select(-1.0, 1.0, i % 2 == 0);
+        let speed = periodicRate(0.1 + hash(seed + 1.0) * 0.3, TAU);
+        let angle = hash(seed + 2.0) * TAU + direction * time * speed;
+        let center = vec2<f32>(cos(angle), sin(angle)) * orbit;
+
+        let radius = 0.003 + amplitude * 0.012;
+        let dist = sdfCircle(uv, center, radius);
+        let freq_ratio = f32(bar_index) / f32(bar_count);
+        let hue = freq_ratio * 0.8 + time * periodicRate(0.05, 1.0);
+        let color = hsv2rgb(vec3<f32>(hue, 0.8, 0.6 + amplitude * 0.4));
+        let alpha = smoothstep(0.001, 0.0, dist) + bloom(max(dist, 0.0), amplitude * 0.6, radius * 3.0);
+        final_color += color * alpha * solo_gain;
+    }
+
+    // Soft core pulsing with the overall energy
+    let energy = totalEnergy();
+    final_color += vec3<f32>(0.3, 0.15, 0.45) * bloom(length(uv), energy * 0.6, 0.05 + energy * 0.1);
+
+    return composeFrame(fragCoord.xy, final_color);
+}
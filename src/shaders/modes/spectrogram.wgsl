@@ -0,0 +1,25 @@
+// Spectrogram mode: recent bar levels scrolling right to left, low frequencies at the bottom
+@group(1) @binding(0) var history_texture: texture_2d<f32>; // one row of 64 bar levels per frame
+@group(1) @binding(1) var<uniform> history: vec4<f32>; // newest row, row count, padding
+
+@fragment
+fn fs_main(@builtin(position) fragCoord: vec4<f32>) -> @location(0) vec4<f32> {
+    var final_color = vec3<f32>(0.0);
+    let st = viewCoord(fragCoord.xy) / uniforms.resolution;
+
+    let rows = i32(history.y);
+    let bar_count = i32(uniforms.bin_size);
+    if (rows > 0 && bar_count > 0) {
+        // Newest frame at the right edge
+        let age = clamp(i32((1.0 - st.x) * f32(rows)), 0, rows - 1);
+        let row = (i32(history.x) - age + rows) % rows;
+        let bar_index = clamp(i32((1.0 - st.y) * f32(bar_count)), 0, bar_count - 1);
+        let level = clamp(textureLoad(history_texture, vec2<i32>(bar_index, row), 0).r * 2.0, 0.0, 1.0);
+
+        // Quiet cells are deep blue, loud ones run through magenta to warm white
+        let color = hsv2rgb(vec3<f32>(0.7 - level * 0.6, 1.0 - level * 0.6, level));
+        final_color = color * smoothstep(0.0, 0.15, level);
+    }
+
+    return composeFrame(fragCoord.xy, final_color);
+}
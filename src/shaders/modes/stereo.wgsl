@@ -0,0 +1,39 @@
+// Stereo mode: the left channel's bars grow up from the center line and the right channel's
+// mirror them downwards, so panning and width show at a glance
+
+const BAR_GAP: f32 = 0.25; // fraction of each slot left empty
+const MAX_HEIGHT: f32 = 0.42; // per channel, leaving a margin at the edges
+
+@fragment
+fn fs_main(@builtin(position) fragCoord: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = screenUv(viewCoord(fragCoord.xy));
+    var final_color = vec3<f32>(0.0);
+
+    let aspect = uniforms.resolution.x / uniforms.resolution.y;
+    let slot_width = aspect / max(uniforms.bin_size, 1.0);
+    let slot = (uv.x + aspect * 0.5) / slot_width;
+
+    if (slot >= 0.0 && slot < uniforms.bin_size) {
+        let bar_index = i32(slot);
+        // Above the center line is the left channel, below it the right
+        let left = uv.y >= 0.0;
+        let amplitude = clamp(select(rightBarAmplitude(bar_index), barAmplitude(bar_index), left) * 2.0, 0.0, 1.0);
+        let height = 0.004 + amplitude * MAX_HEIGHT;
+        let half_size = vec2<f32>(slot_width * (1.0 - BAR_GAP) * 0.5, height * 0.5);
+        let center = vec2<f32>((f32(bar_index) + 0.5) * slot_width - aspect * 0.5, half_size.y);
+        let dist = sdfRoundedBox(vec2<f32>(uv.x, abs(uv.y)), center, half_size, min(half_size.x * 0.5, half_size.y));
+
+        // Warm hues for the left channel, cool for the right
+        let freq_ratio = f32(bar_index) / max(uniforms.bin_size, 1.0);
+        let hue = select(0.55 + freq_ratio * 0.2, 0.95 + freq_ratio * 0.2, left);
+        let brightness = 0.5 + amplitude * 0.5;
+        let color = gradientColor(bar_index, amplitude, hsv2rgb(vec3<f32>(fract(hue), 0.8, brightness)));
+        let alpha = smoothstep(0.002, 0.0, dist) + bloom(max(dist, 0.0), amplitude * 0.3, 0.012);
+        final_color += color * alpha;
+    }
+
+    // Faint center line
+    final_color += vec3<f32>(0.15) * smoothstep(0.003, 0.0, abs(uv.y));
+
+    return composeFrame(fragCoord.xy, final_color);
+}
@@ -0,0 +1,55 @@
+// Tuner mode: a needle swinging over a -50 to +50 cent scale, centered when the pitch is on the
+// nearest note. The note name is drawn by the text layer inside the arc.
+
+const PIVOT: vec2<f32> = vec2<f32>(0.0, -0.3);
+const RADIUS: f32 = 0.55;
+const SWING: f32 = 1.0; // radians either side of center at +/-50 cents
+
+// Green within 5 cents, amber within 15, red beyond
+fn tuningColor(cents: f32) -> vec3<f32> {
+    let off = abs(cents);
+    if (off <= 5.0) {
+        return vec3<f32>(0.3, 1.0, 0.45);
+    }
+    if (off <= 15.0) {
+        return vec3<f32>(1.0, 0.75, 0.25);
+    }
+    return vec3<f32>(1.0, 0.3, 0.25);
+}
+
+@fragment
+fn fs_main(@builtin(position) fragCoord: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = screenUv(viewCoord(fragCoord.xy));
+    var final_color = vec3<f32>(0.0);
+    let p = uv - PIVOT;
+    let radius = length(p);
+    // Angle from straight up, positive to the right
+    let angle = atan2(p.x, p.y);
+
+    // Scale: an arc with a tick every 10 cents, the center one longer
+    if (abs(angle) <= SWING + 0.02) {
+        final_color += vec3<f32>(0.25) * smoothstep(0.003, 0.0, abs(radius - RADIUS));
+        let tick = round(angle / SWING * 5.0);
+        let tick_distance = abs(angle - tick * SWING / 5.0) * radius;
+        let tick_length = select(0.04, 0.08, tick == 0.0);
+        if (radius > RADIUS - tick_length && radius < RADIUS) {
+            final_color += select(vec3<f32>(0.4), tuningColor(0.0), tick == 0.0) * smoothstep(0.003, 0.0, tick_distance);
+        }
+    }
+
+    if (uniforms.tuner.x > 0.5) {
+        let cents = clamp(uniforms.tuner.y, -50.0, 50.0);
+        let needle_angle = cents / 50.0 * SWING;
+        let tip = PIVOT + vec2<f32>(sin(needle_angle), cos(needle_angle)) * (RADIUS - 0.01);
+        let dist = sdfLine(uv, PIVOT, tip);
+        let color = tuningColor(cents);
+        final_color += color * (smoothstep(0.004, 0.0, dist) + bloom(dist, 0.25, 0.02));
+
+        // Soft glow around the pivot in the note's chroma hue
+        let hue = pitchClassHue(i32(uniforms.tuner.z));
+        final_color += hsv2rgb(vec3<f32>(hue, 0.7, 0.8)) * bloom(sdfCircle(uv, PIVOT, 0.02), 0.6, 0.03);
+    }
+    final_color += vec3<f32>(0.6) * smoothstep(0.003, 0.0, sdfCircle(uv, PIVOT, 0.015));
+
+    return composeFrame(fragCoord.xy, final_color);
+}
@@ -0,0 +1,28 @@
+// Waterfall mode: the live bars in front of the last few seconds of bar levels, which scroll
+// upward from the bottom edge and fade as they age
+@group(1) @binding(0) var history_texture: texture_2d<f32>; // one row of 64 bar levels per time step
+@group(1) @binding(1) var<uniform> history: vec4<f32>; // newest row, row count, padding
+
+@fragment
+fn fs_main(@builtin(position) fragCoord: vec4<f32>) -> @location(0) vec4<f32> {
+    let st = viewCoord(fragCoord.xy) / uniforms.resolution;
+    var final_color = vec3<f32>(0.0);
+
+    let rows = i32(history.y);
+    let bar_count = i32(uniforms.bin_size);
+    if (rows > 0 && bar_count > 0) {
+        // Newest row at the bottom edge, behind the bases of the live bars
+        let age = clamp(i32((1.0 - st.y) * f32(rows)), 0, rows - 1);
+        let row = (i32(history.x) - age + rows) % rows;
+        let bar_index = clamp(i32(st.x * f32(bar_count)), 0, bar_count - 1);
+        let level = clamp(textureLoad(history_texture, vec2<i32>(bar_index, row), 0).r * 2.0, 0.0, 1.0);
+
+        // Same hues as the bars, dimmer and fading out towards the oldest row
+        let hue = f32(bar_index) / uniforms.bin_size * 0.8 + uniforms.time * periodicRate(0.05, 1.0);
+        let color = gradientColor(bar_index, level, hsv2rgb(vec3<f32>(hue, 0.8, 0.5 + level * 0.5)));
+        let fade = 1.0 - f32(age) / f32(rows);
+        final_color = color * smoothstep(0.0, 0.15, level) * fade * fade * 0.6;
+    }
+
+    return composeFrame(fragCoord.xy, final_color + barsLayer(fragCoord.xy));
+}
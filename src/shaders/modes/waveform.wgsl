@@ -0,0 +1,35 @@
+// Waveform mode: the whole track's waveform across the canvas, lit up to the playhead and
+// swelling with the current energy
+@fragment
+fn fs_main(@builtin(position) fragCoord: vec4<f32>) -> @location(0) vec4<f32> {
+    let coord = viewCoord(fragCoord.xy);
+    let uv = screenUv(coord);
+    var final_color = vec3<f32>(0.0);
+    let time = uniforms.time;
+    let energy = totalEnergy();
+
+    let columns = i32(uniforms.waveform.w);
+    if (columns > 0) {
+        let x_norm = coord.x / uniforms.resolution.x;
+        let playhead = uniforms.waveform.z;
+        let column = clamp(i32(x_norm * f32(columns)), 0, columns - 1);
+        let min_max = textureLoad(waveform_texture, vec2<i32>(column, 0), 0).xy;
+
+        // Samples span -1..1; scale them to most of the canvas height
+        let half_height = 0.35 + energy * 0.1;
+        let sample_y = uv.y / half_height;
+        let inside = sample_y >= min_max.x && sample_y <= min_max.y;
+
+        // Played portion is colored and bright, the remainder is a dim outline of what's to come
+        let hue = x_norm * 0.8 + time * periodicRate(0.05, 1.0);
+        let played = x_norm <= playhead;
+        let color = hsv2rgb(vec3<f32>(hue, select(0.2, 0.8, played), select(0.3, 0.9, played)));
+        final_color += color * select(0.0, 1.0, inside);
+
+        // Glowing playhead
+        let playhead_dist = abs(coord.x - playhead * uniforms.resolution.x) / uniforms.resolution.y;
+        final_color += vec3<f32>(1.0, 0.95, 0.9) * bloom(playhead_dist, 0.6 + energy, 0.004);
+    }
+
+    return composeFrame(fragCoord.xy, final_color);
+}
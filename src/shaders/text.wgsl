@@ -0,0 +1,53 @@
+// Instanced glyph quads sampling the built-in font atlas
+
+struct Screen {
+    size: vec2<f32>,
+    options: vec2<f32>, // sRGB target (0/1), padding
+}
+@group(0) @binding(0) var<uniform> screen: Screen;
+@group(0) @binding(1) var atlas_texture: texture_2d<f32>;
+@group(0) @binding(2) var atlas_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coord: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertexIndex: u32,
+    @location(0) rect: vec4<f32>,   // x, y, width, height in pixels (y down)
+    @location(1) uv_rect: vec4<f32>, // u0, v0, u1, v1
+    @location(2) color: vec4<f32>,
+) -> VertexOutput {
+    // Two triangles covering the unit quad
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0)
+    );
+    let corner = corners[vertexIndex];
+    let pixel = rect.xy + corner * rect.zw;
+    let ndc = vec2<f32>(pixel.x / screen.size.x * 2.0 - 1.0, 1.0 - pixel.y / screen.size.y * 2.0);
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(ndc, 0.0, 1.0);
+    out.tex_coord = mix(uv_rect.xy, uv_rect.zw, corner);
+    out.color = color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let coverage = textureSample(atlas_texture, atlas_sampler, in.tex_coord).r;
+    var color = in.color.rgb;
+    if (screen.options.x > 0.5) {
+        let c = clamp(color, vec3<f32>(0.0), vec3<f32>(1.0));
+        color = select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+    }
+    return vec4<f32>(color, in.color.a * coverage);
+}
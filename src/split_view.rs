@@ -0,0 +1,88 @@
+use wgpu::*;
+
+use crate::layout::SplitLayout;
+use crate::transition::Crossfade;
+use crate::uniforms::{Bars, Uniforms};
+
+/// A second view drawn next to the renderer's own on the same target, with its own mode and
+/// uniforms. Each view is drawn full-frame into a texture of its viewport's size, so the modes
+/// need no notion of viewports, and the two are then composited into their viewports.
+pub struct SplitView {
+    pub layout: SplitLayout,
+    // Index of the second view's visualizer mode
+    pub mode: usize,
+    pub uniform_buffer: Buffer,
+    pub bars_buffer: Buffer,
+    // Same bindings as the renderer's uniform bind group, with this view's buffers
+    pub bind_group: BindGroup,
+    // Uniform and bar floats as last uploaded, as for the renderer's own
+    pub last_uniforms: Vec<f32>,
+    pub last_bars: Vec<f32>,
+    first: Crossfade,
+    second: Crossfade,
+    // Size of the target the views were last prepared for
+    size: (u32, u32),
+}
+
+impl SplitView {
+    /// `None` when `bind_group` can't bind the view's uniform and bars buffers yet.
+    pub fn new(
+        device: &Device,
+        format: TextureFormat,
+        layout: SplitLayout,
+        mode: usize,
+        bind_group: impl FnOnce(&Buffer, &Buffer) -> Option<BindGroup>,
+    ) -> Option<Self> {
+        let uniform_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Split View Uniform Buffer"),
+            size: std::mem::size_of::<Uniforms>() as BufferAddress,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bars_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Split View Bars Buffer"),
+            size: std::mem::size_of::<Bars>() as BufferAddress,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = bind_group(&uniform_buffer, &bars_buffer)?;
+        Some(Self {
+            layout,
+            mode,
+            uniform_buffer,
+            bars_buffer,
+            bind_group,
+            last_uniforms: Vec::new(),
+            last_bars: Vec::new(),
+            first: Crossfade::new(device, format),
+            second: Crossfade::new(device, format),
+            size: (0, 0),
+        })
+    }
+
+    /// Size the views' textures for a `width` x `height` target.
+    pub fn prepare(&mut self, device: &Device, width: u32, height: u32) {
+        let [(_, _, first_width, first_height), (_, _, second_width, second_height)] = self.layout.viewports(width, height);
+        self.first.prepare(device, first_width, first_height);
+        self.second.prepare(device, second_width, second_height);
+        self.size = (width, height);
+    }
+
+    /// Textures the first and second views are drawn into, once `prepare` has run.
+    pub fn views(&self) -> Option<(&TextureView, &TextureView)> {
+        self.first.view().zip(self.second.view())
+    }
+
+    /// Copy both views into their viewports of `render_pass`'s target, then restore the full
+    /// viewport for whatever is drawn on top.
+    pub fn composite(&self, render_pass: &mut RenderPass) {
+        let (width, height) = self.size;
+        for (view, (x, y, view_width, view_height)) in [&self.first, &self.second].into_iter().zip(self.layout.viewports(width, height)) {
+            render_pass.set_viewport(x as f32, y as f32, view_width as f32, view_height as f32, 0.0, 1.0);
+            render_pass.set_scissor_rect(x, y, view_width, view_height);
+            view.composite(render_pass, 0.0);
+        }
+        render_pass.set_viewport(0.0, 0.0, width as f32, height as f32, 0.0, 1.0);
+        render_pass.set_scissor_rect(0, 0, width, height);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::analysis::{DecodedAudio, MIN_SAMPLE_RATE};
+use crate::batch::{self, BlobReader};
+use crate::config::ViberConfig;
+use crate::markers::Marker;
+
+/// Format version of the state blobs produced by `App.serialize_state`.
+pub const STATE_VERSION: u32 = 1;
+const STATE_MAGIC: &[u8; 4] = b"VBST";
+
+/// Everything `App.restore_state` needs to bring a visualizer back: its settings, the decoded
+/// audio its analysis is recomputed from, and the markers.
+pub struct AppState {
+    pub config: ViberConfig,
+    pub audio: Option<DecodedAudio>,
+    pub markers: Vec<Marker>,
+}
+
+impl AppState {
+    /// Pack the state into a blob, all little-endian:
+    ///
+    /// | offset | contents                                              |
+    /// |--------|-------------------------------------------------------|
+    /// | 0      | magic `VBST`                                          |
+    /// | 4      | u32 format version (`STATE_VERSION`)                  |
+    /// | 8      | u32 config length in bytes, then the config as JSON   |
+    ///
+    /// followed by a u32 that is 1 when audio is loaded and 0 otherwise. Loaded audio follows as
+    /// a u32 sample rate, u16 channel count, u16 bits per sample, then the left (or mono) and the
+    /// right channel, each a u32 sample count and its i16 samples (no right samples for mono).
+    /// The markers come last, stored as in analysis blobs.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let config = serde_json::to_vec(&self.config).expect("settings serialize to JSON");
+        let mut blob = Vec::new();
+        blob.extend_from_slice(STATE_MAGIC);
+        blob.extend_from_slice(&STATE_VERSION.to_le_bytes());
+        blob.extend_from_slice(&(config.len() as u32).to_le_bytes());
+        blob.extend_from_slice(&config);
+        match &self.audio {
+            Some(audio) => {
+                blob.extend_from_slice(&1u32.to_le_bytes());
+                blob.extend_from_slice(&audio.sample_rate.to_le_bytes());
+                blob.extend_from_slice(&audio.channels.to_le_bytes());
+                blob.extend_from_slice(&audio.bits_per_sample.to_le_bytes());
+                for channel in [&audio.samples[..], audio.right.as_deref().unwrap_or_default()] {
+                    blob.extend_from_slice(&(channel.len() as u32).to_le_bytes());
+                    for sample in channel {
+                        blob.extend_from_slice(&sample.to_le_bytes());
+                    }
+                }
+            }
+            None => blob.extend_from_slice(&0u32.to_le_bytes()),
+        }
+        batch::write_markers(&mut blob, &self.markers);
+        blob
+    }
+
+    /// Read a blob from `to_bytes` back.
+    pub fn from_bytes(blob: &[u8]) -> Result<Self, String> {
+        let mut reader = BlobReader::new(blob, "State blob");
+        if reader.bytes(4)? != STATE_MAGIC {
+            return Err("Not a state blob".to_string());
+        }
+        let version = reader.u32()?;
+        if version > STATE_VERSION {
+            return Err(format!("Unsupported state blob version {}", version));
+        }
+        let config_length = reader.u32()? as usize;
+        let config = serde_json::from_slice(reader.bytes(config_length)?).map_err(|e| format!("Invalid settings in state blob: {}", e))?;
+        let audio = match reader.u32()? {
+            0 => None,
+            _ => {
+                let sample_rate = reader.u32()?;
+                let channels = reader.u16()?;
+                let bits_per_sample = reader.u16()?;
+                let mut channel = || -> Result<Vec<i16>, String> {
+                    let count = reader.u32()? as usize;
+                    let bytes = reader.bytes(count.checked_mul(2).ok_or("State blob sizes overflow")?)?;
+                    Ok(bytes.chunks_exact(2).map(|sample| i16::from_le_bytes([sample[0], sample[1]])).collect())
+                };
+                let samples = channel()?;
+                let right = channel()?;
+                // Both would panic once the analysis runs on the restored audio
+                if sample_rate < MIN_SAMPLE_RATE {
+                    return Err(format!("State blob audio has a sample rate of {} Hz, below the {} Hz minimum", sample_rate, MIN_SAMPLE_RATE));
+                }
+                if !right.is_empty() && right.len() != samples.len() {
+                    return Err(format!("State blob channels differ in length: {} left and {} right samples", samples.len(), right.len()));
+                }
+                Some(DecodedAudio {
+                    samples,
+                    right: (!right.is_empty()).then_some(right),
+                    sample_rate,
+                    channels,
+                    bits_per_sample,
+                })
+            }
+        };
+        let markers = reader.markers()?;
+        Ok(Self { config, audio, markers })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(right: Option<Vec<i16>>, sample_rate: u32) -> AppState {
+        AppState {
+            config: ViberConfig::default(),
+            audio: Some(DecodedAudio {
+                samples: vec![1, -2, 3, i16::MIN],
+                channels: if right.is_some() { 2 } else { 1 },
+                right,
+                sample_rate,
+                bits_per_sample: 16,
+            }),
+            markers: vec![Marker {
+                seconds: 1.5,
+                label: "chorus".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let original = state(Some(vec![4, 5, -6, i16::MAX]), 44_100);
+        let blob = original.to_bytes();
+        let restored = AppState::from_bytes(&blob).unwrap();
+
+        let audio = restored.audio.as_ref().unwrap();
+        assert_eq!(audio.samples, [1, -2, 3, i16::MIN]);
+        assert_eq!(audio.right.as_deref(), Some(&[4, 5, -6, i16::MAX][..]));
+        assert_eq!((audio.sample_rate, audio.channels, audio.bits_per_sample), (44_100, 2, 16));
+        assert_eq!(restored.markers, original.markers);
+        assert_eq!(restored.config, original.config);
+        assert_eq!(restored.to_bytes(), blob);
+
+        let mono = AppState::from_bytes(&state(None, MIN_SAMPLE_RATE).to_bytes()).unwrap();
+        assert_eq!(mono.audio.unwrap().right, None);
+    }
+
+    #[test]
+    fn round_trip_without_audio() {
+        let empty = AppState {
+            config: ViberConfig::default(),
+            audio: None,
+            markers: Vec::new(),
+        };
+        let restored = AppState::from_bytes(&empty.to_bytes()).unwrap();
+        assert!(restored.audio.is_none() && restored.markers.is_empty());
+    }
+
+    #[test]
+    fn rejects_mismatched_channels() {
+        let error = AppState::from_bytes(&state(Some(vec![4, 5]), 44_100).to_bytes()).err().unwrap();
+        assert!(error.contains("differ in length"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_sample_rates_the_analysis_cant_frame() {
+        for sample_rate in [0, 1, 119, MIN_SAMPLE_RATE - 1] {
+            let error = AppState::from_bytes(&state(None, sample_rate).to_bytes()).err().unwrap();
+            assert!(error.contains(&format!("sample rate of {} Hz", sample_rate)), "{}", error);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_blobs() {
+        let blob = state(None, 44_100).to_bytes();
+        assert!(AppState::from_bytes(b"VBSX").is_err());
+        assert!(AppState::from_bytes(&blob[..blob.len() - 1]).is_err());
+        for length in [0, 4, 8, 12, 20] {
+            assert!(AppState::from_bytes(&blob[..length]).is_err(), "{} bytes", length);
+        }
+
+        let mut newer = blob.clone();
+        newer[4..8].copy_from_slice(&(STATE_VERSION + 1).to_le_bytes());
+        assert!(AppState::from_bytes(&newer).err().unwrap().contains("Unsupported"));
+
+        let mut bad_config = blob;
+        bad_config[12] = b'[';
+        assert!(AppState::from_bytes(&bad_config).err().unwrap().contains("Invalid settings"));
+    }
+}
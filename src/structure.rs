@@ -0,0 +1,159 @@
+//! Section boundaries (intro, verse, chorus and so on) from the bar frames: frames are averaged
+//! into short blocks, compared with each other, and a checkerboard kernel slid along the diagonal
+//! of that self-similarity matrix (Foote, 2000) scores how strongly the music before each block
+//! differs from the music after it. Peaks of that novelty curve are the boundaries. Only the band
+//! of the matrix the kernel covers is computed, so long tracks cost linear time and memory.
+
+use wasm_bindgen::prelude::*;
+
+// Frames are averaged into blocks this long before comparing, keeping the matrix small
+const BLOCK_SECONDS: f64 = 0.5;
+// Reach of the novelty kernel either side of a candidate boundary
+const KERNEL_SECONDS: f64 = 8.0;
+// Shortest section reported; closer novelty peaks keep only the stronger one
+pub const MIN_SECTION_SECONDS: f64 = 8.0;
+// Novelty peaks must stand this many standard deviations above the curve's mean
+const PEAK_THRESHOLD_DEVIATIONS: f32 = 0.5;
+// ...and above this, so the small wobbles of a steady track don't split it. A change of a single
+// sustained note scores around 0.01.
+const MIN_NOVELTY: f32 = 0.003;
+
+/// A stretch of the track between two detected structural boundaries.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Section {
+    pub start_seconds: f64,
+    pub end_seconds: f64,
+    /// Novelty of the boundary the section starts at, relative to the strongest boundary
+    /// (0.0-1.0); 0.0 for the first section
+    pub strength: f32,
+}
+
+/// Boundaries between sections of `frames`, as frame indices with their relative novelty, in
+/// order. Frame 0 isn't included; tracks too short for two sections give none.
+pub fn section_boundaries(frames: &[Vec<f32>], frames_per_second: f64) -> Vec<(usize, f32)> {
+    let block_frames = ((BLOCK_SECONDS * frames_per_second).round() as usize).max(1);
+    let blocks: Vec<Vec<f32>> = frames.chunks(block_frames).map(mean_frame).collect();
+    let kernel_blocks = ((KERNEL_SECONDS / BLOCK_SECONDS).round() as usize).max(1);
+    let min_gap = ((MIN_SECTION_SECONDS / BLOCK_SECONDS).round() as usize).max(1);
+    if blocks.len() < 2 * min_gap {
+        return Vec::new();
+    }
+
+    // The kernel never compares blocks `2 * kernel_blocks` or more apart: `band[i][d]` is the
+    // similarity of blocks `i` and `i + d` for the offsets it reaches
+    let band: Vec<Vec<f32>> = (0..blocks.len())
+        .map(|i| {
+            let reached = &blocks[i..(i + 2 * kernel_blocks).min(blocks.len())];
+            reached.iter().map(|other| cosine_similarity(&blocks[i], other)).collect()
+        })
+        .collect();
+    let novelty: Vec<f32> = (0..blocks.len()).map(|center| checkerboard_novelty(&band, center, kernel_blocks)).collect();
+
+    // Boundaries closer to the ends than a section's length aren't considered, nor counted in the
+    // threshold, as the kernel is cut off there
+    let candidate_range = min_gap..=blocks.len() - min_gap;
+    let considered = &novelty[candidate_range.clone()];
+    let mean = considered.iter().sum::<f32>() / considered.len() as f32;
+    let deviation = (considered.iter().map(|n| (n - mean).powi(2)).sum::<f32>() / considered.len() as f32).sqrt();
+    let threshold = (mean + PEAK_THRESHOLD_DEVIATIONS * deviation).max(MIN_NOVELTY);
+
+    // Strongest peaks first, each claiming the blocks within `min_gap` of it
+    let mut candidates: Vec<usize> = candidate_range
+        .filter(|&block| novelty[block] >= threshold)
+        .filter(|&block| novelty[block] >= novelty[block - 1] && novelty.get(block + 1).is_none_or(|&next| novelty[block] > next))
+        .collect();
+    candidates.sort_by(|&a, &b| novelty[b].total_cmp(&novelty[a]));
+    let mut chosen: Vec<usize> = Vec::new();
+    for block in candidates {
+        if chosen.iter().all(|&other| other.abs_diff(block) >= min_gap) {
+            chosen.push(block);
+        }
+    }
+    chosen.sort_unstable();
+
+    let strongest = chosen.iter().map(|&block| novelty[block]).fold(0.0f32, f32::max);
+    chosen
+        .into_iter()
+        .map(|block| ((block * block_frames).min(frames.len()), novelty[block] / strongest))
+        .collect()
+}
+
+fn mean_frame(frames: &[Vec<f32>]) -> Vec<f32> {
+    let width = frames.iter().map(Vec::len).max().unwrap_or(0);
+    let mut mean = vec![0.0; width];
+    for frame in frames {
+        for (total, value) in mean.iter_mut().zip(frame) {
+            *total += value;
+        }
+    }
+    mean.iter_mut().for_each(|total| *total /= frames.len() as f32);
+    mean
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norms = a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|y| y * y).sum::<f32>().sqrt();
+    if norms > 0.0 {
+        dot / norms
+    } else {
+        0.0
+    }
+}
+
+// Similarity within the `reach` blocks before and within those after `center`, less the
+// similarity across them, with a Gaussian taper so nearby blocks count most. Near the ends the
+// kernel is cut off and normalized by what remains. `band` is the similarity band built by
+// `section_boundaries`, at least `2 * reach` wide.
+fn checkerboard_novelty(band: &[Vec<f32>], center: usize, reach: usize) -> f32 {
+    let sigma = reach as f32 * 0.5;
+    let window = center.saturating_sub(reach)..(center + reach).min(band.len());
+    let (mut total, mut weight_sum) = (0.0, 0.0);
+    for i in window.clone() {
+        for j in window.clone() {
+            let value = band[i.min(j)][i.abs_diff(j)];
+            // Offsets from the boundary, which lies just before block `center`
+            let (di, dj) = (i as f32 - center as f32 + 0.5, j as f32 - center as f32 + 0.5);
+            let weight = (-(di * di + dj * dj) / (2.0 * sigma * sigma)).exp();
+            let sign = if (di < 0.0) == (dj < 0.0) { 1.0 } else { -1.0 };
+            total += sign * weight * value;
+            weight_sum += weight;
+        }
+    }
+    if weight_sum > 0.0 {
+        total / weight_sum
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAMES_PER_SECOND: f64 = 20.0;
+
+    fn section(bars: [f32; 4], seconds: f64) -> Vec<Vec<f32>> {
+        vec![bars.to_vec(); (seconds * FRAMES_PER_SECOND) as usize]
+    }
+
+    #[test]
+    fn finds_the_change_between_two_sections() {
+        let mut frames = section([1.0, 0.2, 0.0, 0.0], 30.0);
+        frames.extend(section([0.0, 0.0, 0.3, 1.0], 30.0));
+        assert_eq!(section_boundaries(&frames, FRAMES_PER_SECOND), [(600, 1.0)]);
+    }
+
+    #[test]
+    fn long_steady_track_has_no_boundaries() {
+        let frames = section([0.5, 0.4, 0.3, 0.2], 600.0);
+        assert!(section_boundaries(&frames, FRAMES_PER_SECOND).is_empty());
+    }
+
+    #[test]
+    fn short_track_has_no_boundaries() {
+        let mut frames = section([1.0, 0.0, 0.0, 0.0], 6.0);
+        frames.extend(section([0.0, 0.0, 0.0, 1.0], 6.0));
+        assert!(section_boundaries(&frames, FRAMES_PER_SECOND).is_empty());
+    }
+}
@@ -0,0 +1,105 @@
+use wasm_bindgen::prelude::*;
+
+// Upper edges of the frame rate buckets; the last bucket collects everything above
+const FPS_BUCKET_EDGES: [f32; 6] = [15.0, 30.0, 45.0, 55.0, 65.0, 100.0];
+// Frame gaps longer than this (tab switches, paused rendering) are not counted
+const MAX_FRAME_DELTA: f64 = 1.0;
+const MIN_INTERVAL_SECONDS: f64 = 10.0;
+
+/// Aggregated performance and capability stats for one reporting period. Contains nothing that
+/// identifies the user, the device model or the audio being played.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct TelemetryReport {
+    /// Length of the reporting period in seconds
+    pub period_seconds: f64,
+    /// Graphics backend, e.g. "BrowserWebGpu" or "Gl"
+    pub backend: String,
+    /// Adapter class: "DiscreteGpu", "IntegratedGpu", "VirtualGpu", "Cpu" or "Other"
+    /// ("unknown" before the renderer is initialized)
+    pub device_type: String,
+    /// Frames rendered during the period
+    pub frames: u32,
+    /// Upper edges in fps of the `fps_histogram` buckets; the last bucket has no upper edge
+    pub fps_bucket_edges: Vec<f32>,
+    /// Frame counts per frame rate bucket
+    pub fps_histogram: Vec<u32>,
+    /// Tracks analyzed during the period
+    pub tracks_processed: u32,
+    /// Analysis time in milliseconds per minute of audio, if a track was analyzed
+    pub processing_ms_per_audio_minute: Option<f64>,
+}
+
+/// Collects frame and analysis timings and hands a report to the host callback once per interval.
+pub struct Telemetry {
+    callback: js_sys::Function,
+    interval_seconds: f64,
+    period_start: Option<f64>,
+    frames: u32,
+    fps_histogram: [u32; FPS_BUCKET_EDGES.len() + 1],
+    tracks_processed: u32,
+    processing_seconds: f64,
+    audio_seconds: f64,
+}
+
+impl Telemetry {
+    pub fn new(callback: js_sys::Function, interval_seconds: f64) -> Self {
+        Self {
+            callback,
+            interval_seconds: interval_seconds.max(MIN_INTERVAL_SECONDS),
+            period_start: None,
+            frames: 0,
+            fps_histogram: [0; FPS_BUCKET_EDGES.len() + 1],
+            tracks_processed: 0,
+            processing_seconds: 0.0,
+            audio_seconds: 0.0,
+        }
+    }
+
+    /// Count a rendered frame given the time since the previous one.
+    pub fn record_frame(&mut self, delta: Option<f64>) {
+        self.frames += 1;
+        if let Some(delta) = delta.filter(|&delta| delta > 0.0 && delta < MAX_FRAME_DELTA) {
+            let fps = (1.0 / delta) as f32;
+            let bucket = FPS_BUCKET_EDGES.iter().position(|&edge| fps < edge).unwrap_or(FPS_BUCKET_EDGES.len());
+            self.fps_histogram[bucket] += 1;
+        }
+    }
+
+    /// Record how long analyzing `audio_seconds` of audio took.
+    pub fn record_processing(&mut self, processing_seconds: f64, audio_seconds: f64) {
+        self.tracks_processed += 1;
+        self.processing_seconds += processing_seconds;
+        self.audio_seconds += audio_seconds;
+    }
+
+    /// Send the report to the callback if the interval has elapsed at render `time` (seconds),
+    /// then start a new period. Time running backwards restarts the period.
+    pub fn report_if_due(&mut self, time: f64, backend: &str, device_type: &str) {
+        let start = match self.period_start {
+            Some(start) if time >= start => start,
+            _ => *self.period_start.insert(time),
+        };
+        if time - start < self.interval_seconds {
+            return;
+        }
+
+        let report = TelemetryReport {
+            period_seconds: time - start,
+            backend: backend.to_string(),
+            device_type: device_type.to_string(),
+            frames: self.frames,
+            fps_bucket_edges: FPS_BUCKET_EDGES.to_vec(),
+            fps_histogram: self.fps_histogram.to_vec(),
+            tracks_processed: self.tracks_processed,
+            processing_ms_per_audio_minute: (self.audio_seconds > 0.0)
+                .then(|| self.processing_seconds * 1000.0 / (self.audio_seconds / 60.0)),
+        };
+        *self = Self::new(self.callback.clone(), self.interval_seconds);
+        self.period_start = Some(time);
+
+        if let Err(e) = self.callback.call1(&JsValue::NULL, &JsValue::from(report)) {
+            log!("Telemetry callback failed: {:?}", e);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use wasm_bindgen::prelude::*;
+
+// How often (seconds of render time) an automatic scheme re-checks the browser preference
+const AUTO_SCHEME_RECHECK_SECONDS: f64 = 1.0;
+
+/// Requested color scheme; `Auto` follows the browser's `prefers-color-scheme`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ColorScheme {
+    Auto,
+    #[default]
+    Dark,
+    Light,
+}
+
+impl ColorScheme {
+    pub fn parse(name: &str) -> Result<Self, JsValue> {
+        match name {
+            "auto" => Ok(ColorScheme::Auto),
+            "dark" => Ok(ColorScheme::Dark),
+            "light" => Ok(ColorScheme::Light),
+            _ => Err(JsValue::from_str(&format!("Unknown color scheme '{}', expected 'auto', 'dark' or 'light'", name))),
+        }
+    }
+
+    /// Name accepted by `parse`.
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorScheme::Auto => "auto",
+            ColorScheme::Dark => "dark",
+            ColorScheme::Light => "light",
+        }
+    }
+}
+
+/// The palette every mode is drawn with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Theme {
+    /// Light background with dark content instead of glowing content on black
+    pub light: bool,
+    /// Drop faint glow and keep a minimum luminance difference between content and background
+    pub high_contrast: bool,
+}
+
+impl Theme {
+    /// Overlay text color for this theme: darkened on light backgrounds, opaque in high contrast.
+    pub fn text_color(self, [r, g, b, a]: [f32; 4]) -> [f32; 4] {
+        let (r, g, b) = if self.light { (r * 0.2, g * 0.2, b * 0.2) } else { (r, g, b) };
+        [r, g, b, if self.high_contrast { 1.0 } else { a }]
+    }
+}
+
+/// Host theme settings, resolved to a `Theme` as frames are rendered.
+#[derive(Debug, Default)]
+pub struct ThemeSettings {
+    pub scheme: ColorScheme,
+    pub high_contrast: bool,
+    last_check: Option<f64>,
+}
+
+impl ThemeSettings {
+    pub fn new(scheme: ColorScheme, high_contrast: bool) -> Self {
+        Self {
+            scheme,
+            high_contrast,
+            last_check: None,
+        }
+    }
+
+    /// The theme to draw with at render `time`, or `None` when it can't have changed since the
+    /// last call. An automatic scheme re-reads the browser preference about once a second.
+    pub fn poll(&mut self, time: f64) -> Option<Theme> {
+        if let Some(last) = self.last_check {
+            let recheck = self.scheme == ColorScheme::Auto && (time < last || time - last >= AUTO_SCHEME_RECHECK_SECONDS);
+            if !recheck {
+                return None;
+            }
+        }
+        self.last_check = Some(time);
+        Some(self.resolve())
+    }
+
+    fn resolve(&self) -> Theme {
+        let light = match self.scheme {
+            ColorScheme::Auto => prefers_light_scheme(),
+            ColorScheme::Dark => false,
+            ColorScheme::Light => true,
+        };
+        Theme {
+            light,
+            high_contrast: self.high_contrast,
+        }
+    }
+}
+
+// Browser `prefers-color-scheme`; dark where it can't be queried
+fn prefers_light_scheme() -> bool {
+    #[cfg(target_arch = "wasm32")]
+    return web_sys::window()
+        .and_then(|window| window.match_media("(prefers-color-scheme: light)").ok().flatten())
+        .is_some_and(|query| query.matches());
+    #[cfg(not(target_arch = "wasm32"))]
+    return false;
+}
@@ -0,0 +1,124 @@
+//! Positions in an analyzed track and the conversions between them. Seconds, sample indices and
+//! analysis frame indices each get their own type, and every conversion goes through a
+//! `TimeBase` carrying the sample rate and hop size, so there is one place that decides how a
+//! time maps to a frame.
+
+use wasm_bindgen::prelude::*;
+
+// Products of seconds and the sample rate this close to a whole sample are taken to be exactly
+// on it; float rounding would otherwise put round-tripped frame times one sample early
+const SAMPLE_EPSILON: f64 = 1e-6;
+
+/// Position in seconds from the start of the track.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Timestamp(pub f64);
+
+/// Index of a sample in the analyzed (mono) signal.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SampleIndex(pub usize);
+
+/// Index of an analysis frame. Frame `i` starts at sample `start_sample + i * hop_size`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FrameIndex(pub usize);
+
+/// Sample rate, hop size and start of an analysis: everything needed to convert between
+/// seconds, samples and frames. A time maps to the frame that starts at or before it.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeBase {
+    pub sample_rate: u32,
+    /// Samples between the starts of consecutive analysis frames
+    pub hop_size: usize,
+    /// Sample the first frame starts at; nonzero when only part of the track was analyzed
+    pub start_sample: usize,
+}
+
+impl TimeBase {
+    pub fn new(sample_rate: u32, hop_size: usize, start_sample: usize) -> Self {
+        Self {
+            sample_rate: sample_rate.max(1),
+            hop_size: hop_size.max(1),
+            start_sample,
+        }
+    }
+
+    /// The sample playing at `time`; times before the start map to sample 0.
+    pub fn sample_at(self, time: Timestamp) -> SampleIndex {
+        let exact = time.0.max(0.0) * self.sample_rate as f64;
+        let nearest = exact.round();
+        let sample = if (exact - nearest).abs() < SAMPLE_EPSILON { nearest } else { exact.floor() };
+        SampleIndex(sample as usize)
+    }
+
+    pub fn sample_time(self, sample: SampleIndex) -> Timestamp {
+        Timestamp(sample.0 as f64 / self.sample_rate as f64)
+    }
+
+    /// The frame starting at or before `sample`; samples before the first frame map to frame 0.
+    pub fn frame_of_sample(self, sample: SampleIndex) -> FrameIndex {
+        FrameIndex(sample.0.saturating_sub(self.start_sample) / self.hop_size)
+    }
+
+    pub fn frame_start(self, frame: FrameIndex) -> SampleIndex {
+        SampleIndex(self.start_sample + frame.0 * self.hop_size)
+    }
+
+    /// The frame starting at or before `time`.
+    pub fn frame_at(self, time: Timestamp) -> FrameIndex {
+        self.frame_of_sample(self.sample_at(time))
+    }
+
+    pub fn frame_time(self, frame: FrameIndex) -> Timestamp {
+        self.sample_time(self.frame_start(frame))
+    }
+
+    /// Frames elapsed between the start of frame 0 and `time`, with the fraction of the way into
+    /// the current frame; times before the start are 0.0.
+    pub fn frame_position(self, time: Timestamp) -> f64 {
+        ((time.0 * self.sample_rate as f64 - self.start_sample as f64) / self.hop_size as f64).max(0.0)
+    }
+}
+
+#[wasm_bindgen]
+impl TimeBase {
+    /// Analysis frames per second of audio.
+    #[wasm_bindgen]
+    pub fn frames_per_second(&self) -> f64 {
+        self.sample_rate as f64 / self.hop_size as f64
+    }
+
+    /// Frame starting at or before `seconds`.
+    #[wasm_bindgen]
+    pub fn seconds_to_frame(&self, seconds: f64) -> usize {
+        self.frame_at(Timestamp(seconds)).0
+    }
+
+    /// Start of a frame in seconds.
+    #[wasm_bindgen]
+    pub fn frame_to_seconds(&self, frame_index: usize) -> f64 {
+        self.frame_time(FrameIndex(frame_index)).0
+    }
+
+    /// Sample playing at `seconds`.
+    #[wasm_bindgen]
+    pub fn seconds_to_sample(&self, seconds: f64) -> usize {
+        self.sample_at(Timestamp(seconds)).0
+    }
+
+    #[wasm_bindgen]
+    pub fn sample_to_seconds(&self, sample_index: usize) -> f64 {
+        self.sample_time(SampleIndex(sample_index)).0
+    }
+
+    /// Frame starting at or before a sample.
+    #[wasm_bindgen]
+    pub fn sample_to_frame(&self, sample_index: usize) -> usize {
+        self.frame_of_sample(SampleIndex(sample_index)).0
+    }
+
+    /// First sample of a frame.
+    #[wasm_bindgen]
+    pub fn frame_to_sample(&self, frame_index: usize) -> usize {
+        self.frame_start(FrameIndex(frame_index)).0
+    }
+}
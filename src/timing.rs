@@ -0,0 +1,336 @@
+use std::collections::VecDeque;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+use wasm_bindgen::JsValue;
+
+// Number of recent frame deltas used for the refresh-rate estimate
+const DELTA_WINDOW: usize = 60;
+// Refresh rate the smoothing factor is tuned for
+pub const REFERENCE_REFRESH_RATE: f32 = 60.0;
+// Playback clock drift beyond this (seconds) is treated as a seek and snapped immediately
+const CLOCK_SNAP_THRESHOLD: f64 = 0.1;
+// Fraction of the remaining drift corrected on each frame
+const CLOCK_CORRECTION_RATE: f64 = 0.1;
+// The audio clock is considered stopped when it has not moved for this long (seconds)
+const CLOCK_STALL_TIMEOUT: f64 = 0.25;
+// Render time (seconds) the motion-blurred poster frame's samples are spread over
+pub const POSTER_SHUTTER_SECONDS: f64 = 0.1;
+pub const MAX_POSTER_SAMPLES: u32 = 32;
+
+/// Estimates the display refresh rate from requestAnimationFrame timestamps.
+#[derive(Debug, Default)]
+pub struct RefreshRateEstimator {
+    last_time: Option<f64>,
+    last_delta: Option<f64>,
+    deltas: VecDeque<f64>,
+}
+
+impl RefreshRateEstimator {
+    /// Record a frame timestamp in seconds.
+    pub fn observe(&mut self, time_seconds: f64) {
+        self.last_delta = None;
+        if let Some(last) = self.last_time {
+            let delta = time_seconds - last;
+            if delta > 0.0 {
+                self.last_delta = Some(delta);
+            }
+            // Ignore stalls (tab switches) and duplicate timestamps
+            if delta > 1.0 / 400.0 && delta < 0.25 {
+                if self.deltas.len() == DELTA_WINDOW {
+                    self.deltas.pop_front();
+                }
+                self.deltas.push_back(delta);
+            }
+        }
+        self.last_time = Some(time_seconds);
+    }
+
+    /// Time between the two most recent frames, if known.
+    pub fn last_delta(&self) -> Option<f64> {
+        self.last_delta
+    }
+
+    /// Whether the most recent frame took noticeably longer than the typical frame interval.
+    pub fn last_frame_was_slow(&self) -> bool {
+        match self.last_delta {
+            Some(delta) => delta * self.refresh_rate() as f64 > 1.5,
+            None => false,
+        }
+    }
+
+    /// Median-based refresh rate in Hz; the reference rate until enough frames were observed.
+    pub fn refresh_rate(&self) -> f32 {
+        if self.deltas.len() < 10 {
+            return REFERENCE_REFRESH_RATE;
+        }
+        let mut sorted: Vec<f64> = self.deltas.iter().copied().collect();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let median = sorted[sorted.len() / 2];
+        (1.0 / median) as f32
+    }
+}
+
+/// Smooth f64 playback position driven by frame timestamps and corrected against the audio clock.
+///
+/// `HTMLMediaElement.currentTime` advances in coarse, irregular steps, so the position is advanced
+/// by the frame delta each frame and only slewed towards the audio clock. Large differences (seeks)
+/// snap, and a stopped audio clock holds the position.
+#[derive(Debug, Default)]
+pub struct PlaybackClock {
+    position: f64,
+    last_time: Option<f64>,
+    last_audio_time: Option<f64>,
+    last_audio_change: f64,
+    playing: bool,
+}
+
+impl PlaybackClock {
+    /// Advance to frame timestamp `time_seconds` given the current audio clock reading and return
+    /// the corrected playback position in seconds. The audio advances `rate` seconds per second
+    /// (negative in reverse), and both the audio clock and the position wrap within `loop_region`.
+    pub fn update(&mut self, time_seconds: f64, audio_seconds: f64, rate: f64, loop_region: Option<LoopRegion>) -> f64 {
+        let delta = self.last_time.map_or(0.0, |last| (time_seconds - last).max(0.0));
+        self.last_time = Some(time_seconds);
+
+        if self.last_audio_time != Some(audio_seconds) {
+            self.last_audio_time = Some(audio_seconds);
+            self.last_audio_change = time_seconds;
+        }
+        let playing = time_seconds - self.last_audio_change < CLOCK_STALL_TIMEOUT;
+        self.playing = playing;
+
+        let wrap = |seconds: f64| loop_region.map_or(seconds, |region| region.wrap(seconds));
+        let audio_seconds = wrap(audio_seconds);
+        let predicted = match loop_region {
+            Some(region) if playing => region.shift(self.position, delta * rate),
+            _ if playing => self.position + delta * rate,
+            _ => audio_seconds,
+        };
+        // Either side of the loop point the two are a loop apart rather than out of sync
+        let drift = match loop_region {
+            Some(region) => region.offset(predicted, audio_seconds),
+            None => audio_seconds - predicted,
+        };
+        self.position = if drift.abs() > CLOCK_SNAP_THRESHOLD {
+            audio_seconds
+        } else {
+            wrap(predicted + drift * CLOCK_CORRECTION_RATE)
+        };
+        self.position = self.position.max(0.0);
+        self.position
+    }
+
+    pub fn position(&self) -> f64 {
+        self.position
+    }
+
+    /// Whether the audio clock moved recently, as of the last update.
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Which way the host plays the track.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlaybackDirection {
+    #[default]
+    Forward,
+    Reverse,
+}
+
+impl PlaybackDirection {
+    pub fn parse(name: &str) -> Result<Self, JsValue> {
+        match name {
+            "forward" => Ok(PlaybackDirection::Forward),
+            "reverse" => Ok(PlaybackDirection::Reverse),
+            _ => Err(JsValue::from_str(&format!("Unknown direction '{}', expected 'forward' or 'reverse'", name))),
+        }
+    }
+
+    /// Name accepted by `parse`.
+    pub fn name(self) -> &'static str {
+        match self {
+            PlaybackDirection::Forward => "forward",
+            PlaybackDirection::Reverse => "reverse",
+        }
+    }
+
+    /// 1.0 forward, -1.0 in reverse, to scale track time advances by.
+    pub fn sign(self) -> f64 {
+        match self {
+            PlaybackDirection::Forward => 1.0,
+            PlaybackDirection::Reverse => -1.0,
+        }
+    }
+}
+
+/// Stretch of the track that playback repeats, in seconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoopRegion {
+    pub start: f64,
+    pub end: f64,
+    /// Seconds before `end` over which the bars blend into those at `start`, 0.0 for none
+    pub crossfade: f64,
+}
+
+impl LoopRegion {
+    /// `position` wrapped back into the region once it reaches the end. Positions before the
+    /// start are left alone, so playback can run into the loop from before it.
+    pub fn wrap(&self, position: f64) -> f64 {
+        if position < self.end {
+            position
+        } else {
+            self.start + (position - self.start) % (self.end - self.start)
+        }
+    }
+
+    /// `position` moved by `seconds` within the region: wrapping at the end going forward and,
+    /// from inside the region, at the start going back.
+    pub fn shift(&self, position: f64, seconds: f64) -> f64 {
+        let shifted = position + seconds;
+        if position >= self.start && shifted < self.start {
+            self.end - (self.start - shifted) % (self.end - self.start)
+        } else {
+            self.wrap(shifted)
+        }
+    }
+
+    /// Shortest way from `from` to `to` round the loop when both are inside it, else `to - from`.
+    pub fn offset(&self, from: f64, to: f64) -> f64 {
+        let offset = to - from;
+        if from < self.start || to < self.start {
+            return offset;
+        }
+        let length = self.end - self.start;
+        offset - length * (offset / length).round()
+    }
+
+    /// How far (0.0-1.0) the bars at `position` have blended into those across the loop point
+    /// ahead, and where that is: the start going forward, the end in reverse.
+    pub fn crossfade(&self, position: f64, direction: PlaybackDirection) -> (f32, f64) {
+        if self.crossfade <= 0.0 || position < self.start {
+            return (0.0, self.start);
+        }
+        match direction {
+            PlaybackDirection::Forward => (((position - (self.end - self.crossfade)) / self.crossfade).clamp(0.0, 1.0) as f32, self.start),
+            PlaybackDirection::Reverse => ((((self.start + self.crossfade) - position) / self.crossfade).clamp(0.0, 1.0) as f32, self.end),
+        }
+    }
+}
+
+/// Host settings for replacing the animation of a paused player with a still poster frame.
+#[derive(Clone, Copy, Debug)]
+pub struct PowerSaving {
+    /// Seconds the audio clock must stand still before the poster frame is drawn
+    pub idle_seconds: f64,
+    /// Frames averaged into the poster frame; 1 draws it without motion blur
+    pub motion_blur_samples: u32,
+}
+
+/// What to do with a frame requested while power saving may be active.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FramePlan {
+    Animate,
+    Poster,
+    Skip,
+}
+
+/// Decides when a paused player stops animating: after the idle period one poster frame is
+/// drawn and later frames are skipped until playback resumes or the tracker is woken.
+#[derive(Debug, Default)]
+pub struct PauseTracker {
+    settings: Option<PowerSaving>,
+    paused_since: Option<f64>,
+    poster_drawn: bool,
+}
+
+impl PauseTracker {
+    pub fn configure(&mut self, settings: Option<PowerSaving>) {
+        self.settings = settings;
+        self.wake();
+    }
+
+    pub fn settings(&self) -> Option<PowerSaving> {
+        self.settings
+    }
+
+    /// Plan the frame at render `time` given whether the audio clock is moving. Time running
+    /// backwards restarts the idle period.
+    pub fn plan(&mut self, time: f64, playing: bool) -> FramePlan {
+        let Some(settings) = self.settings else {
+            return FramePlan::Animate;
+        };
+        if playing {
+            self.wake();
+            return FramePlan::Animate;
+        }
+        let paused_since = match self.paused_since {
+            Some(paused_since) if time >= paused_since => paused_since,
+            _ => *self.paused_since.insert(time),
+        };
+        if time - paused_since < settings.idle_seconds {
+            FramePlan::Animate
+        } else if self.poster_drawn {
+            FramePlan::Skip
+        } else {
+            self.poster_drawn = true;
+            FramePlan::Poster
+        }
+    }
+
+    /// Whether the poster frame is on screen and frames are being skipped.
+    pub fn is_suspended(&self) -> bool {
+        self.poster_drawn
+    }
+
+    /// Animate again, starting a new idle period.
+    pub fn wake(&mut self) {
+        self.paused_since = None;
+        self.poster_drawn = false;
+    }
+}
+
+/// Wall-clock time in seconds, for measuring how long work takes.
+pub fn now_seconds() -> f64 {
+    #[cfg(target_arch = "wasm32")]
+    return js_sys::Date::now() / 1000.0;
+    #[cfg(not(target_arch = "wasm32"))]
+    return SystemTime::now().duration_since(UNIX_EPOCH).map_or(0.0, |elapsed| elapsed.as_secs_f64());
+}
+
+/// Convert a per-frame smoothing factor tuned at the reference refresh rate into the equivalent
+/// factor for the actual refresh rate, so smoothing follows wall-clock time rather than frame count.
+pub fn frame_rate_independent_smoothing(smoothing_factor: f32, refresh_rate: f32) -> f32 {
+    let smoothing_factor = smoothing_factor.clamp(0.0, 1.0);
+    if smoothing_factor >= 1.0 || refresh_rate <= 0.0 {
+        return smoothing_factor;
+    }
+    1.0 - (1.0 - smoothing_factor).powf(REFERENCE_REFRESH_RATE / refresh_rate)
+}
+
+/// Resolve on the next macrotask so the host can deliver GPU callbacks and input during long work.
+/// Uses the global `setTimeout` so this also works in workers and Node, where there is no `window`.
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn yield_to_event_loop() -> Result<(), String> {
+    use wasm_bindgen::JsCast;
+
+    let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+        let global = js_sys::global();
+        let scheduled = js_sys::Reflect::get(&global, &wasm_bindgen::JsValue::from_str("setTimeout"))
+            .ok()
+            .and_then(|set_timeout| set_timeout.dyn_into::<js_sys::Function>().ok())
+            .map(|set_timeout| set_timeout.call2(&global, &resolve, &wasm_bindgen::JsValue::from(0)).is_ok())
+            .unwrap_or(false);
+        if !scheduled {
+            let _ = resolve.call0(&wasm_bindgen::JsValue::NULL);
+        }
+    });
+    wasm_bindgen_futures::JsFuture::from(promise)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("Failed to yield to the event loop: {:?}", e))
+}
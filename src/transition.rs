@@ -0,0 +1,246 @@
+use wasm_bindgen::prelude::*;
+use wgpu::*;
+
+/// Curve applied to the crossfade between visualizer modes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    #[default]
+    EaseInOut,
+}
+
+impl Easing {
+    pub fn parse(name: &str) -> Result<Self, JsValue> {
+        match name {
+            "linear" => Ok(Easing::Linear),
+            "ease-in" => Ok(Easing::EaseIn),
+            "ease-out" => Ok(Easing::EaseOut),
+            "ease-in-out" => Ok(Easing::EaseInOut),
+            _ => Err(JsValue::from_str(&format!(
+                "Unknown easing '{}', expected 'linear', 'ease-in', 'ease-out' or 'ease-in-out'",
+                name
+            ))),
+        }
+    }
+
+    /// Map linear progress (0.0-1.0) onto the curve.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// A crossfade in progress from the mode at `from` to the active one.
+pub struct ModeTransition {
+    pub from: usize,
+    // Renderer time of the first frame drawn with the transition
+    start: Option<f64>,
+    progress: f32,
+}
+
+impl ModeTransition {
+    pub fn new(from: usize) -> Self {
+        Self { from, start: None, progress: 0.0 }
+    }
+
+    /// Advance to renderer `time`; returns false once the transition has completed. Time running
+    /// backwards (seeking, offline export from the start) restarts the fade rather than freezing it.
+    pub fn advance(&mut self, time: f64, duration: f64, easing: Easing) -> bool {
+        let start = match self.start {
+            Some(start) if time >= start => start,
+            _ => *self.start.insert(time),
+        };
+        let linear = if duration > 0.0 { (time - start) / duration } else { 1.0 };
+        self.progress = easing.apply(linear as f32);
+        linear < 1.0
+    }
+
+    /// Eased weight of the incoming mode, 0.0-1.0.
+    pub fn progress(&self) -> f32 {
+        self.progress
+    }
+}
+
+/// Blends the outgoing mode over the incoming one. The outgoing mode is drawn into an
+/// intermediate texture, then composited onto the target with a constant blend factor.
+pub struct Crossfade {
+    pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    sampler: Sampler,
+    format: TextureFormat,
+    target: Option<(Texture, TextureView, BindGroup)>,
+}
+
+impl Crossfade {
+    pub fn new(device: &Device, format: TextureFormat) -> Self {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Crossfade Shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/crossfade.wgsl").into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Crossfade Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Crossfade Sampler"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Crossfade Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // result = outgoing * constant + incoming * (1 - constant)
+        let blend = BlendComponent {
+            src_factor: BlendFactor::Constant,
+            dst_factor: BlendFactor::OneMinusConstant,
+            operation: BlendOperation::Add,
+        };
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Crossfade Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState { color: blend, alpha: blend }),
+                    write_mask: ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: Some(Face::Back),
+                polygon_mode: PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            sampler,
+            format,
+            target: None,
+        }
+    }
+
+    /// Make sure the intermediate texture matches the size about to be drawn.
+    pub fn prepare(&mut self, device: &Device, width: u32, height: u32) {
+        let reusable = self
+            .target
+            .as_ref()
+            .is_some_and(|(texture, _, _)| texture.width() == width && texture.height() == height);
+        if reusable {
+            return;
+        }
+
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("Crossfade Texture"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: self.format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor {
+            label: Some("Crossfade Texture View"),
+            ..Default::default()
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Crossfade Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+        self.target = Some((texture, view, bind_group));
+    }
+
+    /// Drop the intermediate texture once no transition needs it.
+    pub fn release(&mut self) {
+        self.target = None;
+    }
+
+    /// View the outgoing mode is drawn into, once `prepare` has run.
+    pub fn view(&self) -> Option<&TextureView> {
+        self.target.as_ref().map(|(_, view, _)| view)
+    }
+
+    /// Blend the outgoing mode over what is already in `render_pass`, weighted `1 - progress`.
+    pub fn composite(&self, render_pass: &mut RenderPass, progress: f32) {
+        let Some((_, _, bind_group)) = &self.target else {
+            return;
+        };
+        let outgoing = (1.0 - progress.clamp(0.0, 1.0)) as f64;
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, bind_group, &[]);
+        render_pass.set_blend_constant(Color {
+            r: outgoing,
+            g: outgoing,
+            b: outgoing,
+            a: outgoing,
+        });
+        render_pass.draw(0..3, 0..1);
+    }
+}
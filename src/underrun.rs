@@ -0,0 +1,121 @@
+// Decay used until the host configures one
+const DEFAULT_DECAY_SECONDS: f64 = 0.5;
+
+/// Bars shown while rendering runs past the analyzed frames: the last analyzed bars are held and
+/// fade out, instead of the visualization collapsing to silence at once.
+#[derive(Debug)]
+pub struct UnderrunHold {
+    decay_seconds: f64,
+    last_bars: Vec<f32>,
+    // Render time the current underrun started at
+    started: Option<f64>,
+}
+
+impl Default for UnderrunHold {
+    fn default() -> Self {
+        Self {
+            decay_seconds: DEFAULT_DECAY_SECONDS,
+            last_bars: Vec::new(),
+            started: None,
+        }
+    }
+}
+
+impl UnderrunHold {
+    /// Time constant of the fade in seconds; 0.0 drops to silence immediately.
+    pub fn set_decay(&mut self, decay_seconds: f64) {
+        self.decay_seconds = decay_seconds.max(0.0);
+    }
+
+    /// Remember the bars of an analyzed frame, ending any underrun.
+    pub fn track(&mut self, bars: &[f32]) {
+        self.last_bars.clear();
+        self.last_bars.extend_from_slice(bars);
+        self.started = None;
+    }
+
+    /// Forget the held bars, e.g. when a new track is loaded.
+    pub fn reset(&mut self) {
+        self.last_bars.clear();
+        self.started = None;
+    }
+
+    /// Bars to draw at render `time` for a frame that isn't analyzed. Held bars of a different
+    /// bar count are dropped.
+    pub fn hold(&mut self, time: f64, bin_size: usize) -> Vec<f32> {
+        let start = *self.started.get_or_insert(time);
+        if self.last_bars.len() != bin_size || self.decay_seconds == 0.0 {
+            return vec![0.0; bin_size];
+        }
+        let gain = (-(time - start).max(0.0) / self.decay_seconds).exp() as f32;
+        self.last_bars.iter().map(|bar| bar * gain).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_bars(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len(), "bar count");
+        for (i, (a, e)) in actual.iter().zip(expected).enumerate() {
+            assert!((a - e).abs() < 1e-5, "bar {}: got {}, expected {}", i, a, e);
+        }
+    }
+
+    #[test]
+    fn holds_the_last_bars_when_the_underrun_starts() {
+        let mut hold = UnderrunHold::default();
+        hold.track(&[0.2, 0.8, 0.5]);
+        assert_bars(&hold.hold(10.0, 3), &[0.2, 0.8, 0.5]);
+    }
+
+    #[test]
+    fn fades_with_the_configured_time_constant() {
+        let mut hold = UnderrunHold::default();
+        hold.set_decay(0.25);
+        hold.track(&[1.0, 0.5]);
+        hold.hold(3.0, 2);
+
+        let gain = (-1.0f32).exp();
+        assert_bars(&hold.hold(3.25, 2), &[gain, 0.5 * gain]);
+        let gain = (-4.0f32).exp();
+        assert_bars(&hold.hold(4.0, 2), &[gain, 0.5 * gain]);
+    }
+
+    #[test]
+    fn analyzed_bars_end_the_underrun() {
+        let mut hold = UnderrunHold::default();
+        hold.track(&[1.0]);
+        hold.hold(0.0, 1);
+        hold.hold(2.0, 1);
+
+        hold.track(&[0.6]);
+        assert_bars(&hold.hold(5.0, 1), &[0.6]);
+        let gain = (-1.0f32).exp();
+        assert_bars(&hold.hold(5.5, 1), &[0.6 * gain]);
+    }
+
+    #[test]
+    fn zero_decay_drops_to_silence() {
+        let mut hold = UnderrunHold::default();
+        hold.set_decay(-1.0);
+        hold.track(&[0.7, 0.9]);
+        assert_bars(&hold.hold(1.0, 2), &[0.0, 0.0]);
+    }
+
+    #[test]
+    fn drops_bars_of_another_bar_count() {
+        let mut hold = UnderrunHold::default();
+        hold.track(&[0.7, 0.9]);
+        assert_bars(&hold.hold(1.0, 4), &[0.0; 4]);
+    }
+
+    #[test]
+    fn reset_forgets_the_held_bars() {
+        let mut hold = UnderrunHold::default();
+        hold.track(&[0.7, 0.9]);
+        hold.reset();
+        assert_bars(&hold.hold(1.0, 2), &[0.0, 0.0]);
+    }
+}
@@ -0,0 +1,81 @@
+use bytemuck::{Pod, Zeroable};
+use std::mem::{offset_of, size_of};
+
+/// Host mirror of `struct Uniforms` in shaders/common.wgsl. Vectors and arrays of vectors are
+/// flattened to f32 arrays; the assertions below pin every field to the offset WGSL gives it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct Uniforms {
+    pub time: f32,
+    pub bin_size: f32,
+    pub resolution: [f32; 2],
+    pub waveform: [f32; 4],
+    pub interaction: [f32; 4],
+    pub background: [f32; 4],
+    pub bar_layout: [f32; 4],
+    pub bar_style: [f32; 4],
+    pub theme: [f32; 4],
+    pub clock: [f32; 4],
+    pub transform: [f32; 4],
+    pub beat: [f32; 4],
+    pub chroma: [f32; 12],
+    pub stereo_meter: [f32; 4],
+    pub tuner: [f32; 4],
+    pub axis: [f32; 4],
+}
+
+/// Host mirror of `struct Bars` in shaders/common.wgsl, uploaded separately from `Uniforms` and
+/// only when the levels change.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct Bars {
+    pub mono: [f32; 64],
+    pub right: [f32; 64],
+}
+
+// A uniform struct's size rounds up to its 16-byte alignment in WGSL, and every vec4 member
+// starts on a 16-byte boundary
+const _: () = assert!(size_of::<Uniforms>() == 256);
+const _: () = assert!(size_of::<Uniforms>().is_multiple_of(16));
+const _: () = assert!(offset_of!(Uniforms, resolution) == 8);
+const _: () = assert!(offset_of!(Uniforms, waveform) == 16);
+const _: () = assert!(offset_of!(Uniforms, interaction) == 32);
+const _: () = assert!(offset_of!(Uniforms, background) == 48);
+const _: () = assert!(offset_of!(Uniforms, bar_layout) == 64);
+const _: () = assert!(offset_of!(Uniforms, bar_style) == 80);
+const _: () = assert!(offset_of!(Uniforms, theme) == 96);
+const _: () = assert!(offset_of!(Uniforms, clock) == 112);
+const _: () = assert!(offset_of!(Uniforms, transform) == 128);
+const _: () = assert!(offset_of!(Uniforms, beat) == 144);
+const _: () = assert!(offset_of!(Uniforms, chroma) == 160);
+const _: () = assert!(offset_of!(Uniforms, stereo_meter) == 208);
+const _: () = assert!(offset_of!(Uniforms, tuner) == 224);
+const _: () = assert!(offset_of!(Uniforms, axis) == 240);
+const _: () = assert!(size_of::<Bars>() == 512);
+const _: () = assert!(offset_of!(Bars, right) == 256);
+
+impl Uniforms {
+    /// The struct as the floats uploaded to the uniform buffer.
+    pub fn as_floats(&self) -> &[f32] {
+        bytemuck::cast_slice(std::slice::from_ref(self))
+    }
+}
+
+impl Bars {
+    /// Both channels padded or truncated to 64 bars.
+    pub fn padded(mono: &[f32], right: &[f32]) -> Self {
+        let mut bars = Self::zeroed();
+        for (slot, &bar) in bars.mono.iter_mut().zip(mono) {
+            *slot = bar;
+        }
+        for (slot, &bar) in bars.right.iter_mut().zip(right) {
+            *slot = bar;
+        }
+        bars
+    }
+
+    /// The struct as the floats uploaded to the bars buffer.
+    pub fn as_floats(&self) -> &[f32] {
+        bytemuck::cast_slice(std::slice::from_ref(self))
+    }
+}
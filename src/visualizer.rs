@@ -0,0 +1,609 @@
+//! Visualization modes. Each mode owns its pipeline (and any resources of its own) and draws a
+//! full-screen pass over the shared uniforms at bind group 0; the renderer keeps a registry of
+//! modes and draws whichever one is active. A new mode is a fragment shader in `shaders/modes`
+//! plus an entry in `builtin_visualizers`, or a `Visualizer` implementation when it needs state.
+
+use std::borrow::Cow;
+#[cfg(feature = "dev")]
+use wasm_bindgen::prelude::*;
+#[cfg(feature = "dev")]
+use wgpu::naga;
+use wgpu::*;
+
+use crate::pipeline_cache::PipelineCache;
+
+// Number of bars a mode can receive, matching the uniform array
+const MAX_BARS: usize = 64;
+// Rows of bar history kept by the spectrogram and waterfall
+const HISTORY_ROWS: u32 = 256;
+// Seconds of history shown by the waterfall, sampled evenly into its rows
+const WATERFALL_SECONDS: f64 = 8.0;
+// Sample pairs the goniometer traces per frame: one analysis frame
+const MAX_SCOPE_SAMPLES: usize = crate::analysis::FRAME_SIZE;
+
+// Every mode's fragment shader is appended to the shared prelude
+macro_rules! mode_shader {
+    ($file:literal) => {
+        concat!(include_str!("shaders/common.wgsl"), "\n", include_str!(concat!("shaders/modes/", $file)))
+    };
+}
+
+/// GPU state a mode needs to create its resources.
+pub struct VisualizerContext<'a> {
+    pub device: &'a Device,
+    pub queue: &'a Queue,
+    pub format: TextureFormat,
+    /// Layout of the shared bind group 0: uniforms, waveform texture, background texture and sampler
+    pub uniform_layout: &'a BindGroupLayout,
+    /// Pipelines already built on this device
+    pub pipelines: &'a PipelineCache,
+}
+
+/// Analysis state for the frame about to be drawn.
+pub struct VisualizerFrame<'a> {
+    pub bars: &'a [f32],
+    pub bin_size: usize,
+    /// Renderer time in seconds
+    pub time: f64,
+    /// The frame's (left, right) sample pairs in -1.0..1.0; empty without audio
+    pub scope: &'a [[f32; 2]],
+}
+
+pub trait Visualizer {
+    /// Name the mode is selected by.
+    fn name(&self) -> &'static str;
+
+    /// Create pipelines and pass resources. Called whenever a device is created.
+    fn init(&mut self, context: &VisualizerContext);
+
+    /// Upload per-frame state of the mode's own. Every registered mode sees every frame, so modes
+    /// that keep history are current when they are switched to.
+    fn update(&mut self, _queue: &Queue, _frame: &VisualizerFrame) {}
+
+    /// Draw into `render_pass`, binding `uniforms` at group 0.
+    fn encode(&self, render_pass: &mut RenderPass, uniforms: &BindGroup);
+
+    /// Replace the full shader source (prelude included). Takes effect on the next `init`.
+    #[cfg(feature = "dev")]
+    fn replace_shader(&mut self, source: Cow<'static, str>);
+}
+
+/// Why a mode shader was rejected, located in the fragment source that was submitted.
+#[cfg(feature = "dev")]
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShaderDiagnostic {
+    /// 1-based line in the fragment source, 0 when the error has no location there (it lies in
+    /// the prelude, or concerns the module as a whole)
+    pub line: u32,
+    /// 1-based column on that line, 0 along with the line
+    pub column: u32,
+    pub message: String,
+}
+
+#[cfg(feature = "dev")]
+impl std::fmt::Display for ShaderDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            0 => write!(f, "{}", self.message),
+            line => write!(f, "line {}:{}: {}", line, self.column, self.message),
+        }
+    }
+}
+
+/// Prepend the shared prelude to a mode's fragment shader and check that it parses, validates
+/// and has an `fs_main` entry point. Errors are reported at their place in `fragment_source`.
+#[cfg(feature = "dev")]
+pub fn compile_mode_shader(fragment_source: &str) -> Result<String, ShaderDiagnostic> {
+    use naga::valid::{Capabilities, ValidationFlags, Validator};
+
+    let prelude = include_str!("shaders/common.wgsl");
+    let source = format!("{}\n{}", prelude, fragment_source);
+    let prelude_lines = prelude.matches('\n').count() as u32 + 1;
+    let describe = |message: String, location: Option<naga::SourceLocation>| match location {
+        Some(location) if location.line_number > prelude_lines => ShaderDiagnostic {
+            line: location.line_number - prelude_lines,
+            column: location.line_position,
+            message,
+        },
+        _ => ShaderDiagnostic { line: 0, column: 0, message },
+    };
+
+    let module = naga::front::wgsl::parse_str(&source).map_err(|e| describe(e.message().to_string(), e.location(&source)))?;
+    Validator::new(ValidationFlags::all(), Capabilities::empty())
+        .validate(&module)
+        .map_err(|e| describe(e.as_inner().to_string(), e.location(&source)))?;
+    if !module
+        .entry_points
+        .iter()
+        .any(|entry_point| entry_point.name == "fs_main" && entry_point.stage == naga::ShaderStage::Fragment)
+    {
+        return Err(describe("missing `@fragment fn fs_main`".to_string(), None));
+    }
+    Ok(source)
+}
+
+/// The modes available to `Renderer::set_visualizer_mode`; the first is the default.
+pub fn builtin_visualizers() -> Vec<Box<dyn Visualizer>> {
+    vec![
+        Box::new(ShaderVisualizer::new("bars", mode_shader!("bars.wgsl").into())),
+        Box::new(ShaderVisualizer::new("waveform", mode_shader!("waveform.wgsl").into())),
+        Box::new(SpectrogramVisualizer::new()),
+        Box::new(ShaderVisualizer::new("particles", mode_shader!("particles.wgsl").into())),
+        Box::new(WaterfallVisualizer::new()),
+        Box::new(ShaderVisualizer::new("chroma-wheel", mode_shader!("chroma-wheel.wgsl").into())),
+        Box::new(ShaderVisualizer::new("chroma-bars", mode_shader!("chroma-bars.wgsl").into())),
+        Box::new(ShaderVisualizer::new("stereo", mode_shader!("stereo.wgsl").into())),
+        Box::new(GoniometerVisualizer::new()),
+        Box::new(ShaderVisualizer::new("tuner", mode_shader!("tuner.wgsl").into())),
+    ]
+}
+
+// Full-screen triangle pipeline running a mode's `fs_main`, with the shared uniforms at group 0
+// followed by `extra_layouts`, which `layout_name` identifies in the pipeline cache
+fn create_mode_pipeline(
+    context: &VisualizerContext,
+    name: &str,
+    source: Cow<'static, str>,
+    layout_name: &'static str,
+    extra_layouts: &[&BindGroupLayout],
+) -> RenderPipeline {
+    context
+        .pipelines
+        .get_or_create(&source, context.format, layout_name, || build_mode_pipeline(context, name, source.clone(), extra_layouts))
+}
+
+fn build_mode_pipeline(
+    context: &VisualizerContext,
+    name: &str,
+    source: Cow<'static, str>,
+    extra_layouts: &[&BindGroupLayout],
+) -> RenderPipeline {
+    let device = context.device;
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(&format!("Visualizer Shader ({})", name)),
+        source: ShaderSource::Wgsl(source),
+    });
+
+    let mut bind_group_layouts = vec![context.uniform_layout];
+    bind_group_layouts.extend_from_slice(extra_layouts);
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(&format!("Visualizer Pipeline Layout ({})", name)),
+        bind_group_layouts: &bind_group_layouts,
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some(&format!("Visualizer Pipeline ({})", name)),
+        layout: Some(&pipeline_layout),
+        vertex: VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(ColorTargetState {
+                format: context.format,
+                blend: Some(BlendState::REPLACE),
+                write_mask: ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: Some(Face::Back),
+            polygon_mode: PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// A mode drawn entirely by its fragment shader from the shared uniforms.
+struct ShaderVisualizer {
+    name: &'static str,
+    source: Cow<'static, str>,
+    pipeline: Option<RenderPipeline>,
+}
+
+impl ShaderVisualizer {
+    fn new(name: &'static str, source: Cow<'static, str>) -> Self {
+        Self { name, source, pipeline: None }
+    }
+}
+
+impl Visualizer for ShaderVisualizer {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn init(&mut self, context: &VisualizerContext) {
+        self.pipeline = Some(create_mode_pipeline(context, self.name, self.source.clone(), "uniforms", &[]));
+    }
+
+    fn encode(&self, render_pass: &mut RenderPass, uniforms: &BindGroup) {
+        if let Some(pipeline) = &self.pipeline {
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, uniforms, &[]);
+            render_pass.draw(0..3, 0..1); // Draw a triangle
+        }
+    }
+
+    #[cfg(feature = "dev")]
+    fn replace_shader(&mut self, source: Cow<'static, str>) {
+        self.source = source;
+    }
+}
+
+/// Ring of texture rows holding recent bar levels, bound at group 1 as `history_texture` with the
+/// `history` parameters (newest row, row count).
+struct BarHistory {
+    texture: Texture,
+    buffer: Buffer,
+    bind_group_layout: BindGroupLayout,
+    bind_group: BindGroup,
+    next_row: u32,
+}
+
+impl BarHistory {
+    fn new(context: &VisualizerContext, label: &str) -> Self {
+        let device = context.device;
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some(&format!("{} History Texture", label)),
+            size: Extent3d {
+                width: MAX_BARS as u32,
+                height: HISTORY_ROWS,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::R32Float,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor {
+            label: Some(&format!("{} History Texture View", label)),
+            ..Default::default()
+        });
+
+        // History parameters: [newest row, row count, padding, padding]
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some(&format!("{} History Buffer", label)),
+            size: 4 * 4,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        context.queue.write_buffer(&buffer, 0, bytemuck::cast_slice(&[0.0f32, HISTORY_ROWS as f32, 0.0, 0.0]));
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some(&format!("{} Bind Group Layout", label)),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some(&format!("{} Bind Group", label)),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            texture,
+            buffer,
+            bind_group_layout,
+            bind_group,
+            next_row: 0,
+        }
+    }
+
+    /// Write the frame's bar levels into the next row, overwriting the oldest.
+    fn push(&mut self, queue: &Queue, frame: &VisualizerFrame) {
+        let mut row = [0.0f32; MAX_BARS];
+        for (level, &bar) in row.iter_mut().zip(frame.bars.iter().take(frame.bin_size)) {
+            *level = bar;
+        }
+        queue.write_texture(
+            TexelCopyTextureInfo {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: Origin3d { x: 0, y: self.next_row, z: 0 },
+                aspect: TextureAspect::All,
+            },
+            bytemuck::cast_slice(&row),
+            TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(MAX_BARS as u32 * 4),
+                rows_per_image: None,
+            },
+            Extent3d {
+                width: MAX_BARS as u32,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.next_row as f32, HISTORY_ROWS as f32]));
+        self.next_row = (self.next_row + 1) % HISTORY_ROWS;
+    }
+
+    fn encode(&self, render_pass: &mut RenderPass, pipeline: &RenderPipeline, uniforms: &BindGroup) {
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, uniforms, &[]);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+/// Scrolling spectrogram. Bar levels are written into a ring of texture rows, one per frame.
+struct SpectrogramVisualizer {
+    source: Cow<'static, str>,
+    pipeline: Option<RenderPipeline>,
+    history: Option<BarHistory>,
+}
+
+impl SpectrogramVisualizer {
+    fn new() -> Self {
+        Self {
+            source: mode_shader!("spectrogram.wgsl").into(),
+            pipeline: None,
+            history: None,
+        }
+    }
+}
+
+impl Visualizer for SpectrogramVisualizer {
+    fn name(&self) -> &'static str {
+        "spectrogram"
+    }
+
+    fn init(&mut self, context: &VisualizerContext) {
+        let history = BarHistory::new(context, "Spectrogram");
+        self.pipeline = Some(create_mode_pipeline(
+            context,
+            self.name(),
+            self.source.clone(),
+            "bar-history",
+            &[&history.bind_group_layout],
+        ));
+        self.history = Some(history);
+    }
+
+    fn update(&mut self, queue: &Queue, frame: &VisualizerFrame) {
+        if let Some(history) = &mut self.history {
+            history.push(queue, frame);
+        }
+    }
+
+    fn encode(&self, render_pass: &mut RenderPass, uniforms: &BindGroup) {
+        if let (Some(pipeline), Some(history)) = (&self.pipeline, &self.history) {
+            history.encode(render_pass, pipeline, uniforms);
+        }
+    }
+
+    #[cfg(feature = "dev")]
+    fn replace_shader(&mut self, source: Cow<'static, str>) {
+        self.source = source;
+    }
+}
+
+/// Live bars over the last few seconds of bar levels scrolling upward. History rows are written
+/// at a fixed rate so the visible span doesn't depend on the frame rate.
+struct WaterfallVisualizer {
+    source: Cow<'static, str>,
+    pipeline: Option<RenderPipeline>,
+    history: Option<BarHistory>,
+    last_row_time: Option<f64>,
+}
+
+impl WaterfallVisualizer {
+    fn new() -> Self {
+        Self {
+            source: mode_shader!("waterfall.wgsl").into(),
+            pipeline: None,
+            history: None,
+            last_row_time: None,
+        }
+    }
+}
+
+impl Visualizer for WaterfallVisualizer {
+    fn name(&self) -> &'static str {
+        "waterfall"
+    }
+
+    fn init(&mut self, context: &VisualizerContext) {
+        let history = BarHistory::new(context, "Waterfall");
+        self.pipeline = Some(create_mode_pipeline(
+            context,
+            self.name(),
+            self.source.clone(),
+            "bar-history",
+            &[&history.bind_group_layout],
+        ));
+        self.history = Some(history);
+        self.last_row_time = None;
+    }
+
+    fn update(&mut self, queue: &Queue, frame: &VisualizerFrame) {
+        let Some(history) = &mut self.history else {
+            return;
+        };
+        // Time running backwards (seeking, export from the start) writes a row straight away
+        let row_interval = WATERFALL_SECONDS / HISTORY_ROWS as f64;
+        let due = match self.last_row_time {
+            Some(last) if frame.time >= last => frame.time - last >= row_interval,
+            _ => true,
+        };
+        if due {
+            history.push(queue, frame);
+            self.last_row_time = Some(frame.time);
+        }
+    }
+
+    fn encode(&self, render_pass: &mut RenderPass, uniforms: &BindGroup) {
+        if let (Some(pipeline), Some(history)) = (&self.pipeline, &self.history) {
+            history.encode(render_pass, pipeline, uniforms);
+        }
+    }
+
+    #[cfg(feature = "dev")]
+    fn replace_shader(&mut self, source: Cow<'static, str>) {
+        self.source = source;
+    }
+}
+
+/// Phase scope: the grid is a full-screen pass like the other modes, then the frame's sample pairs
+/// are drawn over it as a line strip, one vertex per pair.
+struct GoniometerVisualizer {
+    source: Cow<'static, str>,
+    pipeline: Option<RenderPipeline>,
+    trace_pipeline: Option<RenderPipeline>,
+    vertices: Option<Buffer>,
+    vertex_count: u32,
+}
+
+impl GoniometerVisualizer {
+    fn new() -> Self {
+        Self {
+            source: mode_shader!("goniometer.wgsl").into(),
+            pipeline: None,
+            trace_pipeline: None,
+            vertices: None,
+            vertex_count: 0,
+        }
+    }
+}
+
+impl Visualizer for GoniometerVisualizer {
+    fn name(&self) -> &'static str {
+        "goniometer"
+    }
+
+    fn init(&mut self, context: &VisualizerContext) {
+        let device = context.device;
+        self.pipeline = Some(create_mode_pipeline(context, self.name(), self.source.clone(), "uniforms", &[]));
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Goniometer Trace Shader"),
+            source: ShaderSource::Wgsl(self.source.clone()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Goniometer Trace Pipeline Layout"),
+            bind_group_layouts: &[context.uniform_layout],
+            push_constant_ranges: &[],
+        });
+        self.trace_pipeline = Some(device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Goniometer Trace Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: Some("vs_trace"),
+                buffers: &[VertexBufferLayout {
+                    array_stride: std::mem::size_of::<[f32; 2]>() as BufferAddress,
+                    step_mode: VertexStepMode::Vertex,
+                    attributes: &vertex_attr_array![0 => Float32x2],
+                }],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: Some("fs_trace"),
+                targets: &[Some(ColorTargetState {
+                    format: context.format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::LineStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        }));
+        self.vertices = Some(device.create_buffer(&BufferDescriptor {
+            label: Some("Goniometer Trace Vertices"),
+            size: (MAX_SCOPE_SAMPLES * std::mem::size_of::<[f32; 2]>()) as BufferAddress,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }));
+        self.vertex_count = 0;
+    }
+
+    fn update(&mut self, queue: &Queue, frame: &VisualizerFrame) {
+        let Some(vertices) = &self.vertices else {
+            return;
+        };
+        let scope = &frame.scope[..frame.scope.len().min(MAX_SCOPE_SAMPLES)];
+        if !scope.is_empty() {
+            queue.write_buffer(vertices, 0, bytemuck::cast_slice(scope));
+        }
+        self.vertex_count = scope.len() as u32;
+    }
+
+    fn encode(&self, render_pass: &mut RenderPass, uniforms: &BindGroup) {
+        let Some(pipeline) = &self.pipeline else {
+            return;
+        };
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, uniforms, &[]);
+        render_pass.draw(0..3, 0..1);
+
+        if let (Some(trace_pipeline), Some(vertices)) = (&self.trace_pipeline, &self.vertices) {
+            if self.vertex_count >= 2 {
+                render_pass.set_pipeline(trace_pipeline);
+                render_pass.set_vertex_buffer(0, vertices.slice(..));
+                render_pass.draw(0..self.vertex_count, 0..1);
+            }
+        }
+    }
+
+    #[cfg(feature = "dev")]
+    fn replace_shader(&mut self, source: Cow<'static, str>) {
+        self.source = source;
+    }
+}
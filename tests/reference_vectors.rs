@@ -0,0 +1,76 @@
+// Checks the analysis against the committed reference fixtures in tests/fixtures/reference.
+// After an intentional change to the analysis outputs, bump `REFERENCE_VECTOR_VERSION` and
+// regenerate the fixtures with:
+//
+//     VIBER_BLESS=1 cargo test --test reference_vectors
+
+use std::path::PathBuf;
+
+use serde_json::Value;
+use viber::reference::{self, ReferenceSignal, REFERENCE_VECTOR_VERSION};
+
+fn fixture_path(signal: ReferenceSignal) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/reference")
+        .join(format!("v{}", REFERENCE_VECTOR_VERSION))
+        .join(format!("{}.json", signal.name()))
+}
+
+fn floats(value: &Value) -> Vec<f64> {
+    value.as_array().expect("array").iter().map(|v| v.as_f64().expect("number")).collect()
+}
+
+// Largest absolute difference between two equally long lists, divided by `scale`
+fn max_difference(expected: &[f64], actual: &[f64], scale: f64) -> f64 {
+    assert_eq!(expected.len(), actual.len(), "length mismatch");
+    expected.iter().zip(actual).map(|(e, a)| (e - a).abs() / scale).fold(0.0, f64::max)
+}
+
+fn check(signal: ReferenceSignal) {
+    let generated = reference::generate(signal).expect("analysis failed");
+    let path = fixture_path(signal);
+    if std::env::var_os("VIBER_BLESS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, &generated).unwrap();
+        return;
+    }
+
+    let expected: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e))).unwrap();
+    let actual: Value = serde_json::from_str(&generated).unwrap();
+    let name = signal.name();
+    let tolerance = expected["tolerance"].as_f64().unwrap();
+
+    for key in ["version", "signal", "sample_rate", "frame_size", "hop_size", "bin_size", "total_frames", "samples"] {
+        assert_eq!(expected[key], actual[key], "{}: {} differs", name, key);
+    }
+    for key in ["bars", "chroma"] {
+        let rows = expected[key].as_array().unwrap().iter().zip(actual[key].as_array().unwrap());
+        for (frame, (e, a)) in rows.enumerate() {
+            let difference = max_difference(&floats(e), &floats(a), 1.0);
+            assert!(difference <= tolerance, "{}: {} of frame {} off by {}", name, key, frame, difference);
+        }
+    }
+    let probes = expected["fft_probes"].as_array().unwrap().iter().zip(actual["fft_probes"].as_array().unwrap());
+    for (e, a) in probes {
+        assert_eq!(e["frame"], a["frame"]);
+        let magnitudes = floats(&e["magnitudes"]);
+        let peak = magnitudes.iter().cloned().fold(0.0, f64::max).max(1.0);
+        let difference = max_difference(&magnitudes, &floats(&a["magnitudes"]), peak);
+        assert!(difference <= tolerance, "{}: FFT of frame {} off by {} of the peak", name, e["frame"], difference);
+    }
+}
+
+#[test]
+fn sine_1k_matches_fixture() {
+    check(ReferenceSignal::Sine1k);
+}
+
+#[test]
+fn sweep_matches_fixture() {
+    check(ReferenceSignal::Sweep);
+}
+
+#[test]
+fn impulse_matches_fixture() {
+    check(ReferenceSignal::Impulse);
+}
@@ -0,0 +1,41 @@
+// Dev loop for builds with the `dev` feature: a local file watcher pushes config and shader
+// edits over a websocket and they are applied without reloading the page.
+//
+// Messages are JSON:
+//   { "type": "config", "config": { "visualizer_mode": "waveform", ... } }
+//   { "type": "shader", "mode": "bars", "source": "@fragment fn fs_main(...) ..." }
+export function connectDevServer(app, url = "ws://localhost:8765") {
+  const socket = new WebSocket(url);
+
+  socket.addEventListener("open", () => console.log(`Dev server connected: ${url}`));
+
+  socket.addEventListener("message", (event) => {
+    let message;
+    try {
+      message = JSON.parse(event.data);
+    } catch (error) {
+      console.error("Dev server sent invalid JSON:", error);
+      return;
+    }
+
+    try {
+      switch (message.type) {
+        case "config":
+          app.apply_config_json(JSON.stringify(message.config));
+          break;
+        case "shader":
+          app.hot_swap_shader(message.mode, message.source);
+          break;
+        default:
+          console.warn(`Unknown dev message type: ${message.type}`);
+      }
+    } catch (error) {
+      console.error(error);
+    }
+  });
+
+  // Keep retrying so restarting the watcher doesn't require a page reload
+  socket.addEventListener("close", () => {
+    setTimeout(() => connectDevServer(app, url), 1000);
+  });
+}
@@ -23,7 +23,6 @@ async function run() {
   let startTime = 0;
   let lastTime = 0;
   let scaledTime = 0;
-  let currentFrame = 0;
   let totalFrames = 0;
   let audioProcessed = false;
   let isPlaying = false;
@@ -31,6 +30,10 @@ async function run() {
   let smoothingFactor = 0.2;
   let selectedBinSize = 64;
   let audioVolume = 0.3;
+  let animating = true;
+
+  // Stop animating a few seconds after playback pauses, leaving a motion-blurred still frame
+  app.set_power_saving(true, 5.0, 8);
 
   // Animation loop
   function animate(time) {
@@ -43,21 +46,31 @@ async function run() {
     scaledTime += deltaTime;
     lastTime = time;
 
-    // Calculate current frame for 120fps only if audio is processed and playing
-    if (audioProcessed && totalFrames > 0 && isPlaying) {
-      // Use audio current time for synchronization
-      const audioCurrentTime = audioElement ? audioElement.currentTime : 0;
-      const frameTime = audioCurrentTime * 120.0; // Convert to frame index
-      currentFrame = Math.floor(frameTime) % totalFrames;
-    } else {
-      currentFrame = 0;
-    }
+    // Let the visualizer map the audio clock to analysis frames; it adapts to the display refresh rate
+    const audioCurrentTime =
+      audioProcessed && totalFrames > 0 && audioElement
+        ? audioElement.currentTime
+        : 0;
 
-    app.render(scaledTime / 1000.0, currentFrame, smoothingFactor);
+    app.render_at_time(scaledTime / 1000.0, audioCurrentTime, smoothingFactor);
+
+    // The poster frame stays on screen without redrawing; resume from wakeRenderLoop
+    if (app.is_render_suspended()) {
+      animating = false;
+      return;
+    }
     requestAnimationFrame(animate);
   }
   requestAnimationFrame(animate);
 
+  function wakeRenderLoop() {
+    app.wake();
+    if (!animating) {
+      animating = true;
+      requestAnimationFrame(animate);
+    }
+  }
+
   // Handle canvas resize
   function resizeCanvas() {
     if (!canvas) {
@@ -88,6 +101,7 @@ async function run() {
     if (app && typeof app.resize === "function") {
       try {
         app.resize(canvas.width, canvas.height);
+        wakeRenderLoop();
       } catch (e) {
         console.error("Error resizing app:", e);
       }
@@ -159,6 +173,7 @@ async function run() {
           app.process_audio_file(uint8Array);
           totalFrames = app.get_total_frames();
           audioProcessed = true;
+          wakeRenderLoop();
 
           // Create audio element for playback
           const audioBlob = new Blob([arrayBuffer], { type: "audio/wav" });
@@ -182,6 +197,7 @@ async function run() {
 
           audioElement.addEventListener("play", () => {
             isPlaying = true;
+            wakeRenderLoop();
             const playPauseBtn = document.getElementById("play-pause-btn");
             playPauseBtn.innerHTML = "<span>Pause</span>";
           });